@@ -0,0 +1,215 @@
+//! High-contrast and reduced-motion modes.
+//!
+//! Each follows the OS preference unless `settings.json` pins it. High
+//! contrast makes `load_layers` prefer a `name.hc.png` next to every
+//! `name.png`; reduced motion caps animation rates and stops the
+//! character wandering.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+use crate::ambient;
+use crate::error::AppError;
+use crate::pet_core::AssetSource;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{FullData, SpritesData};
+
+pub(crate) const IMAGE_EXTS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// `accessibility` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// `null` follows the OS setting.
+    pub high_contrast: Option<bool>,
+    /// `null` follows the OS setting.
+    pub reduced_motion: Option<bool>,
+    /// Highest animation rate (frames per second) under reduced motion;
+    /// 0 shows only the first frame.
+    pub reduced_motion_max_rate: u32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        AccessibilitySettings {
+            high_contrast: None,
+            reduced_motion: None,
+            reduced_motion_max_rate: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Modes {
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+}
+
+// ── OS preferences ──
+
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn detect_os() -> Modes {
+    let flag = |key: &str| {
+        command_stdout("defaults", &["read", "com.apple.universalaccess", key]).as_deref()
+            == Some("1")
+    };
+    Modes {
+        high_contrast: flag("increaseContrast"),
+        reduced_motion: flag("reduceMotion"),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn detect_os() -> Modes {
+    // `reg query` prints `    Name    REG_SZ    value`; the value is last.
+    let reg = |key: &str, name: &str| {
+        command_stdout("reg", &["query", key, "/v", name])
+            .and_then(|out| out.split_whitespace().last().map(str::to_string))
+    };
+    let flags = reg(r"HKCU\Control Panel\Accessibility\HighContrast", "Flags")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(0);
+    Modes {
+        // HCF_HIGHCONTRASTON
+        high_contrast: flags & 1 == 1,
+        reduced_motion: reg(r"HKCU\Control Panel\Desktop\WindowMetrics", "MinAnimate").as_deref()
+            == Some("0"),
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn detect_os() -> Modes {
+    let gsettings = |schema: &str, key: &str| command_stdout("gsettings", &["get", schema, key]);
+    Modes {
+        high_contrast: gsettings("org.gnome.desktop.a11y.interface", "high-contrast").as_deref()
+            == Some("true"),
+        reduced_motion: gsettings("org.gnome.desktop.interface", "enable-animations").as_deref()
+            == Some("false"),
+    }
+}
+
+/// Detected once per run; the probes spawn processes.
+fn os_modes() -> Modes {
+    static OS: OnceLock<Modes> = OnceLock::new();
+    *OS.get_or_init(detect_os)
+}
+
+pub(crate) fn resolve(cfg: &AccessibilitySettings) -> Modes {
+    let os = os_modes();
+    Modes {
+        high_contrast: cfg.high_contrast.unwrap_or(os.high_contrast),
+        reduced_motion: cfg.reduced_motion.unwrap_or(os.reduced_motion),
+    }
+}
+
+// ── applying ──
+
+/// `dir/name.png` → `dir/name.hc.png`; `None` for anything but images.
+fn hc_variant(rel: &str) -> Option<String> {
+    let (stem, ext) = rel.rsplit_once('.')?;
+    IMAGE_EXTS
+        .contains(&ext.to_ascii_lowercase().as_str())
+        .then(|| format!("{stem}.hc.{ext}"))
+}
+
+/// Serves `name.hc.png` in place of `name.png` wherever the pack has one.
+pub(crate) struct HighContrast<'a> {
+    base: &'a dyn AssetSource,
+}
+
+impl<'a> HighContrast<'a> {
+    pub(crate) fn new(base: &'a dyn AssetSource) -> Self {
+        HighContrast { base }
+    }
+
+    fn pick(&self, rel: &str) -> String {
+        hc_variant(rel)
+            .filter(|v| self.base.exists(v))
+            .unwrap_or_else(|| rel.to_string())
+    }
+}
+
+impl AssetSource for HighContrast<'_> {
+    fn exists(&self, rel: &str) -> bool {
+        self.base.exists(rel)
+    }
+
+    fn size(&self, rel: &str) -> Result<u64, AppError> {
+        self.base.size(&self.pick(rel))
+    }
+
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+        self.base.read(&self.pick(rel))
+    }
+
+    /// The protocol handler picks the variant itself.
+    fn asset_url(&self, rel: &str) -> Option<String> {
+        self.base.asset_url(rel)
+    }
+}
+
+pub(crate) fn reduce_motion(data: &mut FullData, max_rate: u32) {
+    data.character.wander = 0.0;
+    slow_down(data.sprites.as_mut(), max_rate);
+    for pet in &mut data.characters {
+        pet.character.wander = 0.0;
+        slow_down(pet.sprites.as_mut(), max_rate);
+    }
+}
+
+fn slow_down(sprites: Option<&mut SpritesData>, max_rate: u32) {
+    let Some(sprites) = sprites else {
+        return;
+    };
+    for anim in &mut sprites.anims {
+        if max_rate == 0 {
+            anim.frames = 1;
+            anim.repeat = 0;
+            if let Some(sequence) = anim.sequence.as_mut() {
+                sequence.truncate(1);
+            }
+            anim.durations_ms = None;
+        } else if anim.rate > max_rate {
+            anim.rate = max_rate;
+            // Per-frame timings would keep the original pace.
+            anim.durations_ms = None;
+        }
+    }
+}
+
+/// Builds layers with the active modes applied.
+pub(crate) fn build_layers(
+    src: &dyn AssetSource,
+    skin: Option<&str>,
+    settings: &Settings,
+) -> Result<FullData, AppError> {
+    let modes = resolve(&settings.accessibility);
+    let mut data = ambient::with_ambient(src, |src| {
+        if modes.high_contrast {
+            crate::pet_core::build_layers_skinned(&HighContrast::new(src), skin, &settings.limits)
+        } else {
+            crate::pet_core::build_layers_skinned(src, skin, &settings.limits)
+        }
+    })?;
+    if modes.reduced_motion {
+        reduce_motion(&mut data, settings.accessibility.reduced_motion_max_rate);
+    }
+    Ok(data)
+}
+
+/// The effective modes, for styling the chrome (bubble animations, theme).
+#[tauri::command]
+pub fn get_accessibility(settings: tauri::State<'_, Mutex<Settings>>) -> Modes {
+    resolve(&settings.lock_or_recover().accessibility)
+}
@@ -0,0 +1,158 @@
+//! How long since the user last touched the keyboard or mouse, from the
+//! OS. Subsystems that care about presence (break reminders, idle modes)
+//! poll [`idle_time`] rather than hooking input themselves.
+//!
+//! `None` means the platform gave no answer, e.g. a Linux session without
+//! `xprintidle` or GNOME's idle monitor.
+//!
+//! A watcher also turns it into `user-idle` once input has stopped for
+//! `idle.idle_secs`, and `user-active` once it resumes; the pet sleeps in
+//! between.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+static AWAY: AtomicBool = AtomicBool::new(false);
+
+/// `idle` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct IdleSettings {
+    pub enabled: bool,
+    /// No input for this long sends `user-idle`.
+    pub idle_secs: u64,
+    /// Back to `user-active` once the last input is this recent.
+    pub active_secs: u64,
+    pub poll_secs: u64,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        IdleSettings {
+            enabled: true,
+            idle_secs: 300,
+            active_secs: 5,
+            poll_secs: 2,
+        }
+    }
+}
+
+fn stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn idle_time() -> Option<Duration> {
+    // `"HIDIdleTime" = 123456789` in nanoseconds.
+    let out = stdout("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+    let line = out.lines().find(|l| l.contains("\"HIDIdleTime\""))?;
+    let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn idle_time() -> Option<Duration> {
+    const SCRIPT: &str = r#"Add-Type @'
+using System; using System.Runtime.InteropServices;
+public struct LII { public uint cbSize; public uint dwTime; }
+public class Idle { [DllImport("user32.dll")] public static extern bool GetLastInputInfo(ref LII p);
+  public static uint Ms() { var l = new LII(); l.cbSize = 8; GetLastInputInfo(ref l); return (uint)Environment.TickCount - l.dwTime; } }
+'@; [Idle]::Ms()"#;
+    let out = stdout(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", SCRIPT],
+    )?;
+    let millis: u64 = out.trim().parse().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn idle_time() -> Option<Duration> {
+    // GNOME (X11 and Wayland): `(uint64 12345,)` in milliseconds.
+    let gnome = || {
+        let out = stdout(
+            "gdbus",
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ],
+        )?;
+        let digits: String = out
+            .split_whitespace()
+            .nth(1)?
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        digits.parse::<u64>().ok()
+    };
+    let x11 = || stdout("xprintidle", &[])?.trim().parse::<u64>().ok();
+    gnome().or_else(x11).map(Duration::from_millis)
+}
+
+/// Whether `user-idle` was the last of the two sent.
+pub(crate) fn user_idle() -> bool {
+    AWAY.load(Ordering::Relaxed)
+}
+
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let cfg = app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .idle
+            .clone();
+        let idle = idle_time();
+        let away = user_idle();
+        let next = match idle {
+            Some(idle) if cfg.enabled && !away && idle.as_secs() >= cfg.idle_secs => Some(true),
+            Some(idle) if away && idle.as_secs() < cfg.active_secs => Some(false),
+            // Switched off while away: wake up.
+            _ if away && !cfg.enabled => Some(false),
+            _ => None,
+        };
+        if let Some(away) = next {
+            AWAY.store(away, Ordering::Relaxed);
+            let secs = idle.map_or(0, |d| d.as_secs());
+            let (icon, event) = if away {
+                ("😴", "user-idle")
+            } else {
+                ("👋", "user-active")
+            };
+            log!("{icon} {event} ({secs}s since input)");
+            let _ = app.emit(event, serde_json::json!({ "idle_secs": secs }));
+        }
+        // Stay quick while away, so the pet wakes as soon as input returns.
+        let poll = Duration::from_secs(cfg.poll_secs.max(1));
+        std::thread::sleep(if user_idle() {
+            poll
+        } else {
+            power::interval(&app, poll)
+        });
+    });
+}
+
+/// `None` when the platform can't tell.
+#[tauri::command]
+pub fn get_idle_seconds() -> Option<f64> {
+    idle_time().map(|d| d.as_secs_f64())
+}
@@ -0,0 +1,276 @@
+//! Day and night: the pack's `name.night.png` variants replace `name.png`
+//! after dark, and the frontend dims the map with `lighting`.
+//!
+//! The mode comes from, in order: `set_ambient_mode`, `ambient.mode`, the
+//! ambient light sensor (Linux IIO, below `dark_lux` is night) and the
+//! `day_start`/`night_start` schedule in the `clock` time zone. Every
+//! change reloads layers and map and goes out as `ambient-changed`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::a11y::IMAGE_EXTS;
+use crate::error::AppError;
+use crate::logs::log;
+use crate::pet_core::AssetSource;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{clock, power, watch};
+
+/// Read by the loaders, which have no app handle.
+static NIGHT: AtomicBool = AtomicBool::new(false);
+/// What went out last as `ambient-changed`.
+static LAST: Mutex<Option<AmbientInfo>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    Day,
+    Night,
+}
+
+/// `ambient` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AmbientSettings {
+    pub enabled: bool,
+    /// Pins the mode; automatic when unset.
+    pub mode: Option<Mode>,
+    /// `HH:MM`, for when no light sensor answers.
+    pub day_start: String,
+    pub night_start: String,
+    /// Use the light sensor where there is one.
+    pub sensor: bool,
+    /// Below this many lux counts as night.
+    pub dark_lux: f64,
+    /// Colour and strength of the night overlay on the map.
+    pub night_tint: String,
+    pub night_darkness: f64,
+    pub poll_secs: u32,
+}
+
+impl Default for AmbientSettings {
+    fn default() -> Self {
+        AmbientSettings {
+            enabled: true,
+            mode: None,
+            day_start: "07:00".to_string(),
+            night_start: "19:00".to_string(),
+            sensor: true,
+            dark_lux: 30.0,
+            night_tint: "#0b1d4a".to_string(),
+            night_darkness: 0.35,
+            poll_secs: 120,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Disabled,
+    Override,
+    Settings,
+    Sensor,
+    Schedule,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Lighting {
+    /// CSS colour.
+    tint: String,
+    /// Overlay opacity, 0 in daylight.
+    darkness: f64,
+}
+
+/// Payload of `ambient-changed`, also returned by `get_ambient_mode`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AmbientInfo {
+    mode: Mode,
+    source: Source,
+    /// The last sensor reading, if any.
+    lux: Option<f64>,
+    lighting: Lighting,
+}
+
+/// `set_ambient_mode` wins over everything until cleared; not persisted.
+#[derive(Default)]
+pub(crate) struct Ambient(Mutex<Option<Mode>>);
+
+pub(crate) fn is_night() -> bool {
+    NIGHT.load(Ordering::Relaxed)
+}
+
+// ── variants ──
+
+/// `dir/name.png` → `dir/name.night.png`; `None` for anything but images.
+fn night_variant(rel: &str) -> Option<String> {
+    let (stem, ext) = rel.rsplit_once('.')?;
+    IMAGE_EXTS
+        .contains(&ext.to_ascii_lowercase().as_str())
+        .then(|| format!("{stem}.night.{ext}"))
+}
+
+/// Serves `name.night.png` in place of `name.png` wherever the pack has
+/// one.
+pub(crate) struct Night<'a> {
+    base: &'a dyn AssetSource,
+}
+
+impl<'a> Night<'a> {
+    pub(crate) fn new(base: &'a dyn AssetSource) -> Self {
+        Night { base }
+    }
+
+    fn pick(&self, rel: &str) -> String {
+        night_variant(rel)
+            .filter(|v| self.base.exists(v))
+            .unwrap_or_else(|| rel.to_string())
+    }
+}
+
+impl AssetSource for Night<'_> {
+    fn exists(&self, rel: &str) -> bool {
+        self.base.exists(rel)
+    }
+
+    fn size(&self, rel: &str) -> Result<u64, AppError> {
+        self.base.size(&self.pick(rel))
+    }
+
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+        self.base.read(&self.pick(rel))
+    }
+
+    /// The protocol handler picks the variant itself.
+    fn asset_url(&self, rel: &str) -> Option<String> {
+        self.base.asset_url(rel)
+    }
+}
+
+/// Runs `f` on the pack as it looks right now, night variants included.
+pub(crate) fn with_ambient<T>(src: &dyn AssetSource, f: impl FnOnce(&dyn AssetSource) -> T) -> T {
+    if is_night() {
+        f(&Night::new(src))
+    } else {
+        f(src)
+    }
+}
+
+// ── deciding ──
+
+/// Lux from the first IIO light sensor.
+#[cfg(target_os = "linux")]
+fn read_lux() -> Option<f64> {
+    let read = |path: std::path::PathBuf| -> Option<f64> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    for entry in std::fs::read_dir("/sys/bus/iio/devices").ok()?.flatten() {
+        let dir = entry.path();
+        if let Some(lux) = read(dir.join("in_illuminance_input")) {
+            return Some(lux);
+        }
+        if let Some(raw) = read(dir.join("in_illuminance_raw")) {
+            let scale = read(dir.join("in_illuminance_scale")).unwrap_or(1.0);
+            return Some(raw * scale);
+        }
+    }
+    None
+}
+
+/// No sensor API without extra dependencies; the schedule applies.
+#[cfg(not(target_os = "linux"))]
+fn read_lux() -> Option<f64> {
+    None
+}
+
+fn decide(app: &AppHandle) -> AmbientInfo {
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let cfg = &settings.ambient;
+    let pinned = *app.state::<Ambient>().0.lock_or_recover();
+    let lux = (cfg.enabled && cfg.sensor).then(read_lux).flatten();
+    let (mode, source) = if !cfg.enabled {
+        (Mode::Day, Source::Disabled)
+    } else if let Some(mode) = pinned {
+        (mode, Source::Override)
+    } else if let Some(mode) = cfg.mode {
+        (mode, Source::Settings)
+    } else if let Some(lux) = lux {
+        let mode = if lux < cfg.dark_lux {
+            Mode::Night
+        } else {
+            Mode::Day
+        };
+        (mode, Source::Sensor)
+    } else {
+        let (tz, _) = clock::zone(None, &settings.clock);
+        let start = clock::parse_time(&cfg.day_start);
+        let end = clock::parse_time(&cfg.night_start);
+        let day = match (start, end) {
+            (Some(start), Some(end)) => clock::in_window(start, end, &clock::now_in(tz)),
+            _ => true,
+        };
+        let mode = if day { Mode::Day } else { Mode::Night };
+        (mode, Source::Schedule)
+    };
+    let darkness = match mode {
+        Mode::Day => 0.0,
+        Mode::Night => cfg.night_darkness.clamp(0.0, 1.0),
+    };
+    AmbientInfo {
+        mode,
+        source,
+        lux,
+        lighting: Lighting {
+            tint: cfg.night_tint.clone(),
+            darkness,
+        },
+    }
+}
+
+/// Applies the current mode; a switch reloads layers and map.
+fn refresh(app: &AppHandle, last: &mut Option<AmbientInfo>) -> AmbientInfo {
+    let info = decide(app);
+    let night = info.mode == Mode::Night;
+    if NIGHT.swap(night, Ordering::Relaxed) != night {
+        log!("🌗 ambient: {:?} ({:?})", info.mode, info.source);
+        let _ = watch::layers_changed(app);
+        let _ = watch::map_changed(app);
+    }
+    if last.as_ref() != Some(&info) {
+        let _ = app.emit("ambient-changed", info.clone());
+        *last = Some(info.clone());
+    }
+    info
+}
+
+/// Settles the mode before the first load, then keeps checking.
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    NIGHT.store(decide(&app).mode == Mode::Night, Ordering::Relaxed);
+    std::thread::spawn(move || loop {
+        refresh(&app, &mut LAST.lock_or_recover());
+        let poll = app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .ambient
+            .poll_secs;
+        let poll = Duration::from_secs(u64::from(poll.max(10)));
+        std::thread::sleep(power::interval(&app, poll));
+    });
+}
+
+#[tauri::command]
+pub fn get_ambient_mode(app: AppHandle) -> AmbientInfo {
+    decide(&app)
+}
+
+/// `null` goes back to automatic. Applied right away.
+#[tauri::command]
+pub fn set_ambient_mode(app: AppHandle, mode: Option<Mode>) -> AmbientInfo {
+    *app.state::<Ambient>().0.lock_or_recover() = mode;
+    refresh(&app, &mut LAST.lock_or_recover())
+}
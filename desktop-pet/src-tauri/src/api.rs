@@ -0,0 +1,248 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use crate::logs::log;
+use crate::sync::LockExt;
+
+/// Bumped whenever a command is removed or changes shape incompatibly.
+pub(crate) const IPC_REVISION: u32 = 3;
+
+/// Revisions a frontend may ask for through `api_version`; older ones are
+/// served by shims and listed in `DEPRECATIONS`.
+pub(crate) const SUPPORTED_REVISIONS: &[u32] = &[1, 2, 3];
+
+/// Calls without `api_version` predate versioning, so they get the shape
+/// every such frontend was written against.
+const UNVERSIONED_REVISION: u32 = 1;
+
+/// `layers.json` / `map.json` / `state.json` layouts this build understands.
+pub(crate) const CONFIG_SCHEMA_VERSIONS: &[u32] = &[1];
+
+/// Every command registered in `run()`; keep in sync with `generate_handler!`.
+pub(crate) const COMMANDS: &[&str] = &[
+    "get_api_info",
+    "read_state",
+    "write_state",
+    "load_layers",
+    "load_map",
+    "load_map_tiled",
+    "enter_minimize_mode",
+    "restore_main_window",
+    "reset_runtime_state",
+    "close_app",
+    "open_external_url",
+    "get_config_schema",
+    "run_doctor",
+    "scaffold_project",
+    "get_dev_status",
+    "start_recording",
+    "stop_recording",
+    "play_recording",
+    "record_clip",
+    "bench_assets",
+    "list_known_states",
+    "update_layer",
+    "update_character",
+    "t",
+    "get_locale",
+    "import_bundle",
+    "list_registry_packs",
+    "install_registry_pack",
+    "get_theme",
+    "check_for_updates",
+    "get_last_crash",
+    "open_path",
+    "report_frame_stats",
+    "get_accessibility",
+    "get_platform_capabilities",
+    "set_click_through",
+    "snooze_break",
+    "get_break_stats",
+    "generate_report",
+    "report_keystrokes",
+    "get_typing_stats",
+    "report_progress",
+    "get_progress_breakdown",
+    "get_screensaver",
+    "get_lan_peers",
+    "get_observer",
+    "get_energy_saver",
+    "watch_state",
+    "get_clock_info",
+    "get_hit_masks",
+    "get_seasonal",
+    "get_effective_paths",
+    "validate_project",
+    "get_onboarding_step",
+    "complete_step",
+    "get_config_schemas",
+    "find_path",
+    "set_pet_tile",
+    "route_to_poi",
+    "follow_route",
+    "stop_route",
+    "save_session",
+    "restore_session",
+    "get_session_recovery",
+    "get_avoid_bounds",
+    "get_maintenance_status",
+    "run_maintenance",
+    "get_control_api",
+    "set_control_api",
+    "send_digest",
+    "get_ambient_mode",
+    "set_ambient_mode",
+    "get_audit_log",
+    "get_wander_paused",
+    "get_sim_snapshot",
+    "reset_sim",
+    "get_idle_seconds",
+    "system_stats",
+    "list_profiles",
+    "switch_profile",
+    "render_anim_preview",
+    "start_pomodoro",
+    "pause_pomodoro",
+    "skip_pomodoro",
+    "stop_pomodoro",
+    "get_pomodoro",
+    "get_pomodoro_history",
+    "get_calendar",
+    "say",
+    "start_build",
+    "stop_build",
+    "get_build",
+    "get_jobs",
+    "cancel_job",
+    "get_ci",
+    "get_integration_status",
+    "play_sound",
+    "set_volume",
+];
+
+#[derive(Debug, Serialize)]
+pub struct Deprecation {
+    command: &'static str,
+    /// Last revision that still gets the old shape.
+    last_revision: u32,
+    note: &'static str,
+}
+
+/// Commands whose old shape is still accepted through a shim.
+pub(crate) const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        command: "write_state",
+        last_revision: 1,
+        note: "revision 2 takes `state` instead of `newState`, fills in `updated_at` and returns the written state",
+    },
+    Deprecation {
+        command: "read_state",
+        last_revision: 2,
+        note: "revision 3 fails with an `AppError` object (`kind`, `message`, …) instead of a string",
+    },
+    Deprecation {
+        command: "load_layers",
+        last_revision: 2,
+        note: "revision 3 fails with an `AppError` object (`kind`, `message`, …) instead of a string",
+    },
+    Deprecation {
+        command: "load_map",
+        last_revision: 2,
+        note: "revision 3 fails with an `AppError` object (`kind`, `message`, …) instead of a string",
+    },
+];
+
+/// Resolves the revision a versioned command should answer with, and
+/// logs once per command when a deprecated shape is used.
+pub(crate) fn negotiate(command: &'static str, api_version: Option<u32>) -> Result<u32, String> {
+    let revision = api_version.unwrap_or(UNVERSIONED_REVISION);
+    if !SUPPORTED_REVISIONS.contains(&revision) {
+        return Err(format!(
+            "{command}: IPC revision {revision} is not supported (this build accepts {SUPPORTED_REVISIONS:?})"
+        ));
+    }
+    static WARNED: Mutex<BTreeSet<&str>> = Mutex::new(BTreeSet::new());
+    let deprecated = DEPRECATIONS
+        .iter()
+        .find(|d| d.command == command && revision <= d.last_revision);
+    if let Some(d) = deprecated {
+        if WARNED.lock_or_recover().insert(command) {
+            log!(
+                "ℹ️ {command} called with IPC revision {revision}; {}",
+                d.note
+            );
+        }
+    }
+    Ok(revision)
+}
+
+/// Optional subsystems compiled into this build. Frontends should feature-
+/// detect through this list rather than probing commands.
+fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "audio") {
+        features.push("audio");
+    }
+    if cfg!(feature = "capture") {
+        features.push("capture");
+    }
+    if cfg!(feature = "ci") {
+        features.push("ci");
+    }
+    if cfg!(all(feature = "dbus", target_os = "linux")) {
+        features.push("dbus");
+    }
+    if cfg!(feature = "lan") {
+        features.push("lan");
+    }
+    if cfg!(feature = "mail") {
+        features.push("mail");
+    }
+    if cfg!(feature = "mqtt") {
+        features.push("mqtt");
+    }
+    if cfg!(feature = "registry") {
+        features.push("registry");
+    }
+    if cfg!(feature = "rgb") {
+        features.push("rgb");
+    }
+    if cfg!(feature = "updates") {
+        features.push("updates");
+    }
+    if cfg!(feature = "voice") {
+        features.push("voice");
+    }
+    if cfg!(feature = "ws") {
+        features.push("ws");
+    }
+    features
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiInfo {
+    version: &'static str,
+    ipc_revision: u32,
+    supported_ipc_revisions: &'static [u32],
+    deprecations: &'static [Deprecation],
+    config_schema_versions: &'static [u32],
+    features: Vec<&'static str>,
+    commands: &'static [&'static str],
+    /// Subset of `commands` also served over JSON-RPC (`--rpc`).
+    rpc_methods: &'static [&'static str],
+}
+
+#[tauri::command]
+pub fn get_api_info() -> ApiInfo {
+    ApiInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        ipc_revision: IPC_REVISION,
+        supported_ipc_revisions: SUPPORTED_REVISIONS,
+        deprecations: DEPRECATIONS,
+        config_schema_versions: CONFIG_SCHEMA_VERSIONS,
+        features: features(),
+        commands: COMMANDS,
+        rpc_methods: crate::rpc::METHODS,
+    }
+}
@@ -0,0 +1,256 @@
+//! Aseprite's "Export Sprite Sheet" JSON, read as a set of anims.
+//!
+//! Every frame tag becomes an anim named after the tag, playing its frames
+//! in the tag's direction with the per-frame durations from the export. A
+//! sheet without tags is one anim named after the JSON file. Frames must
+//! sit on a uniform grid (any layout, padding allowed); trimmed or rotated
+//! exports are rejected.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::imaging;
+use crate::limits::Limits;
+use crate::pet_core::{self, AssetSource};
+
+#[derive(Debug, Deserialize)]
+struct Export {
+    frames: Frames,
+    meta: Meta,
+}
+
+/// "Hash" and "Array" exports; the hash keeps file order.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Array(Vec<Frame>),
+    Hash(Map<String, Value>),
+}
+
+#[derive(Debug, Deserialize)]
+struct Frame {
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    duration: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    image: String,
+    #[serde(rename = "frameTags", default)]
+    frame_tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    /// Play count, as a string since Aseprite 1.3; `0` or unset loops.
+    repeat: Option<Value>,
+}
+
+pub(crate) struct Anim {
+    pub key: String,
+    /// Sheet frame indices in play order.
+    pub sequence: Vec<u32>,
+    pub durations_ms: Vec<u32>,
+    pub repeat: i32,
+}
+
+pub(crate) struct Sheet {
+    /// Image path inside the pack.
+    pub image: String,
+    pub frame: (u32, u32),
+    pub margin: u32,
+    pub spacing: u32,
+    pub anims: Vec<Anim>,
+}
+
+/// Distance between neighbouring frames along one axis, if there are two.
+fn step(positions: &BTreeSet<u32>) -> Option<u32> {
+    positions
+        .iter()
+        .zip(positions.iter().skip(1))
+        .map(|(a, b)| b - a)
+        .min()
+}
+
+/// `frames` there and back again, without repeating either end.
+fn bounce(frames: Vec<usize>) -> Vec<usize> {
+    let back: Vec<usize> = frames
+        .iter()
+        .rev()
+        .skip(1)
+        .take(frames.len().saturating_sub(2))
+        .copied()
+        .collect();
+    frames.into_iter().chain(back).collect()
+}
+
+fn order(tag: &Tag) -> Vec<usize> {
+    let mut frames: Vec<usize> = (tag.from..=tag.to).collect();
+    // `reverse` and `pingpong_reverse` start from the last frame.
+    if tag.direction.ends_with("reverse") {
+        frames.reverse();
+    }
+    if tag.direction.starts_with("pingpong") {
+        bounce(frames)
+    } else {
+        frames
+    }
+}
+
+fn repeat(tag: &Tag) -> i32 {
+    let times = match &tag.repeat {
+        Some(Value::String(s)) => s.trim().parse().unwrap_or(0),
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+        _ => 0,
+    };
+    if times == 0 {
+        -1
+    } else {
+        i32::try_from(times - 1).unwrap_or(i32::MAX)
+    }
+}
+
+pub(crate) fn read(src: &dyn AssetSource, rel: &str, limits: &Limits) -> Result<Sheet, AppError> {
+    let raw = pet_core::read_config(src, rel, limits)?;
+    let export: Export = serde_json::from_str(&raw).map_err(|e| AppError::parse(rel, &e))?;
+    let frames: Vec<Frame> = match export.frames {
+        Frames::Array(frames) => frames,
+        Frames::Hash(map) => map
+            .into_iter()
+            .map(|(name, v)| serde_json::from_value(v).map_err(|e| format!("{rel}: {name}: {e}")))
+            .collect::<Result<_, _>>()?,
+    };
+    let Some(first) = frames.first() else {
+        return Err(format!("{rel}: no frames").into());
+    };
+    let (fw, fh) = (first.frame.w, first.frame.h);
+    if frames.iter().any(|f| f.rotated || f.trimmed) {
+        return Err(format!(
+            "{rel}: trimmed or rotated frames are not supported; export without Trim"
+        )
+        .into());
+    }
+    if frames.iter().any(|f| (f.frame.w, f.frame.h) != (fw, fh)) {
+        return Err(format!("{rel}: frames must all be {fw}×{fh}").into());
+    }
+
+    // Border padding is the margin, shape + inner padding the spacing.
+    let xs: BTreeSet<u32> = frames.iter().map(|f| f.frame.x).collect();
+    let ys: BTreeSet<u32> = frames.iter().map(|f| f.frame.y).collect();
+    let (mx, my) = (xs.first().copied(), ys.first().copied());
+    let margin = mx.unwrap_or(0);
+    if my != mx {
+        return Err(format!("{rel}: horizontal and vertical border padding must match").into());
+    }
+    let spacing_x = step(&xs).map(|s| s.saturating_sub(fw));
+    let spacing_y = step(&ys).map(|s| s.saturating_sub(fh));
+    if let (Some(x), Some(y)) = (spacing_x, spacing_y) {
+        if x != y {
+            return Err(format!("{rel}: horizontal and vertical frame spacing must match").into());
+        }
+    }
+    let spacing = spacing_x.or(spacing_y).unwrap_or(0);
+
+    let image = pet_core::resolve_relative(rel, &export.meta.image)?;
+    if !src.exists(&image) {
+        let message = format!("{rel}: sheet not found: {image}");
+        return Err(AppError::missing(image, message));
+    }
+    let bytes = pet_core::read_asset(src, &image, limits)?;
+    let decoded = imaging::decode(Path::new(&image), &bytes)?;
+    let (cols, _) = imaging::sheet_grid(&image, &decoded, (fw, fh), margin, spacing)?;
+    let index = |f: &Frame| -> Result<u32, String> {
+        let (dx, dy) = (f.frame.x - margin, f.frame.y - margin);
+        if dx % (fw + spacing) != 0 || dy % (fh + spacing) != 0 {
+            return Err(format!(
+                "{rel}: frame at {},{} is off the {fw}×{fh} grid",
+                f.frame.x, f.frame.y
+            ));
+        }
+        Ok(dy / (fh + spacing) * cols + dx / (fw + spacing))
+    };
+    let indices: Vec<u32> = frames.iter().map(index).collect::<Result<_, _>>()?;
+    let durations: Vec<u32> = frames
+        .iter()
+        .map(|f| f.duration.unwrap_or(100).max(1))
+        .collect();
+
+    let mut tags = export.meta.frame_tags;
+    if tags.is_empty() {
+        let stem = Path::new(rel)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("anim");
+        tags.push(Tag {
+            name: stem.to_string(),
+            from: 0,
+            to: frames.len() - 1,
+            direction: String::new(),
+            repeat: None,
+        });
+    }
+    if tags.len() > limits.max_anims {
+        return Err(format!(
+            "{rel}: {} tags exceed limits.max_anims ({})",
+            tags.len(),
+            limits.max_anims
+        )
+        .into());
+    }
+    let mut anims = Vec::with_capacity(tags.len());
+    for tag in &tags {
+        if tag.from > tag.to || tag.to >= frames.len() {
+            return Err(format!(
+                "{rel}: tag `{}` spans frames {}..{} of {}",
+                tag.name,
+                tag.from,
+                tag.to,
+                frames.len()
+            )
+            .into());
+        }
+        let play = order(tag);
+        if play.len() > limits.max_frames as usize {
+            return Err(format!(
+                "{rel}: tag `{}` has {} frames, over limits.max_frames ({})",
+                tag.name,
+                play.len(),
+                limits.max_frames
+            )
+            .into());
+        }
+        anims.push(Anim {
+            key: tag.name.clone(),
+            sequence: play.iter().map(|&i| indices[i]).collect(),
+            durations_ms: play.iter().map(|&i| durations[i]).collect(),
+            repeat: repeat(tag),
+        });
+    }
+    Ok(Sheet {
+        image,
+        frame: (fw, fh),
+        margin,
+        spacing,
+        anims,
+    })
+}
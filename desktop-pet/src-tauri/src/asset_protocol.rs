@@ -0,0 +1,154 @@
+//! `pet-asset://` serves pack images straight from `layers/`.
+//!
+//! `load_layers` and `load_map` used to inline every image as a base64
+//! data URL, so a large tileset sat in the IPC response, the JS string and
+//! the decoded texture all at once. They now go through [`Served`], which
+//! hands out protocol URLs instead, and the webview fetches the bytes on
+//! demand. Night and high-contrast variants are picked here exactly as the
+//! loaders pick them. Other sources (the in-memory test packs, `list_known_states`)
+//! keep producing data URLs.
+
+use std::borrow::Cow;
+use std::sync::Mutex;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::a11y::{self, HighContrast};
+use crate::ambient::{self, Night};
+use crate::error::AppError;
+use crate::pet_core::{self, AssetSource, FsAssets};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::AppPaths;
+
+pub(crate) const SCHEME: &str = "pet-asset";
+
+/// Windows and Android webviews only route custom schemes through
+/// `http://<scheme>.localhost`.
+#[cfg(any(windows, target_os = "android"))]
+const BASE: &str = "http://pet-asset.localhost/";
+#[cfg(not(any(windows, target_os = "android")))]
+const BASE: &str = "pet-asset://localhost/";
+
+fn encode(rel: &str) -> String {
+    let mut out = String::with_capacity(rel.len());
+    for b in rel.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+pub(crate) fn url(rel: &str) -> String {
+    format!("{BASE}{}", encode(rel))
+}
+
+/// `base`, with images addressed by `pet-asset://` URL.
+pub(crate) struct Served<'a> {
+    base: &'a dyn AssetSource,
+}
+
+impl<'a> Served<'a> {
+    pub(crate) fn new(base: &'a dyn AssetSource) -> Self {
+        Served { base }
+    }
+}
+
+impl AssetSource for Served<'_> {
+    fn exists(&self, rel: &str) -> bool {
+        self.base.exists(rel)
+    }
+
+    fn size(&self, rel: &str) -> Result<u64, AppError> {
+        self.base.size(rel)
+    }
+
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+        self.base.read(rel)
+    }
+
+    fn read_text(&self, rel: &str) -> Result<String, AppError> {
+        self.base.read_text(rel)
+    }
+
+    fn asset_url(&self, rel: &str) -> Option<String> {
+        Some(url(rel))
+    }
+}
+
+/// Relative paths only, and never out of `layers/`.
+fn safe_rel(path: &str) -> Option<String> {
+    let rel = decode(path.trim_start_matches('/'))?;
+    let ok = !rel.is_empty()
+        && !rel.contains('\\')
+        && !rel.contains(':')
+        && rel
+            .split('/')
+            .all(|seg| !seg.is_empty() && seg != "." && seg != "..");
+    ok.then_some(rel)
+}
+
+fn respond(status: StatusCode, mime: &str, body: Vec<u8>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime)
+        // The page is served from the backend's origin.
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        // Hot reload swaps files under the same URL.
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Cow::Owned(body))
+        .unwrap_or_default()
+}
+
+pub(crate) fn handle(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let Some(rel) = safe_rel(request.uri().path()) else {
+        return respond(StatusCode::BAD_REQUEST, "text/plain", b"bad path".to_vec());
+    };
+    let layers_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone();
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let fs = FsAssets::new(layers_dir);
+    let night = Night::new(&fs);
+    let base: &dyn AssetSource = if ambient::is_night() { &night } else { &fs };
+    let hc = HighContrast::new(base);
+    let src: &dyn AssetSource = if a11y::resolve(&settings.accessibility).high_contrast {
+        &hc
+    } else {
+        base
+    };
+    if !src.exists(&rel) {
+        return respond(StatusCode::NOT_FOUND, "text/plain", b"not found".to_vec());
+    }
+    match pet_core::read_asset(src, &rel, &settings.limits) {
+        Ok(bytes) => respond(StatusCode::OK, pet_core::mime_for(&rel), bytes),
+        Err(e) => respond(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "text/plain",
+            e.to_string().into_bytes(),
+        ),
+    }
+}
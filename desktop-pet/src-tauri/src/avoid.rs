@@ -0,0 +1,281 @@
+//! Screen regions the pet stays out of, so it never sits on top of the code
+//! being edited.
+//!
+//! Zones come from the `avoid` block of `settings.json` and, in builds with
+//! the `capture` feature, from the frontmost other window. A background
+//! thread turns them into the parts of each monitor the pet may wander in
+//! and emits `avoid-bounds` whenever that changes; `get_avoid_bounds`
+//! returns the latest.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+/// Rectangle in physical pixels on the virtual desktop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> i32 {
+        self.x.saturating_add_unsigned(self.width)
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y.saturating_add_unsigned(self.height)
+    }
+
+    fn from_edges(left: i32, top: i32, right: i32, bottom: i32) -> Option<Rect> {
+        (right > left && bottom > top).then(|| Rect {
+            x: left,
+            y: top,
+            width: right.abs_diff(left),
+            height: bottom.abs_diff(top),
+        })
+    }
+
+    fn grow(&self, by: u32) -> Rect {
+        let by = i32::try_from(by).unwrap_or(i32::MAX);
+        Rect::from_edges(
+            self.x.saturating_sub(by),
+            self.y.saturating_sub(by),
+            self.right().saturating_add(by),
+            self.bottom().saturating_add(by),
+        )
+        .unwrap_or(*self)
+    }
+
+    fn intersect(&self, other: &Rect) -> Option<Rect> {
+        Rect::from_edges(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        )
+    }
+
+    /// What is left of `self` with `hole` cut out: up to four bands, the
+    /// full-width ones above and below and the ones to either side.
+    fn subtract(&self, hole: &Rect) -> Vec<Rect> {
+        let Some(hole) = self.intersect(hole) else {
+            return vec![*self];
+        };
+        [
+            Rect::from_edges(self.x, self.y, self.right(), hole.y),
+            Rect::from_edges(self.x, hole.bottom(), self.right(), self.bottom()),
+            Rect::from_edges(self.x, hole.y, hole.x, hole.bottom()),
+            Rect::from_edges(hole.right(), hole.y, self.right(), hole.bottom()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// One entry of `avoid.zones`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Zone {
+    #[serde(flatten)]
+    pub rect: Rect,
+    /// Monitor name the coordinates are relative to; unset means the
+    /// virtual desktop.
+    pub monitor: Option<String>,
+}
+
+/// `avoid` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AvoidSettings {
+    pub enabled: bool,
+    pub zones: Vec<Zone>,
+    /// Also keep off the frontmost other window (`capture` builds), where
+    /// the caret usually is.
+    pub active_window: bool,
+    /// Extra pixels kept clear around every zone.
+    pub margin: u32,
+    pub poll_secs: u32,
+}
+
+impl Default for AvoidSettings {
+    fn default() -> Self {
+        AvoidSettings {
+            enabled: true,
+            zones: Vec::new(),
+            active_window: false,
+            margin: 16,
+            poll_secs: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorBounds {
+    pub name: Option<String>,
+    pub scale_factor: f64,
+    pub bounds: Rect,
+    /// Parts of `bounds` outside every zone.
+    pub allowed: Vec<Rect>,
+}
+
+/// Payload of `avoid-bounds` and result of `get_avoid_bounds`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AvoidBounds {
+    pub monitors: Vec<MonitorBounds>,
+    /// Every zone in effect, margin included.
+    pub zones: Vec<Rect>,
+    /// Outer rectangle of the main window, to map zones onto map tiles.
+    pub window: Option<Rect>,
+}
+
+/// Latest bounds, as last emitted.
+#[derive(Default)]
+pub(crate) struct Avoid(Mutex<AvoidBounds>);
+
+// ── zones ──
+
+#[cfg(feature = "capture")]
+fn active_window(own_titles: &[String]) -> Option<Rect> {
+    // Listed front to back.
+    let windows = xcap::Window::all().ok()?;
+    let front = windows
+        .into_iter()
+        .filter(|w| !w.is_minimized() && !w.title().is_empty())
+        .find(|w| !own_titles.iter().any(|t| t == w.title()))?;
+    Some(Rect {
+        x: front.x(),
+        y: front.y(),
+        width: front.width(),
+        height: front.height(),
+    })
+}
+
+#[cfg(not(feature = "capture"))]
+fn active_window(_: &[String]) -> Option<Rect> {
+    None
+}
+
+fn window_rect(app: &AppHandle) -> Option<Rect> {
+    let main = app.get_webview_window("main")?;
+    let (pos, size) = (main.outer_position().ok()?, main.outer_size().ok()?);
+    Some(Rect {
+        x: pos.x,
+        y: pos.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+fn compute(app: &AppHandle, cfg: &AvoidSettings, own_titles: &[String]) -> AvoidBounds {
+    let monitors: Vec<(Option<String>, f64, Rect)> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|m| {
+            let (at, size) = (m.position(), m.size());
+            let bounds = Rect {
+                x: at.x,
+                y: at.y,
+                width: size.width,
+                height: size.height,
+            };
+            (m.name().cloned(), m.scale_factor(), bounds)
+        })
+        .collect();
+
+    let mut zones = Vec::new();
+    for zone in &cfg.zones {
+        let origin = match &zone.monitor {
+            None => Some((0, 0)),
+            Some(name) => monitors
+                .iter()
+                .find(|(n, ..)| n.as_ref() == Some(name))
+                .map(|(_, _, b)| (b.x, b.y)),
+        };
+        // A zone on a monitor that isn't plugged in has nothing to cover.
+        if let Some((dx, dy)) = origin {
+            zones.push(Rect {
+                x: zone.rect.x.saturating_add(dx),
+                y: zone.rect.y.saturating_add(dy),
+                ..zone.rect
+            });
+        }
+    }
+    if cfg.active_window {
+        zones.extend(active_window(own_titles));
+    }
+    let zones: Vec<Rect> = zones.iter().map(|z| z.grow(cfg.margin)).collect();
+
+    let monitors = monitors
+        .into_iter()
+        .map(|(name, scale_factor, bounds)| {
+            let allowed = zones.iter().fold(vec![bounds], |free, zone| {
+                free.iter().flat_map(|r| r.subtract(zone)).collect()
+            });
+            MonitorBounds {
+                name,
+                scale_factor,
+                bounds,
+                allowed,
+            }
+        })
+        .collect();
+    AvoidBounds {
+        monitors,
+        zones,
+        window: window_rect(app),
+    }
+}
+
+// ── poller ──
+
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let own_titles: Vec<String> = app
+            .webview_windows()
+            .values()
+            .filter_map(|w| w.title().ok())
+            .collect();
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .avoid
+                .clone();
+            let poll = power::interval(&app, Duration::from_secs(u64::from(cfg.poll_secs.max(1))));
+            if !power::paused(&app, "avoid") {
+                let next = if cfg.enabled {
+                    compute(&app, &cfg, &own_titles)
+                } else {
+                    AvoidBounds::default()
+                };
+                let previous = {
+                    let mut current = app.state::<Avoid>().0.lock_or_recover();
+                    (*current != next).then(|| std::mem::replace(&mut *current, next.clone()))
+                };
+                if let Some(previous) = previous {
+                    if previous.zones.len() != next.zones.len() {
+                        log!("🚧 keeping clear of {} zone(s)", next.zones.len());
+                    }
+                    let _ = app.emit("avoid-bounds", next);
+                }
+            }
+            std::thread::sleep(poll);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_avoid_bounds(avoid: tauri::State<'_, Avoid>) -> AvoidBounds {
+    avoid.0.lock_or_recover().clone()
+}
@@ -0,0 +1,157 @@
+//! Times the asset pipeline phase by phase so changes to loaders or packs
+//! can be measured. Each run is saved to `.star-pet/bench.json` and the
+//! next run reports its change against it.
+//!
+//! There is no in-process asset cache yet, so "cold" is simply the first
+//! iteration (usually an OS page-cache miss) and the rest count as warm.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::locate;
+use crate::pet_core::{self, AssetSource, FsAssets};
+use crate::sync::LockExt;
+use crate::{fsutil, settings, AppPaths};
+
+pub(crate) const MAX_ITERATIONS: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PhaseStats {
+    pub cold_ms: f64,
+    pub warm_mean_ms: f64,
+    pub warm_min_ms: f64,
+    pub warm_max_ms: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BenchRun {
+    at: String,
+    iterations: u32,
+    /// Serialized IPC payload size of `load_layers` + `load_map`.
+    payload_bytes: usize,
+    phases: BTreeMap<String, PhaseStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    run: BenchRun,
+    previous: Option<BenchRun>,
+    /// Warm mean change per phase against the previous run, in percent;
+    /// negative is faster.
+    change_pct: BTreeMap<String, f64>,
+}
+
+#[derive(Default)]
+struct Samples(BTreeMap<&'static str, Vec<f64>>);
+
+impl Samples {
+    fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let out = f();
+        let ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.0.entry(phase).or_default().push(ms);
+        out
+    }
+
+    fn stats(self) -> BTreeMap<String, PhaseStats> {
+        self.0
+            .into_iter()
+            .map(|(phase, ms)| {
+                let warm = if ms.len() > 1 { &ms[1..] } else { &ms[..] };
+                let stats = PhaseStats {
+                    cold_ms: ms[0],
+                    warm_mean_ms: warm.iter().sum::<f64>() / warm.len() as f64,
+                    warm_min_ms: warm.iter().copied().fold(f64::INFINITY, f64::min),
+                    warm_max_ms: warm.iter().copied().fold(0.0, f64::max),
+                };
+                (phase.to_string(), stats)
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn bench(paths: &AppPaths, iterations: u32) -> Result<BenchReport, String> {
+    if !(1..=MAX_ITERATIONS).contains(&iterations) {
+        return Err(format!(
+            "iterations must be between 1 and {MAX_ITERATIONS}, got {iterations}"
+        ));
+    }
+    let limits = settings::load(&paths.settings_path).limits;
+    let fs = FsAssets::new(&paths.layers_dir);
+    let src = locate::with_map(paths, &fs, &limits)?;
+    let has_map = src.exists("map.json");
+
+    let mut samples = Samples::default();
+    let mut payload_bytes = 0;
+    for _ in 0..iterations {
+        samples.time("parse_layers", || pet_core::read_layers_cfg(&src, &limits))?;
+        let layers = samples.time("build_layers", || pet_core::build_layers(&src, &limits))?;
+        let mut payload = samples
+            .time("serialize_layers", || serde_json::to_vec(&layers))
+            .map_err(|e| e.to_string())?
+            .len();
+        if has_map {
+            samples.time("parse_map", || pet_core::read_map_cfg(&src, &limits))?;
+            let map = samples.time("build_map", || pet_core::build_map(&src, &limits))?;
+            payload += samples
+                .time("serialize_map", || serde_json::to_vec(&map))
+                .map_err(|e| e.to_string())?
+                .len();
+        }
+        payload_bytes = payload;
+    }
+
+    let run = BenchRun {
+        at: chrono::Local::now().to_rfc3339(),
+        iterations,
+        payload_bytes,
+        phases: samples.stats(),
+    };
+    let history = paths.data_dir.join("bench.json");
+    let previous = load_previous(&history);
+    let change_pct = previous
+        .as_ref()
+        .map(|prev| {
+            run.phases
+                .iter()
+                .filter_map(|(phase, now)| {
+                    let before = prev.phases.get(phase)?.warm_mean_ms;
+                    (before > 0.0)
+                        .then(|| (phase.clone(), (now.warm_mean_ms - before) / before * 100.0))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    save(&history, &run)?;
+    Ok(BenchReport {
+        run,
+        previous,
+        change_pct,
+    })
+}
+
+fn load_previous(path: &Path) -> Option<BenchRun> {
+    let raw = fsutil::read_text(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn save(path: &Path, run: &BenchRun) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(fsutil::long_path(parent))
+            .map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(run).map_err(|e| e.to_string())?;
+    std::fs::write(fsutil::long_path(path), json).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[tauri::command]
+pub fn bench_assets(
+    iterations: Option<u32>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<BenchReport, String> {
+    let paths = paths.lock_or_recover().clone();
+    bench(&paths, iterations.unwrap_or(20))
+}
@@ -0,0 +1,5 @@
+//! `star-pet-ctl`: controls a running pet over its local socket.
+
+fn main() {
+    std::process::exit(star_desktop_pet_lib::run_ctl());
+}
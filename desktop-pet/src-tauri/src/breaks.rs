@@ -0,0 +1,269 @@
+//! Break reminders ("focus guard").
+//!
+//! Input activity is sampled from the OS. Once a work streak, activity
+//! without an idle gap of `break_minutes`, exceeds `work_minutes`, the pet
+//! escalates every `escalate_minutes`: a speech bubble, then an animation,
+//! then a native notification. Taking the break (going idle) or
+//! `snooze_break` resets it. Daily totals land in `stats/breaks.json`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{activity, notify, stats, AppPaths};
+
+pub(crate) const STATS_NAME: &str = "breaks";
+const KEEP_DAYS: usize = 90;
+
+/// `breaks` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct BreakSettings {
+    pub enabled: bool,
+    /// Streak length that triggers the first reminder.
+    pub work_minutes: u32,
+    /// Idle time that counts as a break and ends the streak.
+    pub break_minutes: u32,
+    /// Time between reminder stages.
+    pub escalate_minutes: u32,
+    pub snooze_minutes: u32,
+    pub poll_secs: u32,
+}
+
+impl Default for BreakSettings {
+    fn default() -> Self {
+        BreakSettings {
+            enabled: true,
+            work_minutes: 50,
+            break_minutes: 5,
+            escalate_minutes: 5,
+            snooze_minutes: 10,
+            poll_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    None,
+    Bubble,
+    Animation,
+    Notification,
+}
+
+impl Stage {
+    fn next(self) -> Stage {
+        match self {
+            Stage::None => Stage::Bubble,
+            Stage::Bubble => Stage::Animation,
+            Stage::Animation | Stage::Notification => Stage::Notification,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreakDay {
+    pub(crate) active_secs: u64,
+    pub(crate) longest_streak_mins: u64,
+    pub(crate) reminders: u32,
+    pub(crate) snoozes: u32,
+    pub(crate) breaks: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BreakStats {
+    /// Keyed by local date, `YYYY-MM-DD`.
+    pub(crate) days: BTreeMap<String, BreakDay>,
+}
+
+/// Payload of `break-reminder`; `stage: none` clears the reminder.
+#[derive(Debug, Clone, Serialize)]
+struct Reminder {
+    stage: Stage,
+    streak_minutes: u64,
+    message: Option<String>,
+}
+
+#[derive(Default)]
+struct Guard {
+    streak_start: Option<Instant>,
+    stage: Option<Stage>,
+    next_stage_at: Option<Instant>,
+    snoozed_until: Option<Instant>,
+}
+
+#[derive(Default)]
+pub(crate) struct Breaks(Mutex<Guard>);
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn record(paths: &AppPaths, update: impl FnOnce(&mut BreakDay)) {
+    let mut stats: BreakStats = stats::load(&paths.data_dir, STATS_NAME);
+    update(stats.days.entry(today()).or_default());
+    let excess = stats.days.len().saturating_sub(KEEP_DAYS);
+    let old: Vec<String> = stats.days.keys().take(excess).cloned().collect();
+    for day in old {
+        stats.days.remove(&day);
+    }
+    if let Err(e) = stats::save(&paths.data_dir, STATS_NAME, &stats) {
+        log!("⚠️ break stats: {e}");
+    }
+}
+
+fn minutes(d: Duration) -> u64 {
+    d.as_secs() / 60
+}
+
+fn remind(app: &AppHandle, stage: Stage, streak: Duration) {
+    let i18n = app.state::<I18n>();
+    let mut args = Map::new();
+    args.insert("minutes".into(), minutes(streak).into());
+    let message = i18n.t("breaks.bubble", &args);
+    if stage == Stage::Notification {
+        let title = i18n.t("breaks.title", &args);
+        let body = i18n.t("breaks.body", &args);
+        if let Err(e) = notify::show(&title, &body) {
+            log!("⚠️ {e}");
+        }
+    }
+    log!(
+        "☕ break reminder ({stage:?}) after {} min",
+        minutes(streak)
+    );
+    let _ = app.emit(
+        "break-reminder",
+        Reminder {
+            stage,
+            streak_minutes: minutes(streak),
+            message: Some(message),
+        },
+    );
+}
+
+fn clear(app: &AppHandle) {
+    let _ = app.emit(
+        "break-reminder",
+        Reminder {
+            stage: Stage::None,
+            streak_minutes: 0,
+            message: None,
+        },
+    );
+}
+
+/// Folds in one idle-time sample; returns the stage to announce, if any.
+fn tick(
+    guard: &mut Guard,
+    idle: Duration,
+    cfg: &BreakSettings,
+    paths: &AppPaths,
+) -> Option<(Stage, Duration)> {
+    let now = Instant::now();
+    let poll = Duration::from_secs(u64::from(cfg.poll_secs.max(1)));
+
+    if idle >= Duration::from_secs(u64::from(cfg.break_minutes) * 60) {
+        if let Some(start) = guard.streak_start.take() {
+            let streak = now.saturating_duration_since(start).saturating_sub(idle);
+            let reminded = guard.stage.is_some();
+            record(paths, |day| {
+                day.longest_streak_mins = day.longest_streak_mins.max(minutes(streak));
+                if reminded {
+                    day.breaks += 1;
+                }
+            });
+        }
+        *guard = Guard::default();
+        return None;
+    }
+
+    if idle < poll {
+        record(paths, |day| day.active_secs += poll.as_secs());
+    }
+    let start = *guard.streak_start.get_or_insert(now);
+    let streak = now.saturating_duration_since(start);
+    let snoozed = guard.snoozed_until.is_some_and(|until| now < until);
+    let due = guard.next_stage_at.is_none_or(|at| now >= at);
+    if snoozed || !due || streak < Duration::from_secs(u64::from(cfg.work_minutes) * 60) {
+        return None;
+    }
+    // The notification is the last stage; don't repeat it.
+    if guard.stage == Some(Stage::Notification) {
+        return None;
+    }
+    let stage = guard.stage.map_or(Stage::Bubble, Stage::next);
+    guard.stage = Some(stage);
+    guard.next_stage_at = Some(now + Duration::from_secs(u64::from(cfg.escalate_minutes) * 60));
+    if stage == Stage::Bubble {
+        record(paths, |day| day.reminders += 1);
+    }
+    Some((stage, streak))
+}
+
+pub(crate) fn spawn_guard(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let cfg = app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .breaks
+            .clone();
+        if cfg.enabled {
+            // No answer from the OS: keep the streak as it is.
+            if let Some(idle) = activity::idle_time() {
+                let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+                let (was_reminding, announce) = {
+                    let mut guard = app.state::<Breaks>().0.lock_or_recover();
+                    let was = guard.stage.is_some();
+                    let announce = tick(&mut guard, idle, &cfg, &paths);
+                    (was && guard.stage.is_none(), announce)
+                };
+                if was_reminding {
+                    clear(&app);
+                }
+                if let Some((stage, streak)) = announce {
+                    remind(&app, stage, streak);
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_secs(u64::from(cfg.poll_secs.max(1))));
+    });
+}
+
+/// Puts reminders off for `minutes` (default `breaks.snooze_minutes`).
+/// The streak keeps counting.
+#[tauri::command]
+pub fn snooze_break(
+    app: AppHandle,
+    minutes: Option<u32>,
+    breaks: tauri::State<'_, Breaks>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) {
+    let minutes = minutes.unwrap_or(settings.lock_or_recover().breaks.snooze_minutes);
+    let paths = paths.lock_or_recover().clone();
+    {
+        let mut guard = breaks.0.lock_or_recover();
+        guard.snoozed_until = Some(Instant::now() + Duration::from_secs(u64::from(minutes) * 60));
+        guard.stage = None;
+        guard.next_stage_at = None;
+    }
+    record(&paths, |day| day.snoozes += 1);
+    log!("😴 break snoozed for {minutes} min");
+    clear(&app);
+}
+
+#[tauri::command]
+pub fn get_break_stats(paths: tauri::State<'_, Mutex<AppPaths>>) -> BreakStats {
+    let data_dir = paths.lock_or_recover().data_dir.clone();
+    stats::load(&data_dir, STATS_NAME)
+}
@@ -0,0 +1,149 @@
+//! Speech bubbles sent by the backend, scripts and integrations: a line
+//! of text, an optional icon and up to a few buttons that each run a
+//! backend command when clicked ("Retry build?").
+//!
+//! Bubbles are checked here before the window sees them. Buttons may only
+//! name commands in [`ACTIONS`], ones a single click can safely trigger;
+//! the window invokes them with the given arguments as-is.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter};
+
+use crate::logs::log;
+use crate::states;
+
+const MAX_TEXT_CHARS: usize = 280;
+const MAX_LABEL_CHARS: usize = 32;
+const MAX_ACTIONS: usize = 3;
+const MAX_ARGS_BYTES: usize = 4 * 1024;
+const MAX_DURATION_MS: u32 = 60_000;
+
+/// Commands a bubble button may run.
+pub(crate) const ACTIONS: &[&str] = &[
+    "write_state",
+    "snooze_break",
+    "start_pomodoro",
+    "pause_pomodoro",
+    "skip_pomodoro",
+    "stop_pomodoro",
+    "route_to_poi",
+    "follow_route",
+    "stop_route",
+    "run_maintenance",
+    "send_digest",
+    "open_external_url",
+    "start_build",
+    "stop_build",
+];
+
+/// A button under the text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BubbleAction {
+    pub label: String,
+    /// One of [`ACTIONS`].
+    pub command: String,
+    /// Passed to the command, named as the frontend names them
+    /// (`{ "workMins": 25 }`).
+    #[serde(default)]
+    pub args: Map<String, Value>,
+}
+
+/// Payload of `say` (the command and the event).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bubble {
+    pub text: String,
+    /// A state name (`error`, `executing`, …); its emoji leads the text.
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<BubbleAction>,
+    /// How long it stays up; by default 3.5 s, or 10 s with buttons.
+    #[serde(default)]
+    pub duration_ms: Option<u32>,
+}
+
+fn check_action(action: &mut BubbleAction) -> Result<(), String> {
+    action.label = action.label.trim().to_string();
+    if action.label.is_empty() || action.label.chars().count() > MAX_LABEL_CHARS {
+        return Err(format!(
+            "button labels need 1–{MAX_LABEL_CHARS} characters (`{}`)",
+            action.label
+        ));
+    }
+    if !ACTIONS.contains(&action.command.as_str()) {
+        return Err(format!(
+            "`{}` can't be run from a bubble; use one of: {}",
+            action.command,
+            ACTIONS.join(", ")
+        ));
+    }
+    let size = serde_json::to_vec(&action.args).map_or(usize::MAX, |v| v.len());
+    if size > MAX_ARGS_BYTES {
+        return Err(format!(
+            "arguments of `{}` exceed {MAX_ARGS_BYTES} bytes",
+            action.label
+        ));
+    }
+    if action.command == "open_external_url" {
+        let url = action.args.get("url").and_then(Value::as_str).unwrap_or("");
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err(format!("`{}` must open an http(s) URL", action.label));
+        }
+    }
+    Ok(())
+}
+
+/// Trims `bubble` and checks it against the limits above.
+pub fn validate(bubble: &mut Bubble) -> Result<(), String> {
+    bubble.text = bubble.text.trim().to_string();
+    let chars = bubble.text.chars().count();
+    if chars == 0 || chars > MAX_TEXT_CHARS {
+        return Err(format!("bubble text needs 1–{MAX_TEXT_CHARS} characters"));
+    }
+    if let Some(icon) = &bubble.icon {
+        let known = states::BUILTIN.iter().any(|(name, _)| name == icon)
+            || states::ALIASES.iter().any(|(alias, _)| alias == icon);
+        if !known {
+            return Err(format!("unknown bubble icon `{icon}` (a state name)"));
+        }
+    }
+    if bubble.actions.len() > MAX_ACTIONS {
+        return Err(format!("at most {MAX_ACTIONS} buttons per bubble"));
+    }
+    bubble.actions.iter_mut().try_for_each(check_action)?;
+    if let Some(ms) = bubble.duration_ms {
+        bubble.duration_ms = Some(ms.clamp(1_000, MAX_DURATION_MS));
+    }
+    Ok(())
+}
+
+/// Validates `bubble` and hands it to the window.
+pub(crate) fn show(app: &AppHandle, mut bubble: Bubble) -> Result<Bubble, String> {
+    validate(&mut bubble)?;
+    log!(
+        "💬 {}{}",
+        bubble.text,
+        if bubble.actions.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " [{}]",
+                bubble
+                    .actions
+                    .iter()
+                    .map(|a| a.label.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            )
+        }
+    );
+    app.emit("say", &bubble).map_err(|e| e.to_string())?;
+    Ok(bubble)
+}
+
+/// Shows a bubble over the pet; rejected bubbles come back as errors.
+#[tauri::command]
+pub fn say(app: AppHandle, bubble: Bubble) -> Result<(), String> {
+    show(&app, bubble).map(|_| ())
+}
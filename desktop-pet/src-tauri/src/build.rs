@@ -0,0 +1,742 @@
+//! Build watcher: the pet as a live build indicator without any scripts.
+//!
+//! `build.command` (say `cargo watch -x check` or `npm run dev`) is started
+//! with the app and again whenever it changes; `start_build` runs it, or
+//! another command, on demand. `build.attach` follows a log file some
+//! other process appends to instead. Either way the output is read line by
+//! line and `state.json` follows the build: `building` with `progress`
+//! from the compilation units, then `success` or `failed`.
+//!
+//! The parser knows cargo (including cargo-watch's markers and the
+//! progress bar), tsc, webpack and vite. For other tools, `npm ERR!`-style
+//! failures, leading percentages and the exit code still count.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::bubble::{self, Bubble, BubbleAction};
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths, PetState};
+
+const POLL: Duration = Duration::from_millis(500);
+/// Longest command shown in `detail`.
+const LABEL_CHARS: usize = 40;
+
+/// `build` block of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct BuildSettings {
+    /// Shell command started with the app, e.g. `cargo watch -x check`.
+    pub command: Option<String>,
+    /// Where it runs, relative to the project root; the root by default.
+    pub cwd: Option<String>,
+    /// A log file another process writes build output to.
+    pub attach: Option<String>,
+    pub building_state: String,
+    pub success_state: String,
+    pub failed_state: String,
+    /// Offer to run the command again when it fails.
+    pub retry_bubble: bool,
+}
+
+impl Default for BuildSettings {
+    fn default() -> Self {
+        BuildSettings {
+            command: None,
+            cwd: None,
+            attach: None,
+            building_state: "building".into(),
+            success_state: "success".into(),
+            failed_state: "failed".into(),
+            retry_bubble: true,
+        }
+    }
+}
+
+// ── parsing ──
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildEvent {
+    Started,
+    Progress,
+    Finished { ok: bool },
+}
+
+/// Follows one stream of build output.
+#[derive(Debug, Clone, Default)]
+pub struct BuildParser {
+    pub running: bool,
+    /// Units compiled so far in this build.
+    pub done: u32,
+    /// From cargo's progress bar, when the output has one.
+    pub total: Option<u32>,
+    pub errors: u32,
+    pub warnings: u32,
+    failed: bool,
+    /// Units the last build compiled; the estimate without a total.
+    last_units: Option<u32>,
+    last_ok: Option<bool>,
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The leading number of `text`.
+fn number(text: &str) -> Option<u32> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+/// `42/180` from `Building [=====>    ] 42/180: foo, bar`.
+fn cargo_bar(line: &str) -> Option<(u32, u32)> {
+    let rest = line.strip_prefix("Building [")?;
+    let (_, counts) = rest.split_once("] ")?;
+    let (done, total) = counts.split_once('/')?;
+    Some((number(done)?, number(total)?))
+}
+
+impl BuildParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 0 to 1 while a build runs with a known or estimated size.
+    pub fn progress(&self) -> Option<f64> {
+        if !self.running {
+            return None;
+        }
+        match (self.total, self.last_units) {
+            (Some(total), _) if total > 0 => {
+                Some(f64::from(self.done.min(total)) / f64::from(total))
+            }
+            // An estimate never claims to be done.
+            (None, Some(last)) if last > 0 => {
+                Some((f64::from(self.done) / f64::from(last)).min(0.99))
+            }
+            _ => None,
+        }
+    }
+
+    fn start(&mut self) -> BuildEvent {
+        *self = BuildParser {
+            running: true,
+            last_units: self.last_units,
+            last_ok: self.last_ok,
+            ..BuildParser::default()
+        };
+        BuildEvent::Started
+    }
+
+    fn finish(&mut self, ok: bool) -> Option<BuildEvent> {
+        if !self.running && self.last_ok == Some(ok) {
+            return None;
+        }
+        if self.running && self.done > 0 {
+            self.last_units = Some(self.total.unwrap_or(self.done).max(self.done));
+        }
+        self.running = false;
+        self.last_ok = Some(ok);
+        Some(BuildEvent::Finished { ok })
+    }
+
+    fn unit(&mut self) -> BuildEvent {
+        if !self.running {
+            self.start();
+            self.done = 1;
+            return BuildEvent::Started;
+        }
+        self.done += 1;
+        BuildEvent::Progress
+    }
+
+    /// Feeds one line of output; what changed, if anything.
+    pub fn feed(&mut self, raw: &str) -> Option<BuildEvent> {
+        let clean = strip_ansi(raw);
+        let line = clean.trim();
+        if line.is_empty() {
+            return None;
+        }
+        let lower = line.to_ascii_lowercase();
+
+        // cargo-watch
+        if line.starts_with("[Running ") {
+            return Some(self.start());
+        }
+        if let Some(code) = line.strip_prefix("[Finished running. Exit status: ") {
+            return self.finish(number(code) == Some(0));
+        }
+        if line == "[Command was successful]" {
+            return self.finish(true);
+        }
+        if line.starts_with("[Command exited with ") {
+            return self.finish(false);
+        }
+
+        // cargo
+        if let Some((done, total)) = cargo_bar(line) {
+            let started = !self.running;
+            if started {
+                self.start();
+            }
+            let changed = (self.done, self.total) != (done.max(self.done), Some(total));
+            self.done = self.done.max(done);
+            self.total = Some(total);
+            if started {
+                return Some(BuildEvent::Started);
+            }
+            return changed.then_some(BuildEvent::Progress);
+        }
+        if ["Compiling ", "Checking ", "Documenting "]
+            .iter()
+            .any(|p| line.starts_with(p))
+        {
+            return Some(self.unit());
+        }
+        if line.starts_with("Finished ") && lower.contains("target(s)") {
+            let ok = !self.failed;
+            return self.finish(ok);
+        }
+        if line.starts_with("warning:") && !lower.contains(" generated ") {
+            self.warnings += 1;
+            return None;
+        }
+        if line.starts_with("error[") || line.starts_with("error:") {
+            if !lower.contains("could not compile") && !lower.contains("aborting due to") {
+                self.errors += 1;
+            }
+            self.failed = true;
+            return None;
+        }
+        if line.starts_with("test result: FAILED") {
+            self.failed = true;
+            return None;
+        }
+
+        // tsc --watch
+        if lower.contains("starting compilation") || lower.contains("file change detected") {
+            return Some(self.start());
+        }
+        if let Some((_, rest)) = lower.split_once("found ") {
+            if let (Some(n), true) = (number(rest), rest.contains(" error")) {
+                self.errors = self.errors.max(n);
+                return self.finish(n == 0);
+            }
+        }
+
+        // webpack, vite
+        if lower.contains("compiled successfully") || lower.contains("✓ built in") {
+            return self.finish(true);
+        }
+        if lower.contains("failed to compile")
+            || lower.contains("error during build")
+            || (lower.contains("compiled with") && lower.contains(" error"))
+        {
+            return self.finish(false);
+        }
+        if lower.starts_with("npm err!") || lower.starts_with("npm error") {
+            self.failed = true;
+            return None;
+        }
+        let percent = line
+            .split_once('%')
+            .and_then(|(head, _)| head.trim().parse::<u32>().ok());
+        if let Some(percent) = percent {
+            let event = if self.running {
+                BuildEvent::Progress
+            } else {
+                self.start()
+            };
+            self.done = percent.min(100);
+            self.total = Some(100);
+            return Some(event);
+        }
+        None
+    }
+
+    /// The command ended with `code` (`None` when killed by a signal).
+    pub fn exited(&mut self, code: Option<i32>) -> Option<BuildEvent> {
+        self.finish(code == Some(0) && !(self.running && self.failed))
+    }
+}
+
+// ── status ──
+
+/// Returned by `get_build` and sent as `build-changed`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildStatus {
+    /// `idle`, `building`, `success` or `failed`.
+    phase: &'static str,
+    /// The command being run, if this build has one.
+    command: Option<String>,
+    /// Whether that command is still running.
+    process: bool,
+    attached: Option<PathBuf>,
+    done: u32,
+    total: Option<u32>,
+    progress: Option<f64>,
+    errors: u32,
+    warnings: u32,
+    exit_code: Option<i32>,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+}
+
+struct Runner {
+    /// Bumped on every start and stop; readers of an older one quit.
+    generation: u64,
+    child: Option<Child>,
+    parser: BuildParser,
+    status: BuildStatus,
+    /// Last `progress` written, in whole percent.
+    written: Option<u32>,
+}
+
+/// Managed by the app.
+pub struct Build(Mutex<Runner>);
+
+impl Default for Build {
+    fn default() -> Self {
+        Build(Mutex::new(Runner {
+            generation: 0,
+            child: None,
+            parser: BuildParser::new(),
+            status: BuildStatus {
+                phase: "idle",
+                ..BuildStatus::default()
+            },
+            written: None,
+        }))
+    }
+}
+
+fn label(command: &str) -> String {
+    let mut label: String = command.chars().take(LABEL_CHARS).collect();
+    if command.chars().count() > LABEL_CHARS {
+        label.push('…');
+    }
+    label
+}
+
+fn settings(app: &AppHandle) -> BuildSettings {
+    app.state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .build
+        .clone()
+}
+
+fn write_state(app: &AppHandle, state: &str, detail: String, progress: Option<f64>) {
+    let state_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .state_path
+        .clone();
+    let state = PetState {
+        state: state.to_string(),
+        detail: Some(detail),
+        progress,
+        updated_at: Some(chrono::Local::now().to_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    if let Err(e) = FsStateStore::new(state_path).save(&state) {
+        log!("⚠️ build state: {e}");
+    }
+}
+
+/// Writes the state for `event` and tells the window.
+fn apply(app: &AppHandle, runner: &mut Runner, event: BuildEvent) {
+    let cfg = settings(app);
+    let p = &runner.parser;
+    let now = chrono::Local::now().to_rfc3339();
+    let status = &mut runner.status;
+    (status.done, status.total, status.errors, status.warnings) =
+        (p.done, p.total, p.errors, p.warnings);
+    status.progress = p.progress();
+    let name = status
+        .command
+        .as_deref()
+        .map(label)
+        .or_else(|| status.attached.as_ref().map(|f| f.display().to_string()))
+        .unwrap_or_default();
+    let mut args = Map::new();
+    args.insert("command".into(), name.clone().into());
+    args.insert("errors".into(), p.errors.into());
+    args.insert("warnings".into(), p.warnings.into());
+    let i18n = app.state::<I18n>();
+    match event {
+        BuildEvent::Started | BuildEvent::Progress => {
+            let percent = status.progress.map(|v| (v * 100.0) as u32);
+            if event == BuildEvent::Started {
+                log!("🔨 build started: {name}");
+                status.phase = "building";
+                status.started_at = Some(now);
+                status.finished_at = None;
+                status.exit_code = None;
+            } else if percent == runner.written {
+                return;
+            }
+            runner.written = percent;
+            write_state(
+                app,
+                &cfg.building_state,
+                i18n.t("build.building", &args),
+                status.progress,
+            );
+        }
+        BuildEvent::Finished { ok } => {
+            status.phase = if ok { "success" } else { "failed" };
+            status.finished_at = Some(now);
+            runner.written = None;
+            let (state, key) = if ok {
+                (&cfg.success_state, "build.success")
+            } else {
+                (&cfg.failed_state, "build.failed")
+            };
+            log!(
+                "🔨 build {}: {} errors, {} warnings",
+                status.phase,
+                p.errors,
+                p.warnings
+            );
+            write_state(app, state, i18n.t(key, &args), None);
+        }
+    }
+    let _ = app.emit("build-changed", &runner.status);
+}
+
+fn feed(app: &AppHandle, generation: u64, line: &str) {
+    let build = app.state::<Build>();
+    let mut runner = build.0.lock_or_recover();
+    if runner.generation != generation {
+        return;
+    }
+    if let Some(event) = runner.parser.feed(line) {
+        apply(app, &mut runner, event);
+    }
+}
+
+// ── running ──
+
+/// Sends what `pipe` prints, split at `\n` and `\r` (progress bars).
+fn forward(mut pipe: impl Read + Send + 'static, tx: Sender<String>) {
+    std::thread::spawn(move || {
+        let (mut buf, mut line) = ([0u8; 4096], Vec::new());
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for &b in &buf[..n] {
+                if b == b'\n' || b == b'\r' {
+                    if !line.is_empty()
+                        && tx
+                            .send(String::from_utf8_lossy(&line).into_owned())
+                            .is_err()
+                    {
+                        return;
+                    }
+                    line.clear();
+                } else {
+                    line.push(b);
+                }
+            }
+        }
+        if !line.is_empty() {
+            let _ = tx.send(String::from_utf8_lossy(&line).into_owned());
+        }
+    });
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let cmd = {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command]);
+        c
+    };
+    #[cfg(not(windows))]
+    let cmd = {
+        use std::os::unix::process::CommandExt;
+        let mut c = Command::new("sh");
+        c.args(["-c", command]);
+        // Its own group, so stopping it reaches what it started too.
+        c.process_group(0);
+        c
+    };
+    cmd
+}
+
+fn kill(child: &mut Child) {
+    let pid = child.id().to_string();
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid])
+        .output();
+    #[cfg(not(windows))]
+    let _ = Command::new("kill")
+        .args(["-TERM", &format!("-{pid}")])
+        .output();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn supervise(app: AppHandle, generation: u64, rx: Receiver<String>) {
+    std::thread::spawn(move || loop {
+        match rx.recv_timeout(POLL) {
+            Ok(line) => feed(&app, generation, &line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if app.state::<Build>().0.lock_or_recover().generation != generation {
+            return;
+        }
+    });
+}
+
+fn wait_for_exit(app: AppHandle, generation: u64) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL);
+        let build = app.state::<Build>();
+        let mut runner = build.0.lock_or_recover();
+        if runner.generation != generation {
+            return;
+        }
+        let Some(child) = runner.child.as_mut() else {
+            return;
+        };
+        let code = match child.try_wait() {
+            Ok(None) => continue,
+            Ok(Some(status)) => status.code(),
+            Err(e) => {
+                log!("⚠️ build: {e}");
+                None
+            }
+        };
+        runner.child = None;
+        runner.status.process = false;
+        runner.status.exit_code = code;
+        log!(
+            "🔨 build command ended ({})",
+            code.map_or("signal".into(), |c| c.to_string())
+        );
+        if let Some(event) = runner.parser.exited(code) {
+            apply(&app, &mut runner, event);
+        } else {
+            let _ = app.emit("build-changed", &runner.status);
+        }
+        let cfg = settings(&app);
+        if code != Some(0) && cfg.retry_bubble {
+            drop(runner);
+            offer_retry(&app);
+        }
+        return;
+    });
+}
+
+fn offer_retry(app: &AppHandle) {
+    let i18n = app.state::<I18n>();
+    let errors = app.state::<Build>().0.lock_or_recover().parser.errors;
+    let mut args = Map::new();
+    args.insert("errors".into(), errors.into());
+    let bubble = Bubble {
+        text: i18n.t("build.retry_bubble", &args),
+        icon: Some("error".into()),
+        actions: vec![BubbleAction {
+            label: i18n.t("build.retry", &Map::new()),
+            command: "start_build".into(),
+            args: Map::new(),
+        }],
+        duration_ms: None,
+    };
+    if let Err(e) = bubble::show(app, bubble) {
+        log!("⚠️ build bubble: {e}");
+    }
+}
+
+/// Stops whatever runs now and starts `command`.
+fn launch(app: &AppHandle, command: &str, cwd: Option<&str>) -> Result<(), String> {
+    let root = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .root
+        .clone();
+    let dir = cwd.map_or(root.clone(), |c| root.join(c));
+    let mut cmd = shell(command);
+    cmd.current_dir(fsutil::long_path(&dir))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Cargo only draws its progress bar, where the unit count comes
+        // from, on a terminal unless asked to.
+        .env("CARGO_TERM_PROGRESS_WHEN", "always")
+        .env("CARGO_TERM_PROGRESS_WIDTH", "100")
+        .env("CARGO_TERM_COLOR", "never");
+    let mut child = cmd.spawn().map_err(|e| format!("{command}: {e}"))?;
+    let (tx, rx) = mpsc::channel();
+    if let Some(out) = child.stdout.take() {
+        forward(out, tx.clone());
+    }
+    if let Some(err) = child.stderr.take() {
+        forward(err, tx);
+    }
+
+    let build = app.state::<Build>();
+    let mut runner = build.0.lock_or_recover();
+    if let Some(mut old) = runner.child.take() {
+        kill(&mut old);
+    }
+    runner.generation += 1;
+    runner.child = Some(child);
+    runner.status.command = Some(command.to_string());
+    runner.status.process = true;
+    runner.status.exit_code = None;
+    let generation = runner.generation;
+    drop(runner);
+    log!("🔨 running `{command}` in {}", dir.display());
+    supervise(app.clone(), generation, rx);
+    wait_for_exit(app.clone(), generation);
+    Ok(())
+}
+
+pub(crate) fn stop(app: &AppHandle) {
+    let build = app.state::<Build>();
+    let mut runner = build.0.lock_or_recover();
+    runner.generation += 1;
+    if let Some(mut child) = runner.child.take() {
+        log!("🔨 stopping the build command");
+        kill(&mut child);
+        runner.status.process = false;
+    }
+}
+
+/// Starts `build.command` with the app and when it changes, and follows
+/// `build.attach`.
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut command: Option<(Option<String>, Option<String>)> = None;
+        let mut tail: Option<(PathBuf, u64, Vec<u8>)> = None;
+        loop {
+            let cfg = settings(&app);
+            let wanted = (cfg.command.clone(), cfg.cwd.clone());
+            if command.as_ref() != Some(&wanted) {
+                match &cfg.command {
+                    Some(c) if !c.trim().is_empty() => {
+                        if let Err(e) = launch(&app, c, cfg.cwd.as_deref()) {
+                            log!("⚠️ build: {e}");
+                        }
+                    }
+                    _ if command.is_some() => stop(&app),
+                    _ => {}
+                }
+                command = Some(wanted);
+            }
+
+            let root = app
+                .state::<Mutex<AppPaths>>()
+                .lock_or_recover()
+                .root
+                .clone();
+            let attach = cfg
+                .attach
+                .as_deref()
+                .filter(|a| !a.is_empty())
+                .map(|a| root.join(a));
+            if tail.as_ref().map(|(p, _, _)| p) != attach.as_ref() {
+                // Only what is written from now on counts.
+                tail = attach.map(|p| {
+                    let len = std::fs::metadata(fsutil::long_path(&p)).map_or(0, |m| m.len());
+                    log!("🔨 following {}", p.display());
+                    (p, len, Vec::new())
+                });
+                let build = app.state::<Build>();
+                build.0.lock_or_recover().status.attached =
+                    tail.as_ref().map(|(p, _, _)| p.clone());
+            }
+            if let Some((path, offset, partial)) = tail.as_mut() {
+                for line in read_new(path, offset, partial) {
+                    let generation = app.state::<Build>().0.lock_or_recover().generation;
+                    feed(&app, generation, &line);
+                }
+            }
+            std::thread::sleep(POLL);
+        }
+    });
+}
+
+/// Complete lines appended to `path` since `offset`; starts over when the
+/// file shrank (rotated or truncated).
+fn read_new(path: &std::path::Path, offset: &mut u64, partial: &mut Vec<u8>) -> Vec<String> {
+    let Ok(mut file) = std::fs::File::open(fsutil::long_path(path)) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map_or(0, |m| m.len());
+    if len < *offset {
+        *offset = 0;
+        partial.clear();
+    }
+    let mut bytes = Vec::new();
+    if file.seek(SeekFrom::Start(*offset)).is_err() || file.read_to_end(&mut bytes).is_err() {
+        return Vec::new();
+    }
+    *offset += bytes.len() as u64;
+    partial.extend(bytes);
+    let Some(last) = partial.iter().rposition(|&b| b == b'\n' || b == b'\r') else {
+        return Vec::new();
+    };
+    let complete: Vec<u8> = partial.drain(..=last).collect();
+    String::from_utf8_lossy(&complete)
+        .split(['\n', '\r'])
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Runs `command`, or `build.command`, replacing the one running now.
+#[tauri::command]
+pub fn start_build(app: AppHandle, command: Option<String>) -> Result<(), String> {
+    let cfg = settings(&app);
+    let command = command
+        .filter(|c| !c.trim().is_empty())
+        .or(cfg.command)
+        .ok_or("no command given and `build.command` is not set")?;
+    launch(&app, &command, cfg.cwd.as_deref())
+}
+
+#[tauri::command]
+pub fn stop_build(app: AppHandle) {
+    stop(&app);
+    let _ = app.emit("build-changed", get_build(app.state()));
+}
+
+#[tauri::command]
+pub fn get_build(build: tauri::State<'_, Build>) -> BuildStatus {
+    build.0.lock_or_recover().status.clone()
+}
@@ -0,0 +1,204 @@
+//! Imports a pack bundle: a zip holding `layers.json` and its assets,
+//! either at the top level or inside a single folder.
+//!
+//! The bundle is unpacked into `.star-pet/staging/`, validated like
+//! `--validate` would, and only then swapped in for `layers/`. The previous
+//! `layers/` is kept under `.star-pet/backups/`.
+
+use serde::Serialize;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::jobs::{self, Job};
+use crate::limits::{self, Limits};
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::validate::{self, Level};
+use crate::{fsutil, settings, watch, AppPaths};
+
+/// More entries than any real pack needs; stops zip bombs made of tiny files.
+const MAX_ENTRIES: usize = 4096;
+
+#[derive(Debug, Serialize)]
+pub struct Imported {
+    name: String,
+    files: usize,
+    backup: Option<PathBuf>,
+    warnings: usize,
+}
+
+/// Folder inside the zip that holds `layers.json`: the shallowest one.
+fn pack_prefix<R: Read + std::io::Seek>(zip: &zip::ZipArchive<R>) -> Result<PathBuf, String> {
+    zip.file_names()
+        .filter(|n| n.rsplit('/').next() == Some("layers.json"))
+        .map(|n| {
+            Path::new(n)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        })
+        .min_by_key(|p| p.components().count())
+        .ok_or_else(|| "bundle has no layers.json".to_string())
+}
+
+fn extract(bytes: &[u8], dest: &Path, limits: &Limits, job: &Job) -> Result<usize, String> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("bundle: {e}"))?;
+    if zip.len() > MAX_ENTRIES {
+        return Err(format!(
+            "bundle has {} entries, limit is {MAX_ENTRIES}",
+            zip.len()
+        ));
+    }
+    let prefix = pack_prefix(&zip)?;
+    let mut written = 0;
+    for i in 0..zip.len() {
+        job.check()?;
+        job.progress(0.8 * i as f64 / zip.len() as f64, "unpacking");
+        let mut entry = zip.by_index(i).map_err(|e| format!("bundle: {e}"))?;
+        // `enclosed_name` rejects absolute paths and `..`.
+        let Some(name) = entry.enclosed_name() else {
+            return Err(format!("bundle: unsafe path `{}`", entry.name()));
+        };
+        let Ok(rel) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        if entry.is_dir() || rel.as_os_str().is_empty() {
+            continue;
+        }
+        let rel_name = rel.display().to_string();
+        limits::check_size(
+            &rel_name,
+            entry.size(),
+            limits.max_asset_bytes,
+            "max_asset_bytes",
+        )?;
+        let path = dest.join(rel);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(fsutil::long_path(parent))
+                .map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry
+            .by_ref()
+            .take(limits.max_asset_bytes + 1)
+            .read_to_end(&mut data)
+            .map_err(|e| format!("bundle {rel_name}: {e}"))?;
+        limits::check_size(
+            &rel_name,
+            data.len() as u64,
+            limits.max_asset_bytes,
+            "max_asset_bytes",
+        )?;
+        std::fs::write(fsutil::long_path(&path), data)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Unpacks, validates and installs a bundle as the active `layers/`.
+pub(crate) fn import(
+    paths: &AppPaths,
+    name: &str,
+    bytes: &[u8],
+    job: &Job,
+) -> Result<Imported, String> {
+    let limits = settings::load(&paths.settings_path).limits;
+    limits::check_size(
+        name,
+        bytes.len() as u64,
+        limits.max_bundle_bytes,
+        "max_bundle_bytes",
+    )?;
+
+    let staging = paths.data_dir.join("staging");
+    if fsutil::exists(&staging) {
+        std::fs::remove_dir_all(fsutil::long_path(&staging))
+            .map_err(|e| format!("{}: {e}", staging.display()))?;
+    }
+    let staged_layers = staging.join("layers");
+    let files = extract(bytes, &staged_layers, &limits, job)?;
+
+    job.progress(0.85, "validating");
+
+    let report = validate::validate_root(&staging);
+    if !report.ok {
+        let errors: Vec<String> = report
+            .issues
+            .iter()
+            .filter(|i| i.level == Level::Error)
+            .map(|i| format!("{}: {}", i.file, i.message))
+            .collect();
+        return Err(format!("{name} failed validation: {}", errors.join("; ")));
+    }
+    // Past here the old `layers/` is moved away.
+    job.check()?;
+    job.progress(0.95, "installing");
+
+    let backup = if fsutil::exists(&paths.layers_dir) {
+        let backup = paths.data_dir.join("backups").join(format!(
+            "layers-{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        if let Some(parent) = backup.parent() {
+            std::fs::create_dir_all(fsutil::long_path(parent))
+                .map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        std::fs::rename(
+            fsutil::long_path(&paths.layers_dir),
+            fsutil::long_path(&backup),
+        )
+        .map_err(|e| format!("{}: {e}", paths.layers_dir.display()))?;
+        Some(backup)
+    } else {
+        None
+    };
+    std::fs::rename(
+        fsutil::long_path(&staged_layers),
+        fsutil::long_path(&paths.layers_dir),
+    )
+    .map_err(|e| format!("{}: {e}", paths.layers_dir.display()))?;
+    let _ = std::fs::remove_dir_all(fsutil::long_path(&staging));
+
+    log!("📦 installed {name} ({files} files)");
+    Ok(Imported {
+        name: name.to_string(),
+        files,
+        backup,
+        warnings: report.warnings,
+    })
+}
+
+/// The old `layers/` was moved away under the watcher, so re-arm it and
+/// tell the frontend to reload everything.
+pub(crate) fn activated(app: &AppHandle) {
+    watch::restart(app);
+    let _ = watch::layers_changed(app);
+    let _ = watch::map_changed(app);
+}
+
+/// Runs as an `import_bundle` job (see [`crate::jobs`]).
+#[tauri::command]
+pub async fn import_bundle(
+    app: AppHandle,
+    path: String,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<Imported, String> {
+    let paths = paths.lock_or_recover().clone();
+    let path = PathBuf::from(path);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let handle = app.clone();
+    jobs::run(&app, "import_bundle", name.clone(), move |job| {
+        job.progress(0.0, "reading");
+        let bytes = fsutil::read_bytes(&path)?;
+        let imported = import(&paths, &name, &bytes, job)?;
+        activated(&handle);
+        Ok(imported)
+    })
+    .await
+}
@@ -0,0 +1,934 @@
+//! Schedule-driven states from iCalendar (`.ics`) files and feeds.
+//!
+//! Every `calendar.refresh_minutes` the sources in settings are read (files
+//! relative to the project root, `http(s)://` or `webcal://` URLs in builds
+//! with network support) and their events expanded around now. While a
+//! busy, timed event runs the pet is in `calendar.state`; the state before
+//! it comes back afterwards unless something else wrote one in between.
+//! `meeting-starting-soon` goes out `warn_minutes` before each event.
+//!
+//! The parser covers what calendar apps export for meetings: `DTSTART`
+//! with UTC, `TZID` or floating times (read in the `clock` zone), `DTEND`
+//! or `DURATION`, `RRULE` with `DAILY`/`WEEKLY`/`MONTHLY`/`YEARLY`,
+//! `INTERVAL`, `COUNT`, `UNTIL` and `BYDAY` (`1MO`, `-1FR` in monthly
+//! rules), `EXDATE`, and `RECURRENCE-ID` overrides. Cancelled events are
+//! dropped; free (`TRANSP:TRANSPARENT`) and all-day ones are listed but
+//! don't change the state.
+
+use chrono::{
+    DateTime, Datelike, Duration as Span, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{clock, fsutil, health, net, notify, power, AppPaths, PetState};
+
+const POLL: Duration = Duration::from_secs(30);
+const MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// Upper bound on recurrence periods walked per event.
+const MAX_PERIODS: i64 = 50_000;
+/// How far ahead `get_calendar` lists events.
+const LOOKAHEAD_HOURS: i64 = 24;
+
+/// `calendar` block of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CalendarSettings {
+    pub enabled: bool,
+    /// `.ics` files (relative to the project root) or `http(s)://` and
+    /// `webcal://` URLs.
+    pub sources: Vec<String>,
+    /// Written to `state.json` during events.
+    pub state: String,
+    /// Lead time of `meeting-starting-soon`.
+    pub warn_minutes: u32,
+    pub refresh_minutes: u32,
+    /// Also show a native notification with the warning.
+    pub notify: bool,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        CalendarSettings {
+            enabled: true,
+            sources: Vec::new(),
+            state: "meeting".into(),
+            warn_minutes: 5,
+            refresh_minutes: 15,
+            notify: false,
+        }
+    }
+}
+
+// ── parsing ──
+
+/// A wall-clock time in the zone it was written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stamp {
+    local: NaiveDateTime,
+    tz: Tz,
+}
+
+impl Stamp {
+    /// The earlier of a repeated hour; a time in a skipped hour moves past
+    /// the gap.
+    fn utc(&self) -> Option<DateTime<Utc>> {
+        self.tz
+            .from_local_datetime(&self.local)
+            .earliest()
+            .or_else(|| {
+                self.tz
+                    .from_local_datetime(&(self.local + Span::hours(1)))
+                    .earliest()
+            })
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    freq: Freq,
+    interval: i64,
+    count: Option<u32>,
+    until: Option<DateTime<Utc>>,
+    /// Weekday with its ordinal within the month, if any (`-1FR`).
+    by_day: Vec<(Option<i64>, Weekday)>,
+}
+
+/// A `VEVENT` as written, before recurrences are expanded.
+#[derive(Debug, Clone)]
+pub struct Event {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: Stamp,
+    end: Option<Stamp>,
+    duration: Option<Span>,
+    all_day: bool,
+    busy: bool,
+    cancelled: bool,
+    rule: Option<Rule>,
+    exdates: Vec<DateTime<Utc>>,
+    recurrence_id: Option<DateTime<Utc>>,
+}
+
+/// One concrete run of an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub uid: String,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub all_day: bool,
+    pub busy: bool,
+}
+
+impl Occurrence {
+    /// Whether it should put the pet in the meeting state.
+    fn blocks(&self) -> bool {
+        self.busy && !self.all_day && self.end > self.start
+    }
+}
+
+/// Joins folded lines (a leading space or tab continues the line before).
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+/// `NAME;PARAM=VALUE:value` → upper-case name, parameters and value.
+fn split_line(line: &str) -> Option<(String, Vec<(String, String)>, &str)> {
+    let mut quoted = false;
+    let (colon, _) = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    })?;
+    let mut head = line[..colon].split(';');
+    let name = head.next()?.trim().to_ascii_uppercase();
+    let params = head
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| {
+            (
+                k.trim().to_ascii_uppercase(),
+                v.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some((name, params, &line[colon + 1..]))
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A date or date-time value; the flag is set for plain dates.
+fn parse_stamp(
+    value: &str,
+    params: &[(String, String)],
+    floating: Tz,
+) -> Result<(Stamp, bool), String> {
+    let value = value.trim();
+    if param(params, "VALUE") == Some("DATE") || !value.contains('T') {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map_err(|e| format!("date `{value}`: {e}"))?;
+        let local = date.and_hms_opt(0, 0, 0).expect("midnight");
+        return Ok((
+            Stamp {
+                local,
+                tz: floating,
+            },
+            true,
+        ));
+    }
+    let (raw, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(raw) => (raw, true),
+        None => (value, false),
+    };
+    let local = NaiveDateTime::parse_from_str(raw, "%Y%m%dT%H%M%S")
+        .map_err(|e| format!("date-time `{value}`: {e}"))?;
+    let tz = if utc {
+        Tz::UTC
+    } else {
+        // Zones that aren't IANA names (Outlook's "W. Europe Standard
+        // Time") fall back to the clock zone.
+        param(params, "TZID")
+            .and_then(|id| id.trim_start_matches('/').parse().ok())
+            .unwrap_or(floating)
+    };
+    Ok((Stamp { local, tz }, false))
+}
+
+/// `P1D`, `PT1H30M`, `-PT15M`, `P2W`.
+fn parse_duration(raw: &str) -> Option<Span> {
+    let raw = raw.trim();
+    let (sign, rest) = match raw.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, raw.strip_prefix('+').unwrap_or(raw)),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let (mut secs, mut num, mut in_time) = (0i64, String::new(), false);
+    for c in rest.chars() {
+        let unit = match (c, in_time) {
+            ('0'..='9', _) => {
+                num.push(c);
+                continue;
+            }
+            ('T', false) => {
+                in_time = true;
+                continue;
+            }
+            ('W', false) => 7 * 86_400,
+            ('D', false) => 86_400,
+            ('H', true) => 3_600,
+            ('M', true) => 60,
+            ('S', true) => 1,
+            _ => return None,
+        };
+        secs += num.parse::<i64>().ok()? * unit;
+        num.clear();
+    }
+    num.is_empty().then(|| Span::seconds(sign * secs))
+}
+
+fn parse_weekday(raw: &str) -> Option<Weekday> {
+    Some(match raw {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn parse_rule(raw: &str, floating: Tz) -> Result<Rule, String> {
+    let mut rule = Rule {
+        freq: Freq::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+    };
+    let mut freq = None;
+    for part in raw.split(';').filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("RRULE part `{part}`"))?;
+        let value = value.trim().to_ascii_uppercase();
+        match key.trim().to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                freq = Some(match value.as_str() {
+                    "DAILY" => Freq::Daily,
+                    "WEEKLY" => Freq::Weekly,
+                    "MONTHLY" => Freq::Monthly,
+                    "YEARLY" => Freq::Yearly,
+                    other => return Err(format!("RRULE FREQ={other} is not supported")),
+                })
+            }
+            "INTERVAL" => {
+                rule.interval = value
+                    .parse::<i64>()
+                    .map_err(|_| format!("RRULE INTERVAL={value}"))?
+                    .max(1)
+            }
+            "COUNT" => {
+                rule.count = Some(value.parse().map_err(|_| format!("RRULE COUNT={value}"))?)
+            }
+            "UNTIL" => rule.until = parse_stamp(&value, &[], floating)?.0.utc(),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let bad = || format!("RRULE BYDAY={day}");
+                    // Feeds aren't always ASCII; a split inside a character is just invalid.
+                    let split = day.len().saturating_sub(2);
+                    let (Some(ordinal), Some(code)) = (day.get(..split), day.get(split..)) else {
+                        return Err(bad());
+                    };
+                    let weekday = parse_weekday(code).ok_or_else(bad)?;
+                    let ordinal = match ordinal {
+                        "" => None,
+                        n => Some(n.parse::<i64>().map_err(|_| bad())?),
+                    };
+                    rule.by_day.push((ordinal, weekday));
+                }
+            }
+            "WKST" => {}
+            other => return Err(format!("RRULE {other} is not supported")),
+        }
+    }
+    rule.freq = freq.ok_or_else(|| "RRULE without FREQ".to_string())?;
+    Ok(rule)
+}
+
+/// Events of every `VEVENT` in `text`; floating times are read in
+/// `floating`. A recurrence rule the parser doesn't cover leaves that
+/// event with its first occurrence only.
+pub fn parse(text: &str, floating: Tz) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    // Components nested in the event, such as `VALARM`.
+    let mut nested = 0usize;
+    for line in unfold(text) {
+        let Some((name, params, value)) = split_line(&line) else {
+            continue;
+        };
+        match (name.as_str(), value.trim().to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => {
+                current = Some(Event {
+                    uid: String::new(),
+                    summary: String::new(),
+                    location: None,
+                    start: Stamp {
+                        local: NaiveDateTime::default(),
+                        tz: floating,
+                    },
+                    end: None,
+                    duration: None,
+                    all_day: false,
+                    busy: true,
+                    cancelled: false,
+                    rule: None,
+                    exdates: Vec::new(),
+                    recurrence_id: None,
+                });
+                nested = 0;
+                continue;
+            }
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", "VEVENT") => {
+                if let Some(event) = current.take() {
+                    if event.start.local != NaiveDateTime::default() {
+                        events.push(event);
+                    }
+                }
+                continue;
+            }
+            ("END", _) if current.is_some() => nested = nested.saturating_sub(1),
+            _ => {}
+        }
+        let Some(event) = current.as_mut() else {
+            continue;
+        };
+        if nested > 0 {
+            continue;
+        }
+        match name.as_str() {
+            "UID" => event.uid = value.trim().to_string(),
+            "SUMMARY" => event.summary = unescape(value),
+            "LOCATION" => event.location = Some(unescape(value)).filter(|l| !l.is_empty()),
+            "DTSTART" => {
+                let (start, all_day) = parse_stamp(value, &params, floating)?;
+                event.start = start;
+                event.all_day = all_day;
+            }
+            "DTEND" => event.end = Some(parse_stamp(value, &params, floating)?.0),
+            "DURATION" => event.duration = parse_duration(value),
+            "RRULE" => match parse_rule(value, floating) {
+                Ok(rule) => event.rule = Some(rule),
+                Err(e) => log!("⚠️ calendar: {e} (event `{}`)", event.summary),
+            },
+            "EXDATE" => {
+                for raw in value.split(',') {
+                    if let Some(at) = parse_stamp(raw, &params, floating)?.0.utc() {
+                        event.exdates.push(at);
+                    }
+                }
+            }
+            "RECURRENCE-ID" => {
+                event.recurrence_id = parse_stamp(value, &params, floating)?.0.utc();
+            }
+            "STATUS" => event.cancelled = value.trim().eq_ignore_ascii_case("CANCELLED"),
+            "TRANSP" => event.busy = !value.trim().eq_ignore_ascii_case("TRANSPARENT"),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+// ── recurrence ──
+
+fn add_months(date: NaiveDate, months: i64) -> (i32, u32) {
+    let total = i64::from(date.year()) * 12 + i64::from(date.month0()) + months;
+    (total.div_euclid(12) as i32, total.rem_euclid(12) as u32 + 1)
+}
+
+/// Days of `weekday` in a month; `ordinal` picks one, counting from the
+/// end when negative.
+fn weekdays_in_month(
+    year: i32,
+    month: u32,
+    weekday: Weekday,
+    ordinal: Option<i64>,
+) -> Vec<NaiveDate> {
+    let all: Vec<NaiveDate> = (1..=31)
+        .filter_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .filter(|d| d.weekday() == weekday)
+        .collect();
+    match ordinal {
+        None => all,
+        Some(n) if n > 0 => all.get(n as usize - 1).copied().into_iter().collect(),
+        Some(n) => all
+            .len()
+            .checked_sub(n.unsigned_abs() as usize)
+            .and_then(|i| all.get(i).copied())
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Candidate days of the `period`th period after the one holding `day0`.
+fn period_days(rule: &Rule, day0: NaiveDate, period: i64) -> Vec<NaiveDate> {
+    let step = period * rule.interval;
+    let mut days = match rule.freq {
+        Freq::Daily => vec![day0 + Span::days(step)],
+        Freq::Weekly => {
+            let monday = day0 - Span::days(i64::from(day0.weekday().num_days_from_monday()));
+            let week = monday + Span::days(step * 7);
+            let weekdays: Vec<Weekday> = if rule.by_day.is_empty() {
+                vec![day0.weekday()]
+            } else {
+                rule.by_day.iter().map(|(_, w)| *w).collect()
+            };
+            weekdays
+                .into_iter()
+                .map(|w| week + Span::days(i64::from(w.num_days_from_monday())))
+                .collect()
+        }
+        Freq::Monthly => {
+            let (year, month) = add_months(day0, step);
+            if rule.by_day.is_empty() {
+                NaiveDate::from_ymd_opt(year, month, day0.day())
+                    .into_iter()
+                    .collect()
+            } else {
+                rule.by_day
+                    .iter()
+                    .flat_map(|&(n, w)| weekdays_in_month(year, month, w, n))
+                    .collect()
+            }
+        }
+        Freq::Yearly => i32::try_from(step)
+            .ok()
+            .and_then(|years| {
+                NaiveDate::from_ymd_opt(day0.year() + years, day0.month(), day0.day())
+            })
+            .into_iter()
+            .collect(),
+    };
+    days.sort();
+    days.dedup();
+    days
+}
+
+/// Starts of `event` overlapping `from..to`, given its length.
+fn starts(event: &Event, len: Span, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let Some(first) = event.start.utc() else {
+        return Vec::new();
+    };
+    let overlaps = |at: DateTime<Utc>| at < to && at + len.max(Span::seconds(1)) > from;
+    let Some(rule) = &event.rule else {
+        return if overlaps(first) {
+            vec![first]
+        } else {
+            Vec::new()
+        };
+    };
+    let (day0, time) = (event.start.local.date(), event.start.local.time());
+    let mut out = Vec::new();
+    let mut count = 0u32;
+    for period in 0..MAX_PERIODS {
+        for day in period_days(rule, day0, period) {
+            if day < day0 {
+                continue;
+            }
+            let Some(at) = (Stamp {
+                local: day.and_time(time),
+                tz: event.start.tz,
+            })
+            .utc() else {
+                continue;
+            };
+            let past_until = rule.until.is_some_and(|until| at > until);
+            let past_count = rule.count.is_some_and(|c| count >= c);
+            if at >= to || past_until || past_count {
+                return out;
+            }
+            count += 1;
+            if overlaps(at) {
+                out.push(at);
+            }
+        }
+    }
+    out
+}
+
+/// Every occurrence overlapping `from..to`, earliest first.
+pub fn occurrences(events: &[Event], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Occurrence> {
+    let moved: HashSet<(&str, DateTime<Utc>)> = events
+        .iter()
+        .filter_map(|e| Some((e.uid.as_str(), e.recurrence_id?)))
+        .collect();
+    let mut out = Vec::new();
+    for event in events.iter().filter(|e| !e.cancelled) {
+        let Some(first) = event.start.utc() else {
+            continue;
+        };
+        let len = match (event.end.and_then(|end| end.utc()), event.duration) {
+            (Some(end), _) => end - first,
+            (None, Some(duration)) => duration,
+            (None, None) if event.all_day => Span::days(1),
+            (None, None) => Span::zero(),
+        }
+        .max(Span::zero());
+        for at in starts(event, len, from, to) {
+            let replaced = event.recurrence_id.is_none()
+                && (moved.contains(&(event.uid.as_str(), at)) || event.exdates.contains(&at));
+            if replaced {
+                continue;
+            }
+            out.push(Occurrence {
+                uid: event.uid.clone(),
+                summary: event.summary.clone(),
+                location: event.location.clone(),
+                start: at,
+                end: at + len,
+                all_day: event.all_day,
+                busy: event.busy,
+            });
+        }
+    }
+    out.sort_by(|a, b| {
+        a.start
+            .cmp(&b.start)
+            .then_with(|| a.summary.cmp(&b.summary))
+    });
+    out
+}
+
+// ── app side ──
+
+/// An occurrence as the window sees it, times in the `clock` zone.
+#[derive(Debug, Clone, Serialize)]
+pub struct Meeting {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: String,
+    end: String,
+    all_day: bool,
+    busy: bool,
+}
+
+fn meeting(o: &Occurrence, tz: Tz) -> Meeting {
+    Meeting {
+        uid: o.uid.clone(),
+        summary: o.summary.clone(),
+        location: o.location.clone(),
+        start: o.start.with_timezone(&tz).to_rfc3339(),
+        end: o.end.with_timezone(&tz).to_rfc3339(),
+        all_day: o.all_day,
+        busy: o.busy,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStatus {
+    /// URLs are cut to their host, as they often carry a private token.
+    source: String,
+    events: usize,
+    error: Option<String>,
+}
+
+/// Returned by `get_calendar`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarInfo {
+    sources: Vec<SourceStatus>,
+    loaded_at: Option<String>,
+    current: Option<Meeting>,
+    upcoming: Vec<Meeting>,
+}
+
+/// Payload of `meeting-starting-soon`.
+#[derive(Debug, Clone, Serialize)]
+struct StartingSoon {
+    #[serde(flatten)]
+    meeting: Meeting,
+    minutes: i64,
+    message: String,
+}
+
+/// Payload of `meeting-changed`.
+#[derive(Debug, Clone, Serialize)]
+struct MeetingChanged {
+    active: bool,
+    meeting: Option<Meeting>,
+}
+
+#[derive(Default)]
+struct Feed {
+    sources: Vec<String>,
+    events: Vec<Event>,
+    status: Vec<SourceStatus>,
+    loaded: Option<Instant>,
+    loaded_at: Option<String>,
+}
+
+/// Managed by the app: the parsed sources.
+#[derive(Default)]
+pub struct Calendar(Mutex<Feed>);
+
+/// `https://host/…` for display; files as they are.
+fn redact(source: &str) -> String {
+    match source.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split(['/', '?']).next().unwrap_or(rest);
+            format!("{scheme}://{host}/…")
+        }
+        None => source.to_string(),
+    }
+}
+
+fn fetch(source: &str, root: &Path) -> Result<String, String> {
+    let lower = source.to_ascii_lowercase();
+    let bytes = if lower.starts_with("webcal://") {
+        net::get(
+            &format!("https://{}", &source["webcal://".len()..]),
+            MAX_BYTES,
+        )
+    } else if lower.starts_with("http://") || lower.starts_with("https://") {
+        net::get(source, MAX_BYTES)
+    } else {
+        fsutil::read_bytes(&root.join(source))
+    }
+    .map_err(|e| e.replace(source, &redact(source)))?;
+    fsutil::decode_text(&bytes)
+}
+
+fn load(feed: &mut Feed, cfg: &CalendarSettings, root: &Path, floating: Tz) {
+    feed.events.clear();
+    feed.status.clear();
+    for source in &cfg.sources {
+        let parsed = fetch(source, root).and_then(|text| parse(&text, floating));
+        let shown = redact(source);
+        match parsed {
+            Ok(events) => {
+                feed.status.push(SourceStatus {
+                    source: shown,
+                    events: events.len(),
+                    error: None,
+                });
+                feed.events.extend(events);
+            }
+            Err(e) => {
+                log!("⚠️ calendar {shown}: {e}");
+                feed.status.push(SourceStatus {
+                    source: shown,
+                    events: 0,
+                    error: Some(e),
+                });
+            }
+        }
+    }
+    feed.sources = cfg.sources.clone();
+    feed.loaded = Some(Instant::now());
+    feed.loaded_at = Some(chrono::Local::now().to_rfc3339());
+}
+
+fn state_store(app: &AppHandle) -> FsStateStore {
+    let state_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .state_path
+        .clone();
+    FsStateStore::new(state_path)
+}
+
+fn write_meeting(store: &FsStateStore, cfg: &CalendarSettings, o: &Occurrence) {
+    let state = PetState {
+        state: cfg.state.clone(),
+        detail: Some(o.summary.clone()).filter(|s| !s.is_empty()),
+        progress: None,
+        updated_at: Some(chrono::Local::now().to_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    if let Err(e) = store.save(&state) {
+        log!("⚠️ meeting state: {e}");
+    }
+}
+
+/// The state before the first meeting and the meeting now shown.
+struct Active {
+    previous: Option<PetState>,
+    key: (String, DateTime<Utc>),
+}
+
+fn warn(app: &AppHandle, cfg: &CalendarSettings, o: &Occurrence, tz: Tz, now: DateTime<Utc>) {
+    let minutes = ((o.start - now).num_seconds() + 59).div_euclid(60).max(1);
+    let mut args = Map::new();
+    args.insert("summary".into(), o.summary.clone().into());
+    args.insert("minutes".into(), minutes.into());
+    let i18n = app.state::<I18n>();
+    let message = i18n.t("calendar.soon", &args);
+    if cfg.notify {
+        if let Err(e) = notify::show(&i18n.t("calendar.soon_title", &args), &message) {
+            log!("⚠️ {e}");
+        }
+    }
+    log!("📅 `{}` in {minutes} min", o.summary);
+    let _ = app.emit(
+        "meeting-starting-soon",
+        StartingSoon {
+            meeting: meeting(o, tz),
+            minutes,
+            message,
+        },
+    );
+}
+
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut active: Option<Active> = None;
+        let mut warned: BTreeSet<(String, DateTime<Utc>)> = BTreeSet::new();
+        loop {
+            let (cfg, clock_cfg) = {
+                let settings = app.state::<Mutex<Settings>>();
+                let settings = settings.lock_or_recover();
+                (settings.calendar.clone(), settings.clock.clone())
+            };
+            let tz = clock::zone(None, &clock_cfg).0;
+            let interval = power::interval(&app, POLL);
+            let running = cfg.enabled && !cfg.sources.is_empty();
+
+            let mut current = None;
+            let now = Utc::now();
+            if running {
+                let refresh = Duration::from_secs(u64::from(cfg.refresh_minutes.max(1)) * 60);
+                let root = app
+                    .state::<Mutex<AppPaths>>()
+                    .lock_or_recover()
+                    .root
+                    .clone();
+                let feed = app.state::<Calendar>();
+                let mut feed = feed.0.lock_or_recover();
+                let stale = feed.sources != cfg.sources
+                    || feed.loaded.is_none_or(|at| at.elapsed() >= refresh);
+                if stale {
+                    load(&mut feed, &cfg, &root, tz);
+                    let errors: Vec<_> = feed
+                        .status
+                        .iter()
+                        .filter_map(|s| Some(format!("{}: {}", s.source, s.error.as_ref()?)))
+                        .collect();
+                    if errors.is_empty() {
+                        health::success(&app, "calendar");
+                    } else {
+                        health::failure(&app, "calendar", errors.join("; "));
+                    }
+                    health::next_poll(&app, "calendar", refresh);
+                }
+                let warn_for = Span::minutes(i64::from(cfg.warn_minutes));
+                let soon = occurrences(&feed.events, now, now + warn_for + Span::seconds(1));
+                drop(feed);
+                for o in soon.iter().filter(|o| o.blocks()) {
+                    if o.start <= now {
+                        current.get_or_insert_with(|| o.clone());
+                    } else if cfg.warn_minutes > 0 && warned.insert((o.uid.clone(), o.start)) {
+                        warn(&app, &cfg, o, tz, now);
+                    }
+                }
+            }
+            warned.retain(|(_, start)| *start > now - Span::hours(1));
+
+            let store = state_store(&app);
+            match (current, active.take()) {
+                (Some(o), None) => {
+                    log!(
+                        "📅 in `{}` until {}",
+                        o.summary,
+                        o.end.with_timezone(&tz).format("%H:%M")
+                    );
+                    // A meeting state left over from a previous run is
+                    // not worth restoring.
+                    let previous = store.load().ok().filter(|s| s.state != cfg.state);
+                    write_meeting(&store, &cfg, &o);
+                    active = Some(Active {
+                        previous,
+                        key: (o.uid.clone(), o.start),
+                    });
+                    let _ = app.emit(
+                        "meeting-changed",
+                        MeetingChanged {
+                            active: true,
+                            meeting: Some(meeting(&o, tz)),
+                        },
+                    );
+                }
+                (Some(o), Some(mut was)) => {
+                    // Straight into the next one: keep the state from
+                    // before the first.
+                    if was.key != (o.uid.clone(), o.start) {
+                        log!("📅 on to `{}`", o.summary);
+                        write_meeting(&store, &cfg, &o);
+                        was.key = (o.uid.clone(), o.start);
+                        let _ = app.emit(
+                            "meeting-changed",
+                            MeetingChanged {
+                                active: true,
+                                meeting: Some(meeting(&o, tz)),
+                            },
+                        );
+                    }
+                    active = Some(was);
+                }
+                (None, Some(was)) => {
+                    log!("📅 meeting over");
+                    let still_ours = store.load().is_ok_and(|s| s.state == cfg.state);
+                    if still_ours {
+                        let mut previous = was.previous.unwrap_or_else(|| PetState {
+                            state: "idle".into(),
+                            detail: None,
+                            progress: None,
+                            updated_at: None,
+                            progress_sources: Default::default(),
+                        });
+                        previous.updated_at = Some(chrono::Local::now().to_rfc3339());
+                        if let Err(e) = store.save(&previous) {
+                            log!("⚠️ restore state: {e}");
+                        }
+                    }
+                    let _ = app.emit(
+                        "meeting-changed",
+                        MeetingChanged {
+                            active: false,
+                            meeting: None,
+                        },
+                    );
+                }
+                (None, None) => {}
+            }
+            std::thread::sleep(interval);
+        }
+    });
+}
+
+/// Sources with their event counts or errors, the meeting running now
+/// and the next day's events (all-day and free ones included).
+#[tauri::command]
+pub fn get_calendar(
+    calendar: tauri::State<'_, Calendar>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> CalendarInfo {
+    let tz = clock::zone(None, &settings.lock_or_recover().clock).0;
+    let feed = calendar.0.lock_or_recover();
+    let now = Utc::now();
+    let all = occurrences(&feed.events, now, now + Span::hours(LOOKAHEAD_HOURS));
+    CalendarInfo {
+        sources: feed.status.clone(),
+        loaded_at: feed.loaded_at.clone(),
+        current: all
+            .iter()
+            .find(|o| o.blocks() && o.start <= now)
+            .map(|o| meeting(o, tz)),
+        upcoming: all
+            .iter()
+            .filter(|o| o.start > now)
+            .map(|o| meeting(o, tz))
+            .collect(),
+    }
+}
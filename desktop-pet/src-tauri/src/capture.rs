@@ -0,0 +1,140 @@
+//! Short animated GIF clips of a pet window, captured natively so they
+//! work no matter what the webview is drawing.
+//!
+//! Needs the `capture` feature; without it `record_clip` reports that it
+//! is unavailable instead of disappearing from the command list.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::sync::LockExt;
+use crate::{jobs, AppPaths};
+
+const MAX_SECONDS: f64 = 30.0;
+const DEFAULT_FPS: u32 = 10;
+const MAX_FPS: u32 = 30;
+
+/// Records `seconds` of the pet window (the visible one unless `window`
+/// names a label) into a looping GIF, by default under `.star-pet/clips/`.
+/// Runs as a `record_clip` job; cancelling it drops the frames so far.
+#[tauri::command]
+pub async fn record_clip(
+    app: AppHandle,
+    seconds: f64,
+    window: Option<String>,
+    fps: Option<u32>,
+    path: Option<String>,
+) -> Result<PathBuf, String> {
+    if !(seconds > 0.0 && seconds <= MAX_SECONDS) {
+        return Err(format!(
+            "seconds must be between 0 and {MAX_SECONDS}, got {seconds}"
+        ));
+    }
+    let fps = fps.unwrap_or(DEFAULT_FPS).clamp(1, MAX_FPS);
+    let title = window_title(&app, window.as_deref())?;
+    let out = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let paths = app.state::<Mutex<AppPaths>>();
+            let data_dir = paths.lock_or_recover().data_dir.clone();
+            data_dir.join("clips").join(format!(
+                "{}.gif",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ))
+        }
+    };
+    let label = format!("{seconds}s of `{title}`");
+    jobs::run(&app, "record_clip", label, move |job| {
+        imp::record(&title, seconds, fps, &out, job).map(|_| out)
+    })
+    .await
+}
+
+fn window_title(app: &AppHandle, label: Option<&str>) -> Result<String, String> {
+    let window = match label {
+        Some(label) => app
+            .get_webview_window(label)
+            .ok_or_else(|| format!("{label} window not found"))?,
+        None => ["mini", "main"]
+            .iter()
+            .filter_map(|label| app.get_webview_window(label))
+            .find(|w| w.is_visible().unwrap_or(false))
+            .ok_or_else(|| "no pet window is visible".to_string())?,
+    };
+    window.title().map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "capture")]
+mod imp {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame};
+    use std::fs::File;
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    use crate::fsutil;
+    use crate::jobs::Job;
+    use crate::logs::log;
+
+    pub(super) fn record(
+        title: &str,
+        seconds: f64,
+        fps: u32,
+        out: &Path,
+        job: &Job,
+    ) -> Result<(), String> {
+        let windows = xcap::Window::all().map_err(|e| format!("list windows: {e}"))?;
+        let target = windows
+            .into_iter()
+            .find(|w| w.title() == title)
+            .ok_or_else(|| format!("window `{title}` not found by the OS"))?;
+
+        let interval = Duration::from_secs_f64(1.0 / fps as f64);
+        let frame_count = (seconds * fps as f64).ceil() as usize;
+        let mut frames = Vec::with_capacity(frame_count);
+        let started = Instant::now();
+        for n in 0..frame_count {
+            job.check()?;
+            job.progress(0.8 * n as f64 / frame_count as f64, "capturing");
+            let img = target
+                .capture_image()
+                .map_err(|e| format!("capture `{title}`: {e}"))?;
+            frames.push(Frame::from_parts(
+                img,
+                0,
+                0,
+                Delay::from_saturating_duration(interval),
+            ));
+            if let Some(wait) = (interval * (n as u32 + 1)).checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(fsutil::long_path(parent))
+                .map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        job.progress(0.8, "encoding");
+        let file =
+            File::create(fsutil::long_path(out)).map_err(|e| format!("{}: {e}", out.display()))?;
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .and_then(|_| encoder.encode_frames(frames))
+            .map_err(|e| format!("{}: encode: {e}", out.display()))?;
+        log!("🎞️ {frame_count} frames → {}", out.display());
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "capture"))]
+mod imp {
+    use std::path::Path;
+
+    use crate::jobs::Job;
+
+    pub(super) fn record(_: &str, _: f64, _: u32, _: &Path, _: &Job) -> Result<(), String> {
+        Err("this build has no clip capture (rebuild with `--features capture`)".into())
+    }
+}
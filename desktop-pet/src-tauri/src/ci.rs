@@ -0,0 +1,611 @@
+//! GitHub Actions runs as pet states.
+//!
+//! With `ci.enabled` and a `ci.repo`, the repository's workflow runs are
+//! polled every `interval_secs` (with the token from `token_env`, if set),
+//! or pushed: a `workflow_run` webhook delivery forwarded to the control
+//! API's `POST /ci` counts as a fresh poll of that run. The newest
+//! matching run, running ones first, drives `state.json`: `running_state`
+//! with `progress` from its finished steps, then `success_state` or
+//! `failed_state`; `detail` names the workflow and branch. Runs that had
+//! already finished when the pet started are not announced.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::bubble::{self, Bubble, BubbleAction};
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{health, net, power, AppPaths, PetState};
+
+const TICK: Duration = Duration::from_secs(5);
+const MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// Runs asked for per poll and kept between polls.
+const PER_PAGE: usize = 20;
+const KEEP_RUNS: usize = 50;
+/// Unauthenticated requests are limited to 60 an hour.
+const MIN_INTERVAL_SECS: u32 = 15;
+const MIN_ANONYMOUS_INTERVAL_SECS: u32 = 120;
+
+/// `ci` block of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CiSettings {
+    pub enabled: bool,
+    /// `owner/name` of the GitHub repository.
+    pub repo: String,
+    /// Environment variable holding a token that can read Actions; public
+    /// repositories work without one, at a slower poll.
+    pub token_env: String,
+    /// Only runs on this branch.
+    pub branch: Option<String>,
+    /// Only these workflows, by name (`CI`) or file (`ci.yml`); all when
+    /// empty.
+    pub workflows: Vec<String>,
+    /// Seconds between polls; 0 to rely on webhooks alone.
+    pub interval_secs: u32,
+    /// For GitHub Enterprise: `https://github.example.com/api/v3`.
+    pub api_url: String,
+    pub running_state: String,
+    pub success_state: String,
+    pub failed_state: String,
+    /// Offer a link to the run when it fails.
+    pub failure_bubble: bool,
+}
+
+impl Default for CiSettings {
+    fn default() -> Self {
+        CiSettings {
+            enabled: false,
+            repo: String::new(),
+            token_env: "STAR_PET_GITHUB_TOKEN".into(),
+            branch: None,
+            workflows: Vec::new(),
+            interval_secs: 60,
+            api_url: "https://api.github.com".into(),
+            running_state: "building".into(),
+            success_state: "success".into(),
+            failed_state: "failed".into(),
+            failure_bubble: true,
+        }
+    }
+}
+
+// ── runs ──
+
+/// A workflow run as the GitHub API and `workflow_run` webhooks send it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: u64,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// `.github/workflows/ci.yml`.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub head_branch: Option<String>,
+    #[serde(default)]
+    pub run_number: u64,
+    #[serde(default)]
+    pub event: String,
+    /// `queued`, `in_progress`, `completed`, …
+    #[serde(default)]
+    pub status: Option<String>,
+    /// `success`, `failure`, `cancelled`, … once completed.
+    #[serde(default)]
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub html_url: String,
+    #[serde(default)]
+    pub run_started_at: Option<String>,
+}
+
+impl WorkflowRun {
+    /// Its name, or the workflow file without the directory.
+    pub fn workflow(&self) -> &str {
+        match self.name.as_deref() {
+            Some(name) if !name.is_empty() => name,
+            _ => self.path.rsplit('/').next().unwrap_or(&self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiPhase {
+    Idle,
+    Running,
+    Success,
+    Failed,
+    /// Cancelled, skipped or otherwise without a verdict.
+    Cancelled,
+}
+
+pub fn phase(run: &WorkflowRun) -> CiPhase {
+    if run.status.as_deref() != Some("completed") {
+        return CiPhase::Running;
+    }
+    match run.conclusion.as_deref() {
+        Some("success") => CiPhase::Success,
+        Some("failure" | "timed_out" | "startup_failure") => CiPhase::Failed,
+        _ => CiPhase::Cancelled,
+    }
+}
+
+fn matches(run: &WorkflowRun, cfg: &CiSettings) -> bool {
+    let branch_ok = cfg
+        .branch
+        .as_deref()
+        .is_none_or(|b| run.head_branch.as_deref() == Some(b));
+    let file = run.path.rsplit('/').next().unwrap_or_default();
+    let workflow_ok = cfg.workflows.is_empty()
+        || cfg
+            .workflows
+            .iter()
+            .any(|w| run.name.as_deref() == Some(w.as_str()) || file == w.as_str());
+    branch_ok && workflow_ok
+}
+
+/// The newest run `cfg` lets through, preferring ones still running.
+pub fn pick<'a>(
+    runs: impl IntoIterator<Item = &'a WorkflowRun>,
+    cfg: &CiSettings,
+) -> Option<&'a WorkflowRun> {
+    let runs: Vec<_> = runs.into_iter().filter(|r| matches(r, cfg)).collect();
+    let newest = |running: bool| {
+        runs.iter()
+            .filter(|r| !running || phase(r) == CiPhase::Running)
+            .max_by_key(|r| r.id)
+            .copied()
+    };
+    newest(true).or_else(|| newest(false))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunStep {
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunJob {
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<RunStep>,
+}
+
+/// Finished steps over all steps; a job that hasn't listed its steps yet
+/// counts as one.
+pub fn job_progress(jobs: &[RunJob]) -> Option<f64> {
+    let completed = |status: &Option<String>| status.as_deref() == Some("completed");
+    let (mut done, mut total) = (0usize, 0usize);
+    for job in jobs {
+        if job.steps.is_empty() {
+            total += 1;
+            done += usize::from(completed(&job.status));
+        } else {
+            total += job.steps.len();
+            done += job.steps.iter().filter(|s| completed(&s.status)).count();
+        }
+    }
+    (total > 0).then(|| done as f64 / total as f64)
+}
+
+// ── tracking ──
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CiEvent {
+    Started,
+    Progress,
+    Finished(CiPhase),
+}
+
+/// Turns successive views of the picked run into state changes.
+#[derive(Debug, Default)]
+pub struct CiTracker {
+    last: Option<(u64, CiPhase)>,
+    percent: Option<u32>,
+    primed: bool,
+}
+
+impl CiTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// From now on a finished run is news, even the first one seen.
+    pub fn prime(&mut self) {
+        self.primed = true;
+    }
+
+    pub fn observe(&mut self, run: Option<&WorkflowRun>, progress: Option<f64>) -> Option<CiEvent> {
+        let run = run?;
+        let now = (run.id, phase(run));
+        let primed = std::mem::replace(&mut self.primed, true);
+        let percent = progress.map(|p| (p.clamp(0.0, 1.0) * 100.0) as u32);
+        if self.last == Some(now) {
+            if now.1 == CiPhase::Running && percent != self.percent {
+                self.percent = percent;
+                return Some(CiEvent::Progress);
+            }
+            return None;
+        }
+        self.last = Some(now);
+        self.percent = percent;
+        match now.1 {
+            CiPhase::Running => Some(CiEvent::Started),
+            _ if !primed => None,
+            phase => Some(CiEvent::Finished(phase)),
+        }
+    }
+}
+
+// ── status ──
+
+/// Returned by `get_ci` and sent as `ci-changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CiStatus {
+    repo: String,
+    phase: CiPhase,
+    run: Option<WorkflowRun>,
+    progress: Option<f64>,
+    checked_at: Option<String>,
+    error: Option<String>,
+}
+
+struct Watch {
+    /// What the cache was filled for; any change starts over.
+    cfg: Option<CiSettings>,
+    runs: BTreeMap<u64, WorkflowRun>,
+    /// Of the run with that id, from its jobs.
+    progress: Option<(u64, f64)>,
+    tracker: CiTracker,
+    last_poll: Option<Instant>,
+    status: CiStatus,
+}
+
+impl Watch {
+    fn new(cfg: Option<CiSettings>) -> Self {
+        let mut tracker = CiTracker::new();
+        if cfg.as_ref().is_some_and(|c| c.interval_secs == 0) {
+            tracker.prime();
+        }
+        Watch {
+            status: CiStatus {
+                repo: cfg.as_ref().map(|c| c.repo.clone()).unwrap_or_default(),
+                phase: CiPhase::Idle,
+                run: None,
+                progress: None,
+                checked_at: None,
+                error: None,
+            },
+            cfg,
+            runs: BTreeMap::new(),
+            progress: None,
+            tracker,
+            last_poll: None,
+        }
+    }
+}
+
+/// Managed by the app.
+pub struct Ci(Mutex<Watch>);
+
+impl Default for Ci {
+    fn default() -> Self {
+        Ci(Mutex::new(Watch::new(None)))
+    }
+}
+
+fn settings(app: &AppHandle) -> CiSettings {
+    app.state::<Mutex<Settings>>().lock_or_recover().ci.clone()
+}
+
+fn state_store(app: &AppHandle) -> FsStateStore {
+    let state_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .state_path
+        .clone();
+    FsStateStore::new(state_path)
+}
+
+fn write_state(app: &AppHandle, state: &str, detail: Option<String>, progress: Option<f64>) {
+    let state = PetState {
+        state: state.to_string(),
+        detail,
+        progress,
+        updated_at: Some(chrono::Local::now().to_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    if let Err(e) = state_store(app).save(&state) {
+        log!("⚠️ CI state: {e}");
+    }
+}
+
+fn failure_bubble(app: &AppHandle, run: &WorkflowRun, text: String) {
+    let i18n = app.state::<I18n>();
+    let mut url = Map::new();
+    url.insert("url".into(), run.html_url.clone().into());
+    let bubble = Bubble {
+        text,
+        icon: Some("error".into()),
+        actions: vec![BubbleAction {
+            label: i18n.t("ci.open", &Map::new()),
+            command: "open_external_url".into(),
+            args: url,
+        }],
+        duration_ms: None,
+    };
+    if let Err(e) = bubble::show(app, bubble) {
+        log!("⚠️ CI bubble: {e}");
+    }
+}
+
+/// Writes the state for `event`.
+fn apply(
+    app: &AppHandle,
+    cfg: &CiSettings,
+    run: &WorkflowRun,
+    event: CiEvent,
+    progress: Option<f64>,
+) {
+    let branch = run.head_branch.clone().unwrap_or_default();
+    let mut args = Map::new();
+    args.insert("workflow".into(), run.workflow().into());
+    args.insert("branch".into(), branch.clone().into());
+    args.insert("number".into(), run.run_number.into());
+    let i18n = app.state::<I18n>();
+    match event {
+        CiEvent::Started | CiEvent::Progress => {
+            if event == CiEvent::Started {
+                log!(
+                    "🚦 CI {} #{} running on {branch}",
+                    run.workflow(),
+                    run.run_number
+                );
+            }
+            let detail = i18n.t("ci.running", &args);
+            write_state(app, &cfg.running_state, Some(detail), progress);
+        }
+        CiEvent::Finished(phase) => {
+            log!(
+                "🚦 CI {} #{} on {branch}: {}",
+                run.workflow(),
+                run.run_number,
+                run.conclusion.as_deref().unwrap_or("?")
+            );
+            match phase {
+                CiPhase::Success => {
+                    let detail = i18n.t("ci.success", &args);
+                    write_state(app, &cfg.success_state, Some(detail), None);
+                }
+                CiPhase::Failed => {
+                    let detail = i18n.t("ci.failed", &args);
+                    write_state(app, &cfg.failed_state, Some(detail), None);
+                    if cfg.failure_bubble && run.html_url.starts_with("http") {
+                        failure_bubble(app, run, i18n.t("ci.failed_bubble", &args));
+                    }
+                }
+                // No verdict: only hand back the running state.
+                _ => {
+                    let ours = state_store(app)
+                        .load()
+                        .is_ok_and(|s| s.state == cfg.running_state);
+                    if ours {
+                        write_state(app, "idle", None, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Re-picks the run to show after the cache changed.
+fn update(app: &AppHandle, watch: &mut Watch, cfg: &CiSettings) {
+    let run = pick(watch.runs.values(), cfg).cloned();
+    let progress = run
+        .as_ref()
+        .filter(|r| phase(r) == CiPhase::Running)
+        .and_then(|r| watch.progress.filter(|(id, _)| *id == r.id))
+        .map(|(_, p)| p);
+    let event = watch.tracker.observe(run.as_ref(), progress);
+    watch.status.phase = run.as_ref().map_or(CiPhase::Idle, phase);
+    watch.status.progress = progress;
+    watch.status.run = run;
+    if let (Some(event), Some(run)) = (event, &watch.status.run) {
+        apply(app, cfg, run, event, progress);
+        let _ = app.emit("ci-changed", &watch.status);
+    }
+}
+
+fn remember(watch: &mut Watch, runs: impl IntoIterator<Item = WorkflowRun>) {
+    for run in runs {
+        watch.runs.insert(run.id, run);
+    }
+    while watch.runs.len() > KEEP_RUNS {
+        watch.runs.pop_first();
+    }
+}
+
+// ── fetching ──
+
+fn token(cfg: &CiSettings) -> Option<String> {
+    std::env::var(&cfg.token_env)
+        .ok()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn query(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn api_get<T: serde::de::DeserializeOwned>(cfg: &CiSettings, path: &str) -> Result<T, String> {
+    let url = format!(
+        "{}/repos/{}/{path}",
+        cfg.api_url.trim_end_matches('/'),
+        cfg.repo
+    );
+    let auth = token(cfg).map(|t| format!("Bearer {t}"));
+    let mut headers = vec![
+        ("Accept", "application/vnd.github+json"),
+        ("X-GitHub-Api-Version", "2022-11-28"),
+    ];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth.as_str()));
+    }
+    let raw = net::get_with(&url, &headers, MAX_BYTES)?;
+    serde_json::from_slice(&raw).map_err(|e| format!("{url}: {e}"))
+}
+
+#[derive(Deserialize)]
+struct RunsPage {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Deserialize)]
+struct JobsPage {
+    jobs: Vec<RunJob>,
+}
+
+/// Recent runs, and the progress of the one to show if it is running.
+fn fetch(cfg: &CiSettings) -> Result<(Vec<WorkflowRun>, Option<(u64, f64)>), String> {
+    let mut path = format!("actions/runs?per_page={PER_PAGE}");
+    if let Some(branch) = &cfg.branch {
+        path.push_str(&format!("&branch={}", query(branch)));
+    }
+    let runs = api_get::<RunsPage>(cfg, &path)?.workflow_runs;
+    let progress = match pick(&runs, cfg).filter(|r| phase(r) == CiPhase::Running) {
+        Some(run) => {
+            let jobs: JobsPage =
+                api_get(cfg, &format!("actions/runs/{}/jobs?per_page=100", run.id))?;
+            job_progress(&jobs.jobs).map(|p| (run.id, p))
+        }
+        None => None,
+    };
+    Ok((runs, progress))
+}
+
+fn poll_interval(app: &AppHandle, cfg: &CiSettings) -> Duration {
+    let floor = if token(cfg).is_some() {
+        MIN_INTERVAL_SECS
+    } else {
+        MIN_ANONYMOUS_INTERVAL_SECS
+    };
+    power::interval(
+        app,
+        Duration::from_secs(u64::from(cfg.interval_secs.max(floor))),
+    )
+}
+
+pub(crate) fn spawn_poller(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let cfg = settings(&app);
+        let on = cfg.enabled && !cfg.repo.trim().is_empty();
+        let due = {
+            let ci = app.state::<Ci>();
+            let mut watch = ci.0.lock_or_recover();
+            if watch.cfg.as_ref() != Some(&cfg) {
+                *watch = Watch::new(Some(cfg.clone()));
+            }
+            on && cfg.interval_secs > 0
+                && !power::paused(&app, "ci")
+                && watch
+                    .last_poll
+                    .is_none_or(|at| at.elapsed() >= poll_interval(&app, &cfg))
+        };
+        if due {
+            let fetched = fetch(&cfg);
+            let ci = app.state::<Ci>();
+            let mut watch = ci.0.lock_or_recover();
+            if watch.cfg.as_ref() == Some(&cfg) {
+                watch.last_poll = Some(Instant::now());
+                watch.status.checked_at = Some(chrono::Local::now().to_rfc3339());
+                health::next_poll(&app, "ci", poll_interval(&app, &cfg));
+                match fetched {
+                    Ok((runs, progress)) => {
+                        health::success(&app, "ci");
+                        watch.status.error = None;
+                        remember(&mut watch, runs);
+                        if progress.is_some() {
+                            watch.progress = progress;
+                        }
+                        update(&app, &mut watch, &cfg);
+                    }
+                    Err(e) => {
+                        health::failure(&app, "ci", e.as_str());
+                        if watch.status.error.as_ref() != Some(&e) {
+                            log!("⚠️ CI {}: {e}", cfg.repo);
+                            watch.status.error = Some(e);
+                            let _ = app.emit("ci-changed", &watch.status);
+                        }
+                    }
+                }
+            }
+        }
+        std::thread::sleep(TICK);
+    });
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: String,
+}
+
+/// Body of a `workflow_run` webhook delivery.
+#[derive(Deserialize)]
+struct Delivery {
+    workflow_run: WorkflowRun,
+    repository: Option<Repository>,
+}
+
+/// Takes a `workflow_run` webhook delivery (`POST /ci`).
+pub(crate) fn receive(app: &AppHandle, body: &[u8]) -> Result<CiStatus, String> {
+    let cfg = settings(app);
+    if !cfg.enabled || cfg.repo.trim().is_empty() {
+        return Err("the CI integration is off".into());
+    }
+    let delivery: Delivery =
+        serde_json::from_slice(body).map_err(|e| format!("workflow_run delivery: {e}"))?;
+    if let Some(repo) = &delivery.repository {
+        if !repo.full_name.eq_ignore_ascii_case(&cfg.repo) {
+            return Err(format!(
+                "delivery is for {}, not {}",
+                repo.full_name, cfg.repo
+            ));
+        }
+    }
+    let ci = app.state::<Ci>();
+    let mut watch = ci.0.lock_or_recover();
+    if watch.cfg.as_ref() != Some(&cfg) {
+        *watch = Watch::new(Some(cfg.clone()));
+    }
+    // Pushed, so whatever it says is news.
+    watch.tracker.prime();
+    remember(&mut watch, [delivery.workflow_run]);
+    update(app, &mut watch, &cfg);
+    Ok(watch.status.clone())
+}
+
+/// The run shown now, with the last poll's time and error.
+#[tauri::command]
+pub fn get_ci(ci: tauri::State<'_, Ci>) -> CiStatus {
+    ci.0.lock_or_recover().status.clone()
+}
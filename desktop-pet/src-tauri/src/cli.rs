@@ -0,0 +1,426 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::bench;
+use crate::doctor::{self, Severity};
+use crate::locate::{self, find_project_root, resolve_project_root, Overrides, RootSource};
+use crate::observe;
+use crate::pet_core::{FsAssets, FsStateStore, StateStore};
+use crate::preview;
+use crate::rpc;
+use crate::scaffold;
+use crate::states;
+use crate::{schema, seasonal, settings, validate, AppPaths, PetState};
+
+/// Star desktop pet. Runs the GUI when no subcommand is given.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Validate configs and assets (same as the `validate` subcommand).
+    #[arg(long, value_name = "ROOT", num_args = 0..=1)]
+    validate: Option<Option<PathBuf>>,
+
+    /// Print the JSON Schema for a config file (or `all`, keyed by kind)
+    /// and exit.
+    #[arg(long, value_name = "KIND", value_parser = schema_kinds())]
+    print_schema: Option<String>,
+
+    /// Pack-author mode: no-cache asset server and timed reload logging.
+    /// Also enabled by `STAR_DEV=1`.
+    #[arg(long)]
+    dev: bool,
+
+    /// Run the engine (backend, watchers, scheduled checks) without any
+    /// window. Also enabled by `STAR_HEADLESS=1`.
+    #[arg(long)]
+    headless: bool,
+
+    /// Also speak JSON-RPC for editor plugins, on `stdio` or a local port.
+    #[arg(long, value_name = "stdio|PORT", value_parser = rpc::Transport::parse)]
+    rpc: Option<rpc::Transport>,
+
+    /// Read-only: show the pet of another instance, e.g.
+    /// `http://buildbox:18791`, instead of the local state file.
+    #[arg(long, value_name = "URL", value_parser = observe::Remote::parse)]
+    observe: Option<observe::Remote>,
+
+    /// Use this state file instead of `<root>/state.json`. Also `STAR_STATE_FILE`.
+    #[arg(long, global = true, value_name = "PATH")]
+    state_file: Option<PathBuf>,
+
+    /// Use this pack directory instead of `<root>/layers`. Also `STAR_LAYERS_DIR`.
+    #[arg(long, global = true, value_name = "DIR")]
+    layers_dir: Option<PathBuf>,
+
+    /// Use this map instead of `<layers>/map.json`. Also `STAR_MAP_FILE`.
+    #[arg(long, global = true, value_name = "PATH")]
+    map_file: Option<PathBuf>,
+
+    /// Keep logs, stats and recordings here instead of `<root>/.star-pet`.
+    /// Also `STAR_DATA_DIR`.
+    #[arg(long, global = true, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Cmd>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Cmd {
+    /// Load and check every config and asset, printing a JSON report.
+    Validate {
+        /// Project root (defaults to auto-detection).
+        root: Option<PathBuf>,
+    },
+    /// Write state.json without launching the GUI.
+    SetState {
+        state: String,
+        #[arg(long)]
+        detail: Option<String>,
+        /// Progress between 0 and 1.
+        #[arg(long)]
+        progress: Option<f64>,
+        #[arg(long)]
+        root: Option<PathBuf>,
+    },
+    /// Write a starter state.json, layers.json, map.json and placeholder art.
+    Init {
+        /// Target directory (defaults to the current directory).
+        dir: Option<PathBuf>,
+        #[arg(long, default_value = "office", value_parser = scaffold::TEMPLATES.to_vec())]
+        template: String,
+        /// Overwrite files that already exist.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Diagnose a broken setup and print a prioritized fix-it list.
+    Doctor {
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// List every state name the current pack understands.
+    States {
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Print descriptions, sources and icons as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Time the asset pipeline and compare against the previous run.
+    Bench {
+        #[arg(long, default_value_t = 20)]
+        iterations: u32,
+        #[arg(long)]
+        root: Option<PathBuf>,
+    },
+    /// Render one anim's frames into a labelled PNG strip.
+    Preview {
+        anim: String,
+        /// Entry of `skins` in layers.json (defaults to the `skin` setting).
+        #[arg(long)]
+        skin: Option<String>,
+        #[arg(long, default_value_t = preview::DEFAULT_SCALE)]
+        scale: u32,
+        /// Output file (defaults to `.star-pet/previews/<anim>.png`).
+        #[arg(long, short)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        root: Option<PathBuf>,
+    },
+}
+
+/// How the GUI should start when no subcommand ran.
+#[derive(Debug, Default)]
+pub(crate) struct LaunchOptions {
+    pub dev: bool,
+    pub headless: bool,
+    pub rpc: Option<rpc::Transport>,
+    pub observe: Option<observe::Remote>,
+}
+
+pub(crate) enum Outcome {
+    Launch(LaunchOptions),
+    Exit(i32),
+}
+
+fn schema_kinds() -> Vec<&'static str> {
+    let mut kinds = schema::KINDS.to_vec();
+    kinds.push("all");
+    kinds
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| matches!(v.trim(), "1" | "true" | "yes"))
+}
+
+/// Handles subcommands and one-shot flags that run without a window.
+/// Returns `Exit` when the process should stop instead of launching the app.
+pub(crate) fn dispatch() -> Outcome {
+    let mut options = LaunchOptions {
+        dev: env_flag("STAR_DEV"),
+        headless: env_flag("STAR_HEADLESS"),
+        rpc: None,
+        observe: None,
+    };
+    if std::env::args_os().len() <= 1 {
+        return Outcome::Launch(options);
+    }
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            attach_console();
+            let _ = e.print();
+            return Outcome::Exit(if e.use_stderr() { 2 } else { 0 });
+        }
+    };
+    if cli.command.is_some() || cli.print_schema.is_some() || cli.validate.is_some() {
+        attach_console();
+    }
+
+    locate::set_cli(Overrides {
+        state_file: cli.state_file,
+        layers_dir: cli.layers_dir,
+        map_file: cli.map_file,
+        data_dir: cli.data_dir,
+    });
+
+    if let Some(kind) = cli.print_schema {
+        return Outcome::Exit(print_schema(&kind));
+    }
+    if let Some(root) = cli.validate {
+        return Outcome::Exit(run_validate(root));
+    }
+    let Some(command) = cli.command else {
+        options.dev |= cli.dev;
+        options.headless |= cli.headless;
+        options.rpc = cli.rpc;
+        options.observe = cli.observe;
+        return Outcome::Launch(options);
+    };
+    Outcome::Exit(match command {
+        Cmd::Validate { root } => run_validate(root),
+        Cmd::SetState {
+            state,
+            detail,
+            progress,
+            root,
+        } => set_state(state, detail, progress, root),
+        Cmd::Init {
+            dir,
+            template,
+            force,
+        } => init(dir, &template, force),
+        Cmd::Doctor { root, json } => doctor(root, json),
+        Cmd::States { root, json } => states(root, json),
+        Cmd::Bench { iterations, root } => bench(iterations, root),
+        Cmd::Preview {
+            anim,
+            skin,
+            scale,
+            out,
+            root,
+        } => render_preview(&anim, skin, scale, out, root),
+    })
+}
+
+/// Release builds on Windows are GUI-subsystem and start without a console,
+/// so subcommand output would go nowhere; borrow the terminal's instead.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // SAFETY: takes no pointers; fails harmlessly when started outside a
+    // console (e.g. from Explorer), leaving output discarded as before.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), i32> {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            println!("{json}");
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("⚠️ serialize: {e}");
+            Err(1)
+        }
+    }
+}
+
+fn print_schema(kind: &str) -> i32 {
+    if kind == "all" {
+        return print_json(&schema::all_schemas()).err().unwrap_or(0);
+    }
+    match schema::config_schema(kind) {
+        Ok(schema) => print_json(&schema).err().unwrap_or(0),
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            2
+        }
+    }
+}
+
+/// Validates every config and asset, printing a JSON report on stdout.
+/// Exits 1 when any error was found so CI can gate shared pet packs on it.
+fn run_validate(root: Option<PathBuf>) -> i32 {
+    let root = root.unwrap_or_else(find_project_root);
+    let report = validate::validate_root(&root);
+    if let Err(code) = print_json(&report) {
+        return code;
+    }
+    if report.ok {
+        0
+    } else {
+        1
+    }
+}
+
+fn set_state(
+    state: String,
+    detail: Option<String>,
+    progress: Option<f64>,
+    root: Option<PathBuf>,
+) -> i32 {
+    if let Some(p) = progress {
+        if !(0.0..=1.0).contains(&p) {
+            eprintln!("⚠️ --progress must be between 0 and 1, got {p}");
+            return 2;
+        }
+    }
+    let paths = launch_paths(root);
+    let new_state = PetState {
+        state,
+        detail,
+        progress,
+        updated_at: Some(chrono::Local::now().to_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    match FsStateStore::new(&paths.state_path).save(&new_state) {
+        Ok(()) => {
+            eprintln!("✅ {} → {}", paths.state_path.display(), new_state.state);
+            0
+        }
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            1
+        }
+    }
+}
+
+fn init(dir: Option<PathBuf>, template: &str, force: bool) -> i32 {
+    let dir = dir.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    match scaffold::scaffold(template, &dir, force) {
+        Ok(written) => {
+            for path in &written {
+                println!("✅ {}", path.display());
+            }
+            println!("🎉 `{template}` pet ready in {}", dir.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            1
+        }
+    }
+}
+
+/// Prints the prioritized fix-it list; exits 1 when anything at error
+/// level or above was found.
+fn doctor(root: Option<PathBuf>, json: bool) -> i32 {
+    let (root, source) = match root {
+        Some(root) => (root, RootSource::Explicit),
+        None => resolve_project_root(),
+    };
+    let report = doctor::diagnose(&root, source);
+    if json {
+        if let Err(code) = print_json(&report) {
+            return code;
+        }
+    } else {
+        for finding in &report.findings {
+            let mark = match finding.severity {
+                Severity::Critical => "🛑",
+                Severity::Error => "❌",
+                Severity::Warning => "⚠️",
+                Severity::Info => "ℹ️",
+            };
+            println!("{mark} [{}] {}", finding.check, finding.message);
+            if let Some(fix) = &finding.fix {
+                println!("   → {fix}");
+            }
+        }
+    }
+    if report.healthy {
+        0
+    } else {
+        1
+    }
+}
+
+/// What a launch from `root` would use, profile included.
+fn launch_paths(root: Option<PathBuf>) -> AppPaths {
+    crate::resolve_paths(&root.unwrap_or_else(find_project_root)).0
+}
+
+/// One name per line by default, for shell completion scripts.
+fn states(root: Option<PathBuf>, json: bool) -> i32 {
+    let paths = launch_paths(root);
+    let states = states::known_states(&paths);
+    if json {
+        return print_json(&states).err().unwrap_or(0);
+    }
+    for state in &states {
+        println!("{}", state.name);
+    }
+    0
+}
+
+fn bench(iterations: u32, root: Option<PathBuf>) -> i32 {
+    let paths = launch_paths(root);
+    match bench::bench(&paths, iterations) {
+        Ok(report) => print_json(&report).err().unwrap_or(0),
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            1
+        }
+    }
+}
+
+fn render_preview(
+    anim: &str,
+    skin: Option<String>,
+    scale: u32,
+    out: Option<PathBuf>,
+    root: Option<PathBuf>,
+) -> i32 {
+    let paths = launch_paths(root);
+    let settings = settings::load(&paths.settings_path);
+    let skin = skin.or_else(|| settings.skin.clone());
+    let out = out.unwrap_or_else(|| preview::default_path(&paths.data_dir, anim));
+    let fs = FsAssets::new(&paths.layers_dir);
+    let written = seasonal::with_season(&fs, &settings, |src| {
+        preview::write_strip(src, anim, skin.as_deref(), scale, &settings.limits, &out)
+    });
+    match written {
+        Ok(()) => {
+            println!("✅ {}", out.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            1
+        }
+    }
+}
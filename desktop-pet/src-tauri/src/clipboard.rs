@@ -0,0 +1,206 @@
+//! Clipboard trigger rules (opt-in).
+//!
+//! With `clipboard.enabled` a background thread reads the clipboard as
+//! text every few seconds and tests it against the user's regex rules. A
+//! match can switch the state and/or ask the frontend for an emote via
+//! `clipboard-match`. Matching happens in memory only: clipboard text is
+//! never written to disk, logged or included in events, and only a hash
+//! of it is kept to notice changes.
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{AppPaths, PetState};
+
+/// One entry of `clipboard.rules`; first match wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ClipboardRule {
+    /// Shown as the state's `detail` and in events, instead of the text.
+    pub name: String,
+    /// Regular expression tested against the clipboard text.
+    pub pattern: String,
+    /// State to switch to on a match.
+    pub state: Option<String>,
+    /// Emote for the frontend to play, e.g. `"concerned"`.
+    pub emote: Option<String>,
+}
+
+/// `clipboard` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    pub enabled: bool,
+    pub poll_secs: u32,
+    /// Only this many characters from the start are matched.
+    pub max_chars: usize,
+    pub rules: Vec<ClipboardRule>,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        ClipboardSettings {
+            enabled: false,
+            poll_secs: 2,
+            max_chars: 20_000,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Payload of `clipboard-match`.
+#[derive(Debug, Clone, Serialize)]
+struct ClipboardMatch {
+    rule: String,
+    state: Option<String>,
+    emote: Option<String>,
+}
+
+fn stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "macos")]
+fn read_text() -> Option<String> {
+    stdout("pbpaste", &[])
+}
+
+#[cfg(target_os = "windows")]
+fn read_text() -> Option<String> {
+    stdout(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "Get-Clipboard -Raw",
+        ],
+    )
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn read_text() -> Option<String> {
+    let wayland = || {
+        std::env::var_os("WAYLAND_DISPLAY")?;
+        stdout("wl-paste", &["--no-newline", "--type", "text"])
+    };
+    wayland()
+        .or_else(|| stdout("xclip", &["-selection", "clipboard", "-o"]))
+        .or_else(|| stdout("xsel", &["--clipboard", "--output"]))
+}
+
+fn fingerprint(text: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    text.hash(&mut h);
+    h.finish()
+}
+
+/// Rules compiled once per change of `clipboard.rules`.
+#[derive(Default)]
+struct Compiled {
+    rules: Vec<ClipboardRule>,
+    regexes: Vec<Option<Regex>>,
+}
+
+impl Compiled {
+    fn refresh(&mut self, rules: &[ClipboardRule]) {
+        if self.rules == rules {
+            return;
+        }
+        self.regexes = rules
+            .iter()
+            .map(|r| {
+                Regex::new(&r.pattern)
+                    .map_err(|e| log!("⚠️ clipboard rule `{}`: {e}", r.name))
+                    .ok()
+            })
+            .collect();
+        self.rules = rules.to_vec();
+    }
+
+    fn first_match(&self, text: &str) -> Option<&ClipboardRule> {
+        self.rules
+            .iter()
+            .zip(&self.regexes)
+            .find(|(_, re)| re.as_ref().is_some_and(|re| re.is_match(text)))
+            .map(|(rule, _)| rule)
+    }
+}
+
+fn apply(app: &AppHandle, rule: &ClipboardRule) {
+    log!("📋 clipboard matched rule `{}`", rule.name);
+    if let Some(state) = &rule.state {
+        let state_path = app
+            .state::<Mutex<AppPaths>>()
+            .lock_or_recover()
+            .state_path
+            .clone();
+        let next = PetState {
+            state: state.clone(),
+            detail: Some(rule.name.clone()),
+            progress: None,
+            updated_at: Some(chrono::Local::now().to_rfc3339()),
+            progress_sources: Default::default(),
+        };
+        if let Err(e) = FsStateStore::new(state_path).save(&next) {
+            log!("⚠️ clipboard state: {e}");
+        }
+    }
+    let _ = app.emit(
+        "clipboard-match",
+        ClipboardMatch {
+            rule: rule.name.clone(),
+            state: rule.state.clone(),
+            emote: rule.emote.clone(),
+        },
+    );
+}
+
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut compiled = Compiled::default();
+        let mut last: Option<u64> = None;
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .clipboard
+                .clone();
+            if !cfg.enabled || cfg.rules.is_empty() || power::paused(&app, "clipboard") {
+                last = None;
+            } else if let Some(text) = read_text() {
+                let text: String = text.chars().take(cfg.max_chars).collect();
+                let print = fingerprint(&text);
+                // The first read only sets the baseline: whatever was
+                // copied before turning this on doesn't count.
+                let changed = last.is_some_and(|p| p != print);
+                last = Some(print);
+                if changed {
+                    compiled.refresh(&cfg.rules);
+                    if let Some(rule) = compiled.first_match(&text) {
+                        apply(&app, rule);
+                    }
+                }
+            }
+            std::thread::sleep(power::interval(
+                &app,
+                Duration::from_secs(u64::from(cfg.poll_secs.max(1))),
+            ));
+        }
+    });
+}
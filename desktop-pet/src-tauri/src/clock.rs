@@ -0,0 +1,244 @@
+//! Wall-clock time in an explicit time zone.
+//!
+//! Time-of-day settings (such as `sounds.quiet_hours`) are read in the zone
+//! they name, else `clock.timezone`, else the system zone. A window such
+//! as "22:00–08:00" runs from the first moment the clock shows its start
+//! to the first moment it shows its end, so across a DST change it stays
+//! on through the repeated hour rather than starting twice, and a start or
+//! end inside the skipped hour takes effect when the clock resumes after it.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, Tz};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+/// `clock` block of `settings.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ClockSettings {
+    /// IANA name such as `Europe/Berlin`; the system zone when unset.
+    pub timezone: Option<String>,
+}
+
+/// Where the zone in use came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneSource {
+    Override,
+    Settings,
+    System,
+    /// Nothing usable was found; UTC it is.
+    Fallback,
+}
+
+fn parse(name: &str) -> Option<Tz> {
+    match name.trim().parse::<Tz>() {
+        Ok(tz) => Some(tz),
+        Err(e) => {
+            log!("⚠️ time zone `{name}`: {e}");
+            None
+        }
+    }
+}
+
+/// Zone for a time-of-day entry: its own `timezone`, then `clock.timezone`,
+/// then the system's.
+pub(crate) fn zone(entry: Option<&str>, cfg: &ClockSettings) -> (Tz, ZoneSource) {
+    if let Some(tz) = entry.and_then(parse) {
+        return (tz, ZoneSource::Override);
+    }
+    if let Some(tz) = cfg.timezone.as_deref().and_then(parse) {
+        return (tz, ZoneSource::Settings);
+    }
+    match iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|n| n.parse().ok())
+    {
+        Some(tz) => (tz, ZoneSource::System),
+        None => (Tz::UTC, ZoneSource::Fallback),
+    }
+}
+
+pub(crate) fn now_in(tz: Tz) -> DateTime<Tz> {
+    Utc::now().with_timezone(&tz)
+}
+
+pub(crate) fn parse_time(raw: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok()
+}
+
+/// The first instant on `date` the clock in `tz` shows `time`, or, when
+/// DST skips it, the moment the clock resumes.
+pub(crate) fn first_instant(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Tz>> {
+    let local = date.and_time(time);
+    (0..=180).find_map(|m| {
+        tz.from_local_datetime(&(local + Duration::minutes(m)))
+            .earliest()
+    })
+}
+
+/// Whether `at` falls in the `[start, end)` window of its day or, for
+/// windows wrapping past midnight (`end` earlier than `start`), of the day
+/// before.
+pub(crate) fn in_window(start: NaiveTime, end: NaiveTime, at: &DateTime<Tz>) -> bool {
+    let tz = at.timezone();
+    let today = at.date_naive();
+    [today.pred_opt(), Some(today)]
+        .into_iter()
+        .flatten()
+        .any(|day| {
+            let end_day = if start <= end {
+                Some(day)
+            } else {
+                day.succ_opt()
+            };
+            let (Some(from), Some(to)) = (
+                first_instant(tz, day, start),
+                end_day.and_then(|d| first_instant(tz, d, end)),
+            ) else {
+                return false;
+            };
+            from <= *at && *at < to
+        })
+}
+
+/// Returned by `get_clock_info`.
+#[derive(Debug, Serialize)]
+pub struct ClockInfo {
+    timezone: String,
+    source: ZoneSource,
+    /// RFC 3339 in `timezone`.
+    now: String,
+    utc_offset_secs: i32,
+    dst: bool,
+}
+
+#[tauri::command]
+pub fn get_clock_info(settings: tauri::State<'_, Mutex<Settings>>) -> ClockInfo {
+    let cfg = settings.lock_or_recover().clock.clone();
+    let (tz, source) = zone(None, &cfg);
+    let now = now_in(tz);
+    let offset = now.offset();
+    ClockInfo {
+        timezone: tz.name().to_string(),
+        source,
+        now: now.to_rfc3339(),
+        utc_offset_secs: offset.fix().local_minus_utc(),
+        dst: offset.dst_offset() != chrono::Duration::zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn hm(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    /// Berlin time at a UTC instant, which stays unambiguous across DST.
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Tz> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0)
+            .unwrap()
+            .with_timezone(&Berlin)
+    }
+
+    #[test]
+    fn plain_and_wrapping_windows() {
+        let (day, night) = ((hm(9, 0), hm(17, 0)), (hm(22, 0), hm(8, 0)));
+        // 2024-06-12 is CEST, UTC+2.
+        assert!(in_window(day.0, day.1, &utc(2024, 6, 12, 7, 0)));
+        assert!(!in_window(day.0, day.1, &utc(2024, 6, 12, 15, 0)));
+        assert!(in_window(night.0, night.1, &utc(2024, 6, 12, 20, 0)));
+        assert!(in_window(night.0, night.1, &utc(2024, 6, 12, 5, 59)));
+        assert!(!in_window(night.0, night.1, &utc(2024, 6, 12, 6, 0)));
+        assert!(!in_window(night.0, night.1, &utc(2024, 6, 12, 12, 0)));
+        assert!(!in_window(hm(8, 0), hm(8, 0), &utc(2024, 6, 12, 6, 0)));
+    }
+
+    #[test]
+    fn fall_back_does_not_start_twice() {
+        // 2024-10-27: 03:00 CEST (01:00 UTC) becomes 02:00 CET.
+        let (start, end) = (hm(2, 30), hm(4, 0));
+        assert!(
+            !in_window(start, end, &utc(2024, 10, 27, 0, 20)),
+            "02:20 CEST"
+        );
+        assert!(
+            in_window(start, end, &utc(2024, 10, 27, 0, 30)),
+            "02:30 CEST"
+        );
+        // The clock reads 02:15 again, then 02:30 again: still on.
+        assert!(
+            in_window(start, end, &utc(2024, 10, 27, 1, 15)),
+            "02:15 CET"
+        );
+        assert!(
+            in_window(start, end, &utc(2024, 10, 27, 1, 45)),
+            "02:45 CET"
+        );
+        assert!(
+            in_window(start, end, &utc(2024, 10, 27, 2, 59)),
+            "03:59 CET"
+        );
+        assert!(
+            !in_window(start, end, &utc(2024, 10, 27, 3, 0)),
+            "04:00 CET"
+        );
+    }
+
+    #[test]
+    fn fall_back_ends_at_the_first_end() {
+        // The end falls in the repeated hour; the second 02:15 stays off.
+        let (start, end) = (hm(22, 0), hm(2, 15));
+        assert!(
+            in_window(start, end, &utc(2024, 10, 26, 20, 0)),
+            "22:00 CEST"
+        );
+        assert!(
+            in_window(start, end, &utc(2024, 10, 27, 0, 10)),
+            "02:10 CEST"
+        );
+        assert!(
+            !in_window(start, end, &utc(2024, 10, 27, 0, 15)),
+            "02:15 CEST"
+        );
+        assert!(
+            !in_window(start, end, &utc(2024, 10, 27, 1, 10)),
+            "02:10 CET"
+        );
+    }
+
+    #[test]
+    fn spring_forward_starts_when_the_clock_resumes() {
+        // 2024-03-31: 02:00 CET (01:00 UTC) becomes 03:00 CEST.
+        let (start, end) = (hm(2, 30), hm(5, 0));
+        assert!(
+            !in_window(start, end, &utc(2024, 3, 31, 0, 59)),
+            "01:59 CET"
+        );
+        assert!(in_window(start, end, &utc(2024, 3, 31, 1, 0)), "03:00 CEST");
+        assert!(
+            in_window(start, end, &utc(2024, 3, 31, 2, 59)),
+            "04:59 CEST"
+        );
+        assert!(
+            !in_window(start, end, &utc(2024, 3, 31, 3, 0)),
+            "05:00 CEST"
+        );
+
+        // An end in the skipped hour ends the window as the clock jumps.
+        let (start, end) = (hm(1, 0), hm(2, 30));
+        assert!(in_window(start, end, &utc(2024, 3, 31, 0, 30)), "01:30 CET");
+        assert!(
+            !in_window(start, end, &utc(2024, 3, 31, 1, 0)),
+            "03:00 CEST"
+        );
+    }
+}
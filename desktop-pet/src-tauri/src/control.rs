@@ -0,0 +1,439 @@
+//! HTTP control API for scripts, build systems and other apps that would
+//! rather not write `state.json` themselves:
+//!
+//! - `GET /state`: the state as `read_state` returns it
+//! - `PUT /state`: a state object, written like `write_state` does
+//! - `POST /reload`: re-reads settings, mappers, theme, layers, map and
+//!   locales as if each file had changed
+//! - `PUT /settings/<block>`: replaces one top-level block of
+//!   `settings.json` (not `permissions`), applied by the file watcher
+//! - `POST /say`: shows a speech bubble, buttons included (see
+//!   [`crate::bubble`])
+//! - `POST /ci`: a GitHub `workflow_run` webhook delivery (see
+//!   [`crate::ci`])
+//!
+//! Off by default and only on `127.0.0.1`. Its port and token live in
+//! `.star-pet/control.json`, which the backend creates with a random token
+//! and rewrites through `set_control_api`; every request must send
+//! `Authorization: Bearer <token>`, or one from `permissions.tokens`;
+//! webhook relays that can't set headers may pass it as `?token=`. What
+//! each may do is up to [`crate::permissions`].
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::permissions::{self, Caller, Channel, Scope};
+use crate::settings::{self, Settings};
+use crate::sync::LockExt;
+use crate::{api, bubble, ci, fsutil, status, watch, AppPaths, PetState};
+
+pub(crate) const FILE: &str = "control.json";
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Larger than any state object.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// Bumped on every (re)start; a listener from an older one stops.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// `.star-pet/control.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        ControlConfig {
+            enabled: false,
+            port: 18795,
+            token: String::new(),
+        }
+    }
+}
+
+/// 128 bits from the OS random source, as hex.
+pub(crate) fn fresh_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("random token: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn path(app: &AppHandle) -> PathBuf {
+    app.state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .data_dir
+        .join(FILE)
+}
+
+fn save(path: &Path, cfg: &ControlConfig) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(fsutil::long_path(dir))
+            .map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    let json = serde_json::to_vec_pretty(cfg).map_err(|e| e.to_string())?;
+    fsutil::write_atomic(path, &json)
+}
+
+/// Creates the file on first use, and fills in a missing or empty `token`,
+/// so scripts always find one there. A broken file keeps the API off
+/// rather than guessing.
+fn load(path: &Path) -> ControlConfig {
+    let mut cfg = if fsutil::exists(path) {
+        let parsed = fsutil::read_text(path)
+            .and_then(|raw| serde_json::from_str(&raw).map_err(|e| format!("{FILE}: {e}")));
+        match parsed {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                log!("⚠️ {e}; control API off");
+                return ControlConfig {
+                    enabled: false,
+                    ..ControlConfig::default()
+                };
+            }
+        }
+    } else {
+        ControlConfig::default()
+    };
+    if cfg.token.trim().is_empty() {
+        match fresh_token() {
+            Ok(token) => cfg.token = token,
+            Err(e) => {
+                log!("⚠️ {FILE}: {e}");
+                return cfg;
+            }
+        }
+        if let Err(e) = save(path, &cfg) {
+            log!("⚠️ {FILE}: {e}");
+        }
+    }
+    cfg
+}
+
+// ── requests ──
+
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: impl Read) -> std::io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.to_string();
+
+    let (mut token, mut length) = (status::query_param(query, "token"), 0usize);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            token = value.strip_prefix("Bearer ").map(str::to_string);
+        } else if name.eq_ignore_ascii_case("content-length") {
+            length = value.parse().unwrap_or_default();
+        }
+    }
+    let mut body = Vec::new();
+    reader
+        .take(length.min(MAX_BODY_BYTES) as u64)
+        .read_to_end(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        token,
+        body,
+    })
+}
+
+type Reply = (&'static str, String);
+
+fn json_reply<T: Serialize>(status: &'static str, value: &T) -> Reply {
+    (status, serde_json::to_string(value).unwrap_or_default())
+}
+
+fn error(status: &'static str, message: impl Into<String>) -> Reply {
+    json_reply(status, &serde_json::json!({ "error": message.into() }))
+}
+
+/// The block a `PUT /settings/<block>` names; `permissions` never
+/// changes over HTTP, whatever the caller's scopes.
+fn settings_key(path: &str) -> Result<&str, Reply> {
+    let key = path.strip_prefix("/settings/").unwrap_or(path);
+    if key == "permissions" {
+        return Err(error(
+            "403 Forbidden",
+            "`permissions` is only changed in settings.json",
+        ));
+    }
+    Ok(key)
+}
+
+/// Validated against the whole schema before the file is touched.
+fn put_settings(app: &AppHandle, key: &str, body: &[u8]) -> Reply {
+    let value: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(value) => value,
+        Err(e) => return error("400 Bad Request", format!("{key}: {e}")),
+    };
+    let settings_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    let mut probe = serde_json::to_value(settings::load(&settings_path)).unwrap_or_default();
+    let Some(slot) = probe.get_mut(key) else {
+        return error("404 Not Found", format!("no settings block `{key}`"));
+    };
+    *slot = value.clone();
+    if let Err(e) = serde_json::from_value::<Settings>(probe) {
+        return error("400 Bad Request", format!("{key}: {e}"));
+    }
+    match settings::save_block(&settings_path, key, &value) {
+        Ok(()) => json_reply("200 OK", &serde_json::json!({ "saved": key })),
+        Err(e) => error("500 Internal Server Error", e),
+    }
+}
+
+/// What a request needs beyond a valid token; reads need nothing.
+fn scope_for(method: &str, path: &str) -> Option<Scope> {
+    match (method, path) {
+        ("PUT", "/state") | ("POST", "/ci") => Some(Scope::SetState),
+        ("POST", "/reload" | "/say") => Some(Scope::Commands),
+        ("PUT", p) if p.starts_with("/settings/") => Some(Scope::ChangeSettings),
+        _ => None,
+    }
+}
+
+fn route(app: &AppHandle, req: &Request, caller: &Caller) -> Reply {
+    let action = format!("{} {}", req.method, req.path);
+    if let Some(scope) = scope_for(&req.method, &req.path) {
+        if let Err(e) = permissions::authorize(app, caller, scope, &action) {
+            return error("403 Forbidden", e);
+        }
+    }
+    match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/state") => match crate::current_state(app.state(), app.state()) {
+            Ok(state) => json_reply("200 OK", &state),
+            Err(e) => json_reply("503 Service Unavailable", &e),
+        },
+        ("PUT", "/state") => {
+            let state: PetState = match serde_json::from_slice(&req.body) {
+                Ok(state) => state,
+                Err(e) => return error("400 Bad Request", format!("state: {e}")),
+            };
+            let written = crate::write_state(
+                app.clone(),
+                Some(state),
+                None,
+                Some(api::IPC_REVISION),
+                app.state(),
+                app.state(),
+            );
+            match written {
+                Ok(state) => json_reply("200 OK", &state),
+                Err(e) => error("409 Conflict", e),
+            }
+        }
+        ("POST", "/reload") => {
+            watch::reload_all(app);
+            json_reply("200 OK", &serde_json::json!({ "reloaded": true }))
+        }
+        ("POST", "/say") => {
+            let bubble: bubble::Bubble = match serde_json::from_slice(&req.body) {
+                Ok(bubble) => bubble,
+                Err(e) => return error("400 Bad Request", format!("bubble: {e}")),
+            };
+            match bubble::show(app, bubble) {
+                Ok(bubble) => json_reply("200 OK", &bubble),
+                Err(e) => error("422 Unprocessable Entity", e),
+            }
+        }
+        ("POST", "/ci") => match ci::receive(app, &req.body) {
+            Ok(status) => json_reply("200 OK", &status),
+            Err(e) => error("422 Unprocessable Entity", e),
+        },
+        ("PUT", p) if p.starts_with("/settings/") => match settings_key(p) {
+            Ok(key) => put_settings(app, key, &req.body),
+            Err(reply) => reply,
+        },
+        (_, "/state" | "/reload" | "/say" | "/ci") => {
+            error("405 Method Not Allowed", "method not allowed")
+        }
+        _ => error("404 Not Found", "not found"),
+    }
+}
+
+fn handle(app: &AppHandle, mut stream: TcpStream, token: &str) -> std::io::Result<()> {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let req = read_request(&stream)?;
+    let caller = Caller::from_token(app, Channel::Control, req.token.as_deref(), token);
+    let (status, body) = match caller {
+        Some(caller) => route(app, &req, &caller),
+        None => error("401 Unauthorized", "missing or wrong bearer token"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=utf-8\r\n\
+         Content-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())
+}
+
+// ── server ──
+
+/// (Re)starts the listener from `control.json`; an old one stops within
+/// [`ACCEPT_POLL`].
+pub(crate) fn start(app: AppHandle) {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let cfg = load(&path(&app));
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.token.is_empty() {
+        log!("⚠️ {FILE}: empty token, control API off");
+        return;
+    }
+    // The listener being replaced may hold the port for one more poll.
+    let mut bound = TcpListener::bind(("127.0.0.1", cfg.port));
+    for _ in 0..5 {
+        if bound.is_ok() {
+            break;
+        }
+        std::thread::sleep(ACCEPT_POLL);
+        bound = TcpListener::bind(("127.0.0.1", cfg.port));
+    }
+    let listener = match bound {
+        Ok(listener) => listener,
+        Err(e) => {
+            log!("⚠️ control API on port {}: {e}", cfg.port);
+            return;
+        }
+    };
+    if let Err(e) = listener.set_nonblocking(true) {
+        log!("⚠️ control API: {e}");
+        return;
+    }
+    log!("🎛️ control API on 127.0.0.1:{}", cfg.port);
+    std::thread::spawn(move || {
+        while GENERATION.load(Ordering::SeqCst) == generation {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let (app, token) = (app.clone(), cfg.token.clone());
+                    std::thread::spawn(move || {
+                        if let Err(e) = handle(&app, stream, &token) {
+                            log!("⚠️ control request: {e}");
+                        }
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(ACCEPT_POLL);
+                }
+                Err(e) => log!("⚠️ control API: {e}"),
+            }
+        }
+    });
+}
+
+/// Stops the listener within [`ACCEPT_POLL`]; used on exit.
+pub(crate) fn stop() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn get_control_api(app: AppHandle) -> ControlConfig {
+    load(&path(&app))
+}
+
+/// Unset fields keep their value; `rotate_token` issues a new token, which
+/// clients then have to read again. Applied right away.
+#[tauri::command]
+pub fn set_control_api(
+    app: AppHandle,
+    enabled: Option<bool>,
+    port: Option<u16>,
+    rotate_token: Option<bool>,
+) -> Result<ControlConfig, String> {
+    let path = path(&app);
+    let mut cfg = load(&path);
+    cfg.enabled = enabled.unwrap_or(cfg.enabled);
+    cfg.port = port.unwrap_or(cfg.port);
+    if rotate_token.unwrap_or(false) {
+        cfg.token = fresh_token()?;
+    }
+    save(&path, &cfg)?;
+    start(app);
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_need_a_scope_and_reads_do_not() {
+        assert_eq!(scope_for("GET", "/state"), None);
+        assert_eq!(scope_for("PUT", "/state"), Some(Scope::SetState));
+        assert_eq!(scope_for("POST", "/ci"), Some(Scope::SetState));
+        assert_eq!(scope_for("POST", "/reload"), Some(Scope::Commands));
+        assert_eq!(scope_for("POST", "/say"), Some(Scope::Commands));
+        assert_eq!(
+            scope_for("PUT", "/settings/sounds"),
+            Some(Scope::ChangeSettings)
+        );
+        assert_eq!(
+            scope_for("PUT", "/settings/permissions"),
+            Some(Scope::ChangeSettings)
+        );
+        assert_eq!(scope_for("DELETE", "/state"), None);
+        assert_eq!(scope_for("GET", "/settings/sounds"), None);
+    }
+
+    #[test]
+    fn permissions_block_is_forbidden_over_http() {
+        assert_eq!(settings_key("/settings/sounds").ok(), Some("sounds"));
+        let (status, body) = settings_key("/settings/permissions").unwrap_err();
+        assert_eq!(status, "403 Forbidden");
+        assert!(body.contains("only changed in settings.json"), "{body}");
+    }
+
+    #[test]
+    fn token_comes_from_the_bearer_header_or_query() {
+        let raw = "PUT /state HTTP/1.1\r\nAuthorization: Bearer abc\r\n\
+                   Content-Length: 2\r\n\r\n{}";
+        let req = read_request(raw.as_bytes()).unwrap();
+        assert_eq!((req.method.as_str(), req.path.as_str()), ("PUT", "/state"));
+        assert_eq!(req.token.as_deref(), Some("abc"));
+        assert_eq!(req.body, b"{}");
+
+        let req = read_request("POST /ci?token=xyz HTTP/1.1\r\n\r\n".as_bytes()).unwrap();
+        assert_eq!(req.path, "/ci");
+        assert_eq!(req.token.as_deref(), Some("xyz"));
+
+        let req = read_request("GET /state HTTP/1.1\r\n\r\n".as_bytes()).unwrap();
+        assert_eq!(req.token, None);
+    }
+}
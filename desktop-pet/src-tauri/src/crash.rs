@@ -0,0 +1,156 @@
+//! Panic reports for bug reports: the hook writes the message, location,
+//! backtrace, recent log lines and version to `.star-pet/crashes/`, and
+//! the next launch surfaces the newest one through `get_last_crash`.
+//!
+//! Only Rust panics are caught; a native crash (e.g. in the webview)
+//! leaves no report.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::logs::{self, log};
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+/// Older reports are pruned when a new one is written.
+const KEEP: usize = 10;
+const SEEN_MARKER: &str = "last-seen";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    version: String,
+    at: String,
+    os: String,
+    arch: String,
+    thread: String,
+    message: String,
+    location: Option<String>,
+    backtrace: String,
+    log_tail: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LastCrash {
+    path: PathBuf,
+    /// `false` the first time this report is returned.
+    seen: bool,
+    report: CrashReport,
+}
+
+fn dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("crashes")
+}
+
+fn payload_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".into())
+}
+
+/// Chains onto the existing hook, so the usual stderr message still prints.
+pub(crate) fn install(data_dir: &Path) {
+    let dir = dir(data_dir);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let report = CrashReport {
+            version: env!("CARGO_PKG_VERSION").into(),
+            at: chrono::Local::now().to_rfc3339(),
+            os: std::env::consts::OS.into(),
+            arch: std::env::consts::ARCH.into(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("unnamed")
+                .to_string(),
+            message: payload_message(info),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            log_tail: logs::tail(),
+        };
+        match write(&dir, &report) {
+            Ok(path) => eprintln!("💥 crash report: {}", path.display()),
+            Err(e) => eprintln!("⚠️ crash report: {e}"),
+        }
+    }));
+}
+
+fn write(dir: &Path, report: &CrashReport) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(fsutil::long_path(dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(format!(
+        "crash-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    let json = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(fsutil::long_path(&path), json)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    for old in reports(dir).into_iter().skip(KEEP) {
+        let _ = std::fs::remove_file(fsutil::long_path(&old));
+    }
+    Ok(path)
+}
+
+/// Report files, newest first (names sort by timestamp).
+fn reports(dir: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = std::fs::read_dir(fsutil::long_path(dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("crash-") && n.ends_with(".json"))
+        })
+        .collect();
+    found.sort();
+    found.reverse();
+    found
+}
+
+/// Logged once at startup so the terminal shows the previous run died.
+pub(crate) fn announce_unseen(data_dir: &Path) {
+    if let Ok(Some(last)) = last(data_dir, false) {
+        if !last.seen {
+            log!("💥 previous run crashed: {}", last.path.display());
+        }
+    }
+}
+
+fn last(data_dir: &Path, mark_seen: bool) -> Result<Option<LastCrash>, String> {
+    let dir = dir(data_dir);
+    let Some(path) = reports(&dir).into_iter().next() else {
+        return Ok(None);
+    };
+    let report: CrashReport = serde_json::from_str(&fsutil::read_text(&path)?)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let marker = dir.join(SEEN_MARKER);
+    let seen = fsutil::read_text(&marker)
+        .map(|s| s.trim() == name)
+        .unwrap_or(false);
+    if mark_seen && !seen {
+        std::fs::write(fsutil::long_path(&marker), &name)
+            .map_err(|e| format!("{}: {e}", marker.display()))?;
+    }
+    Ok(Some(LastCrash { path, seen, report }))
+}
+
+/// Newest crash report, if any. The first call after a crash has
+/// `seen: false`, so the frontend can offer it once without nagging.
+#[tauri::command]
+pub fn get_last_crash(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<Option<LastCrash>, String> {
+    let data_dir = paths.lock_or_recover().data_dir.clone();
+    last(&data_dir, true)
+}
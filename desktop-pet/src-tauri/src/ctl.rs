@@ -0,0 +1,353 @@
+//! Local control socket for `star-pet-ctl`: a Unix domain socket on
+//! Linux and macOS, a named pipe on Windows. Only processes of the same
+//! user on this machine can reach it.
+//!
+//! The running app writes where it listens to `.star-pet-ctl.json` in the
+//! project root. Each connection sends one JSON object per line and gets
+//! one back:
+//!
+//! | request                                  | reply                           |
+//! |------------------------------------------|---------------------------------|
+//! | `{ "cmd": "get-state" }`                 | `{ "ok": true, "state": … }`    |
+//! | `{ "cmd": "set-state", "state": { … } }` | `{ "ok": true, "state": … }`    |
+//! | `{ "cmd": "reload-assets" }`             | `{ "ok": true }`                |
+//!
+//! Failures are `{ "ok": false, "error": "…" }`. `set-state` needs the
+//! `ctl` channel's `set_state` scope, `reload-assets` its `commands` (see
+//! [`crate::permissions`]).
+
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::locate::find_project_root;
+use crate::logs::log;
+use crate::permissions::{self, Caller, Channel, Scope};
+use crate::sync::LockExt;
+use crate::{api, fsutil, watch, AppPaths, PetState};
+
+pub(crate) const FILE: &str = ".star-pet-ctl.json";
+/// Longest socket path every Unix accepts (`sun_path` is 104 bytes on macOS).
+#[cfg(unix)]
+const MAX_SOCKET_PATH: usize = 100;
+
+/// `.star-pet-ctl.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Endpoint {
+    pub pid: u32,
+    /// `unix` or `pipe`.
+    pub transport: String,
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+enum Request {
+    GetState,
+    SetState { state: PetState },
+    ReloadAssets,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Reply {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<PetState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Reply {
+    fn done(state: Option<PetState>) -> Reply {
+        Reply {
+            ok: true,
+            state,
+            error: None,
+        }
+    }
+
+    fn failed(error: impl Into<String>) -> Reply {
+        Reply {
+            ok: false,
+            state: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+fn answer(app: &AppHandle, line: &str) -> Reply {
+    let request = match serde_json::from_str::<Request>(line) {
+        Ok(request) => request,
+        Err(e) => return Reply::failed(format!("request: {e}")),
+    };
+    let caller = Caller::new(Channel::Ctl);
+    match request {
+        Request::GetState => match crate::current_state(app.state(), app.state()) {
+            Ok(state) => Reply::done(Some(state)),
+            Err(e) => Reply::failed(e.to_string()),
+        },
+        Request::SetState { state } => {
+            let action = format!("set-state {}", state.state);
+            if let Err(e) = permissions::authorize(app, &caller, Scope::SetState, &action) {
+                return Reply::failed(e);
+            }
+            let written = crate::write_state(
+                app.clone(),
+                Some(state),
+                None,
+                Some(api::IPC_REVISION),
+                app.state(),
+                app.state(),
+            );
+            match written {
+                Ok(state) => Reply::done(state),
+                Err(e) => Reply::failed(e),
+            }
+        }
+        Request::ReloadAssets => {
+            if let Err(e) = permissions::authorize(app, &caller, Scope::Commands, "reload-assets") {
+                return Reply::failed(e);
+            }
+            let reloaded = watch::layers_changed(app).and_then(|_| watch::map_changed(app));
+            match reloaded {
+                Ok(()) => Reply::done(None),
+                Err(e) => Reply::failed(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Answers requests until the client hangs up.
+fn session(app: &AppHandle, input: impl BufRead, mut output: impl Write) {
+    for line in input.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = serde_json::to_string(&answer(app, &line)).unwrap_or_default();
+        if writeln!(output, "{reply}")
+            .and_then(|_| output.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+fn endpoint_path(root: &Path) -> PathBuf {
+    root.join(FILE)
+}
+
+// ── server ──
+
+#[cfg(unix)]
+fn listen(app: AppHandle, data_dir: &Path) -> Result<Endpoint, String> {
+    use std::os::unix::net::UnixListener;
+
+    let mut path = data_dir.join("ctl.sock");
+    if path.as_os_str().len() > MAX_SOCKET_PATH {
+        path = std::env::temp_dir().join(format!("star-pet-{}.sock", std::process::id()));
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    // Left behind by an earlier run that didn't shut down cleanly.
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                let Ok(writer) = stream.try_clone() else {
+                    return;
+                };
+                session(&app, BufReader::new(stream), writer);
+            });
+        }
+    });
+    Ok(Endpoint {
+        pid: std::process::id(),
+        transport: "unix".into(),
+        path: path.display().to_string(),
+    })
+}
+
+#[cfg(windows)]
+fn listen(app: AppHandle, _: &Path) -> Result<Endpoint, String> {
+    use interprocess::local_socket::{prelude::*, GenericNamespaced, ListenerOptions};
+
+    let name = format!("star-pet-{}", std::process::id());
+    let listener = ListenerOptions::new()
+        .name(
+            name.as_str()
+                .to_ns_name::<GenericNamespaced>()
+                .map_err(|e| format!("pipe {name}: {e}"))?,
+        )
+        .create_sync()
+        .map_err(|e| format!("pipe {name}: {e}"))?;
+    std::thread::spawn(move || {
+        for conn in listener.incoming().flatten() {
+            let app = app.clone();
+            std::thread::spawn(move || session(&app, BufReader::new(&conn), &conn));
+        }
+    });
+    Ok(Endpoint {
+        pid: std::process::id(),
+        transport: "pipe".into(),
+        path: format!(r"\\.\pipe\{name}"),
+    })
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let endpoint = match listen(app, &paths.data_dir) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            log!("⚠️ control socket: {e}");
+            return;
+        }
+    };
+    let file = endpoint_path(&paths.root);
+    let json = serde_json::to_vec_pretty(&endpoint).unwrap_or_default();
+    match fsutil::write_atomic(&file, &json) {
+        Ok(()) => log!("🎛️ control socket at {}", endpoint.path),
+        Err(e) => log!("⚠️ {FILE}: {e}"),
+    }
+}
+
+/// Removes the socket and `.star-pet-ctl.json` on exit, unless another
+/// instance has taken the file over since.
+pub(crate) fn stop(app: &AppHandle) {
+    let root = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .root
+        .clone();
+    let file = endpoint_path(&root);
+    let Ok(raw) = fsutil::read_text(&file) else {
+        return;
+    };
+    let Ok(endpoint) = serde_json::from_str::<Endpoint>(&raw) else {
+        return;
+    };
+    if endpoint.pid != std::process::id() {
+        return;
+    }
+    if endpoint.transport == "unix" {
+        let _ = std::fs::remove_file(&endpoint.path);
+    }
+    let _ = std::fs::remove_file(fsutil::long_path(&file));
+}
+
+// ── client ──
+
+/// Control a running Star desktop pet.
+#[derive(Debug, Parser)]
+#[command(name = "star-pet-ctl", version, about)]
+struct Ctl {
+    /// Project root of the running pet (defaults to auto-detection).
+    #[arg(long, global = true)]
+    root: Option<PathBuf>,
+    #[command(subcommand)]
+    command: CtlCmd,
+}
+
+#[derive(Debug, Subcommand)]
+enum CtlCmd {
+    /// Print the current state as JSON.
+    GetState,
+    /// Set the state, as `write_state` does.
+    SetState {
+        state: String,
+        #[arg(long)]
+        detail: Option<String>,
+        /// Progress between 0 and 1.
+        #[arg(long)]
+        progress: Option<f64>,
+    },
+    /// Reload the layers and map.
+    ReloadAssets,
+}
+
+#[cfg(unix)]
+fn connect(endpoint: &Endpoint) -> std::io::Result<(Box<dyn BufRead>, Box<dyn Write>)> {
+    let stream = std::os::unix::net::UnixStream::connect(&endpoint.path)?;
+    let writer = stream.try_clone()?;
+    Ok((Box::new(BufReader::new(stream)), Box::new(writer)))
+}
+
+#[cfg(windows)]
+fn connect(endpoint: &Endpoint) -> std::io::Result<(Box<dyn BufRead>, Box<dyn Write>)> {
+    use interprocess::local_socket::{prelude::*, GenericNamespaced, Stream};
+
+    let name = endpoint.path.trim_start_matches(r"\\.\pipe\");
+    let stream = Stream::connect(name.to_ns_name::<GenericNamespaced>()?)?;
+    let (recv, send) = stream.split();
+    Ok((Box::new(BufReader::new(recv)), Box::new(send)))
+}
+
+fn request(root: &Path, request: &serde_json::Value) -> Result<Reply, String> {
+    let file = endpoint_path(root);
+    let raw = fsutil::read_text(&file).map_err(|e| format!("{e} (is the pet running?)"))?;
+    let endpoint: Endpoint =
+        serde_json::from_str(&raw).map_err(|e| format!("{}: {e}", file.display()))?;
+    let (mut input, mut output) = connect(&endpoint).map_err(|e| {
+        format!(
+            "{}: {e} (stale {FILE} from pid {}?)",
+            endpoint.path, endpoint.pid
+        )
+    })?;
+    writeln!(output, "{request}")
+        .and_then(|_| output.flush())
+        .map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    input.read_line(&mut line).map_err(|e| e.to_string())?;
+    serde_json::from_str(&line).map_err(|e| format!("reply: {e}"))
+}
+
+/// Entry point of the `star-pet-ctl` binary; returns the exit code.
+pub(crate) fn ctl_main() -> i32 {
+    let cli = Ctl::parse();
+    let body = match cli.command {
+        CtlCmd::GetState => serde_json::json!({ "cmd": "get-state" }),
+        CtlCmd::SetState {
+            state,
+            detail,
+            progress,
+        } => {
+            if progress.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+                eprintln!("⚠️ --progress must be between 0 and 1");
+                return 2;
+            }
+            let state =
+                serde_json::json!({ "state": state, "detail": detail, "progress": progress });
+            serde_json::json!({ "cmd": "set-state", "state": state })
+        }
+        CtlCmd::ReloadAssets => serde_json::json!({ "cmd": "reload-assets" }),
+    };
+    let root = cli.root.unwrap_or_else(find_project_root);
+    match request(&root, &body) {
+        Ok(Reply {
+            ok: true, state, ..
+        }) => {
+            if let Some(state) = state {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&state).unwrap_or_default()
+                );
+            }
+            0
+        }
+        Ok(reply) => {
+            eprintln!("⚠️ {}", reply.error.unwrap_or_default());
+            1
+        }
+        Err(e) => {
+            eprintln!("⚠️ {e}");
+            1
+        }
+    }
+}
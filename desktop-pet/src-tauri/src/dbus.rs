@@ -0,0 +1,143 @@
+//! `org.starooffice.Pet` on the Linux session bus, for shell scripts,
+//! GNOME extensions and systemd units:
+//!
+//! | member         | kind   | signature                                |
+//! |----------------|--------|------------------------------------------|
+//! | `SetState`     | method | `(s state, s detail)`                    |
+//! | `GetState`     | method | `() → (s state, s detail, d progress)`   |
+//! | `StateChanged` | signal | `(s state, s detail, d progress)`        |
+//!
+//! D-Bus has no null, so an empty `detail` means none and a `progress` of
+//! -1 means unset. `SetState` needs the `dbus` channel's `set_state` scope
+//! (see [`crate::permissions`]). Needs the `dbus` feature; elsewhere
+//! `start` does nothing.
+
+use crate::logs::log;
+
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+mod imp {
+    use std::sync::{Mutex, OnceLock};
+    use tauri::{AppHandle, Manager};
+    use zbus::blocking::{connection, Connection};
+    use zbus::object_server::InterfaceRef;
+    use zbus::{fdo, interface, SignalContext};
+
+    use crate::logs::log;
+    use crate::permissions::{self, Caller, Channel, Scope};
+    use crate::pet_core::{FsStateStore, StateStore};
+    use crate::sync::LockExt;
+    use crate::{AppPaths, PetState};
+
+    const BUS_NAME: &str = "org.starooffice.Pet";
+    const OBJECT_PATH: &str = "/org/starooffice/Pet";
+
+    /// Holding the connection keeps the bus name.
+    static BUS: OnceLock<(Connection, InterfaceRef<Pet>)> = OnceLock::new();
+
+    struct Pet {
+        app: AppHandle,
+    }
+
+    impl Pet {
+        fn store(&self) -> FsStateStore {
+            let paths = self.app.state::<Mutex<AppPaths>>();
+            let state_path = paths.lock_or_recover().state_path.clone();
+            FsStateStore::new(state_path)
+        }
+    }
+
+    fn flatten(state: &PetState) -> (String, String, f64) {
+        (
+            state.state.clone(),
+            state.detail.clone().unwrap_or_default(),
+            state.progress.unwrap_or(-1.0),
+        )
+    }
+
+    #[interface(name = "org.starooffice.Pet")]
+    impl Pet {
+        fn set_state(&self, state: &str, detail: &str) -> fdo::Result<()> {
+            if state.trim().is_empty() {
+                return Err(fdo::Error::InvalidArgs("state must not be empty".into()));
+            }
+            let caller = Caller::new(Channel::Dbus);
+            let action = format!("SetState {state}");
+            permissions::authorize(&self.app, &caller, Scope::SetState, &action)
+                .map_err(fdo::Error::AccessDenied)?;
+            let next = PetState {
+                state: state.to_string(),
+                detail: (!detail.is_empty()).then(|| detail.to_string()),
+                progress: None,
+                updated_at: Some(chrono::Local::now().to_rfc3339()),
+                progress_sources: Default::default(),
+            };
+            self.store().save(&next).map_err(fdo::Error::Failed)
+        }
+
+        fn get_state(&self) -> fdo::Result<(String, String, f64)> {
+            let state = self.store().load().map_err(fdo::Error::Failed)?;
+            Ok(flatten(&crate::present(state)))
+        }
+
+        #[zbus(signal)]
+        async fn state_changed(
+            ctxt: &SignalContext<'_>,
+            state: &str,
+            detail: &str,
+            progress: f64,
+        ) -> zbus::Result<()>;
+    }
+
+    pub(super) fn start(app: AppHandle) -> Result<(), String> {
+        let pet = Pet { app: app.clone() };
+        let conn = connection::Builder::session()
+            .and_then(|b| b.name(BUS_NAME))
+            .and_then(|b| b.serve_at(OBJECT_PATH, pet))
+            .and_then(|b| b.build())
+            .map_err(|e| format!("D-Bus {BUS_NAME}: {e}"))?;
+        log!("🚌 D-Bus: {BUS_NAME} at {OBJECT_PATH}");
+
+        let iface = conn
+            .object_server()
+            .interface::<_, Pet>(OBJECT_PATH)
+            .map_err(|e| format!("D-Bus {OBJECT_PATH}: {e}"))?;
+        let _ = BUS.set((conn, iface));
+        Ok(())
+    }
+
+    pub(super) fn publish(state: &PetState) {
+        let Some((_, iface)) = BUS.get() else {
+            return;
+        };
+        let (name, detail, progress) = flatten(state);
+        let sent = zbus::block_on(Pet::state_changed(
+            iface.signal_context(),
+            &name,
+            &detail,
+            progress,
+        ));
+        if let Err(e) = sent {
+            log!("⚠️ D-Bus StateChanged: {e}");
+        }
+    }
+}
+
+#[cfg(not(all(feature = "dbus", target_os = "linux")))]
+mod imp {
+    pub(super) fn start(_: tauri::AppHandle) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub(super) fn publish(_: &crate::PetState) {}
+}
+
+pub(crate) fn start(app: tauri::AppHandle) {
+    if let Err(e) = imp::start(app) {
+        log!("⚠️ {e}");
+    }
+}
+
+/// Sends `StateChanged`; does nothing before `start` got the bus.
+pub(crate) fn publish(state: &crate::PetState) {
+    imp::publish(state);
+}
@@ -0,0 +1,159 @@
+//! `--dev` mode for pack authors: a no-cache localhost asset server and a
+//! timed log of every hot reload, exposed through `get_dev_status`.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::logs::log;
+use crate::pet_core::{self, AssetSource, FsAssets};
+use crate::sync::LockExt;
+
+pub(crate) const DEFAULT_ASSET_PORT: u16 = 18792;
+const LOG_CAPACITY: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReloadEntry {
+    pub subsystem: String,
+    pub at: String,
+    pub millis: f64,
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DevStatus {
+    enabled: bool,
+    asset_server: Option<String>,
+    reloads: u64,
+    recent: VecDeque<ReloadEntry>,
+}
+
+/// Managed by the app in every mode so `get_dev_status` always answers;
+/// only records anything when dev mode is on.
+#[derive(Default)]
+pub(crate) struct DevState(Mutex<DevStatus>);
+
+impl DevState {
+    pub(crate) fn new(enabled: bool) -> Self {
+        DevState(Mutex::new(DevStatus {
+            enabled,
+            ..DevStatus::default()
+        }))
+    }
+
+    pub(crate) fn record(&self, entry: ReloadEntry) {
+        let mut status = self.0.lock_or_recover();
+        if !status.enabled {
+            return;
+        }
+        let mark = if entry.ok { "🔁" } else { "⚠️" };
+        log!("{mark} reload {} in {:.1}ms", entry.subsystem, entry.millis);
+        status.reloads += 1;
+        if status.recent.len() == LOG_CAPACITY {
+            status.recent.pop_front();
+        }
+        status.recent.push_back(entry);
+    }
+
+    fn set_asset_server(&self, url: String) {
+        self.0.lock_or_recover().asset_server = Some(url);
+    }
+}
+
+#[tauri::command]
+pub fn get_dev_status(dev: tauri::State<'_, DevState>) -> DevStatus {
+    dev.0.lock_or_recover().clone()
+}
+
+// ── asset server ──
+
+/// Serves `layers_dir` on localhost with caching disabled, so a browser
+/// or the webview always sees the file as it is on disk right now.
+pub(crate) fn serve_assets(dev: &DevState, layers_dir: PathBuf, port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("dev asset server on 127.0.0.1:{port}: {e}"))?;
+    let url = format!("http://127.0.0.1:{port}/");
+    log!("🛠️ dev assets: {url} → {}", layers_dir.display());
+    dev.set_asset_server(url);
+
+    let assets = Arc::new(FsAssets::new(layers_dir));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let assets = Arc::clone(&assets);
+            std::thread::spawn(move || {
+                if let Err(e) = handle(stream, assets.as_ref()) {
+                    log!("⚠️ dev asset request: {e}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, assets: &FsAssets) -> std::io::Result<()> {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+
+    let rel = percent_decode(target.split('?').next().unwrap_or("/"));
+    let rel = rel.trim_start_matches('/');
+    let (status, mime, body) = if method != "GET" {
+        ("405 Method Not Allowed", "text/plain", b"GET only".to_vec())
+    } else if !is_safe_relative(rel) {
+        ("403 Forbidden", "text/plain", b"forbidden".to_vec())
+    } else {
+        match assets.read(rel) {
+            Ok(bytes) => ("200 OK", content_type(rel), bytes),
+            Err(_) => ("404 Not Found", "text/plain", b"not found".to_vec()),
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {mime}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
+
+fn content_type(rel: &str) -> &'static str {
+    if rel.ends_with(".json") {
+        "application/json; charset=utf-8"
+    } else {
+        pet_core::mime_for(rel)
+    }
+}
+
+/// Only plain relative paths inside the served directory.
+pub(crate) fn is_safe_relative(rel: &str) -> bool {
+    !rel.is_empty()
+        && Path::new(rel)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+pub(crate) fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(h), Some(l)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((h * 16 + l) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,325 @@
+//! End-of-day digest: once a day after `digest.at`, today's report (time
+//! per state, tasks completed, active time and breaks, see
+//! [`crate::report`]) goes out through each of `digest.deliver`:
+//!
+//! - `notification`: a one-line summary as a native notification
+//! - `markdown`: the full report as `.star-pet/reports/digest-<date>.md`
+//! - `webhook`: JSON POSTed to `digest.webhook_url` (needs the `net` feature)
+//! - `email`: the report as plain text over SMTP (needs the `mail` feature)
+//!
+//! The last day sent is kept in `stats/digest.json`, so a restart in the
+//! evening doesn't send it twice. `send_digest` sends one right away.
+//! `digest.at` and the day are read in `digest.timezone`, else the
+//! `clock` zone.
+
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::report::{self, Report};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{clock, fsutil, health, net, notify, power, stats, AppPaths};
+
+const STATS_NAME: &str = "digest";
+const POLL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    Notification,
+    Markdown,
+    Webhook,
+    Email,
+}
+
+impl Delivery {
+    fn name(self) -> &'static str {
+        match self {
+            Delivery::Notification => "notification",
+            Delivery::Markdown => "markdown",
+            Delivery::Webhook => "webhook",
+            Delivery::Email => "email",
+        }
+    }
+}
+
+/// `digest.smtp`. The password is read from the environment variable
+/// named by `password_env` rather than stored in `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Spoken to with STARTTLS.
+    pub port: u16,
+    /// Sent without logging in when empty.
+    pub username: String,
+    pub password_env: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        SmtpSettings {
+            host: String::new(),
+            port: 587,
+            username: String::new(),
+            password_env: "STAR_PET_SMTP_PASSWORD".to_string(),
+            from: String::new(),
+            to: String::new(),
+        }
+    }
+}
+
+/// `digest` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    /// Local time, `HH:MM`.
+    pub at: String,
+    /// IANA zone; `clock.timezone` or the system zone when unset.
+    pub timezone: Option<String>,
+    pub deliver: Vec<Delivery>,
+    pub webhook_url: String,
+    pub smtp: SmtpSettings,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        DigestSettings {
+            enabled: false,
+            at: "18:00".to_string(),
+            timezone: None,
+            deliver: vec![Delivery::Notification, Delivery::Markdown],
+            webhook_url: String::new(),
+            smtp: SmtpSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct History {
+    /// Local date, `YYYY-MM-DD`.
+    last_sent: Option<String>,
+}
+
+/// What `send_digest` returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestResult {
+    date: String,
+    summary: String,
+    delivered: Vec<Delivery>,
+    /// One per delivery that failed, prefixed with its name.
+    errors: Vec<String>,
+    /// The Markdown file, with `markdown` delivery.
+    file: Option<PathBuf>,
+}
+
+fn summary(r: &Report) -> String {
+    let mut line = format!(
+        "Tracked {} · {} task(s) · {} break(s)",
+        report::hours(r.tracked_secs),
+        r.tasks_completed,
+        r.breaks
+    );
+    if let Some((state, _)) = r.states.first() {
+        line += &format!(" · mostly {state}");
+    }
+    line
+}
+
+fn write_markdown(paths: &AppPaths, date: NaiveDate, body: &str) -> Result<PathBuf, String> {
+    let dir = report::dir(paths);
+    std::fs::create_dir_all(fsutil::long_path(&dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(format!("digest-{date}.md"));
+    fsutil::write_atomic(&path, body.as_bytes())?;
+    Ok(path)
+}
+
+fn webhook(cfg: &DigestSettings, r: &Report, summary: &str, body: &str) -> Result<(), String> {
+    if cfg.webhook_url.trim().is_empty() {
+        return Err("digest.webhook_url is empty".into());
+    }
+    let states: Vec<_> = r
+        .states
+        .iter()
+        .map(|(state, secs)| serde_json::json!({ "state": state, "secs": secs }))
+        .collect();
+    let payload = serde_json::json!({
+        "date": r.from.to_string(),
+        "summary": summary,
+        "tracked_secs": r.tracked_secs,
+        "tasks_completed": r.tasks_completed,
+        "active_secs": r.active_secs,
+        "longest_streak_mins": r.longest_streak_mins,
+        "breaks": r.breaks,
+        "states": states,
+        "markdown": body,
+    });
+    net::post_json(&cfg.webhook_url, &payload)
+}
+
+/// Sends today's digest through every configured delivery.
+fn send(app: &AppHandle, cfg: &DigestSettings) -> DigestResult {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let i18n = app.state::<I18n>();
+    let today = now(app, cfg).date_naive();
+    let r = report::compile(&paths, today, today, |state| {
+        report::state_label(&i18n, state)
+    });
+    let title = format!("Star Office digest {today}");
+    let (line, body) = (summary(&r), report::markdown(&r));
+    let mut result = DigestResult {
+        date: today.to_string(),
+        summary: line.clone(),
+        delivered: Vec::new(),
+        errors: Vec::new(),
+        file: None,
+    };
+    for &delivery in &cfg.deliver {
+        let outcome = match delivery {
+            Delivery::Notification => notify::show(&title, &line),
+            Delivery::Markdown => {
+                write_markdown(&paths, today, &body).map(|path| result.file = Some(path))
+            }
+            Delivery::Webhook => webhook(cfg, &r, &line, &body),
+            Delivery::Email => mail::send(&cfg.smtp, &title, &body),
+        };
+        match outcome {
+            Ok(()) => result.delivered.push(delivery),
+            Err(e) => {
+                log!("⚠️ digest {}: {e}", delivery.name());
+                result.errors.push(format!("{}: {e}", delivery.name()));
+            }
+        }
+    }
+    let mut history: History = stats::load(&paths.data_dir, STATS_NAME);
+    history.last_sent = Some(result.date.clone());
+    if let Err(e) = stats::save(&paths.data_dir, STATS_NAME, &history) {
+        log!("⚠️ digest stats: {e}");
+    }
+    log!("📬 digest {today}: {line}");
+    if result.errors.is_empty() {
+        health::success(app, "digest");
+    } else {
+        health::failure(app, "digest", result.errors.join("; "));
+    }
+    result
+}
+
+fn settings(app: &AppHandle) -> DigestSettings {
+    app.state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .digest
+        .clone()
+}
+
+/// The time in the zone `digest.at` is read in.
+fn now(app: &AppHandle, cfg: &DigestSettings) -> DateTime<Tz> {
+    let clock = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .clock
+        .clone();
+    let (tz, _) = clock::zone(cfg.timezone.as_deref(), &clock);
+    clock::now_in(tz)
+}
+
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut warned = false;
+        loop {
+            let cfg = settings(&app);
+            if cfg.enabled {
+                match clock::parse_time(&cfg.at) {
+                    Some(at) => {
+                        let now = now(&app, &cfg);
+                        let data_dir = app
+                            .state::<Mutex<AppPaths>>()
+                            .lock_or_recover()
+                            .data_dir
+                            .clone();
+                        let history: History = stats::load(&data_dir, STATS_NAME);
+                        let today = now.format("%Y-%m-%d").to_string();
+                        if now.time() >= at && history.last_sent.as_deref() != Some(today.as_str())
+                        {
+                            send(&app, &cfg);
+                        }
+                    }
+                    None if !warned => {
+                        log!("⚠️ digest.at `{}` isn't HH:MM; no digest", cfg.at);
+                        warned = true;
+                    }
+                    None => {}
+                }
+            }
+            std::thread::sleep(power::interval(&app, POLL));
+        }
+    });
+}
+
+/// Sends today's digest now, whether or not `digest.enabled` is set.
+#[tauri::command]
+pub async fn send_digest(app: AppHandle) -> Result<DigestResult, String> {
+    let cfg = settings(&app);
+    tauri::async_runtime::spawn_blocking(move || send(&app, &cfg))
+        .await
+        .map_err(|e| format!("digest task: {e}"))
+}
+
+#[cfg(feature = "mail")]
+mod mail {
+    use lettre::message::header::ContentType;
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{Message, SmtpTransport, Transport};
+
+    use super::SmtpSettings;
+
+    pub(super) fn send(cfg: &SmtpSettings, subject: &str, body: &str) -> Result<(), String> {
+        if cfg.host.trim().is_empty() {
+            return Err("digest.smtp.host is empty".into());
+        }
+        let email = Message::builder()
+            .from(
+                cfg.from
+                    .parse()
+                    .map_err(|e| format!("digest.smtp.from: {e}"))?,
+            )
+            .to(cfg.to.parse().map_err(|e| format!("digest.smtp.to: {e}"))?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| e.to_string())?;
+        let mut mailer = SmtpTransport::starttls_relay(&cfg.host)
+            .map_err(|e| format!("{}: {e}", cfg.host))?
+            .port(cfg.port);
+        if !cfg.username.is_empty() {
+            let password = std::env::var(&cfg.password_env).unwrap_or_default();
+            mailer = mailer.credentials(Credentials::new(cfg.username.clone(), password));
+        }
+        mailer
+            .build()
+            .send(&email)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {e}", cfg.host))
+    }
+}
+
+#[cfg(not(feature = "mail"))]
+mod mail {
+    use super::SmtpSettings;
+
+    pub(super) fn send(_: &SmtpSettings, _: &str, _: &str) -> Result<(), String> {
+        Err("this build has no email support (rebuild with `--features mail`)".into())
+    }
+}
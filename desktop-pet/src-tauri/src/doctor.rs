@@ -0,0 +1,292 @@
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::locate::{self, RootSource};
+use crate::pet_core::{self, FsAssets};
+use crate::sync::LockExt;
+use crate::validate::{self, Level};
+use crate::{fsutil, settings, AppPaths};
+
+// ── findings ──
+
+/// Ordered most to least urgent; the report is sorted on this.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Critical,
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub message: String,
+    /// What the user should do about it, phrased for a setup wizard.
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    pub(crate) root: PathBuf,
+    pub(crate) root_source: RootSource,
+    pub(crate) healthy: bool,
+    pub(crate) findings: Vec<Finding>,
+}
+
+struct Findings(Vec<Finding>);
+
+impl Findings {
+    fn add(
+        &mut self,
+        severity: Severity,
+        check: &'static str,
+        message: impl Into<String>,
+        fix: Option<&str>,
+    ) {
+        self.0.push(Finding {
+            severity,
+            check,
+            message: message.into(),
+            fix: fix.map(str::to_string),
+        });
+    }
+}
+
+// ── checks ──
+
+pub(crate) fn diagnose(root: &Path, source: RootSource) -> DoctorReport {
+    let mut f = Findings(Vec::new());
+    check_root(&mut f, root, source);
+    check_configs(&mut f, root);
+    check_asset_paths(&mut f, root);
+    check_permissions(&mut f, root);
+    check_webview(&mut f);
+    check_backend(&mut f, root);
+
+    f.0.sort_by_key(|finding| finding.severity);
+    let healthy = !f.0.iter().any(|x| x.severity <= Severity::Error);
+    DoctorReport {
+        root: root.to_path_buf(),
+        root_source: source,
+        healthy,
+        findings: f.0,
+    }
+}
+
+fn check_root(f: &mut Findings, root: &Path, source: RootSource) {
+    if std::env::var_os("STAR_PROJECT_ROOT").is_some() && source != RootSource::Env {
+        f.add(
+            Severity::Warning,
+            "root",
+            "STAR_PROJECT_ROOT is set but does not contain backend/app.py, so it was ignored",
+            Some("Point STAR_PROJECT_ROOT at the Star-Office-UI checkout"),
+        );
+    }
+    match source {
+        RootSource::Fallback => f.add(
+            Severity::Critical,
+            "root",
+            format!(
+                "no project root found; using the working directory {}",
+                root.display()
+            ),
+            Some("Run from inside the Star-Office-UI checkout or set STAR_PROJECT_ROOT"),
+        ),
+        RootSource::HomeGuess => f.add(
+            Severity::Info,
+            "root",
+            format!(
+                "project root guessed from the home directory: {}",
+                root.display()
+            ),
+            Some("Set STAR_PROJECT_ROOT to make this explicit"),
+        ),
+        _ => f.add(
+            Severity::Info,
+            "root",
+            format!("project root: {}", root.display()),
+            None,
+        ),
+    }
+}
+
+fn check_configs(f: &mut Findings, root: &Path) {
+    for issue in validate::validate_root(root).issues {
+        let severity = match issue.level {
+            Level::Error => Severity::Error,
+            Level::Warning => Severity::Warning,
+        };
+        let fix = format!("Edit {} and re-run the check", issue.file);
+        f.add(
+            severity,
+            "config",
+            format!("{}: {}", issue.file, issue.message),
+            Some(&fix),
+        );
+    }
+}
+
+/// Paths in `layers.json` should be relative, stay inside `layers/`, and
+/// use `/` so packs work on every OS.
+fn check_asset_paths(f: &mut Findings, root: &Path) {
+    let (paths, _) = crate::resolve_paths(root);
+    let limits = settings::load(&paths.settings_path).limits;
+    let Ok(cfg) = pet_core::read_layers_cfg(&FsAssets::new(root.join("layers")), &limits) else {
+        return; // already reported by check_configs
+    };
+    let layer_files = cfg.layers.unwrap_or_default().into_iter().map(|l| l.image);
+    let pets = cfg.characters.unwrap_or_default();
+    let skins = cfg.skins.unwrap_or_default().into_values().map(Some);
+    let anim_files = std::iter::once(cfg.sprites)
+        .chain(pets.into_iter().map(|p| p.sprites))
+        .chain(skins)
+        .flatten()
+        .flat_map(|s| s.anims.unwrap_or_default().into_values())
+        .map(|a| a.file);
+    for rel in layer_files.chain(anim_files) {
+        let path = Path::new(&rel);
+        let problem = if path.is_absolute() {
+            Some("is absolute")
+        } else if path
+            .components()
+            .any(|c| c == std::path::Component::ParentDir)
+        {
+            Some("points outside layers/")
+        } else if rel.contains('\\') {
+            Some("uses backslashes, which only work on Windows")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            f.add(
+                Severity::Warning,
+                "asset-path",
+                format!("layers.json: `{rel}` {problem}"),
+                Some("Use a relative path under layers/ with forward slashes"),
+            );
+        }
+    }
+}
+
+fn check_permissions(f: &mut Findings, root: &Path) {
+    let probe = root.join(".star-doctor-probe");
+    let writable = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(fsutil::long_path(&probe))
+        .is_ok();
+    let _ = std::fs::remove_file(fsutil::long_path(&probe));
+    if !writable {
+        f.add(
+            Severity::Error,
+            "permissions",
+            format!(
+                "cannot write to {}; state.json updates will fail",
+                root.display()
+            ),
+            Some("Make the project directory writable for the current user"),
+        );
+    }
+    let layers = root.join("layers");
+    if fsutil::exists(&layers) && std::fs::read_dir(fsutil::long_path(&layers)).is_err() {
+        f.add(
+            Severity::Error,
+            "permissions",
+            format!("cannot list {}", layers.display()),
+            Some("Make layers/ readable for the current user"),
+        );
+    }
+}
+
+fn command_succeeds(cmd: &mut Command) -> bool {
+    cmd.output().map(|o| o.status.success()).unwrap_or(false)
+}
+
+fn check_webview(f: &mut Findings) {
+    #[cfg(target_os = "windows")]
+    let (found, fix) = (
+        command_succeeds(Command::new("reg").args([
+            "query",
+            r"HKLM\SOFTWARE\WOW6432Node\Microsoft\EdgeUpdate\Clients\{F3017226-FE2A-4295-8BDF-00C3A9A7E4C5}",
+            "/v",
+            "pv",
+        ])) || command_succeeds(Command::new("reg").args([
+            "query",
+            r"HKCU\Software\Microsoft\EdgeUpdate\Clients\{F3017226-FE2A-4295-8BDF-00C3A9A7E4C5}",
+            "/v",
+            "pv",
+        ])),
+        "Install the Microsoft Edge WebView2 Runtime",
+    );
+
+    #[cfg(target_os = "macos")]
+    let (found, fix) = (true, "");
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let (found, fix) = (
+        Command::new("ldconfig")
+            .arg("-p")
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).contains("libwebkit2gtk-4.1"))
+            .unwrap_or(false),
+        "Install webkit2gtk 4.1 (e.g. `sudo apt install libwebkit2gtk-4.1-0`)",
+    );
+
+    if !found {
+        f.add(
+            Severity::Critical,
+            "webview",
+            "system webview runtime not found",
+            Some(fix),
+        );
+    }
+}
+
+fn check_backend(f: &mut Findings, root: &Path) {
+    if std::net::TcpStream::connect("127.0.0.1:18791").is_ok() {
+        return;
+    }
+    if !fsutil::exists(&root.join("backend").join("app.py")) {
+        f.add(
+            Severity::Warning,
+            "backend",
+            "backend/app.py not found and nothing listens on 127.0.0.1:18791",
+            Some("Start the Star-Office-UI backend or fix the project root"),
+        );
+        return;
+    }
+    let python = std::env::var_os("STAR_BACKEND_PYTHON")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| crate::venv_python(root));
+    let usable = command_succeeds(Command::new(&python).arg("--version"))
+        || command_succeeds(Command::new("python3").arg("--version"));
+    if !usable {
+        f.add(
+            Severity::Error,
+            "backend",
+            "no Python interpreter found to start the backend",
+            Some("Create .venv (see README) or set STAR_BACKEND_PYTHON"),
+        );
+    }
+}
+
+// ── command ──
+
+#[tauri::command]
+pub fn run_doctor(paths: tauri::State<'_, Mutex<AppPaths>>) -> DoctorReport {
+    let root = paths.lock_or_recover().root.clone();
+    let (resolved, source) = locate::resolve_project_root();
+    let source = if resolved == root {
+        source
+    } else {
+        RootSource::Explicit
+    };
+    diagnose(&root, source)
+}
@@ -0,0 +1,268 @@
+//! In-app edit mode: patch layer and character placement live, optionally
+//! writing the result back to `layers.json`.
+//!
+//! Unsaved edits are kept as a patched copy of `layers.json` and served to
+//! `load_layers` through an [`Overlay`], so a reload shows them too. Each
+//! patch emits a small event instead of forcing a full `layers-updated`:
+//!
+//! | command            | event               | payload                      |
+//! |--------------------|---------------------|------------------------------|
+//! | `update_layer`     | `layer-patched`     | `{ index, layer }`           |
+//! | `update_character` | `character-patched` | the `character` object       |
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::limits::{self, Limits};
+use crate::pet_core::{self, AssetSource, FsAssets, Overlay};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths, CfgFile};
+
+/// A write-back lands on the watcher shortly after; it is ours, not an
+/// outside change, so it should not trigger a full reload.
+const OWN_WRITE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum LayerRef {
+    Index(usize),
+    /// The layer's `image`, with or without its extension.
+    Name(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerPatch {
+    x: Option<f64>,
+    y: Option<f64>,
+    depth: Option<i32>,
+    scale: Option<f64>,
+    alpha: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CharacterPatch {
+    x: Option<f64>,
+    y: Option<f64>,
+    scale: Option<f64>,
+    depth: Option<i32>,
+    wander: Option<f64>,
+}
+
+#[derive(Default)]
+struct Session {
+    /// Patched `layers.json` not yet written to disk.
+    unsaved: Option<Value>,
+    last_write: Option<Instant>,
+}
+
+/// Managed by the app for the lifetime of the process.
+#[derive(Default)]
+pub(crate) struct EditSession(Mutex<Session>);
+
+impl EditSession {
+    /// The pack as `load_layers` should see it, unsaved edits included.
+    pub(crate) fn with_edits<T>(
+        &self,
+        base: &dyn AssetSource,
+        f: impl FnOnce(&dyn AssetSource) -> T,
+    ) -> T {
+        let unsaved = self
+            .0
+            .lock_or_recover()
+            .unsaved
+            .as_ref()
+            .map(Value::to_string);
+        match unsaved {
+            Some(raw) => f(&Overlay::new(base).with("layers.json", raw)),
+            None => f(base),
+        }
+    }
+
+    pub(crate) fn is_own_write(&self) -> bool {
+        self.0
+            .lock_or_recover()
+            .last_write
+            .is_some_and(|at| at.elapsed() < OWN_WRITE_WINDOW)
+    }
+}
+
+// ── patching ──
+
+fn load_raw(session: &Session, dir: &Path, limits: &Limits) -> Result<Value, String> {
+    if let Some(unsaved) = &session.unsaved {
+        return Ok(unsaved.clone());
+    }
+    let src = FsAssets::new(dir);
+    if !src.exists("layers.json") {
+        return Ok(json!({}));
+    }
+    let raw = pet_core::read_config(&src, "layers.json", limits)?;
+    serde_json::from_str(&raw).map_err(|e| format!("layers.json: {e}"))
+}
+
+/// Copies an inherited `layers` list into `cfg`. Arrays replace rather than
+/// merge, so patching one layer of an `extends` variant writes out the list.
+fn own_layers(cfg: &mut Value, dir: &Path, limits: &Limits) -> Result<(), String> {
+    let Some(root) = cfg.as_object_mut().filter(|r| !r.contains_key("layers")) else {
+        return Ok(());
+    };
+    if !root.contains_key("extends") && !root.contains_key("include") {
+        return Ok(());
+    }
+    let fs = FsAssets::new(dir);
+    let src = Overlay::new(&fs).with("layers.json", Value::Object(root.clone()).to_string());
+    let merged = pet_core::read_config_merged(&src, "layers.json", limits)?;
+    if let Some(layers) = merged.get("layers") {
+        root.insert("layers".into(), layers.clone());
+    }
+    Ok(())
+}
+
+fn apply<T: Serialize>(target: &mut Map<String, Value>, patch: &T) -> Result<(), String> {
+    let Value::Object(fields) = serde_json::to_value(patch).map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+    for (key, value) in fields {
+        if !value.is_null() {
+            target.insert(key, value);
+        }
+    }
+    Ok(())
+}
+
+fn check_ranges(scale: Option<f64>, alpha: Option<f64>) -> Result<(), String> {
+    if let Some(scale) = scale.filter(|s| !(s.is_finite() && *s > 0.0)) {
+        return Err(format!("scale must be greater than 0, got {scale}"));
+    }
+    if let Some(alpha) = alpha.filter(|a| !(0.0..=1.0).contains(a)) {
+        return Err(format!("alpha must be between 0 and 1, got {alpha}"));
+    }
+    Ok(())
+}
+
+fn find_layer(layers: &[Value], target: &LayerRef) -> Result<usize, String> {
+    match target {
+        LayerRef::Index(i) if *i < layers.len() => Ok(*i),
+        LayerRef::Index(i) => Err(format!("layer {i} out of range ({} layers)", layers.len())),
+        LayerRef::Name(name) => layers
+            .iter()
+            .position(|layer| {
+                let image = layer.get("image").and_then(Value::as_str).unwrap_or("");
+                image == name
+                    || Path::new(image).file_stem().and_then(|s| s.to_str()) == Some(name.as_str())
+            })
+            .ok_or_else(|| format!("no layer with image `{name}`")),
+    }
+}
+
+/// Validates the patched config, then either keeps it in memory or
+/// writes it back and drops the in-memory copy.
+fn commit(
+    session: &mut Session,
+    dir: &Path,
+    limits: &Limits,
+    cfg: Value,
+    persist: bool,
+) -> Result<(), String> {
+    let parsed: CfgFile =
+        serde_json::from_value(cfg.clone()).map_err(|e| format!("layers.json: {e}"))?;
+    limits::check_layers(&parsed, limits)?;
+    if persist {
+        let path = dir.join("layers.json");
+        let pretty = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+        session.last_write = Some(Instant::now());
+        fsutil::write_atomic(&path, pretty.as_bytes())?;
+        session.unsaved = None;
+    } else {
+        session.unsaved = Some(cfg);
+    }
+    Ok(())
+}
+
+/// Read before taking the session lock; `load_layers` locks in the
+/// other order.
+fn context(app: &AppHandle) -> (PathBuf, Limits) {
+    let dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone();
+    let limits = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .limits
+        .clone();
+    (dir, limits)
+}
+
+// ── commands ──
+
+/// Patches one entry of `layers`, addressed by index or image name.
+/// Returns the patched entry.
+#[tauri::command]
+pub fn update_layer(
+    app: AppHandle,
+    target: LayerRef,
+    patch: LayerPatch,
+    persist: Option<bool>,
+) -> Result<Value, String> {
+    check_ranges(patch.scale, patch.alpha)?;
+    let (dir, limits) = context(&app);
+    let edits = app.state::<EditSession>();
+    let mut session = edits.0.lock_or_recover();
+    let mut cfg = load_raw(&session, &dir, &limits)?;
+    own_layers(&mut cfg, &dir, &limits)?;
+
+    let layers = cfg
+        .get_mut("layers")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| "layers.json has no layers".to_string())?;
+    let index = find_layer(layers, &target)?;
+    let layer = layers[index]
+        .as_object_mut()
+        .ok_or_else(|| format!("layers[{index}] is not an object"))?;
+    apply(layer, &patch)?;
+    let patched = Value::Object(layer.clone());
+
+    commit(&mut session, &dir, &limits, cfg, persist.unwrap_or(false))?;
+    drop(session);
+    let _ = app.emit("layer-patched", json!({ "index": index, "layer": patched }));
+    Ok(patched)
+}
+
+/// Patches the `character` block. Returns the patched block.
+#[tauri::command]
+pub fn update_character(
+    app: AppHandle,
+    patch: CharacterPatch,
+    persist: Option<bool>,
+) -> Result<Value, String> {
+    check_ranges(patch.scale, None)?;
+    let (dir, limits) = context(&app);
+    let edits = app.state::<EditSession>();
+    let mut session = edits.0.lock_or_recover();
+    let mut cfg = load_raw(&session, &dir, &limits)?;
+
+    let root = cfg
+        .as_object_mut()
+        .ok_or_else(|| "layers.json is not an object".to_string())?;
+    let character = root
+        .entry("character")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| "layers.json: character is not an object".to_string())?;
+    apply(character, &patch)?;
+    let patched = Value::Object(character.clone());
+
+    commit(&mut session, &dir, &limits, cfg, persist.unwrap_or(false))?;
+    drop(session);
+    let _ = app.emit("character-patched", &patched);
+    Ok(patched)
+}
@@ -0,0 +1,120 @@
+//! `AppError`: what `read_state`, `load_layers` and `load_map` reject with
+//! from IPC revision 3, as `{ "kind": …, "message": …, … }` so the frontend
+//! can branch on the kind and point at the file to fix.
+//!
+//! The loaders build it where things go wrong: an `io::Error` becomes `io`
+//! or `missing_asset` by its kind, a `serde_json::Error` `parse` with its
+//! line and column, a `limits` check `limit_exceeded`. Callers still on
+//! `Result<_, String>` take the message through `?`.
+
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+/// Every kind carries the full `message`, worded as before revision 3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppError {
+    /// Reading a file failed for a reason other than it missing.
+    Io { path: String, message: String },
+    /// A config isn't valid JSON, or has a field of the wrong type.
+    Parse {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A config or asset it refers to doesn't exist.
+    MissingAsset { path: String, message: String },
+    /// Over one of the `limits` in `settings.json`.
+    LimitExceeded { message: String },
+    /// The observed pet or the local backend didn't answer.
+    Backend { message: String },
+    /// Anything else, e.g. a bad value or an unknown skin.
+    Invalid { message: String },
+}
+
+impl AppError {
+    /// Reading `path` failed with `e`.
+    pub(crate) fn io(path: impl fmt::Display, e: &io::Error) -> AppError {
+        let (path, message) = (path.to_string(), format!("{path}: {e}"));
+        if e.kind() == io::ErrorKind::NotFound {
+            AppError::MissingAsset { path, message }
+        } else {
+            AppError::Io { path, message }
+        }
+    }
+
+    /// `file` didn't deserialize. Errors from `from_value` have no
+    /// position and report line and column 0.
+    pub(crate) fn parse(file: impl fmt::Display, e: &serde_json::Error) -> AppError {
+        AppError::Parse {
+            file: file.to_string(),
+            line: e.line(),
+            column: e.column(),
+            message: format!("{file}: {e}"),
+        }
+    }
+
+    pub(crate) fn missing(path: impl Into<String>, message: String) -> AppError {
+        AppError::MissingAsset {
+            path: path.into(),
+            message,
+        }
+    }
+
+    pub(crate) fn backend(message: String) -> AppError {
+        AppError::Backend { message }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (AppError::Io { message, .. }
+        | AppError::Parse { message, .. }
+        | AppError::MissingAsset { message, .. }
+        | AppError::LimitExceeded { message }
+        | AppError::Backend { message }
+        | AppError::Invalid { message }) = self;
+        f.write_str(message)
+    }
+}
+
+/// What a versioned loader rejects with: the bare message up to revision
+/// 2, the typed error from 3.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ErrorReply {
+    Message(String),
+    Typed(AppError),
+}
+
+impl ErrorReply {
+    pub(crate) fn at(revision: u32, error: AppError) -> ErrorReply {
+        if revision >= 3 {
+            ErrorReply::Typed(error)
+        } else {
+            ErrorReply::Message(error.to_string())
+        }
+    }
+}
+
+/// Checks without a kind of their own.
+impl From<String> for AppError {
+    fn from(message: String) -> AppError {
+        AppError::Invalid { message }
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> String {
+        error.to_string()
+    }
+}
+
+/// `negotiate` turning the revision down.
+impl From<String> for ErrorReply {
+    fn from(message: String) -> ErrorReply {
+        ErrorReply::Message(message)
+    }
+}
@@ -0,0 +1,72 @@
+//! Opens the app's folders in the OS file manager, so nobody has to work
+//! out where `layers.json` or the logs actually live.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::sync::LockExt;
+use crate::{fsutil, logs, AppPaths};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderKind {
+    Root,
+    Layers,
+    Logs,
+    Data,
+}
+
+impl FolderKind {
+    pub(crate) const ALL: [FolderKind; 4] = [
+        FolderKind::Root,
+        FolderKind::Layers,
+        FolderKind::Logs,
+        FolderKind::Data,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            FolderKind::Root => "root",
+            FolderKind::Layers => "layers",
+            FolderKind::Logs => "logs",
+            FolderKind::Data => "data",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    fn path(self, paths: &AppPaths) -> PathBuf {
+        match self {
+            FolderKind::Root => paths.root.clone(),
+            FolderKind::Layers => paths.layers_dir.clone(),
+            FolderKind::Logs => logs::dir(&paths.data_dir),
+            FolderKind::Data => paths.data_dir.clone(),
+        }
+    }
+}
+
+/// Creates the folder first if needed: the data and logs folders only
+/// appear once something was written to them.
+pub(crate) fn open(paths: &AppPaths, kind: FolderKind) -> Result<PathBuf, String> {
+    let path = kind.path(paths);
+    if !fsutil::exists(&path) {
+        std::fs::create_dir_all(fsutil::long_path(&path))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    crate::os_open(path.as_os_str())
+        .map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+/// `kind` is `root`, `layers`, `logs` or `data`; returns the opened path.
+#[tauri::command]
+pub fn open_path(
+    kind: FolderKind,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<PathBuf, String> {
+    let paths = paths.lock_or_recover().clone();
+    open(&paths, kind)
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+// ── path handling ──
+
+/// Returns a path the OS will accept regardless of length.
+///
+/// On Windows, absolute drive and UNC paths are rewritten to the `\\?\`
+/// extended-length form so deep pack folders don't hit `MAX_PATH`. Verbatim
+/// paths skip all normalisation, so `.` and `..` are resolved here first.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    let mut comps = path.components();
+    let mut out = match comps.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) => PathBuf::from(format!(r"\\?\{}:\", letter as char)),
+            Prefix::UNC(server, share) => {
+                let mut p = PathBuf::from(r"\\?\UNC\");
+                p.push(server);
+                p.push(share);
+                p
+            }
+            _ => return path.to_path_buf(),
+        },
+        _ => return path.to_path_buf(),
+    };
+    for comp in comps {
+        match comp {
+            Component::Normal(part) => out.push(part),
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    out
+}
+
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Home directory without assuming `HOME` exists (it usually doesn't on Windows).
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+// ── reads ──
+
+pub(crate) fn read_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    Ok(load_bytes(path)?)
+}
+
+/// Reads a config file as text, tolerating what Windows editors produce:
+/// a UTF-8 BOM, or UTF-16 with or without a BOM.
+pub(crate) fn read_text(path: &Path) -> Result<String, String> {
+    Ok(load_text(path)?)
+}
+
+/// [`read_bytes`] for the loaders, which tell a missing file apart.
+pub(crate) fn load_bytes(path: &Path) -> Result<Vec<u8>, AppError> {
+    fs::read(long_path(path)).map_err(|e| AppError::io(path.display(), &e))
+}
+
+/// [`read_text`] for the loaders.
+pub(crate) fn load_text(path: &Path) -> Result<String, AppError> {
+    let bytes = load_bytes(path)?;
+    decode_text(&bytes).map_err(|e| AppError::from(format!("{}: {e}", path.display())))
+}
+
+pub(crate) fn decode_text(bytes: &[u8]) -> Result<String, String> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => utf8(rest),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        // JSON starts with ASCII, so a zero in the first pair gives BOM-less UTF-16 away.
+        [a, 0, ..] if *a != 0 => utf16(bytes, u16::from_le_bytes),
+        [0, b, ..] if *b != 0 => utf16(bytes, u16::from_be_bytes),
+        _ => utf8(bytes),
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| {
+        format!(
+            "not valid UTF-8 at byte {}; re-save the file as UTF-8",
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err("truncated UTF-16 text (odd byte count)".into());
+    }
+    let units = bytes.chunks_exact(2).map(|c| unit([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| format!("invalid UTF-16 text: {e}"))
+}
+
+/// Writes through a sibling `.tmp` file and renames it over `path`, so
+/// watchers and readers never see a half-written file.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = Path::new(&tmp);
+    fs::write(long_path(tmp), bytes).map_err(|e| format!("{}: {e}", tmp.display()))?;
+    fs::rename(long_path(tmp), long_path(path)).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub(crate) fn exists(path: &Path) -> bool {
+    long_path(path).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh scratch folder whose name is not ASCII, like a pack called
+    /// `星星办公室 🐱`.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("star-pet-{}-{name}", std::process::id()))
+            .join("星星办公室 🐱");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(long_path(&dir)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_and_writes_under_non_ascii_folders() {
+        let dir = scratch("rw");
+        let path = dir.join("图层.json");
+        write_atomic(&path, "{ \"名字\": \"小猫\" }".as_bytes()).unwrap();
+        assert!(exists(&path));
+        assert_eq!(read_text(&path).unwrap(), "{ \"名字\": \"小猫\" }");
+        assert!(!exists(&dir.join("图层.json.tmp")));
+
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(read_bytes(&path).unwrap(), b"{}");
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn missing_file_is_a_missing_asset() {
+        let dir = scratch("missing");
+        let err = load_text(&dir.join("没有.json")).unwrap_err();
+        assert!(matches!(err, AppError::MissingAsset { .. }), "{err:?}");
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn decodes_what_windows_editors_save() {
+        let text = "{\"🐱\": 1}";
+        let utf16 = |bom: &[u8], unit: fn(u16) -> [u8; 2]| -> Vec<u8> {
+            let mut out = bom.to_vec();
+            out.extend(text.encode_utf16().flat_map(unit));
+            out
+        };
+        let bom8 = [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat();
+        assert_eq!(decode_text(&bom8).unwrap(), text);
+        assert_eq!(
+            decode_text(&utf16(&[0xFF, 0xFE], u16::to_le_bytes)).unwrap(),
+            text
+        );
+        assert_eq!(
+            decode_text(&utf16(&[0xFE, 0xFF], u16::to_be_bytes)).unwrap(),
+            text
+        );
+        assert_eq!(decode_text(&utf16(&[], u16::to_le_bytes)).unwrap(), text);
+        assert_eq!(decode_text(&utf16(&[], u16::to_be_bytes)).unwrap(), text);
+        assert_eq!(decode_text(text.as_bytes()).unwrap(), text);
+
+        assert!(decode_text(&[0xFF, 0xFE, b'{']).is_err());
+        assert!(decode_text(&[b'{', 0xC3]).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_get_the_verbatim_prefix() {
+        assert_eq!(
+            long_path(Path::new(r"C:\宠物\.\layers\..\皮肤 🐱\a.png")),
+            PathBuf::from(r"\\?\C:\宠物\皮肤 🐱\a.png")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\星星\map.json")),
+            PathBuf::from(r"\\?\UNC\server\share\星星\map.json")
+        );
+        assert_eq!(
+            long_path(Path::new(r"layers\图层.json")),
+            PathBuf::from(r"layers\图层.json")
+        );
+
+        let deep = (0..40).fold(scratch("deep"), |p, i| p.join(format!("文件夹{i}")));
+        assert!(deep.as_os_str().len() > 260);
+        fs::create_dir_all(long_path(&deep)).unwrap();
+        write_atomic(&deep.join("map.json"), b"{}").unwrap();
+        assert_eq!(read_text(&deep.join("map.json")).unwrap(), "{}");
+        let root = std::env::temp_dir().join(format!("star-pet-{}-deep", std::process::id()));
+        let _ = fs::remove_dir_all(long_path(&root));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn long_path_leaves_other_platforms_alone() {
+        let path = Path::new("/tmp/星星/../皮肤 🐱/a.png");
+        assert_eq!(long_path(path), path);
+    }
+}
@@ -0,0 +1,297 @@
+//! Gets the pet out of the way while a game runs.
+//!
+//! A background thread polls for known game processes (and, in builds
+//! with the `capture` feature, any other app covering a whole monitor).
+//! On entering it saves the current state, writes `gaming` and hides or
+//! shrinks the pet; on leaving it puts both back.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{AppPaths, PetState};
+
+pub(crate) const STATE: &str = "gaming";
+
+/// Executable names (lowercase, without `.exe`) of popular games.
+const KNOWN_GAMES: &[&str] = &[
+    "cs2",
+    "csgo",
+    "dota2",
+    "valorant-win64-shipping",
+    "league of legends",
+    "r5apex",
+    "overwatch",
+    "fortniteclient-win64-shipping",
+    "rocketleague",
+    "eldenring",
+    "gta5",
+    "genshinimpact",
+    "starrail",
+    "bg3",
+    "cyberpunk2077",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GamingAction {
+    /// Hide every pet window.
+    #[default]
+    Hide,
+    /// Switch to the mini window.
+    Shrink,
+    /// Only switch the state.
+    None,
+}
+
+/// `gaming` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct GamingSettings {
+    pub enabled: bool,
+    pub action: GamingAction,
+    /// Extra executable names to treat as games, e.g. `"factorio"`.
+    pub processes: Vec<String>,
+    /// Also react to any app covering a whole monitor (`capture` builds).
+    pub detect_fullscreen: bool,
+    pub poll_secs: u32,
+}
+
+impl Default for GamingSettings {
+    fn default() -> Self {
+        GamingSettings {
+            enabled: true,
+            action: GamingAction::Hide,
+            processes: Vec::new(),
+            detect_fullscreen: true,
+            poll_secs: 5,
+        }
+    }
+}
+
+/// Payload of `gaming-changed`.
+#[derive(Debug, Clone, Serialize)]
+struct GamingChanged {
+    active: bool,
+    /// The process or fullscreen app that triggered it.
+    trigger: Option<String>,
+    action: GamingAction,
+}
+
+/// What `enter` changed, so `leave` can undo exactly that.
+struct Active {
+    action: GamingAction,
+    previous: Option<PetState>,
+    main_visible: bool,
+    mini_visible: bool,
+}
+
+// ── detection ──
+
+fn normalize(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name).trim();
+    let lower = base.to_lowercase();
+    lower.strip_suffix(".exe").unwrap_or(&lower).to_string()
+}
+
+fn running_processes() -> Vec<String> {
+    #[cfg(target_os = "windows")]
+    let output = Command::new("tasklist")
+        .args(["/fo", "csv", "/nh"])
+        .output();
+    #[cfg(not(target_os = "windows"))]
+    let output = Command::new("ps").args(["-axo", "comm="]).output();
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        // `tasklist` CSV puts the image name first, quoted.
+        .map(|l| l.split("\",").next().unwrap_or(l).trim_matches('"'))
+        .filter(|l| !l.is_empty())
+        .map(normalize)
+        .collect()
+}
+
+#[cfg(feature = "capture")]
+fn fullscreen_app(own_titles: &[String]) -> Option<String> {
+    let windows = xcap::Window::all().ok()?;
+    windows
+        .into_iter()
+        .filter(|w| !w.is_minimized() && !w.title().is_empty())
+        .filter(|w| !own_titles.iter().any(|t| t == w.title()))
+        .find(|w| {
+            let monitor = w.current_monitor();
+            w.width() >= monitor.width() && w.height() >= monitor.height()
+        })
+        .map(|w| w.app_name().to_string())
+}
+
+#[cfg(not(feature = "capture"))]
+fn fullscreen_app(_: &[String]) -> Option<String> {
+    None
+}
+
+fn detect(cfg: &GamingSettings, own_titles: &[String]) -> Option<String> {
+    let extra: Vec<String> = cfg.processes.iter().map(|p| normalize(p)).collect();
+    let game = running_processes()
+        .into_iter()
+        .find(|p| KNOWN_GAMES.contains(&p.as_str()) || extra.contains(p));
+    game.or_else(|| {
+        cfg.detect_fullscreen
+            .then(|| fullscreen_app(own_titles))
+            .flatten()
+    })
+}
+
+// ── transitions ──
+
+fn state_store(app: &AppHandle) -> FsStateStore {
+    let state_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .state_path
+        .clone();
+    FsStateStore::new(state_path)
+}
+
+fn visible(app: &AppHandle, label: &str) -> bool {
+    app.get_webview_window(label)
+        .and_then(|w| w.is_visible().ok())
+        .unwrap_or(false)
+}
+
+fn enter(app: &AppHandle, trigger: &str, action: GamingAction) -> Active {
+    log!("🎮 {trigger} running, stepping aside");
+    let store = state_store(app);
+    let previous = store.load().ok();
+    let gaming = PetState {
+        state: STATE.into(),
+        detail: Some(trigger.to_string()),
+        progress: None,
+        updated_at: Some(chrono::Local::now().to_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    if let Err(e) = store.save(&gaming) {
+        log!("⚠️ gaming state: {e}");
+    }
+
+    let active = Active {
+        action,
+        previous,
+        main_visible: visible(app, "main"),
+        mini_visible: visible(app, "mini"),
+    };
+    match action {
+        GamingAction::Hide => {
+            for label in ["main", "mini"] {
+                if let Some(w) = app.get_webview_window(label) {
+                    let _ = w.hide();
+                }
+            }
+        }
+        GamingAction::Shrink if active.main_visible => {
+            if let Err(e) = crate::enter_minimize_mode(app.clone(), app.state()) {
+                log!("⚠️ gaming shrink: {e}");
+            }
+        }
+        GamingAction::Shrink | GamingAction::None => {}
+    }
+    active
+}
+
+fn leave(app: &AppHandle, active: Active) {
+    log!("🎮 game closed, back to work");
+    let store = state_store(app);
+    // Only restore if nobody wrote a new state in the meantime.
+    let still_ours = store.load().is_ok_and(|s| s.state == STATE);
+    if let (true, Some(mut previous)) = (still_ours, active.previous) {
+        previous.updated_at = Some(chrono::Local::now().to_rfc3339());
+        if let Err(e) = store.save(&previous) {
+            log!("⚠️ restore state: {e}");
+        }
+    }
+
+    match active.action {
+        GamingAction::Hide => {
+            for (label, was_visible) in
+                [("main", active.main_visible), ("mini", active.mini_visible)]
+            {
+                if let (true, Some(w)) = (was_visible, app.get_webview_window(label)) {
+                    let _ = w.show();
+                }
+            }
+        }
+        GamingAction::Shrink if active.main_visible => {
+            if let Err(e) = crate::restore_main_window(app.clone()) {
+                log!("⚠️ gaming restore: {e}");
+            }
+        }
+        GamingAction::Shrink | GamingAction::None => {}
+    }
+}
+
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let own_titles: Vec<String> = app
+            .webview_windows()
+            .values()
+            .filter_map(|w| w.title().ok())
+            .collect();
+        let mut active: Option<Active> = None;
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .gaming
+                .clone();
+            let poll = power::interval(&app, Duration::from_secs(u64::from(cfg.poll_secs.max(1))));
+            if power::paused(&app, "gaming") {
+                // Whatever was detected stays in effect until detection resumes.
+                std::thread::sleep(poll);
+                continue;
+            }
+            let trigger = if cfg.enabled {
+                detect(&cfg, &own_titles)
+            } else {
+                None
+            };
+            match (trigger, active.take()) {
+                (Some(trigger), None) => {
+                    active = Some(enter(&app, &trigger, cfg.action));
+                    let _ = app.emit(
+                        "gaming-changed",
+                        GamingChanged {
+                            active: true,
+                            trigger: Some(trigger),
+                            action: cfg.action,
+                        },
+                    );
+                }
+                (None, Some(was)) => {
+                    let action = was.action;
+                    leave(&app, was);
+                    let _ = app.emit(
+                        "gaming-changed",
+                        GamingChanged {
+                            active: false,
+                            trigger: None,
+                            action,
+                        },
+                    );
+                }
+                (_, still) => active = still,
+            }
+            std::thread::sleep(poll);
+        }
+    });
+}
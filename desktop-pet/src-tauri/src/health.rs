@@ -0,0 +1,129 @@
+//! How the integrations are doing, for a health dashboard in settings.
+//!
+//! Pollers and clients report each round here: a success, an error, and
+//! when they look again. `get_integration_status` joins that with which
+//! integrations are enabled (and paused by the energy saver), so "the CI
+//! widget went quiet" can be told apart from "the token expired".
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+#[derive(Debug, Clone, Default)]
+struct Record {
+    connected: Option<bool>,
+    last_success: Option<String>,
+    last_error: Option<String>,
+    last_error_at: Option<String>,
+    next_poll: Option<String>,
+}
+
+/// Managed by the app.
+#[derive(Default)]
+pub(crate) struct Health(Mutex<BTreeMap<&'static str, Record>>);
+
+/// Returned by `get_integration_status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationStatus {
+    name: &'static str,
+    /// Skipped for now because the energy saver is on.
+    paused: bool,
+    /// Whether the last attempt got through; `None` before the first.
+    connected: Option<bool>,
+    last_success: Option<String>,
+    /// Kept after later successes, with its time, for the record.
+    last_error: Option<String>,
+    last_error_at: Option<String>,
+    next_poll: Option<String>,
+}
+
+/// Every integration the status page and dashboard know, and whether
+/// `settings` turns it on.
+pub(crate) fn integrations(s: &Settings) -> Vec<(&'static str, bool)> {
+    vec![
+        ("updates", s.updates.enabled),
+        ("gaming", s.gaming.enabled),
+        ("avoid", s.avoid.enabled),
+        ("breaks", s.breaks.enabled),
+        ("clipboard", s.clipboard.enabled),
+        ("screensaver", s.screensaver.enabled),
+        ("seasonal", s.seasonal.enabled),
+        ("ambient", s.ambient.enabled),
+        ("lan", s.lan.enabled),
+        ("power", s.power.enabled),
+        ("maintenance", s.maintenance.enabled),
+        ("digest", s.digest.enabled),
+        ("mqtt", s.mqtt.enabled),
+        ("rgb", s.rgb.enabled),
+        (
+            "calendar",
+            s.calendar.enabled && !s.calendar.sources.is_empty(),
+        ),
+        (
+            "build",
+            s.build.command.is_some() || s.build.attach.is_some(),
+        ),
+        ("ci", s.ci.enabled && !s.ci.repo.trim().is_empty()),
+    ]
+}
+
+fn record(app: &AppHandle, name: &'static str, change: impl FnOnce(&mut Record)) {
+    let health = app.state::<Health>();
+    change(health.0.lock_or_recover().entry(name).or_default());
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+/// `name` got through this round.
+pub(crate) fn success(app: &AppHandle, name: &'static str) {
+    record(app, name, |r| {
+        r.connected = Some(true);
+        r.last_success = Some(now());
+    });
+}
+
+/// `name` failed this round with `error`.
+pub(crate) fn failure(app: &AppHandle, name: &'static str, error: impl Into<String>) {
+    record(app, name, |r| {
+        r.connected = Some(false);
+        r.last_error = Some(error.into());
+        r.last_error_at = Some(now());
+    });
+}
+
+/// `name` looks again after `wait`.
+pub(crate) fn next_poll(app: &AppHandle, name: &'static str, wait: Duration) {
+    let at = chrono::Local::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+    record(app, name, |r| r.next_poll = Some(at.to_rfc3339()));
+}
+
+/// Enabled integrations with how their last rounds went.
+#[tauri::command]
+pub fn get_integration_status(app: AppHandle) -> Vec<IntegrationStatus> {
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let records = app.state::<Health>().0.lock_or_recover().clone();
+    integrations(&settings)
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| {
+            let r = records.get(name).cloned().unwrap_or_default();
+            IntegrationStatus {
+                name,
+                paused: power::paused(&app, name),
+                connected: r.connected,
+                last_success: r.last_success,
+                last_error: r.last_error,
+                last_error_at: r.last_error_at,
+                next_poll: r.next_poll,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,225 @@
+//! Localized pet text. Built-in English and Chinese strings can be
+//! overridden or extended per language with `locales/<lang>.json` in the
+//! project root; nested objects become dotted keys (`state.idle`).
+//!
+//! Lookup falls back from the exact tag to its base language to English,
+//! then to the key itself, so a missing string never blanks a bubble.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("en", include_str!("../templates/locales/en.json")),
+    ("zh", include_str!("../templates/locales/zh.json")),
+];
+const FALLBACK: &str = "en";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LocaleInfo {
+    pub(crate) lang: String,
+    available: Vec<String>,
+    strings: BTreeMap<String, String>,
+}
+
+/// Managed by the app; rebuilt when settings or a locale file change.
+pub(crate) struct I18n(Mutex<LocaleInfo>);
+
+impl I18n {
+    pub(crate) fn new(info: LocaleInfo) -> Self {
+        I18n(Mutex::new(info))
+    }
+
+    pub(crate) fn lang(&self) -> String {
+        self.0.lock_or_recover().lang.clone()
+    }
+
+    pub(crate) fn replace(&self, info: LocaleInfo) {
+        *self.0.lock_or_recover() = info;
+    }
+
+    pub(crate) fn t(&self, key: &str, args: &Map<String, Value>) -> String {
+        let info = self.0.lock_or_recover();
+        let template = info.strings.get(key).map_or(key, String::as_str);
+        fill(template, args)
+    }
+}
+
+/// Replaces `{name}` with the matching argument; unknown placeholders are
+/// left as they are.
+pub(crate) fn fill(template: &str, args: &Map<String, Value>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match args.get(name) {
+                    Some(Value::String(s)) => out.push_str(s),
+                    Some(value) => out.push_str(&value.to_string()),
+                    None => out.push_str(&rest[start..start + end + 2]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// ── loading ──
+
+/// `zh_CN.UTF-8` / `zh-cn` → `zh-CN`.
+pub(crate) fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.split(['.', '@']).next()?.trim().replace('_', "-");
+    let mut parts = tag.split('-');
+    let lang = parts.next().filter(|l| !l.is_empty())?.to_lowercase();
+    if matches!(lang.as_str(), "c" | "posix") {
+        return None;
+    }
+    Some(match parts.next() {
+        Some(region) if !region.is_empty() => format!("{lang}-{}", region.to_uppercase()),
+        _ => lang,
+    })
+}
+
+fn detect_os_locale() -> Option<String> {
+    for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+        if let Some(tag) = std::env::var(var).ok().and_then(|v| normalize(&v)) {
+            return Some(tag);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", "(Get-Culture).Name"])
+        .output();
+    #[cfg(target_os = "macos")]
+    let output = Command::new("defaults")
+        .args(["read", "-g", "AppleLocale"])
+        .output();
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let output = Command::new("locale").output();
+
+    let stdout = String::from_utf8_lossy(&output.ok()?.stdout).into_owned();
+    // `locale` prints `LANG=...` lines; the others print the bare tag.
+    let tag = stdout
+        .lines()
+        .map(|l| l.rsplit('=').next().unwrap_or(l).trim_matches('"'))
+        .find_map(normalize)?;
+    Some(tag)
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{prefix}.{k}")
+                };
+                flatten(&key, v, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+fn merge(raw: &str, name: &str, out: &mut BTreeMap<String, String>) {
+    match serde_json::from_str::<Value>(raw) {
+        Ok(value) => flatten("", &value, out),
+        Err(e) => log!("⚠️ {name}: {e}"),
+    }
+}
+
+fn user_locales(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(fsutil::long_path(dir)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect()
+}
+
+/// Resolves the language (`requested`, else the OS, else English) and
+/// merges every layer of strings for it.
+pub(crate) fn load(locales_dir: &Path, requested: Option<&str>) -> LocaleInfo {
+    let lang = requested
+        .and_then(normalize)
+        .or_else(detect_os_locale)
+        .unwrap_or_else(|| FALLBACK.to_string());
+    let base = lang.split('-').next().unwrap_or(&lang).to_string();
+
+    let mut chain = vec![FALLBACK.to_string()];
+    for tag in [&base, &lang] {
+        if !chain.contains(tag) {
+            chain.push(tag.clone());
+        }
+    }
+
+    let mut strings = BTreeMap::new();
+    for tag in &chain {
+        if let Some((_, raw)) = BUILTIN.iter().find(|(t, _)| *t == tag.as_str()) {
+            merge(raw, &format!("built-in {tag}"), &mut strings);
+        }
+        let path = locales_dir.join(format!("{tag}.json"));
+        if fsutil::exists(&path) {
+            match fsutil::read_text(&path) {
+                Ok(raw) => merge(&raw, &path.display().to_string(), &mut strings),
+                Err(e) => log!("⚠️ {e}"),
+            }
+        }
+    }
+
+    let mut available: Vec<String> = BUILTIN.iter().map(|(t, _)| t.to_string()).collect();
+    available.extend(user_locales(locales_dir));
+    available.sort();
+    available.dedup();
+    LocaleInfo {
+        lang,
+        available,
+        strings,
+    }
+}
+
+pub(crate) fn load_for(paths: &AppPaths) -> LocaleInfo {
+    let locale = crate::settings::load(&paths.settings_path).locale;
+    load(&paths.locales_dir, locale.as_deref())
+}
+
+// ── commands ──
+
+#[tauri::command]
+pub fn t(key: String, args: Option<Map<String, Value>>, i18n: tauri::State<'_, I18n>) -> String {
+    i18n.t(&key, &args.unwrap_or_default())
+}
+
+/// The resolved language and its full string table, so the frontend can
+/// translate locally instead of calling `t` per string.
+#[tauri::command]
+pub fn get_locale(i18n: tauri::State<'_, I18n>) -> LocaleInfo {
+    i18n.0.lock_or_recover().clone()
+}
@@ -0,0 +1,84 @@
+use image::{DynamicImage, GenericImageView};
+use std::path::Path;
+
+/// Fully decodes an image, so a truncated or mislabelled file fails here
+/// instead of somewhere in the webview.
+pub(crate) fn decode(path: &Path, bytes: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(bytes).map_err(|e| format!("{}: decode: {e}", path.display()))
+}
+
+/// Column and row count of a tileset, or an error naming the real
+/// dimensions when the art was exported at the wrong scale.
+pub(crate) fn tile_grid(
+    name: &str,
+    img: &DynamicImage,
+    tile_size: u32,
+) -> Result<(u32, u32), String> {
+    let (w, h) = img.dimensions();
+    if tile_size == 0 {
+        return Err("map.json: tile_size must be greater than 0".into());
+    }
+    if w == 0 || h == 0 || !w.is_multiple_of(tile_size) || !h.is_multiple_of(tile_size) {
+        return Err(format!(
+            "tileset {name} is {w}x{h}px, not a multiple of tile_size {tile_size} \
+             (exported at the wrong scale?)"
+        ));
+    }
+    Ok((w / tile_size, h / tile_size))
+}
+
+/// Column and row count of a spritesheet with `margin` around the edge
+/// and `spacing` between frames.
+pub(crate) fn sheet_grid(
+    name: &str,
+    img: &DynamicImage,
+    frame: (u32, u32),
+    margin: u32,
+    spacing: u32,
+) -> Result<(u32, u32), String> {
+    let (w, h) = img.dimensions();
+    let (fw, fh) = frame;
+    if fw == 0 || fh == 0 {
+        return Err("layers.json: frame size must be greater than 0".into());
+    }
+    // Margins or spacing too large to add up fit no frame at all.
+    let fit = |total: u32, size: u32| {
+        let (Some(edges), Some(step)) = (margin.checked_mul(2), size.checked_add(spacing)) else {
+            return 0;
+        };
+        match total.checked_sub(edges) {
+            Some(inner) if inner >= size => (inner - size) / step + 1,
+            _ => 0,
+        }
+    };
+    let (cols, rows) = (fit(w, fw), fit(h, fh));
+    if cols == 0 || rows == 0 {
+        return Err(format!(
+            "sheet {name} is {w}x{h}px, too small for one {fw}x{fh} frame with margin {margin}"
+        ));
+    }
+    Ok((cols, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheet_grid_counts_frames_between_margins() {
+        let img = DynamicImage::new_rgba8(100, 40);
+        assert_eq!(sheet_grid("a.png", &img, (32, 32), 0, 0), Ok((3, 1)));
+        assert_eq!(sheet_grid("a.png", &img, (32, 32), 2, 2), Ok((2, 1)));
+        assert!(sheet_grid("a.png", &img, (32, 32), 5, 0).is_err());
+        assert!(sheet_grid("a.png", &img, (0, 32), 0, 0).is_err());
+    }
+
+    #[test]
+    fn sheet_grid_rejects_overflowing_margin_and_spacing() {
+        let img = DynamicImage::new_rgba8(100, 40);
+        let err = sheet_grid("a.png", &img, (32, 32), u32::MAX, 0).unwrap_err();
+        assert!(err.contains("too small"), "{err}");
+        let err = sheet_grid("a.png", &img, (32, 32), 0, u32::MAX).unwrap_err();
+        assert!(err.contains("too small"), "{err}");
+    }
+}
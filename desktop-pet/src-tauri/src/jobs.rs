@@ -0,0 +1,304 @@
+//! Background queue for slow backend work: pack imports and installs,
+//! maintenance runs (backups included) and clip recording.
+//!
+//! Jobs run one at a time on a worker thread, in the order they were
+//! queued. Each reports progress through its [`Job`] handle, which also
+//! carries the cancel flag; work checks it between steps and stops with
+//! [`CANCELLED`]. Every change goes out as a `job-progress` event and
+//! `get_jobs` lists what is queued, running and recently finished.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::sync::LockExt;
+
+/// Error of work stopped by `cancel_job`.
+pub(crate) const CANCELLED: &str = "cancelled";
+/// Finished jobs kept for `get_jobs`.
+const KEEP_FINISHED: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Done | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Returned by `get_jobs` and sent as `job-progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    id: u64,
+    /// Which operation: `import_bundle`, `install_pack`, `maintenance`,
+    /// `record_clip`.
+    kind: &'static str,
+    /// What it works on, for display.
+    label: String,
+    state: JobState,
+    /// 0 to 1, once the job knows.
+    progress: Option<f64>,
+    /// The current step.
+    message: Option<String>,
+    error: Option<String>,
+    queued_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+}
+
+struct Entry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+type Task = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Queue {
+    next_id: u64,
+    entries: Vec<Entry>,
+    worker: Option<Sender<Task>>,
+}
+
+/// Managed by the app.
+#[derive(Default)]
+pub(crate) struct Jobs(Mutex<Queue>);
+
+/// Handed to running work.
+pub(crate) struct Job {
+    id: u64,
+    app: AppHandle,
+    cancel: Arc<AtomicBool>,
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+/// Applies `change` to job `id` and announces it.
+fn update(app: &AppHandle, id: u64, change: impl FnOnce(&mut JobInfo) -> bool) {
+    let jobs = app.state::<Jobs>();
+    let mut queue = jobs.0.lock_or_recover();
+    let Some(entry) = queue.entries.iter_mut().find(|e| e.info.id == id) else {
+        return;
+    };
+    if change(&mut entry.info) {
+        let _ = app.emit("job-progress", &entry.info);
+    }
+}
+
+impl Job {
+    /// Reports `value` (0 to 1) and the step it is at.
+    pub(crate) fn progress(&self, value: f64, message: impl Into<String>) {
+        let (value, message) = (value.clamp(0.0, 1.0), message.into());
+        update(&self.app, self.id, |info| {
+            // Whole percents or a new step, to keep the event rate sane.
+            let before = info.progress.map(|p| (p * 100.0) as u32);
+            let changed = before != Some((value * 100.0) as u32)
+                || info.message.as_deref() != Some(message.as_str());
+            info.progress = Some(value);
+            info.message = Some(message);
+            changed
+        });
+    }
+
+    /// `Err(CANCELLED)` once `cancel_job` asked this job to stop.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.cancel.load(Ordering::SeqCst) {
+            Err(CANCELLED.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn spawn_worker(app: AppHandle) {
+    let (tx, rx) = mpsc::channel::<Task>();
+    app.state::<Jobs>().0.lock_or_recover().worker = Some(tx);
+    std::thread::spawn(move || {
+        for task in rx {
+            task();
+        }
+    });
+}
+
+/// Runs `work` once jobs queued before it are done; its result arrives on
+/// the returned channel.
+pub(crate) fn submit<T, F>(
+    app: &AppHandle,
+    kind: &'static str,
+    label: String,
+    work: F,
+) -> Result<Receiver<Result<T, String>>, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Job) -> Result<T, String> + Send + 'static,
+{
+    let jobs = app.state::<Jobs>();
+    let mut queue = jobs.0.lock_or_recover();
+    let worker = queue.worker.clone().ok_or("the job queue is not running")?;
+    queue.next_id += 1;
+    let id = queue.next_id;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let info = JobInfo {
+        id,
+        kind,
+        label,
+        state: JobState::Queued,
+        progress: None,
+        message: None,
+        error: None,
+        queued_at: now(),
+        started_at: None,
+        finished_at: None,
+    };
+    let _ = app.emit("job-progress", &info);
+    queue.entries.push(Entry {
+        info,
+        cancel: cancel.clone(),
+    });
+    drop(queue);
+
+    let (tx, rx) = mpsc::channel();
+    let job = Job {
+        id,
+        app: app.clone(),
+        cancel,
+    };
+    let task: Task = Box::new(move || {
+        let app = job.app.clone();
+        let mut skipped = false;
+        update(&app, id, |info| {
+            skipped = info.state == JobState::Cancelled;
+            if !skipped {
+                info.state = JobState::Running;
+                info.started_at = Some(now());
+            }
+            !skipped
+        });
+        let result = if skipped {
+            Err(CANCELLED.to_string())
+        } else {
+            let result = work(&job);
+            finish(&job, result.as_ref().err());
+            result
+        };
+        let _ = tx.send(result);
+    });
+    worker
+        .send(task)
+        .map_err(|_| "the job queue stopped".to_string())?;
+    Ok(rx)
+}
+
+fn finish(job: &Job, error: Option<&String>) {
+    update(&job.app, job.id, |info| {
+        info.state = match error {
+            None => JobState::Done,
+            Some(_) if job.cancel.load(Ordering::SeqCst) => JobState::Cancelled,
+            Some(_) => JobState::Failed,
+        };
+        if info.state == JobState::Done {
+            info.progress = Some(1.0);
+        }
+        info.error = error.filter(|e| e.as_str() != CANCELLED).cloned();
+        info.finished_at = Some(now());
+        log!(
+            "🧰 job {} ({}) {:?}{}",
+            info.id,
+            info.kind,
+            info.state,
+            info.error
+                .as_deref()
+                .map(|e| format!(": {e}"))
+                .unwrap_or_default()
+        );
+        true
+    });
+    let jobs = job.app.state::<Jobs>();
+    let mut queue = jobs.0.lock_or_recover();
+    let finished = queue
+        .entries
+        .iter()
+        .filter(|e| e.info.state.finished())
+        .count();
+    let mut excess = finished.saturating_sub(KEEP_FINISHED);
+    queue.entries.retain(|e| {
+        let old = excess > 0 && e.info.state.finished();
+        excess -= usize::from(old);
+        !old
+    });
+}
+
+/// Waits for a job from [`submit`].
+pub(crate) fn wait<T>(rx: Receiver<Result<T, String>>) -> Result<T, String> {
+    rx.recv()
+        .map_err(|_| "the job ended without a result".to_string())?
+}
+
+/// Queues `work` and waits for it without blocking the async runtime.
+pub(crate) async fn run<T, F>(
+    app: &AppHandle,
+    kind: &'static str,
+    label: String,
+    work: F,
+) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Job) -> Result<T, String> + Send + 'static,
+{
+    let rx = submit(app, kind, label, work)?;
+    tauri::async_runtime::spawn_blocking(move || wait(rx))
+        .await
+        .map_err(|e| format!("job task: {e}"))?
+}
+
+/// Queued, running and the last few finished jobs, oldest first.
+#[tauri::command]
+pub fn get_jobs(jobs: tauri::State<'_, Jobs>) -> Vec<JobInfo> {
+    jobs.0
+        .lock_or_recover()
+        .entries
+        .iter()
+        .map(|e| e.info.clone())
+        .collect()
+}
+
+/// Drops a queued job, or asks a running one to stop at its next step.
+#[tauri::command]
+pub fn cancel_job(app: AppHandle, id: u64) -> Result<(), String> {
+    let jobs = app.state::<Jobs>();
+    let mut queue = jobs.0.lock_or_recover();
+    let entry = queue
+        .entries
+        .iter_mut()
+        .find(|e| e.info.id == id)
+        .ok_or_else(|| format!("no job {id}"))?;
+    match entry.info.state {
+        JobState::Queued => {
+            entry.info.state = JobState::Cancelled;
+            entry.info.finished_at = Some(now());
+            let _ = app.emit("job-progress", &entry.info);
+        }
+        JobState::Running => {
+            entry.cancel.store(true, Ordering::SeqCst);
+            log!("🧰 cancelling job {id} ({})", entry.info.kind);
+        }
+        _ => return Err(format!("job {id} already finished")),
+    }
+    Ok(())
+}
@@ -0,0 +1,358 @@
+//! LAN sync: pets on teammates' machines visit each other as guests.
+//!
+//! With `lan.enabled` each pet answers TCP connections on `lan.port` with
+//! one JSON line of its public state, then hangs up. Peers come from
+//! `lan.peers` (`host:port`) and, with the `lan` feature, from mDNS
+//! (`_starpet._tcp`). Every `poll_secs` each peer is asked for its state
+//! and the result goes out as `lan-peers` for the frontend to draw as
+//! guest sprites on the map. `detail` is only shared with
+//! `lan.share_detail`. Read once at startup.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{health, power, AppPaths, PetState};
+
+/// Wire format revision, bumped on incompatible changes.
+const PROTOCOL: u32 = 1;
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest line accepted from a peer.
+const MAX_LINE: u64 = 16 * 1024;
+
+/// `lan` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LanSettings {
+    pub enabled: bool,
+    /// Shown under the guest sprite; the host name when empty.
+    pub name: String,
+    pub port: u16,
+    pub share_detail: bool,
+    pub poll_secs: u32,
+    /// Fixed `host:port` peers, for networks without mDNS.
+    pub peers: Vec<String>,
+}
+
+impl Default for LanSettings {
+    fn default() -> Self {
+        LanSettings {
+            enabled: false,
+            name: String::new(),
+            port: 18793,
+            share_detail: false,
+            poll_secs: 10,
+            peers: Vec::new(),
+        }
+    }
+}
+
+/// The one line a pet sends to each connecting peer.
+#[derive(Debug, Serialize, Deserialize)]
+struct Announce {
+    v: u32,
+    name: String,
+    state: String,
+    detail: Option<String>,
+    progress: Option<f64>,
+}
+
+/// A guest as reported in `lan-peers` and by `get_lan_peers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Peer {
+    name: String,
+    addr: String,
+    state: String,
+    detail: Option<String>,
+    progress: Option<f64>,
+    seen_at: String,
+}
+
+/// Last answer of each peer, keyed like [`Addrs`].
+#[derive(Default)]
+pub(crate) struct LanPeers(Mutex<BTreeMap<String, Peer>>);
+
+/// Where to reach each peer: mDNS full name or the `lan.peers` entry.
+type Addrs = Arc<Mutex<HashMap<String, SocketAddr>>>;
+
+fn display_name(cfg: &LanSettings) -> String {
+    let name = cfg.name.trim();
+    if !name.is_empty() {
+        return name.to_string();
+    }
+    ["HOSTNAME", "COMPUTERNAME", "USER", "USERNAME"]
+        .iter()
+        .find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "pet".into())
+}
+
+fn announce(name: &str, state: &PetState, share_detail: bool) -> Announce {
+    Announce {
+        v: PROTOCOL,
+        name: name.to_string(),
+        state: state.state.clone(),
+        detail: state.detail.clone().filter(|_| share_detail),
+        progress: state.progress,
+    }
+}
+
+// ── protocol ──
+
+fn serve(app: AppHandle, listener: TcpListener, name: String, share_detail: bool) {
+    for mut stream in listener.incoming().flatten() {
+        let state_path = app
+            .state::<Mutex<AppPaths>>()
+            .lock_or_recover()
+            .state_path
+            .clone();
+        let Ok(state) = FsStateStore::new(state_path).load().map(crate::present) else {
+            continue;
+        };
+        let Ok(line) = serde_json::to_string(&announce(&name, &state, share_detail)) else {
+            continue;
+        };
+        let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+        let _ = writeln!(stream, "{line}");
+    }
+}
+
+fn fetch(addr: &SocketAddr) -> Result<Announce, String> {
+    let stream = TcpStream::connect_timeout(addr, IO_TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_LINE))
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    let msg: Announce = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+    if msg.v != PROTOCOL {
+        return Err(format!("protocol v{} (expected v{PROTOCOL})", msg.v));
+    }
+    Ok(msg)
+}
+
+fn publish(app: &AppHandle) {
+    let peers: Vec<Peer> = app
+        .state::<LanPeers>()
+        .0
+        .lock_or_recover()
+        .values()
+        .cloned()
+        .collect();
+    let _ = app.emit("lan-peers", peers);
+}
+
+fn poll(app: AppHandle, addrs: Addrs, interval: Duration) {
+    loop {
+        if power::paused(&app, "lan") {
+            std::thread::sleep(power::interval(&app, interval));
+            continue;
+        }
+        let targets: Vec<(String, SocketAddr)> = addrs
+            .lock_or_recover()
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect();
+        let mut failed = Vec::new();
+        for (key, addr) in targets {
+            let answer = fetch(&addr);
+            let mut peers = app.state::<LanPeers>().0.lock_or_recover();
+            match answer {
+                Ok(msg) => {
+                    peers.insert(
+                        key,
+                        Peer {
+                            name: msg.name,
+                            addr: addr.to_string(),
+                            state: msg.state,
+                            detail: msg.detail,
+                            progress: msg.progress,
+                            seen_at: chrono::Local::now().to_rfc3339(),
+                        },
+                    );
+                }
+                // Unreachable guests leave the map until they answer again.
+                Err(e) => {
+                    if peers.remove(&key).is_some() {
+                        log!("⚠️ LAN peer {addr}: {e}");
+                    }
+                    failed.push(format!("{addr}: {e}"));
+                }
+            }
+        }
+        publish(&app);
+        if failed.is_empty() {
+            health::success(&app, "lan");
+        } else {
+            health::failure(&app, "lan", failed.join("; "));
+        }
+        let wait = power::interval(&app, interval);
+        health::next_poll(&app, "lan", wait);
+        std::thread::sleep(wait);
+    }
+}
+
+// ── discovery ──
+
+#[cfg(feature = "lan")]
+mod imp {
+    use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+    use tauri::{AppHandle, Manager};
+
+    use super::{publish, Addrs, LanPeers, LanSettings, PROTOCOL};
+    use crate::logs::log;
+    use crate::sync::LockExt;
+
+    const SERVICE_TYPE: &str = "_starpet._tcp.local.";
+
+    /// The daemon and our own service name, for the goodbye on exit.
+    static ADVERTISED: Mutex<Option<(ServiceDaemon, String)>> = Mutex::new(None);
+
+    pub(super) fn withdraw() {
+        let Some((mdns, own)) = ADVERTISED.lock_or_recover().take() else {
+            return;
+        };
+        if let Ok(done) = mdns.unregister(&own) {
+            let _ = done.recv_timeout(std::time::Duration::from_secs(1));
+        }
+        let _ = mdns.shutdown();
+    }
+
+    /// Drops a peer that mDNS withdrew.
+    fn forget(app: &AppHandle, addrs: &Addrs, key: &str) {
+        addrs.lock_or_recover().remove(key);
+        app.state::<LanPeers>().0.lock_or_recover().remove(key);
+        publish(app);
+    }
+
+    pub(super) fn discover(
+        app: AppHandle,
+        cfg: &LanSettings,
+        name: &str,
+        addrs: Addrs,
+    ) -> Result<(), String> {
+        let mdns = ServiceDaemon::new().map_err(|e| format!("mDNS: {e}"))?;
+        // Instance names must be unique on the network; the pid tells
+        // apart two pets that share a display name.
+        let instance = format!("{name}-{}", std::process::id());
+        let label: String = instance
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let host = format!("{label}.local.");
+        let version = PROTOCOL.to_string();
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &host,
+            "",
+            cfg.port,
+            &[("name", name), ("v", version.as_str())][..],
+        )
+        .map_err(|e| format!("mDNS service: {e}"))?
+        .enable_addr_auto();
+        let own = info.get_fullname().to_string();
+        mdns.register(info)
+            .map_err(|e| format!("mDNS register: {e}"))?;
+        let events = mdns
+            .browse(SERVICE_TYPE)
+            .map_err(|e| format!("mDNS browse: {e}"))?;
+        *ADVERTISED.lock_or_recover() = Some((mdns.clone(), own.clone()));
+
+        std::thread::spawn(move || {
+            // Dropping the daemon would withdraw our own advertisement.
+            let _mdns = mdns;
+            while let Ok(event) = events.recv() {
+                match event {
+                    ServiceEvent::ServiceResolved(info) if info.get_fullname() != own => {
+                        let Some(ip) = info.get_addresses().iter().next().copied() else {
+                            continue;
+                        };
+                        let addr = SocketAddr::new(ip, info.get_port());
+                        log!("🐾 LAN peer {} at {addr}", info.get_fullname());
+                        addrs
+                            .lock_or_recover()
+                            .insert(info.get_fullname().to_string(), addr);
+                    }
+                    ServiceEvent::ServiceRemoved(_, fullname) => forget(&app, &addrs, &fullname),
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "lan"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use super::{Addrs, LanSettings};
+    use crate::logs::log;
+
+    pub(super) fn discover(_: AppHandle, _: &LanSettings, _: &str, _: Addrs) -> Result<(), String> {
+        log!("🐾 mDNS discovery needs the `lan` feature; using lan.peers only");
+        Ok(())
+    }
+
+    pub(super) fn withdraw() {}
+}
+
+fn start_inner(app: AppHandle, cfg: &LanSettings) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", cfg.port))
+        .map_err(|e| format!("LAN sync on port {}: {e}", cfg.port))?;
+    let name = display_name(cfg);
+    log!("🐾 LAN sync as `{name}` on port {}", cfg.port);
+
+    let addrs = Addrs::default();
+    for peer in &cfg.peers {
+        match peer.to_socket_addrs().map(|mut a| a.next()) {
+            Ok(Some(addr)) => {
+                addrs.lock_or_recover().insert(peer.clone(), addr);
+            }
+            _ => log!("⚠️ lan.peers: can't resolve `{peer}`"),
+        }
+    }
+    if let Err(e) = imp::discover(app.clone(), cfg, &name, Arc::clone(&addrs)) {
+        log!("⚠️ {e}");
+    }
+
+    let share_detail = cfg.share_detail;
+    let served = app.clone();
+    std::thread::spawn(move || serve(served, listener, name, share_detail));
+    let interval = Duration::from_secs(u64::from(cfg.poll_secs.max(1)));
+    std::thread::spawn(move || poll(app, addrs, interval));
+    Ok(())
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let cfg = app.state::<Mutex<Settings>>().lock_or_recover().lan.clone();
+    if !cfg.enabled {
+        return;
+    }
+    if let Err(e) = start_inner(app, &cfg) {
+        log!("⚠️ {e}");
+    }
+}
+
+/// Withdraws the mDNS advertisement on exit, so peers drop us right away.
+pub(crate) fn stop() {
+    imp::withdraw();
+}
+
+#[tauri::command]
+pub fn get_lan_peers(peers: tauri::State<'_, LanPeers>) -> Vec<Peer> {
+    peers.0.lock_or_recover().values().cloned().collect()
+}
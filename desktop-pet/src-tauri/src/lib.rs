@@ -1,46 +1,198 @@
-use base64::engine::general_purpose::STANDARD as B64;
-use base64::Engine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
+use tauri::webview::PageLoadEvent;
 use tauri::{Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
 
+mod a11y;
+mod activity;
+mod ambient;
+mod api;
+mod aseprite;
+mod asset_protocol;
+mod avoid;
+mod bench;
+mod breaks;
+pub mod bubble;
+pub mod build;
+mod bundle;
+pub mod calendar;
+mod capture;
+pub mod ci;
+mod cli;
+mod clipboard;
+mod clock;
+mod control;
+mod crash;
+mod ctl;
+mod dbus;
+mod dev;
+mod digest;
+mod doctor;
+mod edit;
+mod error;
+mod folders;
+mod fsutil;
+mod gaming;
+mod health;
+mod i18n;
+mod imaging;
+mod jobs;
+mod lan;
+mod limits;
+mod locate;
+mod logs;
+mod maintenance;
+mod mappers;
+mod mqtt;
+mod net;
+mod notifications;
+mod notify;
+mod observe;
+mod onboarding;
+mod overlay;
+mod pathfind;
+mod perf;
+mod permissions;
+pub mod pet_core;
+mod platform;
+mod pomodoro;
+mod power;
+pub mod preview;
+mod profiles;
+mod progress;
+mod registry;
+mod replay;
+mod rgb;
+mod rpc;
+mod report;
+mod scaffold;
+mod screensaver;
+mod schema;
+mod seasonal;
+mod session;
+mod settings;
+mod shutdown;
+pub mod sim;
+mod sound;
+mod state_file;
+mod states;
+mod stats;
+mod status;
+mod sync;
+mod system_stats;
+mod taskbar;
+mod theme;
+mod tiled;
+mod timeline;
+mod tray;
+mod typing;
+mod updates;
+mod validate;
+mod voice;
+mod watch;
+mod ws;
+
+use error::{AppError, ErrorReply};
+use locate::find_project_root;
+use logs::log;
+use pet_core::{AssetSource, FsAssets, FsStateStore, StateStore};
+use progress::ProgressSource;
+use settings::Settings;
+use sync::LockExt;
+
 // ── state.json ──
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct PetState {
     pub state: String,
     pub detail: Option<String>,
     pub progress: Option<f64>,
     pub updated_at: Option<String>,
+    /// Named progress per producer; when present, `progress` is their
+    /// weighted mean. See `progress`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub progress_sources: BTreeMap<String, ProgressSource>,
 }
 
 // ── layers.json input ──
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct CfgFile {
     width: Option<u32>,
     height: Option<u32>,
     character: Option<CharCfg>,
+    /// Several pets sharing the scene. Unset fields fall back to
+    /// `character`, and a pet without `sprites` uses the top-level sheet.
+    characters: Option<Vec<PetCfg>>,
     layers: Option<Vec<LayerCfg>>,
     sprites: Option<SpritesCfg>,
+    /// Alternate sheets by name, swapped in for `sprites` on request, e.g.
+    /// when a map asks for a chibi variant.
+    skins: Option<HashMap<String, SpritesCfg>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct PetCfg {
+    /// Unique within the file; defaults to `pet1`, `pet2`, …
+    name: Option<String>,
+    #[serde(flatten)]
+    character: CharCfg,
+    sprites: Option<SpritesCfg>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
 struct CharCfg {
     x: Option<f64>,
     y: Option<f64>,
     scale: Option<f64>,
     depth: Option<i32>,
     wander: Option<f64>,
+    shadow: Option<ShadowCfg>,
+    footprints: Option<FootprintsCfg>,
 }
 
-#[derive(Debug, Deserialize)]
+impl CharCfg {
+    /// `self`, with unset fields taken from `base`.
+    fn or(self, base: &CharCfg) -> CharCfg {
+        CharCfg {
+            x: self.x.or(base.x),
+            y: self.y.or(base.y),
+            scale: self.scale.or(base.scale),
+            depth: self.depth.or(base.depth),
+            wander: self.wander.or(base.wander),
+            shadow: self.shadow.or_else(|| base.shadow.clone()),
+            footprints: self.footprints.or_else(|| base.footprints.clone()),
+        }
+    }
+}
+
+/// Ellipse under the character's feet, in unscaled pixels.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+struct ShadowCfg {
+    width: Option<f64>,
+    height: Option<f64>,
+    opacity: Option<f64>,
+    offset_x: Option<f64>,
+    offset_y: Option<f64>,
+}
+
+/// Decal left behind while the character walks.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+struct FootprintsCfg {
+    image: String,
+    /// Seconds until a print has faded out.
+    fade_secs: Option<f64>,
+    /// Distance walked between prints, in pixels.
+    spacing: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 struct LayerCfg {
     image: String,
     x: Option<f64>,
@@ -50,20 +202,42 @@ struct LayerCfg {
     alpha: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct SpritesCfg {
     frame_width: Option<u32>,
     frame_height: Option<u32>,
     anims: Option<HashMap<String, AnimCfg>>,
+    /// Aseprite sheet export (`.json`); each frame tag becomes an anim.
+    /// Entries in `anims` win over tags of the same name.
+    aseprite: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct AnimCfg {
     file: String,
     frames: Option<u32>,
     rate: Option<u32>,
     #[serde(default = "neg_one")]
     repeat: i32,
+    /// Row of the sheet the anim starts on, for sheets holding several.
+    row: Option<u32>,
+    /// Column within `row` of the first frame.
+    start_frame: Option<u32>,
+    /// Pixels around the whole sheet.
+    margin: Option<u32>,
+    /// Pixels between neighbouring frames.
+    spacing: Option<u32>,
+}
+
+impl AnimCfg {
+    /// Whether frames are addressed inside a shared sheet rather than the
+    /// sheet starting at frame 0 with no padding.
+    fn addressed(&self) -> bool {
+        self.row.is_some()
+            || self.start_frame.is_some()
+            || self.margin.is_some()
+            || self.spacing.is_some()
+    }
 }
 
 fn neg_one() -> i32 {
@@ -72,7 +246,7 @@ fn neg_one() -> i32 {
 
 // ── map.json input ──
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct MapCfgFile {
     tile_size: Option<u32>,
     cols: Option<u32>,
@@ -86,33 +260,106 @@ struct MapCfgFile {
     objects: Vec<Vec<i32>>,
     collision: Vec<Vec<u8>>,
     pois: Option<HashMap<String, PoiCfg>>,
+    /// Named patrols over `pois`, walked by `follow_route`.
+    routes: Option<HashMap<String, RouteCfg>>,
     state_icons: Option<HashMap<String, String>>,
+    walk_cycle: Option<WalkCycleCfg>,
+    /// How the pet looks and moves in this scene.
+    character: Option<MapCharCfg>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Per-map overrides for the pet; unset fields keep what `layers.json`
+/// says.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+struct MapCharCfg {
+    scale: Option<f64>,
+    /// Tiles per second; wins over the map's `character_speed`.
+    speed: Option<f64>,
+    /// Entry of `skins` in `layers.json` to draw the pet with.
+    skin: Option<String>,
+}
+
+/// One full walk cycle: `frames` animation frames while covering
+/// `stride_tiles` tiles.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WalkCycleCfg {
+    frames: Option<u32>,
+    stride_tiles: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 struct PoiCfg {
     col: u32,
     row: u32,
 }
 
+/// POIs visited in order, pausing at each.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+struct RouteCfg {
+    stops: Vec<RouteStopCfg>,
+    /// Seconds at a stop that doesn't set its own; 3 when unset.
+    dwell_secs: Option<f64>,
+    /// Starts over from the first stop until `stop_route`.
+    #[serde(default)]
+    repeat: bool,
+}
+
+/// A POI name, or one with its own dwell time.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum RouteStopCfg {
+    Poi(String),
+    Timed { poi: String, dwell_secs: f64 },
+}
+
 // ── IPC responses ──
 
 #[derive(Debug, Serialize)]
-struct FullData {
+pub struct FullData {
     width: u32,
     height: u32,
+    /// The first entry of `characters`, for single-pet frontends.
     character: CharData,
     layers: Vec<LayerItem>,
     sprites: Option<SpritesData>,
+    /// Every pet in the scene; just one unless `characters` is set.
+    characters: Vec<PetData>,
+    /// The entry of `skins` in use, if one was asked for.
+    skin: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
+struct PetData {
+    name: String,
+    character: CharData,
+    sprites: Option<SpritesData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct CharData {
     x: f64,
     y: f64,
     scale: f64,
     depth: i32,
     wander: f64,
+    shadow: Option<ShadowData>,
+    footprints: Option<FootprintsData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ShadowData {
+    width: f64,
+    height: f64,
+    opacity: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FootprintsData {
+    data_url: String,
+    fade_secs: f64,
+    spacing: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -125,30 +372,41 @@ struct LayerItem {
     alpha: f64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct SpritesData {
     frame_width: u32,
     frame_height: u32,
     anims: Vec<AnimItem>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct AnimItem {
     key: String,
     data_url: String,
     frames: u32,
     rate: u32,
     repeat: i32,
+    margin: u32,
+    spacing: u32,
+    /// Index of the first frame, counting left to right, top to bottom.
+    first_frame: u32,
+    /// Frame indices in play order, when they aren't `first_frame` onwards.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence: Option<Vec<u32>>,
+    /// Per-frame durations, when they differ from frame to frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    durations_ms: Option<Vec<u32>>,
 }
 
 #[derive(Debug, Serialize)]
-struct MapData {
+pub struct MapData {
     tile_size: u32,
     cols: u32,
     rows: u32,
     zoom: u32,
     tileset_url: String,
     tileset_cols: u32,
+    tileset_rows: u32,
     character_speed: f64,
     ground: Vec<Vec<i32>>,
     border: Vec<Vec<i32>>,
@@ -157,6 +415,20 @@ struct MapData {
     collision: Vec<Vec<u8>>,
     pois: HashMap<String, PoiOut>,
     state_icons: HashMap<String, String>,
+    walk: WalkSync,
+    /// The map's overrides as written; `load_layers(skin)` resolves the
+    /// skin.
+    character: MapCharCfg,
+}
+
+/// Walk animation timing that keeps the feet planted at `character_speed`.
+#[derive(Debug, Serialize)]
+struct WalkSync {
+    frames_per_tile: f64,
+    /// Walk animation rate in frames per second.
+    frame_rate: f64,
+    /// On-screen distance per animation frame, after `zoom`.
+    pixels_per_frame: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -165,11 +437,46 @@ struct PoiOut {
     row: u32,
 }
 
+/// One bitset per frame of every anim: row-major over the frame, bit `i`
+/// of byte `i / 8` (least significant first) set where the pixel's alpha
+/// is above `threshold`, base64-encoded.
+#[derive(Debug, Serialize)]
+pub struct HitMasks {
+    frame_width: u32,
+    frame_height: u32,
+    threshold: u8,
+    anims: BTreeMap<String, Vec<String>>,
+    /// Every pet's masks by `name`, as `load_layers` lists them.
+    characters: BTreeMap<String, SheetMasks>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SheetMasks {
+    frame_width: u32,
+    frame_height: u32,
+    anims: BTreeMap<String, Vec<String>>,
+}
+
 // ── shared ──
 
+#[derive(Clone)]
 struct AppPaths {
+    root: PathBuf,
     state_path: PathBuf,
     layers_dir: PathBuf,
+    /// `layers/map.json` unless `--map-file` moved it.
+    map_path: PathBuf,
+    settings_path: PathBuf,
+    locales_dir: PathBuf,
+    /// App-owned files (recordings, caches); created on first use.
+    data_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// Paths under `root`, with any `--state-file`-style overrides applied.
+    fn from_root(root: &Path) -> Self {
+        locate::resolve(root).into()
+    }
 }
 
 struct BackendProcess {
@@ -185,296 +492,255 @@ impl Drop for BackendProcess {
     }
 }
 
-fn encode_image(path: &PathBuf) -> Result<String, String> {
-    let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("png");
-    let mime = match ext {
-        "png" => "image/png",
-        "jpg" | "jpeg" => "image/jpeg",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        _ => "image/png",
-    };
-    Ok(format!("data:{mime};base64,{}", B64.encode(&bytes)))
-}
-
 // ── commands ──
 
-fn read_state_file(state_path: &PathBuf) -> Result<PetState, String> {
-    let raw = fs::read_to_string(state_path)
-        .map_err(|e| format!("{}: {e}", state_path.display()))?;
-    serde_json::from_str(&raw).map_err(|e| format!("parse: {e}"))
+/// `state.json` as the producers wrote it, for changing and saving back.
+fn read_state_file(state_path: &Path) -> Result<PetState, String> {
+    FsStateStore::new(state_path).load()
+}
+
+/// The state as it is shown: renamed by `mappers.json`, with `progress`
+/// and each source's `stale` worked out from `progress_sources`. Never
+/// saved back, so the producers' own names and values stay in the file.
+pub(crate) fn present(mut state: PetState) -> PetState {
+    mappers::apply(&mut state);
+    progress::resolve(&mut state);
+    state
 }
 
 fn read_state_via_backend() -> Result<PetState, String> {
-    let mut stream = std::net::TcpStream::connect("127.0.0.1:18791")
-        .map_err(|e| format!("backend connect: {e}"))?;
-    let _ = stream.set_read_timeout(Some(Duration::from_millis(1200)));
-    let _ = stream.set_write_timeout(Some(Duration::from_millis(1200)));
-
-    let request = b"GET /status HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n";
-    stream
-        .write_all(request)
-        .map_err(|e| format!("backend write: {e}"))?;
-
-    let mut raw = String::new();
-    stream
-        .read_to_string(&mut raw)
-        .map_err(|e| format!("backend read: {e}"))?;
-
-    let body = raw
-        .split_once("\r\n\r\n")
-        .map(|(_, b)| b)
-        .ok_or_else(|| "backend response parse failed".to_string())?;
-    serde_json::from_str(body).map_err(|e| format!("backend json parse: {e}"))
-}
-
-fn read_state_with_fallback(state_path: &PathBuf) -> Result<PetState, String> {
-    match read_state_file(state_path) {
-        Ok(state) => Ok(state),
+    observe::fetch_status(&observe::Remote::local())
+}
+
+/// In observer mode the remote pet stands in for the local file. When the
+/// backend can't stand in for a broken file either, the file's error is
+/// the one worth fixing.
+fn read_state_with_fallback(
+    state_path: &Path,
+    observer: &observe::Observer,
+) -> Result<PetState, AppError> {
+    if let Some(remote) = observer.remote() {
+        return observe::fetch_status(remote).map_err(AppError::backend);
+    }
+    match state_file::read(state_path) {
+        Ok(state) => Ok(present(state)),
         Err(file_err) => {
-            eprintln!("⚠️ read state file failed, fallback to backend: {file_err}");
-            read_state_via_backend()
+            log!("⚠️ read state file failed, fallback to backend: {file_err}");
+            read_state_via_backend().map_err(|backend_err| {
+                log!("⚠️ backend fallback failed: {backend_err}");
+                file_err
+            })
         }
     }
 }
 
-#[tauri::command]
-fn read_state(paths: tauri::State<'_, Mutex<AppPaths>>) -> Result<PetState, String> {
-    let p = paths.lock().map_err(|e| e.to_string())?;
-    read_state_with_fallback(&p.state_path)
+pub(crate) fn current_state(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    observer: tauri::State<'_, observe::Observer>,
+) -> Result<PetState, AppError> {
+    let state_path = paths.lock_or_recover().state_path.clone();
+    read_state_with_fallback(&state_path, &observer)
 }
 
+/// From revision 3 failures are an [`AppError`], the message before.
 #[tauri::command]
-fn load_layers(paths: tauri::State<'_, Mutex<AppPaths>>) -> Result<FullData, String> {
-    let p = paths.lock().map_err(|e| e.to_string())?;
-    let cfg_path = p.layers_dir.join("layers.json");
+fn read_state(
+    api_version: Option<u32>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    observer: tauri::State<'_, observe::Observer>,
+) -> Result<PetState, ErrorReply> {
+    let revision = api::negotiate("read_state", api_version)?;
+    current_state(paths, observer).map_err(|e| ErrorReply::at(revision, e))
+}
 
-    let cfg: CfgFile = if cfg_path.exists() {
-        let raw = fs::read_to_string(&cfg_path).map_err(|e| format!("layers.json: {e}"))?;
-        serde_json::from_str(&raw).map_err(|e| format!("layers.json: {e}"))?
+/// Revision 1 takes `newState` and returns nothing. From revision 2 it
+/// takes `state`, stamps `updated_at` when missing and returns what was
+/// written. Either way the file is replaced atomically and `state-changed`
+/// goes out at once rather than after the watcher's quiet period.
+#[tauri::command]
+fn write_state(
+    app: tauri::AppHandle,
+    state: Option<PetState>,
+    new_state: Option<PetState>,
+    api_version: Option<u32>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    observer: tauri::State<'_, observe::Observer>,
+) -> Result<Option<PetState>, String> {
+    let revision = api::negotiate("write_state", api_version)?;
+    if let Some(remote) = observer.remote() {
+        return Err(format!("write_state: read-only while observing {remote}"));
+    }
+    let store = FsStateStore::new(paths.lock_or_recover().state_path.clone());
+    let state = if revision == 1 {
+        new_state
+            .or(state)
+            .ok_or_else(|| "write_state: missing `newState`".to_string())?
     } else {
-        CfgFile {
-            width: None,
-            height: None,
-            character: None,
-            layers: None,
-            sprites: None,
-        }
-    };
-
-    let w = cfg.width.unwrap_or(200);
-    let h = cfg.height.unwrap_or(250);
-    let cc = cfg.character.unwrap_or(CharCfg {
-        x: None, y: None, scale: None, depth: None, wander: None,
-    });
-    let character = CharData {
-        x: cc.x.unwrap_or(w as f64 / 2.0),
-        y: cc.y.unwrap_or(h as f64 * 0.66),
-        scale: cc.scale.unwrap_or(2.5),
-        depth: cc.depth.unwrap_or(0),
-        wander: cc.wander.unwrap_or(18.0),
+        let mut state = state.ok_or_else(|| "write_state: missing `state`".to_string())?;
+        state
+            .updated_at
+            .get_or_insert_with(|| chrono::Local::now().to_rfc3339());
+        state
     };
-
-    let mut items = Vec::new();
-    for entry in cfg.layers.unwrap_or_default() {
-        let img_path = p.layers_dir.join(&entry.image);
-        if !img_path.exists() {
-            continue;
-        }
-        items.push(LayerItem {
-            data_url: encode_image(&img_path)?,
-            x: entry.x.unwrap_or(w as f64 / 2.0),
-            y: entry.y.unwrap_or(h as f64 / 2.0),
-            depth: entry.depth.unwrap_or(-1),
-            scale: entry.scale.unwrap_or(1.0),
-            alpha: entry.alpha.unwrap_or(1.0),
-        });
+    store.save(&state)?;
+    if let Err(e) = watch::announce_state(&app, state.clone()) {
+        log!("⚠️ emit state-changed: {e}");
     }
+    // As `read_state` would return it.
+    Ok((revision > 1).then(|| present(state)))
+}
 
-    let sprites_data = if let Some(scfg) = cfg.sprites {
-        let fw = scfg.frame_width.unwrap_or(32);
-        let fh = scfg.frame_height.unwrap_or(32);
-        let mut anims = Vec::new();
-        for (key, acfg) in scfg.anims.unwrap_or_default() {
-            let img_path = p.layers_dir.join(&acfg.file);
-            if !img_path.exists() {
-                continue;
-            }
-            anims.push(AnimItem {
-                key,
-                data_url: encode_image(&img_path)?,
-                frames: acfg.frames.unwrap_or(1),
-                rate: acfg.rate.unwrap_or(4),
-                repeat: acfg.repeat,
-            });
-        }
-        Some(SpritesData {
-            frame_width: fw,
-            frame_height: fh,
-            anims,
+/// `threshold` defaults to [`pet_core::DEFAULT_HIT_ALPHA`]; `skin` is
+/// resolved as `load_layers` does.
+#[tauri::command]
+fn get_hit_masks(
+    threshold: Option<u8>,
+    skin: Option<String>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    edits: tauri::State<'_, edit::EditSession>,
+) -> Result<HitMasks, String> {
+    let settings = settings.lock_or_recover().clone();
+    let skin = skin.or_else(|| settings.skin.clone());
+    let layers_dir = paths.lock_or_recover().layers_dir.clone();
+    let threshold = threshold.unwrap_or(pet_core::DEFAULT_HIT_ALPHA);
+    edits.with_edits(&FsAssets::new(layers_dir), |src| {
+        seasonal::with_season(src, &settings, |src| {
+            pet_core::build_hit_masks_skinned(src, skin.as_deref(), &settings.limits, threshold)
         })
-    } else {
-        None
-    };
-
-    Ok(FullData {
-        width: w,
-        height: h,
-        character,
-        layers: items,
-        sprites: sprites_data,
     })
 }
 
-#[tauri::command]
-fn load_map(paths: tauri::State<'_, Mutex<AppPaths>>) -> Result<MapData, String> {
-    let p = paths.lock().map_err(|e| e.to_string())?;
-    let map_path = p.layers_dir.join("map.json");
-
-    if !map_path.exists() {
-        return Err("map.json not found".into());
-    }
-
-    let raw = fs::read_to_string(&map_path).map_err(|e| format!("map.json: {e}"))?;
-    let cfg: MapCfgFile = serde_json::from_str(&raw).map_err(|e| format!("map.json: {e}"))?;
-
-    let ts = cfg.tile_size.unwrap_or(16);
-    let cols = cfg.cols.unwrap_or(cfg.ground.first().map_or(12, |r| r.len() as u32));
-    let rows = cfg.rows.unwrap_or(cfg.ground.len() as u32);
-
-    let tileset_path = p.layers_dir.join(&cfg.tileset);
-    if !tileset_path.exists() {
-        return Err(format!("tileset not found: {}", cfg.tileset));
-    }
-    let tileset_url = encode_image(&tileset_path)?;
-
-    // figure out tileset column count from image width
-    let img_bytes = fs::read(&tileset_path).map_err(|e| e.to_string())?;
-    let tileset_cols = png_width(&img_bytes).unwrap_or(160) / ts;
+pub(crate) fn layers_data(
+    skin: Option<String>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    edits: tauri::State<'_, edit::EditSession>,
+) -> Result<FullData, AppError> {
+    let settings = settings.lock_or_recover().clone();
+    let skin = skin.or_else(|| settings.skin.clone());
+    let layers_dir = paths.lock_or_recover().layers_dir.clone();
+    let fs = FsAssets::new(layers_dir);
+    edits
+        .with_edits(&asset_protocol::Served::new(&fs), |src| {
+            seasonal::with_season(src, &settings, |src| {
+                a11y::build_layers(src, skin.as_deref(), &settings)
+            })
+        })
+}
 
-    let mut pois = HashMap::new();
-    for (k, v) in cfg.pois.unwrap_or_default() {
-        pois.insert(k, PoiOut { col: v.col, row: v.row });
-    }
+/// From revision 3 failures are an [`AppError`], the message before.
+#[tauri::command]
+fn load_layers(
+    skin: Option<String>,
+    api_version: Option<u32>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    edits: tauri::State<'_, edit::EditSession>,
+) -> Result<FullData, ErrorReply> {
+    let revision = api::negotiate("load_layers", api_version)?;
+    layers_data(skin, paths, settings, edits).map_err(|e| ErrorReply::at(revision, e))
+}
 
-    let icons_dir = p.layers_dir.join("Small (24x24) PNG");
-    let mut state_icons = HashMap::new();
-    for (state, filename) in cfg.state_icons.unwrap_or_default() {
-        let path = icons_dir.join(&filename);
-        if path.exists() {
-            if let Ok(url) = encode_image(&path) {
-                state_icons.insert(state, url);
-            }
-        }
+pub(crate) fn map_data(
+    map: Option<String>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> Result<MapData, AppError> {
+    let limits = settings.lock_or_recover().limits.clone();
+    let p = paths.lock_or_recover();
+    let fs = FsAssets::new(&p.layers_dir);
+    let assets = asset_protocol::Served::new(&fs);
+    let Some(name) = map else {
+        return locate::with_map(&p, &assets, &limits)
+            .and_then(|src| pet_core::build_map(&src, &limits));
+    };
+    if !screensaver::valid_map_name(&name) {
+        return Err(AppError::Invalid {
+            message: format!("invalid map name `{name}`"),
+        });
     }
-
-    Ok(MapData {
-        tile_size: ts,
-        cols,
-        rows,
-        zoom: cfg.zoom.unwrap_or(2),
-        tileset_url,
-        tileset_cols,
-        character_speed: cfg.character_speed.unwrap_or(2.5),
-        ground: cfg.ground,
-        border: cfg.border.unwrap_or_default(),
-        rug: cfg.rug.unwrap_or_default(),
-        objects: cfg.objects,
-        collision: cfg.collision,
-        pois,
-        state_icons,
+    let rel = format!("{}/{name}.json", screensaver::MAPS_DIR);
+    pet_core::read_asset(&assets, &rel, &limits).and_then(|raw| {
+        pet_core::build_map(&pet_core::Overlay::new(&assets).with("map.json", raw), &limits)
     })
 }
 
-fn png_width(data: &[u8]) -> Option<u32> {
-    if data.len() < 24 || &data[0..4] != b"\x89PNG" {
-        return None;
+/// `map` names an alternate `layers/maps/<map>.json`, e.g. the one the
+/// screensaver picked; the usual `map.json` otherwise. From revision 3
+/// failures are an [`AppError`], the message before.
+#[tauri::command]
+fn load_map(
+    map: Option<String>,
+    api_version: Option<u32>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> Result<MapData, ErrorReply> {
+    let revision = api::negotiate("load_map", api_version)?;
+    map_data(map, paths, settings).map_err(|e| ErrorReply::at(revision, e))
+}
+
+/// `path` is a Tiled `.tmx` relative to `layers/`, e.g. `maps/office.tmx`.
+#[tauri::command]
+fn load_map_tiled(
+    path: String,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> Result<MapData, String> {
+    let rel = path.trim().replace('\\', "/");
+    if !rel.ends_with(".tmx")
+        || rel.starts_with('/')
+        || rel.contains(':')
+        || rel.split('/').any(|seg| seg == "..")
+    {
+        return Err(format!("`{path}` must be a .tmx file inside layers/"));
     }
-    Some(u32::from_be_bytes([data[16], data[17], data[18], data[19]]))
+    let limits = settings.lock_or_recover().limits.clone();
+    let p = paths.lock_or_recover().clone();
+    let fs = FsAssets::new(&p.layers_dir);
+    let served = asset_protocol::Served::new(&fs);
+    let assets = locate::with_map(&p, &served, &limits)?;
+    if !assets.exists(&rel) {
+        return Err(format!("map not found: {rel}"));
+    }
+    pet_core::build_map_tiled(&assets, &rel, &limits).map_err(String::from)
 }
 
 // ── bootstrap ──
 
-fn find_project_root() -> PathBuf {
-    if let Ok(p) = std::env::var("STAR_PROJECT_ROOT") {
-        let candidate = PathBuf::from(&p);
-        let abs = if candidate.is_absolute() {
-            candidate
-        } else {
-            std::env::current_dir().unwrap_or_default().join(candidate)
-        };
-        if abs.join("backend").join("app.py").exists() {
-            return abs;
-        }
-    }
-    let mut dir = std::env::current_dir().unwrap_or_default();
-    for _ in 0..8 {
-        if dir.join("backend").join("app.py").exists()
-            || dir.join("state.json").exists()
-            || dir.join("state.sample.json").exists()
-        {
-            return dir;
-        }
-        if !dir.pop() {
-            break;
-        }
-    }
-    if let Ok(home) = std::env::var("HOME") {
-        let home = PathBuf::from(home);
-        let candidates = [
-            home.join("Documents").join("GitHub").join("Star-Office-UI"),
-            home.join("GitHub").join("Star-Office-UI"),
-            home.join("Documents").join("Star-Office-UI"),
-            home.join("Star-Office-UI"),
-        ];
-        for candidate in candidates {
-            if candidate.join("backend").join("app.py").exists() {
-                return candidate;
-            }
-        }
+fn venv_python(root: &Path) -> PathBuf {
+    let venv = root.join(".venv");
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
     }
-    std::env::current_dir().unwrap_or_default()
 }
 
-fn spawn_backend(root: &PathBuf) -> Option<Child> {
+/// With `rpc_stdio` the child stays off the pet's stdin/stdout, which
+/// then belong to JSON-RPC; its output goes to stderr instead.
+fn spawn_backend(root: &Path, rpc_stdio: bool) -> Option<Child> {
     if std::net::TcpStream::connect("127.0.0.1:18791").is_ok() {
-        eprintln!("ℹ️ backend already running on 127.0.0.1:18791");
+        log!("ℹ️ backend already running on 127.0.0.1:18791");
         return None;
     }
 
     let script = root.join("backend").join("app.py");
-    if !script.exists() {
-        eprintln!("⚠️ backend/app.py not found: {}", script.display());
+    if !fsutil::exists(&script) {
+        log!("⚠️ backend/app.py not found: {}", script.display());
         return None;
     }
 
-    let mut candidates: Vec<(PathBuf, Vec<String>)> = vec![
-        (
-            root.join(".venv").join("bin").join("python"),
-            vec![script.to_string_lossy().to_string()],
-        ),
-        (
-            PathBuf::from("python3"),
-            vec![script.to_string_lossy().to_string()],
-        ),
-        (
-            PathBuf::from("python"),
-            vec![script.to_string_lossy().to_string()],
-        ),
+    // Pass the script as an OsString: lossy conversion mangles non-UTF-8 paths.
+    let script_arg: OsString = script.into_os_string();
+    let mut candidates: Vec<(PathBuf, Vec<OsString>)> = vec![
+        (venv_python(root), vec![script_arg.clone()]),
+        (PathBuf::from("python3"), vec![script_arg.clone()]),
+        (PathBuf::from("python"), vec![script_arg.clone()]),
     ];
 
-    if let Ok(custom_python) = std::env::var("STAR_BACKEND_PYTHON") {
-        candidates.insert(
-            0,
-            (
-                PathBuf::from(custom_python),
-                vec![script.to_string_lossy().to_string()],
-            ),
-        );
+    if let Some(custom_python) = std::env::var_os("STAR_BACKEND_PYTHON") {
+        candidates.insert(0, (PathBuf::from(custom_python), vec![script_arg]));
     }
 
     for (bin, args) in candidates {
@@ -483,14 +749,17 @@ fn spawn_backend(root: &PathBuf) -> Option<Child> {
             .args(&args)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit());
+        if rpc_stdio {
+            cmd.stdin(Stdio::null()).stdout(std::io::stderr());
+        }
 
         match cmd.spawn() {
             Ok(child) => {
-                eprintln!("🚀 backend started with {}", bin.display());
+                log!("🚀 backend started with {}", bin.display());
                 return Some(child);
             }
             Err(err) => {
-                eprintln!("⚠️ failed to spawn {}: {}", bin.display(), err);
+                log!("⚠️ failed to spawn {}: {}", bin.display(), err);
             }
         }
     }
@@ -522,10 +791,10 @@ fn enter_minimize_mode(
         .ok_or_else(|| "mini window not found".to_string())?;
 
     let state_path = {
-        let p = paths.lock().map_err(|e| e.to_string())?;
+        let p = paths.lock_or_recover();
         p.state_path.clone()
     };
-    if let Ok(snapshot) = read_state_with_fallback(&state_path) {
+    if let Ok(snapshot) = read_state_with_fallback(&state_path, &app.state()) {
         // Sync mini immediately before showing it, avoiding stale one-shot transition.
         let _ = mini.emit("mini-sync-state", snapshot);
     }
@@ -556,86 +825,376 @@ fn restore_main_window(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Last resort when the app is wedged: re-resolves the project root and
+/// replaces all runtime path state as if freshly launched.
+#[tauri::command]
+fn reset_runtime_state(paths: tauri::State<'_, Mutex<AppPaths>>) {
+    let root = find_project_root();
+    log!("♻️ runtime state reset, root: {}", root.display());
+    *paths.lock_or_recover() = resolve_paths(&root).0;
+}
+
+/// Paths as a launch sees them: the root's (with overrides), then the
+/// chosen profile's settings and data, then the last session's map.
+/// Returns the profile in use too. CLI subcommands read through it as well.
+pub(crate) fn resolve_paths(root: &Path) -> (AppPaths, String) {
+    let mut paths = AppPaths::from_root(root);
+    let profile = profiles::apply(&mut paths);
+    session::restore_map(&mut paths, locate::map_pinned(root));
+    (paths, profile)
+}
+
 #[tauri::command]
 fn close_app(app: tauri::AppHandle) {
+    shutdown::run(&app);
     app.exit(0);
 }
 
 #[tauri::command]
 fn open_external_url(url: String) -> Result<(), String> {
+    os_open(url.as_ref()).map_err(|e| format!("failed to open browser: {e}"))
+}
+
+/// Hands a URL or path to the OS default handler (browser, file manager).
+fn os_open(target: &std::ffi::OsStr) -> std::io::Result<()> {
     #[cfg(target_os = "macos")]
     let mut cmd = {
         let mut c = Command::new("open");
-        c.arg(&url);
+        c.arg(target);
         c
     };
 
     #[cfg(target_os = "windows")]
     let mut cmd = {
         let mut c = Command::new("cmd");
-        c.args(["/C", "start", "", &url]);
+        c.args(["/C", "start", ""]).arg(target);
         c
     };
 
     #[cfg(all(unix, not(target_os = "macos")))]
     let mut cmd = {
         let mut c = Command::new("xdg-open");
-        c.arg(&url);
+        c.arg(target);
         c
     };
 
-    cmd.spawn()
-        .map(|_| ())
-        .map_err(|e| format!("failed to open browser: {e}"))
+    cmd.spawn().map(|_| ())
+}
+
+/// Hidden mini window and the tray icon; skipped in headless mode.
+fn build_windows(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    // Hidden mini window: transparent square with only avatar + status.
+    let mini = WebviewWindowBuilder::new(
+        app,
+        "mini",
+        WebviewUrl::App("minimized.html".into()),
+    )
+    .title("Star Mini")
+    .inner_size(220.0, 240.0)
+    .min_inner_size(180.0, 200.0)
+    .resizable(false)
+    .decorations(false)
+    .transparent(true)
+    .always_on_top(true)
+    .shadow(false)
+    .visible(false)
+    .build()
+    .map_err(|e| e.to_string())?;
+    let _ = mini.hide();
+
+    if let Err(e) = tray::build(app) {
+        log!("⚠️ tray icon: {e}");
+    }
+    Ok(())
+}
+
+/// The `star-pet-ctl` binary; returns its exit code.
+pub fn run_ctl() -> i32 {
+    ctl::ctl_main()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let options = match cli::dispatch() {
+        cli::Outcome::Launch(options) => options,
+        cli::Outcome::Exit(code) => std::process::exit(code),
+    };
+
     let root = find_project_root();
-    eprintln!("📦 State : {}", root.join("state.json").display());
-    eprintln!("🎨 Layers: {}", root.join("layers").display());
-    let backend_child = spawn_backend(&root);
-    let backend_ready = wait_backend_ready();
-    if !backend_ready {
-        eprintln!("⚠️ backend not ready within 10s");
+    let (paths, profile) = resolve_paths(&root);
+    logs::init(&paths.data_dir);
+    crash::install(&paths.data_dir);
+    crash::announce_unseen(&paths.data_dir);
+    let recovery = session::begin(&paths);
+    mappers::reload(&paths.root);
+    notifications::reload(&paths.root);
+    if profile != profiles::DEFAULT {
+        log!("👤 Profile: {profile}");
+    }
+    log!("📦 State : {}", paths.state_path.display());
+    log!("🎨 Layers: {}", paths.layers_dir.display());
+    let settings = settings::load(&paths.settings_path);
+    platform::prepare(&settings.platform);
+    let locale = i18n::load(&paths.locales_dir, settings.locale.as_deref());
+    log!("🌐 Locale: {}", locale.lang);
+    let dev_state = dev::DevState::new(options.dev);
+    if options.dev {
+        log!("🛠️ dev mode: caching off, every reload is logged");
+        let layers_dir = paths.layers_dir.clone();
+        if let Err(e) = dev::serve_assets(&dev_state, layers_dir, dev::DEFAULT_ASSET_PORT) {
+            log!("⚠️ {e}");
+        }
+    }
+    // An observer shows someone else's pet; no local backend needed.
+    let backend_child = if options.observe.is_none() {
+        let child = spawn_backend(&root, options.rpc == Some(rpc::Transport::Stdio));
+        if !wait_backend_ready() {
+            log!("⚠️ backend not ready within 10s");
+        }
+        child
+    } else {
+        None
+    };
+
+    let mut context = tauri::generate_context!();
+    let headless = options.headless;
+    let rpc_transport = options.rpc;
+    if headless {
+        // Drops the config's main window too; nothing is ever shown.
+        context.config_mut().app.windows.clear();
+        log!("🕶️ headless: engine only, no windows");
     }
 
     tauri::Builder::default()
         .manage(Mutex::new(BackendProcess { child: backend_child }))
-        .manage(Mutex::new(AppPaths {
-            state_path: root.join("state.json"),
-            layers_dir: root.join("layers"),
-        }))
-        .setup(|app| {
-            // Hidden mini window: transparent square with only avatar + status.
-            let mini = WebviewWindowBuilder::new(
-                app,
-                "mini",
-                WebviewUrl::App("minimized.html".into()),
-            )
-            .title("Star Mini")
-            .inner_size(220.0, 240.0)
-            .min_inner_size(180.0, 200.0)
-            .resizable(false)
-            .decorations(false)
-            .transparent(true)
-            .always_on_top(true)
-            .shadow(false)
-            .visible(false)
-            .build()
-            .map_err(|e| e.to_string())?;
-            let _ = mini.hide();
+        .manage(Mutex::new(settings))
+        .manage(Mutex::new(paths))
+        .manage(dev_state)
+        .manage(replay::Recorder::default())
+        .manage(edit::EditSession::default())
+        .manage(i18n::I18n::new(locale))
+        .manage(perf::Perf::default())
+        .manage(breaks::Breaks::default())
+        .manage(timeline::Timeline::default())
+        .manage(typing::Typing::default())
+        .manage(screensaver::ScreensaverState::default())
+        .manage(lan::LanPeers::default())
+        .manage(observe::Observer::new(options.observe))
+        .manage(power::Power::default())
+        .manage(pathfind::PetTile::default())
+        .manage(pathfind::Patrol::default())
+        .manage(avoid::Avoid::default())
+        .manage(maintenance::Maintenance::default())
+        .manage(ambient::Ambient::default())
+        .manage(session::Startup::new(recovery))
+        .manage(sim::Engine::default())
+        .manage(system_stats::SystemMonitor::default())
+        .manage(profiles::Current(profile))
+        .manage(pomodoro::Pomodoro::default())
+        .manage(calendar::Calendar::default())
+        .manage(build::Build::default())
+        .manage(jobs::Jobs::default())
+        .manage(ci::Ci::default())
+        .manage(health::Health::default())
+        .register_uri_scheme_protocol(asset_protocol::SCHEME, |ctx, request| {
+            asset_protocol::handle(ctx.app_handle(), &request)
+        })
+        .setup(move |app| {
+            // No Dock icon for a process without windows.
+            #[cfg(target_os = "macos")]
+            if headless {
+                app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+            }
+            if !headless {
+                build_windows(app)?;
+            }
+            session::restore(app.handle());
+
+            // Start the clock on whatever state the pet launched in.
+            let (state_path, data_dir) = {
+                let p = app.state::<Mutex<AppPaths>>();
+                let p = p.lock_or_recover();
+                (p.state_path.clone(), p.data_dir.clone())
+            };
+            if let Ok(state) = read_state_file(&state_path).map(present) {
+                app.state::<timeline::Timeline>().observe(&data_dir, &state);
+                taskbar::update(app.handle(), &state);
+                tray::update(app.handle(), &state);
+                app.state::<sim::Engine>().observe(&state);
+                rgb::show(&state);
+            }
+            sound::reload(app.handle());
+
+            match watch::start(app.handle().clone()) {
+                Ok(service) => {
+                    app.manage(Mutex::new(service));
+                }
+                Err(e) => log!("⚠️ config watcher disabled: {e}"),
+            }
+            jobs::spawn_worker(app.handle().clone());
+            power::spawn_monitor(app.handle().clone());
+            updates::spawn_scheduler(app.handle().clone());
+            gaming::spawn_watcher(app.handle().clone());
+            avoid::spawn_watcher(app.handle().clone());
+            breaks::spawn_guard(app.handle().clone());
+            pomodoro::spawn_ticker(app.handle().clone());
+            calendar::spawn_scheduler(app.handle().clone());
+            build::spawn_watcher(app.handle().clone());
+            ci::spawn_poller(app.handle().clone());
+            clipboard::spawn_watcher(app.handle().clone());
+            screensaver::spawn_watcher(app.handle().clone());
+            activity::spawn_watcher(app.handle().clone());
+            system_stats::spawn_sampler(app.handle().clone());
+            seasonal::spawn_scheduler(app.handle().clone());
+            ambient::spawn_watcher(app.handle().clone());
+            progress::spawn_watchdog(app.handle().clone());
+            maintenance::spawn_scheduler(app.handle().clone());
+            digest::spawn_scheduler(app.handle().clone());
+            sim::spawn_engine(app.handle().clone());
+            dbus::start(app.handle().clone());
+            lan::start(app.handle().clone());
+            status::start(app.handle().clone());
+            control::start(app.handle().clone());
+            ctl::start(app.handle().clone());
+            voice::start(app.handle().clone());
+            ws::start(app.handle().clone());
+            mqtt::start(app.handle().clone());
+            rgb::start(app.handle().clone());
+            observe::spawn_poller(app.handle().clone());
+            if let Some(transport) = rpc_transport {
+                if let Err(e) = rpc::start(app.handle().clone(), transport) {
+                    log!("⚠️ {e}");
+                }
+            }
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            if webview.label() == "main" && payload.event() == PageLoadEvent::Finished {
+                session::announce(webview.app_handle());
+            }
+        })
+        .on_window_event(|window, event| {
+            if window.label() == "main"
+                && matches!(event, tauri::WindowEvent::CloseRequested { .. })
+            {
+                session::save_on_exit(window.app_handle());
+            }
+            if let tauri::WindowEvent::Moved(pos) = event {
+                let moved = replay::WindowMoved {
+                    label: window.label().to_string(),
+                    x: pos.x,
+                    y: pos.y,
+                };
+                window
+                    .state::<replay::Recorder>()
+                    .capture(replay::WINDOW_MOVED, &moved);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
+            api::get_api_info,
             read_state,
+            write_state,
             load_layers,
             load_map,
+            load_map_tiled,
             enter_minimize_mode,
             restore_main_window,
+            reset_runtime_state,
             close_app,
-            open_external_url
+            open_external_url,
+            schema::get_config_schema,
+            doctor::run_doctor,
+            scaffold::scaffold_project,
+            dev::get_dev_status,
+            replay::start_recording,
+            replay::stop_recording,
+            replay::play_recording,
+            capture::record_clip,
+            bench::bench_assets,
+            states::list_known_states,
+            edit::update_layer,
+            edit::update_character,
+            i18n::t,
+            i18n::get_locale,
+            bundle::import_bundle,
+            registry::list_registry_packs,
+            registry::install_registry_pack,
+            theme::get_theme,
+            updates::check_for_updates,
+            crash::get_last_crash,
+            folders::open_path,
+            perf::report_frame_stats,
+            a11y::get_accessibility,
+            platform::get_platform_capabilities,
+            platform::set_click_through,
+            breaks::snooze_break,
+            breaks::get_break_stats,
+            report::generate_report,
+            typing::report_keystrokes,
+            typing::get_typing_stats,
+            progress::report_progress,
+            progress::get_progress_breakdown,
+            screensaver::get_screensaver,
+            lan::get_lan_peers,
+            observe::get_observer,
+            power::get_energy_saver,
+            watch::watch_state,
+            clock::get_clock_info,
+            get_hit_masks,
+            seasonal::get_seasonal,
+            locate::get_effective_paths,
+            validate::validate_project,
+            onboarding::get_onboarding_step,
+            onboarding::complete_step,
+            schema::get_config_schemas,
+            pathfind::find_path,
+            pathfind::set_pet_tile,
+            pathfind::route_to_poi,
+            pathfind::follow_route,
+            pathfind::stop_route,
+            session::save_session,
+            session::restore_session,
+            session::get_session_recovery,
+            avoid::get_avoid_bounds,
+            maintenance::get_maintenance_status,
+            maintenance::run_maintenance,
+            control::get_control_api,
+            control::set_control_api,
+            digest::send_digest,
+            ambient::get_ambient_mode,
+            ambient::set_ambient_mode,
+            permissions::get_audit_log,
+            tray::get_wander_paused,
+            sim::get_sim_snapshot,
+            sim::reset_sim,
+            activity::get_idle_seconds,
+            system_stats::system_stats,
+            profiles::list_profiles,
+            profiles::switch_profile,
+            preview::render_anim_preview,
+            pomodoro::start_pomodoro,
+            pomodoro::pause_pomodoro,
+            pomodoro::skip_pomodoro,
+            pomodoro::stop_pomodoro,
+            pomodoro::get_pomodoro,
+            pomodoro::get_pomodoro_history,
+            calendar::get_calendar,
+            bubble::say,
+            build::start_build,
+            build::stop_build,
+            build::get_build,
+            jobs::get_jobs,
+            jobs::cancel_job,
+            ci::get_ci,
+            health::get_integration_status,
+            sound::play_sound,
+            sound::set_volume
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown::run(app);
+            }
+        });
 }
@@ -0,0 +1,162 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::{CfgFile, MapCfgFile, SpritesCfg};
+
+/// Hard caps applied before and after parsing configs, so a hostile or
+/// mis-exported pack is rejected instead of hanging or exhausting memory.
+/// Overridable under `limits` in `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Limits {
+    /// Largest accepted `layers.json` / `map.json` / `state.json`, in bytes.
+    pub max_config_bytes: u64,
+    /// Largest accepted image file, in bytes.
+    pub max_asset_bytes: u64,
+    /// Largest accepted map width or height, in tiles.
+    pub max_grid_dim: u32,
+    pub max_layers: usize,
+    pub max_anims: usize,
+    /// Pets listed under `characters` in `layers.json`.
+    pub max_characters: usize,
+    pub max_frames: u32,
+    /// Largest accepted pack bundle (zip), in bytes.
+    pub max_bundle_bytes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_config_bytes: 8 * 1024 * 1024,
+            max_asset_bytes: 32 * 1024 * 1024,
+            max_grid_dim: 1024,
+            max_layers: 256,
+            max_anims: 512,
+            max_characters: 64,
+            max_frames: 4096,
+            max_bundle_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+fn exceeded(
+    what: String,
+    actual: impl std::fmt::Display,
+    limit: impl std::fmt::Display,
+    key: &str,
+) -> AppError {
+    AppError::LimitExceeded {
+        message: format!("{what} is {actual}, limit is {limit} (settings.json limits.{key})"),
+    }
+}
+
+/// Refuses files over `max` bytes before anything reads them into memory.
+pub(crate) fn check_size(name: &str, len: u64, max: u64, key: &str) -> Result<(), AppError> {
+    if len > max {
+        return Err(exceeded(name.to_string(), format!("{len} bytes"), max, key));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_layers(cfg: &CfgFile, limits: &Limits) -> Result<(), AppError> {
+    let layers = cfg.layers.as_ref().map_or(0, Vec::len);
+    if layers > limits.max_layers {
+        return Err(exceeded(
+            "layers.json: layer count".into(),
+            layers,
+            limits.max_layers,
+            "max_layers",
+        ));
+    }
+    let pets = cfg.characters.as_deref().unwrap_or_default();
+    if pets.len() > limits.max_characters {
+        return Err(exceeded(
+            "layers.json: character count".into(),
+            pets.len(),
+            limits.max_characters,
+            "max_characters",
+        ));
+    }
+    check_anims(cfg.sprites.as_ref(), limits)?;
+    for pet in pets {
+        check_anims(pet.sprites.as_ref(), limits)?;
+    }
+    for skin in cfg.skins.iter().flat_map(|s| s.values()) {
+        check_anims(Some(skin), limits)?;
+    }
+    Ok(())
+}
+
+fn check_anims(sprites: Option<&SpritesCfg>, limits: &Limits) -> Result<(), AppError> {
+    if let Some(anims) = sprites.and_then(|s| s.anims.as_ref()) {
+        if anims.len() > limits.max_anims {
+            return Err(exceeded(
+                "layers.json: anim count".into(),
+                anims.len(),
+                limits.max_anims,
+                "max_anims",
+            ));
+        }
+        for (key, anim) in anims {
+            let frames = anim.frames.unwrap_or(1);
+            if frames > limits.max_frames {
+                return Err(exceeded(
+                    format!("layers.json: anim `{key}` frames"),
+                    frames,
+                    limits.max_frames,
+                    "max_frames",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_grid<T>(name: &str, grid: &[Vec<T>], limits: &Limits) -> Result<(), AppError> {
+    let max = limits.max_grid_dim as usize;
+    if grid.len() > max {
+        return Err(exceeded(
+            format!("map.json: {name} row count"),
+            grid.len(),
+            max,
+            "max_grid_dim",
+        ));
+    }
+    if let Some(widest) = grid.iter().map(Vec::len).max() {
+        if widest > max {
+            return Err(exceeded(
+                format!("map.json: {name} row width"),
+                widest,
+                max,
+                "max_grid_dim",
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn check_map(cfg: &MapCfgFile, limits: &Limits) -> Result<(), AppError> {
+    for (name, declared) in [("cols", cfg.cols), ("rows", cfg.rows)] {
+        if let Some(n) = declared {
+            if n > limits.max_grid_dim {
+                return Err(exceeded(
+                    format!("map.json: {name}"),
+                    n,
+                    limits.max_grid_dim,
+                    "max_grid_dim",
+                ));
+            }
+        }
+    }
+    check_grid("ground", &cfg.ground, limits)?;
+    check_grid("objects", &cfg.objects, limits)?;
+    check_grid("collision", &cfg.collision, limits)?;
+    if let Some(border) = &cfg.border {
+        check_grid("border", border, limits)?;
+    }
+    if let Some(rug) = &cfg.rug {
+        check_grid("rug", rug, limits)?;
+    }
+    Ok(())
+}
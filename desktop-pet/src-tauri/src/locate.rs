@@ -0,0 +1,240 @@
+//! Where every file lives: the project root and per-path overrides.
+//!
+//! Each overridable path is resolved here, first match wins:
+//!
+//! | path         | flag           | env               | default               |
+//! |--------------|----------------|-------------------|-----------------------|
+//! | `state.json` | `--state-file` | `STAR_STATE_FILE` | `<root>/state.json`   |
+//! | `layers/`    | `--layers-dir` | `STAR_LAYERS_DIR` | `<root>/layers`       |
+//! | `map.json`   | `--map-file`   | `STAR_MAP_FILE`   | `<layers>/map.json`   |
+//! | app data     | `--data-dir`   | `STAR_DATA_DIR`   | `<root>/.star-pet`    |
+//!
+//! The root itself comes from `STAR_PROJECT_ROOT` or auto-detection (or a
+//! subcommand's `--root`). `get_effective_paths` reports what won.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::AppError;
+use crate::limits::Limits;
+use crate::pet_core::{self, AssetSource, FsAssets, Overlay};
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+/// How `find_project_root` arrived at its answer, for diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RootSource {
+    Env,
+    Ancestor,
+    HomeGuess,
+    Fallback,
+    /// Chosen by the caller rather than auto-detected.
+    Explicit,
+}
+
+pub(crate) fn find_project_root() -> PathBuf {
+    resolve_project_root().0
+}
+
+pub(crate) fn resolve_project_root() -> (PathBuf, RootSource) {
+    if let Some(p) = std::env::var_os("STAR_PROJECT_ROOT") {
+        let abs = absolute(p);
+        if fsutil::exists(&abs.join("backend").join("app.py")) {
+            return (abs, RootSource::Env);
+        }
+    }
+    let mut dir = std::env::current_dir().unwrap_or_default();
+    for _ in 0..8 {
+        if fsutil::exists(&dir.join("backend").join("app.py"))
+            || fsutil::exists(&dir.join("state.json"))
+            || fsutil::exists(&dir.join("state.sample.json"))
+        {
+            return (dir, RootSource::Ancestor);
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+    if let Some(home) = fsutil::home_dir() {
+        let candidates = [
+            home.join("Documents").join("GitHub").join("Star-Office-UI"),
+            home.join("GitHub").join("Star-Office-UI"),
+            home.join("Documents").join("Star-Office-UI"),
+            home.join("Star-Office-UI"),
+        ];
+        for candidate in candidates {
+            if fsutil::exists(&candidate.join("backend").join("app.py")) {
+                return (candidate, RootSource::HomeGuess);
+            }
+        }
+    }
+    (
+        std::env::current_dir().unwrap_or_default(),
+        RootSource::Fallback,
+    )
+}
+
+fn absolute(path: impl Into<PathBuf>) -> PathBuf {
+    let path = path.into();
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    }
+}
+
+// ── overrides ──
+
+/// Paths given on the command line; unset ones fall through to the env.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overrides {
+    pub state_file: Option<PathBuf>,
+    pub layers_dir: Option<PathBuf>,
+    pub map_file: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+}
+
+static CLI: OnceLock<Overrides> = OnceLock::new();
+
+/// Records the CLI flags; called once from `cli::dispatch`.
+pub(crate) fn set_cli(overrides: Overrides) {
+    let _ = CLI.set(overrides);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Origin {
+    Flag,
+    Env,
+    /// Derived from the project root (or, for the map, `layers/`).
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Effective {
+    pub path: PathBuf,
+    pub from: Origin,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivePaths {
+    root: PathBuf,
+    state_file: Effective,
+    layers_dir: Effective,
+    map_file: Effective,
+    data_dir: Effective,
+    settings_file: PathBuf,
+    locales_dir: PathBuf,
+}
+
+fn pick(flag: Option<&PathBuf>, env: &str, default: PathBuf) -> Effective {
+    if let Some(path) = flag {
+        return Effective {
+            path: absolute(path),
+            from: Origin::Flag,
+        };
+    }
+    match std::env::var_os(env).filter(|v| !v.is_empty()) {
+        Some(v) => Effective {
+            path: absolute(v),
+            from: Origin::Env,
+        },
+        None => Effective {
+            path: default,
+            from: Origin::Default,
+        },
+    }
+}
+
+/// Every path under `root`, overrides applied.
+pub(crate) fn resolve(root: &Path) -> EffectivePaths {
+    let cli = CLI.get().cloned().unwrap_or_default();
+    let state_file = pick(
+        cli.state_file.as_ref(),
+        "STAR_STATE_FILE",
+        root.join("state.json"),
+    );
+    let layers_dir = pick(
+        cli.layers_dir.as_ref(),
+        "STAR_LAYERS_DIR",
+        root.join("layers"),
+    );
+    let map_file = pick(
+        cli.map_file.as_ref(),
+        "STAR_MAP_FILE",
+        layers_dir.path.join("map.json"),
+    );
+    let data_dir = pick(
+        cli.data_dir.as_ref(),
+        "STAR_DATA_DIR",
+        root.join(".star-pet"),
+    );
+    EffectivePaths {
+        root: root.to_path_buf(),
+        state_file,
+        layers_dir,
+        map_file,
+        data_dir,
+        settings_file: root.join("settings.json"),
+        locales_dir: root.join("locales"),
+    }
+}
+
+impl From<EffectivePaths> for AppPaths {
+    fn from(e: EffectivePaths) -> Self {
+        AppPaths {
+            root: e.root,
+            state_path: e.state_file.path,
+            layers_dir: e.layers_dir.path,
+            map_path: e.map_file.path,
+            settings_path: e.settings_file,
+            locales_dir: e.locales_dir,
+            data_dir: e.data_dir.path,
+        }
+    }
+}
+
+// ── map file ──
+
+/// `base` (the `layers/` source) with `map.json` read from `--map-file`
+/// when that moved it. Asset paths inside stay relative to `layers/`.
+pub(crate) fn with_map<'a>(
+    paths: &AppPaths,
+    base: &'a dyn AssetSource,
+    limits: &Limits,
+) -> Result<Overlay<'a>, AppError> {
+    let overlay = Overlay::new(base);
+    if paths.map_path == paths.layers_dir.join("map.json") {
+        return Ok(overlay);
+    }
+    let (Some(dir), Some(name)) = (
+        paths.map_path.parent(),
+        paths.map_path.file_name().and_then(|n| n.to_str()),
+    ) else {
+        return Err(format!("invalid map file: {}", paths.map_path.display()).into());
+    };
+    let fs = FsAssets::new(dir);
+    if !fs.exists(name) {
+        let path = paths.map_path.display().to_string();
+        return Err(AppError::missing(
+            &path,
+            format!("map file not found: {path}"),
+        ));
+    }
+    let raw = pet_core::read_config(&fs, name, limits)?;
+    Ok(overlay.with("map.json", raw))
+}
+
+/// Whether `--map-file` or `STAR_MAP_FILE` chose the map, which the last
+/// session's map then doesn't replace.
+pub(crate) fn map_pinned(root: &Path) -> bool {
+    resolve(root).map_file.from != Origin::Default
+}
+
+#[tauri::command]
+pub fn get_effective_paths(paths: tauri::State<'_, Mutex<AppPaths>>) -> EffectivePaths {
+    let root = paths.lock_or_recover().root.clone();
+    resolve(&root)
+}
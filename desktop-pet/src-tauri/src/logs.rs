@@ -0,0 +1,102 @@
+//! Runtime log: every line goes to stderr as before, to
+//! `.star-pet/logs/desktop-pet.log`, and to a short in-memory tail that
+//! crash reports include.
+//!
+//! Before [`init`] (CLI subcommands) lines only go to stderr.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::fsutil;
+
+pub(crate) const FILE: &str = "desktop-pet.log";
+/// Past this the log is rotated to `desktop-pet.old.log` at startup.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const TAIL_LINES: usize = 200;
+
+struct Sink {
+    file: Option<File>,
+    tail: VecDeque<String>,
+}
+
+static SINK: OnceLock<Mutex<Sink>> = OnceLock::new();
+
+/// `eprintln!` that also lands in the log file and the crash tail.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::logs::write(format!($($arg)*))
+    };
+}
+pub(crate) use log;
+
+pub(crate) fn dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("logs")
+}
+
+/// Opens the log file under `data_dir`. A failure leaves logging on
+/// stderr and the tail, never stops the app.
+pub(crate) fn init(data_dir: &Path) {
+    let dir = dir(data_dir);
+    let path = dir.join(FILE);
+    let file = std::fs::create_dir_all(fsutil::long_path(&dir))
+        .and_then(|_| {
+            let big = std::fs::metadata(fsutil::long_path(&path))
+                .map(|m| m.len() > MAX_FILE_BYTES)
+                .unwrap_or(false);
+            if big {
+                std::fs::rename(
+                    fsutil::long_path(&path),
+                    fsutil::long_path(&dir.join("desktop-pet.old.log")),
+                )?;
+            }
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(fsutil::long_path(&path))
+        })
+        .map_err(|e| eprintln!("⚠️ log file {}: {e}", path.display()))
+        .ok();
+    let sink = SINK.get_or_init(|| {
+        Mutex::new(Sink {
+            file: None,
+            tail: VecDeque::with_capacity(TAIL_LINES),
+        })
+    });
+    sink.lock().unwrap_or_else(|p| p.into_inner()).file = file;
+}
+
+pub(crate) fn write(line: String) {
+    eprintln!("{line}");
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    // Not `lock_or_recover`: that logs, and would come straight back here.
+    let mut sink = sink.lock().unwrap_or_else(|p| p.into_inner());
+    let stamped = format!(
+        "{} {line}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(file) = sink.file.as_mut() {
+        let _ = writeln!(file, "{stamped}");
+    }
+    if sink.tail.len() == TAIL_LINES {
+        sink.tail.pop_front();
+    }
+    sink.tail.push_back(stamped);
+}
+
+/// Recent lines, oldest first. Gives up rather than block if the panic
+/// being reported happened while the log was locked.
+pub(crate) fn tail() -> Vec<String> {
+    let Some(sink) = SINK.get() else {
+        return Vec::new();
+    };
+    match sink.try_lock() {
+        Ok(sink) => sink.tail.iter().cloned().collect(),
+        Err(std::sync::TryLockError::Poisoned(p)) => p.into_inner().tail.iter().cloned().collect(),
+        Err(std::sync::TryLockError::WouldBlock) => Vec::new(),
+    }
+}
@@ -0,0 +1,486 @@
+//! Housekeeping that is too heavy to run while someone works: clearing
+//! leftovers, recompressing pack PNGs, backing up the project and pruning
+//! old recordings.
+//!
+//! The scheduler runs the configured tasks once every `interval_hours`,
+//! but only after `idle_secs` without input and while on mains power. If
+//! the user comes back mid-run, the remaining tasks wait for the next idle
+//! spell. `run_maintenance` starts a run right away, ignoring both checks.
+//! Runs go through the job queue (see [`crate::jobs`]), which can also
+//! cancel them between tasks.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::jobs::{self, Job};
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{activity, fsutil, imaging, power, stats, AppPaths};
+
+const STATS_NAME: &str = "maintenance";
+/// Folders under `.star-pet/` whose files `prune` expires by age.
+const PRUNED_DIRS: &[&str] = &["clips", "recordings", "reports"];
+/// `.tmp` files older than this are left over from a crashed write.
+const STALE_TMP: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+    /// Removes what interrupted work left behind: `staging/` and stray
+    /// `.tmp` files.
+    Compact,
+    /// Recompresses PNGs under `layers/`, keeping a file only if it shrank.
+    OptimizePng,
+    /// Zips `layers/` and the root config files into `backups/`.
+    Backup,
+    /// Deletes old clips, recordings, reports, backups and rotated logs.
+    Prune,
+}
+
+/// `maintenance` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    pub enabled: bool,
+    /// Run in this order; `optimize_png` rewrites pack art, so it is opt-in.
+    pub tasks: Vec<Task>,
+    pub interval_hours: u32,
+    /// Seconds without keyboard or mouse input before a run may start.
+    pub idle_secs: u64,
+    /// Backups kept by `backup` and `prune`.
+    pub keep_backups: usize,
+    /// Age in days after which `prune` removes files.
+    pub keep_days: u32,
+    pub poll_secs: u32,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        MaintenanceSettings {
+            enabled: true,
+            tasks: vec![Task::Compact, Task::Backup, Task::Prune],
+            interval_hours: 24,
+            idle_secs: 600,
+            keep_backups: 5,
+            keep_days: 30,
+            poll_secs: 60,
+        }
+    }
+}
+
+/// Why the scheduler hasn't started a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waiting {
+    Disabled,
+    NotDue,
+    /// Input within `idle_secs`, or the OS can't say how long it's been.
+    UserActive,
+    OnBattery,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResult {
+    task: Task,
+    ok: bool,
+    /// What was done, or the error.
+    message: String,
+    finished_at: String,
+}
+
+/// Kept in `stats/maintenance.json` so the interval survives restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct History {
+    /// Last run that got through every task.
+    last_run: Option<String>,
+    results: Vec<TaskResult>,
+}
+
+/// Result of `get_maintenance_status` and payload of `maintenance`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceStatus {
+    running: Option<Task>,
+    waiting: Option<Waiting>,
+    last_run: Option<String>,
+    next_due: Option<String>,
+    /// Latest result per task.
+    results: Vec<TaskResult>,
+}
+
+#[derive(Default)]
+pub(crate) struct Maintenance(Mutex<MaintenanceStatus>);
+
+// ── tasks ──
+
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(fsutil::long_path(dir)) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = dir.join(entry.file_name());
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn remove(path: &Path) -> bool {
+    let long = fsutil::long_path(path);
+    let removed = if long.is_dir() {
+        std::fs::remove_dir_all(&long)
+    } else {
+        std::fs::remove_file(&long)
+    };
+    removed
+        .map_err(|e| log!("⚠️ maintenance: {}: {e}", path.display()))
+        .is_ok()
+}
+
+fn age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(fsutil::long_path(path))
+        .and_then(|m| m.modified())
+        .ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+fn compact(paths: &AppPaths) -> Result<String, String> {
+    let mut removed = 0;
+    let staging = paths.data_dir.join("staging");
+    if fsutil::exists(&staging) && remove(&staging) {
+        removed += 1;
+    }
+    for file in files_under(&paths.data_dir) {
+        // A fresh one may belong to a write still in progress.
+        let stale = age(&file).is_some_and(|a| a > STALE_TMP);
+        if file.extension().is_some_and(|e| e == "tmp") && stale && remove(&file) {
+            removed += 1;
+        }
+    }
+    Ok(format!("removed {removed} leftover(s)"))
+}
+
+fn optimize_png(paths: &AppPaths) -> Result<String, String> {
+    use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+
+    let (mut files, mut saved) = (0, 0u64);
+    for path in files_under(&paths.layers_dir) {
+        if !path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("png"))
+        {
+            continue;
+        }
+        let bytes = fsutil::read_bytes(&path)?;
+        let image = match imaging::decode(&path, &bytes) {
+            Ok(image) => image,
+            Err(e) => {
+                log!("⚠️ maintenance: {e}");
+                continue;
+            }
+        };
+        let mut out = Vec::new();
+        let encoder =
+            PngEncoder::new_with_quality(&mut out, CompressionType::Best, FilterType::Adaptive);
+        if let Err(e) = image.write_with_encoder(encoder) {
+            log!("⚠️ maintenance: {}: {e}", path.display());
+            continue;
+        }
+        if out.len() < bytes.len() {
+            fsutil::write_atomic(&path, &out)?;
+            files += 1;
+            saved += (bytes.len() - out.len()) as u64;
+        }
+    }
+    Ok(format!("{files} PNG(s) smaller by {} KiB", saved / 1024))
+}
+
+fn backups_dir(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("backups")
+}
+
+/// Backups of one kind, newest first (names sort by timestamp).
+fn backups(paths: &AppPaths, prefix: &str) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = std::fs::read_dir(fsutil::long_path(&backups_dir(paths)))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| backups_dir(paths).join(e.file_name()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(prefix))
+        })
+        .collect();
+    found.sort();
+    found.reverse();
+    found
+}
+
+fn backup(paths: &AppPaths, keep: usize) -> Result<String, String> {
+    let dir = backups_dir(paths);
+    std::fs::create_dir_all(fsutil::long_path(&dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(format!(
+        "project-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    // Entry names: root config files as they are, the pack under `layers/`.
+    let entry = |base: &Path, under: &str, file: &Path| {
+        let rel = file
+            .strip_prefix(base)
+            .ok()?
+            .to_string_lossy()
+            .replace('\\', "/");
+        Some((format!("{under}{rel}"), file.to_path_buf()))
+    };
+    let root_configs = std::fs::read_dir(fsutil::long_path(&paths.root))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| paths.root.join(e.file_name()))
+        .filter(|p| p.extension().is_some_and(|e| e == "json"));
+    let mut files: Vec<(String, PathBuf)> = root_configs
+        .filter_map(|p| entry(&paths.root, "", &p))
+        .chain(
+            files_under(&paths.layers_dir)
+                .iter()
+                .filter_map(|p| entry(&paths.layers_dir, "layers/", p)),
+        )
+        .collect();
+    files.sort();
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, file) in &files {
+        let bytes = fsutil::read_bytes(file)?;
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("{}: {e}", file.display()))?;
+        zip.write_all(&bytes)
+            .map_err(|e| format!("{}: {e}", file.display()))?;
+    }
+    let bytes = zip.finish().map_err(|e| e.to_string())?.into_inner();
+    fsutil::write_atomic(&path, &bytes)?;
+
+    for old in backups(paths, "project-").into_iter().skip(keep.max(1)) {
+        remove(&old);
+    }
+    Ok(format!("{} file(s) → {}", files.len(), path.display()))
+}
+
+fn prune(paths: &AppPaths, cfg: &MaintenanceSettings) -> Result<String, String> {
+    let max_age = Duration::from_secs(u64::from(cfg.keep_days) * 24 * 3600);
+    let expired = |path: &Path| age(path).is_some_and(|a| a > max_age);
+    let mut old: Vec<PathBuf> = PRUNED_DIRS
+        .iter()
+        .flat_map(|d| files_under(&paths.data_dir.join(d)))
+        .chain([crate::logs::dir(&paths.data_dir).join("desktop-pet.old.log")])
+        .filter(|p| fsutil::exists(p) && expired(p))
+        .collect();
+    // `layers/` saved by pack imports.
+    old.extend(
+        backups(paths, "layers-")
+            .into_iter()
+            .skip(cfg.keep_backups.max(1)),
+    );
+    let removed = old.iter().filter(|p| remove(p)).count();
+    Ok(format!("removed {removed} old file(s)"))
+}
+
+fn run_task(app: &AppHandle, task: Task, cfg: &MaintenanceSettings) -> Result<String, String> {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    match task {
+        Task::Compact => compact(&paths),
+        Task::OptimizePng => optimize_png(&paths),
+        Task::Backup => backup(&paths, cfg.keep_backups),
+        Task::Prune => prune(&paths, cfg),
+    }
+}
+
+// ── scheduling ──
+
+fn data_dir(app: &AppHandle) -> PathBuf {
+    app.state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .data_dir
+        .clone()
+}
+
+fn next_due(
+    history: &History,
+    cfg: &MaintenanceSettings,
+) -> Option<chrono::DateTime<chrono::Local>> {
+    let last = chrono::DateTime::parse_from_rfc3339(history.last_run.as_deref()?).ok()?;
+    Some(
+        last.with_timezone(&chrono::Local) + chrono::Duration::hours(i64::from(cfg.interval_hours)),
+    )
+}
+
+fn user_idle(cfg: &MaintenanceSettings) -> bool {
+    activity::idle_time().is_some_and(|t| t >= Duration::from_secs(cfg.idle_secs))
+}
+
+fn waiting(history: &History, cfg: &MaintenanceSettings) -> Option<Waiting> {
+    if !cfg.enabled {
+        Some(Waiting::Disabled)
+    } else if next_due(history, cfg).is_some_and(|due| due > chrono::Local::now()) {
+        Some(Waiting::NotDue)
+    } else if !power::on_ac_power() {
+        Some(Waiting::OnBattery)
+    } else if !user_idle(cfg) {
+        Some(Waiting::UserActive)
+    } else {
+        None
+    }
+}
+
+fn announce(app: &AppHandle, history: &History, cfg: &MaintenanceSettings, running: Option<Task>) {
+    let status = MaintenanceStatus {
+        running,
+        waiting: running.is_none().then(|| waiting(history, cfg)).flatten(),
+        last_run: history.last_run.clone(),
+        next_due: next_due(history, cfg).map(|t| t.to_rfc3339()),
+        results: history.results.clone(),
+    };
+    *app.state::<Maintenance>().0.lock_or_recover() = status.clone();
+    let _ = app.emit("maintenance", status);
+}
+
+fn settings(app: &AppHandle) -> MaintenanceSettings {
+    app.state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .maintenance
+        .clone()
+}
+
+/// Runs `tasks` in order. A scheduled run stops early when the user comes
+/// back; a manual one finishes unless its job is cancelled.
+fn run(app: &AppHandle, tasks: &[Task], manual: bool, job: &Job) {
+    let cfg = settings(app);
+    let data_dir = data_dir(app);
+    let mut history: History = stats::load(&data_dir, STATS_NAME);
+    log!("🧹 maintenance: {} task(s)", tasks.len());
+    let mut finished = true;
+    for (i, &task) in tasks.iter().enumerate() {
+        if !manual && !user_idle(&cfg) {
+            log!("🧹 maintenance paused, user is back");
+            finished = false;
+            break;
+        }
+        if job.check().is_err() {
+            log!("🧹 maintenance cancelled");
+            finished = false;
+            break;
+        }
+        job.progress(i as f64 / tasks.len() as f64, format!("{task:?}"));
+        announce(app, &history, &cfg, Some(task));
+        let (ok, message) = match run_task(app, task, &cfg) {
+            Ok(message) => (true, message),
+            Err(e) => (false, e),
+        };
+        log!("🧹 {task:?}: {message}");
+        history.results.retain(|r| r.task != task);
+        history.results.push(TaskResult {
+            task,
+            ok,
+            message,
+            finished_at: chrono::Local::now().to_rfc3339(),
+        });
+    }
+    if finished {
+        history.last_run = Some(chrono::Local::now().to_rfc3339());
+    }
+    if let Err(e) = stats::save(&data_dir, STATS_NAME, &history) {
+        log!("⚠️ maintenance history: {e}");
+    }
+    announce(app, &history, &cfg, None);
+}
+
+/// Queues a claimed run as a `maintenance` job.
+fn queue(
+    app: &AppHandle,
+    tasks: Vec<Task>,
+    manual: bool,
+) -> Result<std::sync::mpsc::Receiver<Result<(), String>>, String> {
+    let label = tasks
+        .iter()
+        .map(|t| format!("{t:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let handle = app.clone();
+    let queued = jobs::submit(app, "maintenance", label, move |job| {
+        run(&handle, &tasks, manual, job);
+        Ok(())
+    });
+    if queued.is_err() {
+        app.state::<Maintenance>().0.lock_or_recover().running = None;
+    }
+    queued
+}
+
+/// Marks a run as started unless one already is, so a manual trigger and
+/// the scheduler never overlap.
+fn claim(app: &AppHandle, tasks: &[Task]) -> bool {
+    let mut status = app.state::<Maintenance>().0.lock_or_recover();
+    if status.running.is_some() || tasks.is_empty() {
+        return false;
+    }
+    status.running = tasks.first().copied();
+    true
+}
+
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let cfg = settings(&app);
+        let history: History = stats::load(&data_dir(&app), STATS_NAME);
+        let busy = app
+            .state::<Maintenance>()
+            .0
+            .lock_or_recover()
+            .running
+            .is_some();
+        if !busy {
+            if waiting(&history, &cfg).is_none() && claim(&app, &cfg.tasks) {
+                if let Err(e) = queue(&app, cfg.tasks.clone(), false).and_then(jobs::wait) {
+                    log!("⚠️ maintenance: {e}");
+                }
+            } else {
+                announce(&app, &history, &cfg, None);
+            }
+        }
+        let poll = Duration::from_secs(u64::from(cfg.poll_secs.max(1)));
+        std::thread::sleep(power::interval(&app, poll));
+    });
+}
+
+#[tauri::command]
+pub fn get_maintenance_status(maintenance: tauri::State<'_, Maintenance>) -> MaintenanceStatus {
+    maintenance.0.lock_or_recover().clone()
+}
+
+/// Starts `tasks` (the configured ones when omitted) now, without waiting
+/// for idle time or mains power. Progress arrives as `maintenance` and
+/// `job-progress` events.
+#[tauri::command]
+pub fn run_maintenance(app: AppHandle, tasks: Option<Vec<Task>>) -> Result<(), String> {
+    let tasks = tasks.unwrap_or_else(|| settings(&app).tasks);
+    if tasks.is_empty() {
+        return Err("no maintenance tasks to run".into());
+    }
+    if !claim(&app, &tasks) {
+        return Err("maintenance is already running".into());
+    }
+    queue(&app, tasks, true).map(|_| ())
+}
@@ -0,0 +1,145 @@
+//! `mappers.json` in the project root: rules that turn whatever state names
+//! producers send into the ones the pet has art for, so tools don't need to
+//! agree on exact names.
+//!
+//! ```json
+//! { "rules": [
+//!   { "match": ["npm_install", "npm_ci"], "state": "building", "detail_prefix": "npm: " },
+//!   { "match": "cargo_*", "state": "building", "detail_prefix": "cargo: " },
+//!   { "match": "deploy_*", "state": "executing", "detail": "{state}: {detail}" }
+//! ] }
+//! ```
+//!
+//! Rules are tried in order and the first match wins; its target is then
+//! mapped again, so rules can chain. Applied wherever a state is read,
+//! before progress is aggregated, and reloaded when the file changes.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::fsutil;
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::PetState;
+
+pub(crate) const FILE: &str = "mappers.json";
+/// Longest rule chain followed for one state; longer means a cycle.
+const MAX_STEPS: usize = 8;
+
+static RULES: Mutex<Vec<Rule>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub(crate) struct MappersFile {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum Patterns {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(crate) struct Rule {
+    /// State name or names the rule takes; `*` matches any run of
+    /// characters.
+    #[serde(rename = "match")]
+    pub patterns: Patterns,
+    /// New state name; unset keeps the incoming one.
+    pub state: Option<String>,
+    /// Replaces `detail`; `{state}` and `{detail}` stand for the incoming
+    /// values.
+    pub detail: Option<String>,
+    /// Put in front of `detail`, unless it is already there.
+    pub detail_prefix: Option<String>,
+}
+
+impl Rule {
+    fn matches(&self, name: &str) -> bool {
+        match &self.patterns {
+            Patterns::One(p) => glob(p, name),
+            Patterns::Many(ps) => ps.iter().any(|p| glob(p, name)),
+        }
+    }
+
+    fn apply(&self, state: &mut PetState) {
+        let incoming = state.state.clone();
+        if let Some(template) = &self.detail {
+            let detail = template
+                .replace("{state}", &incoming)
+                .replace("{detail}", state.detail.as_deref().unwrap_or_default());
+            state.detail = Some(detail);
+        }
+        if let Some(prefix) = &self.detail_prefix {
+            let detail = state.detail.take().unwrap_or_default();
+            state.detail = Some(if detail.starts_with(prefix.as_str()) {
+                detail
+            } else {
+                format!("{prefix}{detail}")
+            });
+        }
+        if let Some(target) = &self.state {
+            state.state = target.clone();
+        }
+    }
+}
+
+/// `*` matches any run of characters, everything else itself.
+pub(crate) fn glob(pattern: &str, name: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut name) = name.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let tail = parts.pop().unwrap_or_default();
+    for part in parts {
+        match name.find(part) {
+            Some(at) => name = &name[at + part.len()..],
+            None => return false,
+        }
+    }
+    name.ends_with(tail)
+}
+
+/// Reads `mappers.json`; a missing file means no rules, a broken one is
+/// reported and leaves the previous rules in place.
+pub(crate) fn reload(root: &Path) {
+    let path = root.join(FILE);
+    if !fsutil::exists(&path) {
+        RULES.lock_or_recover().clear();
+        return;
+    }
+    let parsed = fsutil::read_text(&path).and_then(|raw| {
+        serde_json::from_str::<MappersFile>(&raw).map_err(|e| format!("{FILE}: {e}"))
+    });
+    match parsed {
+        Ok(file) => {
+            log!("🔀 {} state mapping rule(s)", file.rules.len());
+            *RULES.lock_or_recover() = file.rules;
+        }
+        Err(e) => log!("⚠️ {e}; keeping the previous rules"),
+    }
+}
+
+/// Rewrites `state` by the loaded rules.
+pub(crate) fn apply(state: &mut PetState) {
+    let rules = RULES.lock_or_recover();
+    let mut seen = vec![state.state.clone()];
+    for _ in 0..MAX_STEPS {
+        let Some(rule) = rules.iter().find(|r| r.matches(&state.state)) else {
+            return;
+        };
+        rule.apply(state);
+        if seen.contains(&state.state) {
+            return;
+        }
+        seen.push(state.state.clone());
+    }
+    log!("⚠️ {FILE}: `{}` keeps being remapped", seen[0]);
+}
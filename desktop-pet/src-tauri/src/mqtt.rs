@@ -0,0 +1,237 @@
+//! MQTT bridge for home automation (Home Assistant scenes and the like).
+//!
+//! Messages on `mqtt.subscribe` become the pet's state: a payload listed
+//! in `mqtt.payloads` maps to the state given there, a JSON object is
+//! taken as a state as is, and a bare state name or alias (built-in or from
+//! the pack) sets just that.
+//! Every state that goes out as `state-changed` is published to
+//! `mqtt.publish` as JSON, retained, so dashboards see it on connect.
+//!
+//! Needs the `mqtt` feature and `mqtt.enabled`. The password is read from
+//! the environment variable named by `password_env`. Read once at startup.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::permissions::{self, Caller, Channel, Scope};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{api, states, AppPaths, PetState};
+
+/// `mqtt.payloads` entry.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PayloadState {
+    pub state: String,
+    pub detail: Option<String>,
+    pub progress: Option<f64>,
+}
+
+/// `mqtt` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Connects without logging in when empty.
+    pub username: String,
+    pub password_env: String,
+    /// Topic to take states from; off when empty.
+    pub subscribe: String,
+    /// Topic to publish state changes to; off when empty.
+    pub publish: String,
+    /// Payload, e.g. a scene name, → state.
+    pub payloads: BTreeMap<String, PayloadState>,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "star-desktop-pet".to_string(),
+            username: String::new(),
+            password_env: "STAR_PET_MQTT_PASSWORD".to_string(),
+            subscribe: "star-pet/set".to_string(),
+            publish: "star-pet/state".to_string(),
+            payloads: BTreeMap::new(),
+        }
+    }
+}
+
+/// What a payload on `mqtt.subscribe` asks for, if anything.
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+fn to_state(cfg: &MqttSettings, paths: &AppPaths, payload: &str) -> Option<PetState> {
+    let payload = payload.trim();
+    let fresh = |state: &str, detail: Option<String>, progress: Option<f64>| PetState {
+        state: state.to_string(),
+        detail,
+        progress,
+        updated_at: None,
+        progress_sources: Default::default(),
+    };
+    if let Some(mapped) = cfg.payloads.get(payload) {
+        return Some(fresh(&mapped.state, mapped.detail.clone(), mapped.progress));
+    }
+    if payload.starts_with('{') {
+        return serde_json::from_str(payload)
+            .map_err(|e| log!("⚠️ MQTT state: {e}"))
+            .ok();
+    }
+    let known = states::ALIASES.iter().any(|(alias, _)| *alias == payload)
+        || states::known_states(paths)
+            .iter()
+            .any(|s| s.name == payload);
+    known.then(|| fresh(payload, None, None))
+}
+
+/// Handles a message from the broker.
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+fn receive(app: &AppHandle, cfg: &MqttSettings, payload: &[u8]) {
+    let text = String::from_utf8_lossy(payload);
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let Some(state) = to_state(cfg, &paths, &text) else {
+        log!("📡 MQTT payload `{}` ignored", text.trim());
+        return;
+    };
+    let caller = Caller::new(Channel::Mqtt);
+    let action = format!("message {}", text.trim());
+    if permissions::authorize(app, &caller, Scope::SetState, &action).is_err() {
+        return;
+    }
+    let written = crate::write_state(
+        app.clone(),
+        Some(state),
+        None,
+        Some(api::IPC_REVISION),
+        app.state(),
+        app.state(),
+    );
+    if let Err(e) = written {
+        log!("⚠️ MQTT state: {e}");
+    }
+}
+
+/// Publishes `state` while connected; called from `announce_state`.
+pub(crate) fn publish(state: &PetState) {
+    imp::publish(state);
+}
+
+/// Disconnects from the broker on exit.
+pub(crate) fn stop() {
+    imp::disconnect();
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .mqtt
+        .clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.host.trim().is_empty() {
+        log!("⚠️ MQTT needs mqtt.host");
+        return;
+    }
+    imp::connect(app, cfg);
+}
+
+#[cfg(feature = "mqtt")]
+mod imp {
+    use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    use super::MqttSettings;
+    use crate::health;
+    use crate::logs::log;
+    use crate::sync::LockExt;
+    use crate::PetState;
+
+    const RETRY: Duration = Duration::from_secs(5);
+
+    /// The client and the topic to publish to (empty for none).
+    static CLIENT: Mutex<Option<(Client, String)>> = Mutex::new(None);
+
+    pub(super) fn publish(state: &PetState) {
+        let client = CLIENT.lock_or_recover();
+        let Some((client, topic)) = client.as_ref().filter(|(_, topic)| !topic.is_empty()) else {
+            return;
+        };
+        let payload = serde_json::to_vec(state).unwrap_or_default();
+        if let Err(e) = client.try_publish(topic.as_str(), QoS::AtLeastOnce, true, payload) {
+            log!("⚠️ MQTT publish: {e}");
+        }
+    }
+
+    pub(super) fn disconnect() {
+        if let Some((client, _)) = CLIENT.lock_or_recover().take() {
+            let _ = client.disconnect();
+        }
+    }
+
+    pub(super) fn connect(app: AppHandle, cfg: MqttSettings) {
+        let mut options = MqttOptions::new(&cfg.client_id, &cfg.host, cfg.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if !cfg.username.is_empty() {
+            let password = std::env::var(&cfg.password_env).unwrap_or_default();
+            options.set_credentials(&cfg.username, password);
+        }
+        let (client, mut connection) = Client::new(options, 16);
+        *CLIENT.lock_or_recover() = Some((client.clone(), cfg.publish.clone()));
+        log!("📡 MQTT → {}:{}", cfg.host, cfg.port);
+        std::thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    // Subscriptions don't survive a reconnect.
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        health::success(&app, "mqtt");
+                        if cfg.subscribe.is_empty() {
+                            continue;
+                        }
+                        if let Err(e) = client.subscribe(cfg.subscribe.as_str(), QoS::AtLeastOnce) {
+                            log!("⚠️ MQTT subscribe {}: {e}", cfg.subscribe);
+                            health::failure(&app, "mqtt", format!("subscribe: {e}"));
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(msg))) if msg.topic == cfg.subscribe => {
+                        super::receive(&app, &cfg, &msg.payload);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log!("⚠️ MQTT: {e}; retrying");
+                        health::failure(&app, "mqtt", e.to_string());
+                        health::next_poll(&app, "mqtt", RETRY);
+                        std::thread::sleep(RETRY);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "mqtt"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use super::MqttSettings;
+    use crate::logs::log;
+    use crate::PetState;
+
+    pub(super) fn publish(_: &PetState) {}
+
+    pub(super) fn disconnect() {}
+
+    pub(super) fn connect(_: AppHandle, _: MqttSettings) {
+        log!("⚠️ mqtt.enabled is set, but this build has no MQTT support (rebuild with `--features mqtt`)");
+    }
+}
@@ -0,0 +1,94 @@
+//! Blocking HTTP for the optional online features (pack registry, update
+//! checks, digest webhooks, Razer Chroma, CI status). Compiled in with the `net` feature, which those
+//! features enable; otherwise every request fails with a clear message.
+
+#[cfg(feature = "net")]
+pub(crate) fn get(url: &str, max: u64) -> Result<Vec<u8>, String> {
+    get_with(url, &[], max)
+}
+
+/// [`get`] with extra request headers, e.g. `Authorization`.
+#[cfg(feature = "net")]
+pub(crate) fn get_with(url: &str, headers: &[(&str, &str)], max: u64) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut request = ureq::get(url).set(
+        "User-Agent",
+        concat!("star-desktop-pet/", env!("CARGO_PKG_VERSION")),
+    );
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = request.call().map_err(|e| format!("{url}: {e}"))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(max + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("{url}: {e}"))?;
+    if bytes.len() as u64 > max {
+        return Err(format!("{url}: larger than {max} bytes"));
+    }
+    Ok(bytes)
+}
+
+/// POSTs `body` as JSON; only the status matters.
+#[cfg(feature = "net")]
+pub(crate) fn post_json(url: &str, body: &serde_json::Value) -> Result<(), String> {
+    ureq::post(url)
+        .set(
+            "User-Agent",
+            concat!("star-desktop-pet/", env!("CARGO_PKG_VERSION")),
+        )
+        .set("Content-Type", "application/json")
+        .send_string(&body.to_string())
+        .map(|_| ())
+        .map_err(|e| format!("{url}: {e}"))
+}
+
+/// `method` with `body` as JSON (none when `null`); the reply as JSON,
+/// `null` when empty.
+#[cfg(feature = "net")]
+#[cfg_attr(not(feature = "rgb"), allow(dead_code))]
+pub(crate) fn send_json(
+    method: &str,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let request = ureq::request(method, url).set(
+        "User-Agent",
+        concat!("star-desktop-pet/", env!("CARGO_PKG_VERSION")),
+    );
+    let response = if body.is_null() {
+        request.call()
+    } else {
+        request
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+    }
+    .map_err(|e| format!("{url}: {e}"))?;
+    let text = response.into_string().map_err(|e| format!("{url}: {e}"))?;
+    if text.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_str(&text).map_err(|e| format!("{url}: {e}"))
+}
+
+#[cfg(not(feature = "net"))]
+pub(crate) fn get(url: &str, _: u64) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "{url}: this build has no network support (rebuild with `--features registry` or `--features updates`)"
+    ))
+}
+
+#[cfg(not(feature = "net"))]
+pub(crate) fn get_with(url: &str, _: &[(&str, &str)], max: u64) -> Result<Vec<u8>, String> {
+    get(url, max)
+}
+
+#[cfg(not(feature = "net"))]
+pub(crate) fn post_json(url: &str, _: &serde_json::Value) -> Result<(), String> {
+    Err(format!(
+        "{url}: this build has no network support (rebuild with `--features net`)"
+    ))
+}
@@ -0,0 +1,157 @@
+//! `notifications.json` in the project root: desktop notifications for
+//! state transitions, so a failed build is noticed with the pet hidden.
+//!
+//! ```json
+//! { "rules": [
+//!   { "when": "building -> failed", "title": "Build failed", "body": "{detail}" },
+//!   { "when": "* -> done", "title": "{from} finished", "cooldown_secs": 300 }
+//! ] }
+//! ```
+//!
+//! Checked on every state the watcher announces whose name differs from the
+//! previous one; the first matching rule fires. The state the app starts
+//! with is not a transition. Reloaded when the file changes.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::logs::log;
+use crate::mappers::glob;
+use crate::sync::LockExt;
+use crate::{fsutil, notify, PetState};
+
+pub(crate) const FILE: &str = "notifications.json";
+
+static RULES: Mutex<NotificationsFile> = Mutex::new(NotificationsFile::empty());
+/// Name of the last state seen, `None` before the first.
+static PREVIOUS: Mutex<Option<String>> = Mutex::new(None);
+/// When each rule, by index, last fired; cleared on reload.
+static FIRED: Mutex<BTreeMap<usize, Instant>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default)]
+pub(crate) struct NotificationsFile {
+    pub rules: Vec<Rule>,
+    /// Skip while the OS reports do-not-disturb / focus mode.
+    pub respect_dnd: bool,
+}
+
+impl NotificationsFile {
+    const fn empty() -> Self {
+        NotificationsFile {
+            rules: Vec::new(),
+            respect_dnd: true,
+        }
+    }
+}
+
+impl Default for NotificationsFile {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(crate) struct Rule {
+    /// `from -> to`; either side is a state name where `*` matches any run
+    /// of characters.
+    pub when: String,
+    /// `{from}`, `{to}`, `{detail}` and `{progress}` (percent) stand for
+    /// the transition's values.
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    /// Quiet time after firing, however often the transition repeats.
+    #[serde(default)]
+    pub cooldown_secs: u64,
+}
+
+impl Rule {
+    fn matches(&self, from: &str, to: &str) -> bool {
+        match self.when.split_once("->") {
+            Some((f, t)) => glob(f.trim(), from) && glob(t.trim(), to),
+            None => false,
+        }
+    }
+
+    fn render(template: &str, from: &str, state: &PetState) -> String {
+        let progress = state
+            .progress
+            .map(|p| format!("{:.0}%", p * 100.0))
+            .unwrap_or_default();
+        template
+            .replace("{from}", from)
+            .replace("{to}", &state.state)
+            .replace("{detail}", state.detail.as_deref().unwrap_or_default())
+            .replace("{progress}", &progress)
+    }
+}
+
+/// Reads `notifications.json`; a missing file means no rules, a broken one
+/// is reported and leaves the previous rules in place.
+pub(crate) fn reload(root: &Path) {
+    let path = root.join(FILE);
+    if !fsutil::exists(&path) {
+        *RULES.lock_or_recover() = NotificationsFile::empty();
+        FIRED.lock_or_recover().clear();
+        return;
+    }
+    let parsed = fsutil::read_text(&path).and_then(|raw| {
+        serde_json::from_str::<NotificationsFile>(&raw).map_err(|e| format!("{FILE}: {e}"))
+    });
+    match parsed {
+        Ok(file) => {
+            for rule in file.rules.iter().filter(|r| !r.when.contains("->")) {
+                log!("⚠️ {FILE}: `{}` is not `from -> to`", rule.when);
+            }
+            log!("🔔 {} notification rule(s)", file.rules.len());
+            *RULES.lock_or_recover() = file;
+            FIRED.lock_or_recover().clear();
+        }
+        Err(e) => log!("⚠️ {e}; keeping the previous rules"),
+    }
+}
+
+/// Notifies for the first rule matching the change to `state`, if any.
+pub(crate) fn observe(state: &PetState) {
+    let from = {
+        let mut previous = PREVIOUS.lock_or_recover();
+        if previous.as_deref() == Some(state.state.as_str()) {
+            return;
+        }
+        match previous.replace(state.state.clone()) {
+            Some(from) => from,
+            None => return,
+        }
+    };
+    let file = RULES.lock_or_recover();
+    let Some((index, rule)) = file
+        .rules
+        .iter()
+        .enumerate()
+        .find(|(_, r)| r.matches(&from, &state.state))
+    else {
+        return;
+    };
+    {
+        let mut fired = FIRED.lock_or_recover();
+        let cooldown = Duration::from_secs(rule.cooldown_secs);
+        if fired.get(&index).is_some_and(|at| at.elapsed() < cooldown) {
+            return;
+        }
+        fired.insert(index, Instant::now());
+    }
+    if file.respect_dnd && notify::do_not_disturb() == Some(true) {
+        return;
+    }
+    let title = Rule::render(&rule.title, &from, state);
+    let body = Rule::render(&rule.body, &from, state);
+    log!("🔔 {from} -> {}: {title}", state.state);
+    if let Err(e) = notify::show(&title, &body) {
+        log!("⚠️ {e}");
+    }
+}
@@ -0,0 +1,106 @@
+//! Native desktop notifications through each platform's own tooling, so
+//! they show even while every pet window is hidden. Text is passed as
+//! arguments or environment, never spliced into a script.
+
+use std::process::Command;
+
+const APP_NAME: &str = "Star Desktop Pet";
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Command {
+    let mut c = Command::new("osascript");
+    c.args([
+        "-e",
+        "on run argv",
+        "-e",
+        // Shown as coming from Script Editor, so name the app in the subtitle.
+        "display notification (item 2 of argv) with title (item 1 of argv) subtitle (item 3 of argv)",
+        "-e",
+        "end run",
+        title,
+        body,
+        APP_NAME,
+    ]);
+    c
+}
+
+#[cfg(target_os = "windows")]
+fn command(title: &str, body: &str) -> Command {
+    const SCRIPT: &str = r#"
+$m = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$t = $m::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$x = $t.GetElementsByTagName('text')
+$x.Item(0).AppendChild($t.CreateTextNode($env:STAR_NOTIFY_TITLE)) > $null
+$x.Item(1).AppendChild($t.CreateTextNode($env:STAR_NOTIFY_BODY)) > $null
+$m::CreateToastNotifier($env:STAR_NOTIFY_APP).Show([Windows.UI.Notifications.ToastNotification]::new($t))
+"#;
+    let mut c = Command::new("powershell");
+    c.args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("STAR_NOTIFY_TITLE", title)
+        .env("STAR_NOTIFY_BODY", body)
+        .env("STAR_NOTIFY_APP", APP_NAME);
+    c
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command(title: &str, body: &str) -> Command {
+    let mut c = Command::new("notify-send");
+    c.args(["--app-name", APP_NAME, title, body]);
+    c
+}
+
+/// Fire and forget; the notifier process is not waited on.
+pub(crate) fn show(title: &str, body: &str) -> Result<(), String> {
+    command(title, body)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("notification: {e}"))
+}
+
+// ── do not disturb ──
+
+/// Whether the OS is holding notifications back (macOS Focus, Windows
+/// notifications turned off, GNOME "Do Not Disturb"). `None` when it
+/// can't tell.
+#[cfg(target_os = "macos")]
+pub(crate) fn do_not_disturb() -> Option<bool> {
+    // Active Focus modes are listed as assertion records.
+    let path = crate::fsutil::home_dir()?.join("Library/DoNotDisturb/DB/Assertions.json");
+    let raw = crate::fsutil::read_text(&path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&raw).ok()?;
+    let records = json
+        .get("data")?
+        .as_array()?
+        .iter()
+        .filter_map(|d| d.get("storeAssertionRecords")?.as_array())
+        .map(Vec::len)
+        .sum::<usize>();
+    Some(records > 0)
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn do_not_disturb() -> Option<bool> {
+    const SCRIPT: &str = r#"(Get-ItemProperty -Path 'HKCU:\Software\Microsoft\Windows\CurrentVersion\Notifications\Settings' -Name NOC_GLOBAL_SETTING_TOASTS_ENABLED -ErrorAction SilentlyContinue).NOC_GLOBAL_SETTING_TOASTS_ENABLED"#;
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "0" => Some(true),
+        "1" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn do_not_disturb() -> Option<bool> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "false" => Some(true),
+        "true" => Some(false),
+        _ => None,
+    }
+}
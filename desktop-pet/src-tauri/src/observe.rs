@@ -0,0 +1,172 @@
+//! Read-only observer mode: `--observe http://buildbox:18791` shows another
+//! machine's pet instead of the local one.
+//!
+//! The remote endpoint is polled with `GET /status`; changes go out as the
+//! usual `state-changed`, so the windows, taskbar, sounds and JSON-RPC
+//! sessions all follow the remote pet. No backend is started, the local
+//! `state.json` is ignored and `write_state` is refused.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::replay::Recorder;
+use crate::{watch, PetState};
+
+const DEFAULT_PORT: u16 = 18791;
+const IO_TIMEOUT: Duration = Duration::from_millis(1200);
+const POLL: Duration = Duration::from_secs(2);
+/// Status bodies are tiny; anything bigger isn't a pet.
+const MAX_RESPONSE_BYTES: u64 = 256 * 1024;
+
+/// An `http://host[:port][/prefix]` endpoint serving `/status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Remote {
+    host: String,
+    port: u16,
+    prefix: String,
+}
+
+impl Remote {
+    /// The backend this app starts itself.
+    pub(crate) fn local() -> Remote {
+        Remote {
+            host: "127.0.0.1".into(),
+            port: DEFAULT_PORT,
+            prefix: String::new(),
+        }
+    }
+
+    pub(crate) fn parse(raw: &str) -> Result<Remote, String> {
+        let rest = raw.trim();
+        if rest.starts_with("https://") {
+            return Err(format!("`{raw}`: https is not supported, use http://"));
+        }
+        let rest = rest.strip_prefix("http://").unwrap_or(rest);
+        let (authority, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("`{raw}`: bad port `{port}`"))?;
+                (host, port)
+            }
+            None => (authority, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("`{raw}`: missing host"));
+        }
+        let prefix = prefix.trim_end_matches('/');
+        Ok(Remote {
+            host: host.to_string(),
+            port,
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("/{prefix}")
+            },
+        })
+    }
+}
+
+impl fmt::Display for Remote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}:{}{}", self.host, self.port, self.prefix)
+    }
+}
+
+/// `GET <prefix>/status` from `remote`, parsed as a pet state.
+pub(crate) fn fetch_status(remote: &Remote) -> Result<PetState, String> {
+    let addr = (remote.host.as_str(), remote.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("{remote}: {e}"))?
+        .next()
+        .ok_or_else(|| format!("{remote}: no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)
+        .map_err(|e| format!("{remote} connect: {e}"))?;
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+
+    let request = format!(
+        "GET {}/status HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        remote.prefix, remote.host
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("{remote} write: {e}"))?;
+
+    let mut raw = String::new();
+    stream
+        .take(MAX_RESPONSE_BYTES)
+        .read_to_string(&mut raw)
+        .map_err(|e| format!("{remote} read: {e}"))?;
+
+    let (head, body) = raw
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| format!("{remote}: response parse failed"))?;
+    if !head.lines().next().is_some_and(|l| l.contains(" 200")) {
+        let status = head.lines().next().unwrap_or_default();
+        return Err(format!("{remote}: {status}"));
+    }
+    let state: PetState =
+        serde_json::from_str(body).map_err(|e| format!("{remote} json parse: {e}"))?;
+    Ok(crate::present(state))
+}
+
+/// The observed endpoint, when running in observer mode.
+#[derive(Default)]
+pub(crate) struct Observer(Option<Remote>);
+
+impl Observer {
+    pub(crate) fn new(remote: Option<Remote>) -> Self {
+        Observer(remote)
+    }
+
+    pub(crate) fn remote(&self) -> Option<&Remote> {
+        self.0.as_ref()
+    }
+}
+
+/// Polls the remote pet and re-emits its changes as `state-changed`.
+pub(crate) fn spawn_poller(app: AppHandle) {
+    let Some(remote) = app.state::<Observer>().remote().cloned() else {
+        return;
+    };
+    log!("👀 observing {remote} (read-only)");
+    std::thread::spawn(move || {
+        let mut last: Option<String> = None;
+        let mut reachable = true;
+        loop {
+            match fetch_status(&remote) {
+                Ok(state) => {
+                    if !reachable {
+                        log!("👀 {remote} is back");
+                        reachable = true;
+                    }
+                    let key = serde_json::to_string(&state).ok();
+                    if key != last {
+                        last = key;
+                        app.state::<Recorder>().capture("state-changed", &state);
+                        watch::follow_state(&app, &state);
+                        let _ = app.emit("state-changed", state);
+                    }
+                }
+                Err(e) => {
+                    if reachable {
+                        log!("⚠️ {e}");
+                        reachable = false;
+                    }
+                }
+            }
+            std::thread::sleep(POLL);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_observer(observer: tauri::State<'_, Observer>) -> Option<String> {
+    observer.remote().map(Remote::to_string)
+}
@@ -0,0 +1,153 @@
+//! First-run setup as a short checklist the pet can walk a new user
+//! through. Steps complete on their own once the app notices them, or when
+//! the frontend calls `complete_step`; progress is kept in the `onboarding`
+//! block of `settings.json`.
+//!
+//! | step           | noticed when                                         |
+//! |----------------|------------------------------------------------------|
+//! | `project_root` | the root holds `state.json` or `backend/app.py`      |
+//! | `first_state`  | `state.json` changes while the app is running        |
+//! | `integration`  | a producer reports `progress_sources`, or LAN is on  |
+//!
+//! Each change is announced as `onboarding-changed` with the same payload
+//! as `get_onboarding_step`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::settings::{self, Settings};
+use crate::sync::LockExt;
+use crate::{fsutil, read_state_file, AppPaths, PetState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    ProjectRoot,
+    FirstState,
+    Integration,
+}
+
+impl Step {
+    /// Guidance order.
+    const ALL: [Step; 3] = [Step::ProjectRoot, Step::FirstState, Step::Integration];
+
+    fn hint_key(self) -> &'static str {
+        match self {
+            Step::ProjectRoot => "onboarding.project_root",
+            Step::FirstState => "onboarding.first_state",
+            Step::Integration => "onboarding.integration",
+        }
+    }
+}
+
+/// `onboarding` block of `settings.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub(crate) struct OnboardingSettings {
+    /// Steps already done, in the order they were completed.
+    pub completed: Vec<Step>,
+    /// Stop guiding even though steps remain.
+    pub dismissed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingInfo {
+    /// Next step to guide; `None` once everything is done or dismissed.
+    step: Option<Step>,
+    completed: Vec<Step>,
+    /// What the pet says about `step`, in the current language.
+    hint: Option<String>,
+}
+
+fn info(app: &AppHandle) -> OnboardingInfo {
+    let onboarding = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .onboarding
+        .clone();
+    let step = Step::ALL
+        .into_iter()
+        .find(|s| !onboarding.completed.contains(s))
+        .filter(|_| !onboarding.dismissed);
+    let hint = step.map(|s| app.state::<I18n>().t(s.hint_key(), &Map::new()));
+    OnboardingInfo {
+        step,
+        completed: onboarding.completed,
+        hint,
+    }
+}
+
+/// Steps the app can see are done without being told.
+fn detected(app: &AppHandle) -> Vec<Step> {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let lan = app.state::<Mutex<Settings>>().lock_or_recover().lan.enabled;
+    let mut done = Vec::new();
+    if fsutil::exists(&paths.state_path)
+        || fsutil::exists(&paths.root.join("backend").join("app.py"))
+    {
+        done.push(Step::ProjectRoot);
+    }
+    let producers =
+        read_state_file(&paths.state_path).is_ok_and(|state| !state.progress_sources.is_empty());
+    if lan || producers {
+        done.push(Step::Integration);
+    }
+    done
+}
+
+/// Marks `steps` done, persisting and announcing only when that changed.
+fn complete(app: &AppHandle, steps: &[Step]) -> Result<OnboardingInfo, String> {
+    let updated = {
+        let settings = app.state::<Mutex<Settings>>();
+        let mut settings = settings.lock_or_recover();
+        let fresh: Vec<Step> = steps
+            .iter()
+            .copied()
+            .filter(|s| !settings.onboarding.completed.contains(s))
+            .collect();
+        if fresh.is_empty() {
+            None
+        } else {
+            settings.onboarding.completed.extend(fresh);
+            Some(settings.onboarding.clone())
+        }
+    };
+    let Some(onboarding) = updated else {
+        return Ok(info(app));
+    };
+    let settings_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    settings::save_block(&settings_path, "onboarding", &onboarding)?;
+    let fresh = info(app);
+    let _ = app.emit("onboarding-changed", &fresh);
+    Ok(fresh)
+}
+
+/// Called for every new state the app announces.
+pub(crate) fn observe_state(app: &AppHandle, state: &PetState) {
+    let mut steps = vec![Step::FirstState];
+    if !state.progress_sources.is_empty() {
+        steps.push(Step::Integration);
+    }
+    if let Err(e) = complete(app, &steps) {
+        log!("⚠️ onboarding: {e}");
+    }
+}
+
+#[tauri::command]
+pub fn get_onboarding_step(app: AppHandle) -> Result<OnboardingInfo, String> {
+    complete(&app, &detected(&app))
+}
+
+#[tauri::command]
+pub fn complete_step(app: AppHandle, step: Step) -> Result<OnboardingInfo, String> {
+    complete(&app, &[step])
+}
@@ -0,0 +1,153 @@
+//! Transparent stream overlay for OBS and other browser sources, served
+//! by the status page so streamers don't need window capture:
+//!
+//! - `/overlay`: an HTML page with a clear background that draws the pet's
+//!   animation for the current state
+//! - `/overlay/pet.json`: the pack's sprites as data URLs, fetched once
+//! - `/overlay/state.json`: the current state, polled every second
+//!
+//! The page passes its own query on, so `?token=` works as on `/status`.
+//! `scale`, `skin` and `detail=0` tune it.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::pet_core::FsAssets;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{a11y, seasonal, states, AppPaths, SpritesData};
+
+#[derive(Debug, Serialize)]
+struct Pet {
+    sprites: Option<SpritesData>,
+    /// Alias → standard state, for states the pack has no animation for.
+    aliases: HashMap<&'static str, &'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Reply<T> {
+    Ok(T),
+    Err { error: AppError },
+}
+
+/// Images go out as data URLs: a browser source can't fetch `pet-asset://`.
+fn pet(app: &AppHandle, skin: Option<&str>) -> Result<Pet, AppError> {
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let layers_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone();
+    let fs = FsAssets::new(layers_dir);
+    let data = seasonal::with_season(&fs, &settings, |src| {
+        a11y::build_layers(src, skin, &settings)
+    })?;
+    Ok(Pet {
+        sprites: data.sprites,
+        aliases: states::ALIASES.iter().copied().collect(),
+    })
+}
+
+fn to_json<T: Serialize>(result: Result<T, AppError>) -> String {
+    let reply = match result {
+        Ok(value) => Reply::Ok(value),
+        Err(error) => Reply::Err { error },
+    };
+    serde_json::to_string(&reply).unwrap_or_default()
+}
+
+pub(crate) fn pet_json(app: &AppHandle, skin: Option<&str>) -> String {
+    to_json(pet(app, skin.filter(|s| !s.is_empty())))
+}
+
+pub(crate) fn state_json(app: &AppHandle) -> String {
+    to_json(crate::current_state(app.state(), app.state()))
+}
+
+pub(crate) const PAGE: &str = r#"<!doctype html><html><head><meta charset="utf-8">
+<title>Star Desktop Pet overlay</title><style>
+html,body{margin:0;background:transparent;overflow:hidden}
+#pet{display:block;margin:0 auto;image-rendering:pixelated}
+#detail{font:bold 16px system-ui,sans-serif;color:#fff;text-align:center;
+text-shadow:0 0 3px #000,0 0 3px #000;min-height:20px}
+</style></head><body><canvas id="pet"></canvas><div id="detail"></div><script>
+const q = new URLSearchParams(location.search);
+const canvas = document.getElementById('pet'), ctx = canvas.getContext('2d');
+const detailEl = document.getElementById('detail');
+if (q.get('detail') === '0') detailEl.remove();
+const images = {};
+let pet = null, state = null, anim = null, frame = 0, cycles = 0, nextAt = 0;
+
+async function get(path) {
+    const r = await fetch(path + location.search, { cache: 'no-store' });
+    const body = await r.json();
+    if (body.error) throw new Error(body.error.message);
+    return body;
+}
+
+function animFor(name) {
+    const anims = (pet && pet.sprites && pet.sprites.anims) || [];
+    const byKey = k => anims.find(a => a.key === k);
+    return byKey(name) || byKey(pet.aliases[name]) || byKey('idle') || anims[0] || null;
+}
+
+function frames(a) {
+    return a.sequence || Array.from({ length: a.frames }, (_, i) => a.first_frame + i);
+}
+
+function play(a) {
+    if (a === anim) return;
+    anim = a; frame = 0; cycles = 0; nextAt = 0;
+}
+
+function draw(now) {
+    requestAnimationFrame(draw);
+    const s = pet && pet.sprites, img = anim && images[anim.key];
+    if (!s || !img || !img.complete) return;
+    const seq = frames(anim);
+    if (now >= nextAt) {
+        if (nextAt) {
+            const loops = anim.repeat < 0 ? Infinity : anim.repeat + 1;
+            if (frame + 1 < seq.length) frame++;
+            else if (++cycles < loops) frame = 0;
+        }
+        const ms = (anim.durations_ms && anim.durations_ms[frame]) || 1000 / (anim.rate || 1);
+        nextAt = now + ms;
+    }
+    const fw = s.frame_width, fh = s.frame_height, step = anim.spacing;
+    const cols = Math.max(1, Math.floor((img.width - anim.margin * 2 + step) / (fw + step)));
+    const f = seq[frame];
+    const sx = anim.margin + (f % cols) * (fw + step), sy = anim.margin + Math.floor(f / cols) * (fh + step);
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    ctx.drawImage(img, sx, sy, fw, fh, 0, 0, canvas.width, canvas.height);
+}
+
+async function loadPet() {
+    pet = await get('/overlay/pet.json');
+    const s = pet.sprites;
+    if (!s) throw new Error('this pack has no sprites');
+    const scale = Number(q.get('scale')) || 3;
+    canvas.width = Math.round(s.frame_width * scale);
+    canvas.height = Math.round(s.frame_height * scale);
+    ctx.imageSmoothingEnabled = false;
+    s.anims.forEach(a => { images[a.key] = Object.assign(new Image(), { src: a.data_url }); });
+}
+
+async function poll() {
+    try {
+        state = await get('/overlay/state.json');
+        const a = animFor(state.state);
+        if (a) play(a);
+        if (detailEl.isConnected) detailEl.textContent = state.detail || '';
+    } catch (e) {
+        console.warn(e);
+    }
+    setTimeout(poll, 1000);
+}
+
+loadPet().then(() => { requestAnimationFrame(draw); poll(); }, e => console.error(e));
+</script></body></html>"#;
@@ -0,0 +1,594 @@
+//! A* over the collision grid of `map.json`, so the frontend doesn't need
+//! its own copy of the grid to walk the pet around.
+//!
+//! Moves are one tile up, down, left or right onto a `0` cell. The goal
+//! must be walkable; the start need not be, so a pet standing on furniture
+//! can still walk off it.
+//!
+//! The frontend reports where the pet stands through `set_pet_tile` (and
+//! implicitly as the `from` of `find_path`), which `route_to_poi` starts
+//! from.
+//!
+//! `follow_route` walks one of the map's named `routes`: it plans every
+//! leg up front, then sends each as a `route-waypoint` event once the pet
+//! should have walked the previous one and dwelt at its stop, and ends
+//! with `route-finished`.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::asset_protocol;
+use crate::locate;
+use crate::logs::log;
+use crate::pet_core::{self, FsAssets};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{AppPaths, MapCfgFile, PoiCfg, RouteCfg, RouteStopCfg};
+
+/// Seconds at a stop when neither it nor its route says.
+const DEFAULT_DWELL_SECS: f64 = 3.0;
+/// Shortest time per leg, so a repeating one-stop route doesn't spin.
+const MIN_LEG: Duration = Duration::from_millis(500);
+const MAX_LEG: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often a waiting patrol checks for `stop_route`.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Tile {
+    col: u32,
+    row: u32,
+}
+
+impl Tile {
+    /// Up, down, left, right.
+    fn neighbours(self) -> impl Iterator<Item = Tile> {
+        let Tile { col, row } = self;
+        [
+            row.checked_sub(1).map(|row| Tile { col, row }),
+            row.checked_add(1).map(|row| Tile { col, row }),
+            col.checked_sub(1).map(|col| Tile { col, row }),
+            col.checked_add(1).map(|col| Tile { col, row }),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn distance(self, other: Tile) -> u32 {
+        self.col.abs_diff(other.col) + self.row.abs_diff(other.row)
+    }
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.col, self.row)
+    }
+}
+
+fn cell(grid: &[Vec<u8>], t: Tile) -> Option<u8> {
+    grid.get(t.row as usize)?.get(t.col as usize).copied()
+}
+
+/// Tiles from `from` to `to`, both included; `None` when `to` can't be
+/// reached. `from` may be blocked, so a pet left on an obstacle can step
+/// off it, but has to be on the map.
+pub(crate) fn astar(grid: &[Vec<u8>], from: Tile, to: Tile) -> Option<Vec<Tile>> {
+    if cell(grid, from).is_none() || cell(grid, to) != Some(0) {
+        return None;
+    }
+    let mut open = BinaryHeap::from([Reverse((from.distance(to), 0u32, from))]);
+    let mut cost = HashMap::from([(from, 0u32)]);
+    let mut came_from = HashMap::new();
+    while let Some(Reverse((_, g, current))) = open.pop() {
+        if current == to {
+            let mut path = vec![current];
+            while let Some(&prev) = came_from.get(path.last()?) {
+                path.push(prev);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // A cheaper route here was already expanded.
+        if cost.get(&current).is_some_and(|&best| g > best) {
+            continue;
+        }
+        for next in current.neighbours() {
+            if cell(grid, next) != Some(0) {
+                continue;
+            }
+            let tentative = g + 1;
+            if cost.get(&next).is_none_or(|&best| tentative < best) {
+                cost.insert(next, tentative);
+                came_from.insert(next, current);
+                open.push(Reverse((tentative + next.distance(to), tentative, next)));
+            }
+        }
+    }
+    None
+}
+
+/// Last tile the frontend reported the pet on.
+#[derive(Default)]
+pub(crate) struct PetTile(Mutex<Option<Tile>>);
+
+impl PetTile {
+    pub(crate) fn current(&self) -> Option<Tile> {
+        *self.0.lock_or_recover()
+    }
+
+    pub(crate) fn set(&self, tile: Tile) {
+        *self.0.lock_or_recover() = Some(tile);
+    }
+}
+
+/// Why `route_to_poi` or `follow_route` has no route, as
+/// `{ "kind": …, … }`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RouteError {
+    NoSuchPoi {
+        name: String,
+    },
+    NoSuchRoute {
+        name: String,
+    },
+    /// The route lists no stops.
+    EmptyRoute {
+        name: String,
+    },
+    Unreachable {
+        from: Tile,
+        to: Tile,
+    },
+    /// Nothing reported yet and the map has no `idle` POI to start from.
+    UnknownPosition,
+    Map {
+        message: String,
+    },
+}
+
+fn poi_tile(pois: &HashMap<String, PoiCfg>, name: &str) -> Result<Tile, RouteError> {
+    pois.get(name)
+        .map(|p| Tile {
+            col: p.col,
+            row: p.row,
+        })
+        .ok_or_else(|| RouteError::NoSuchPoi { name: name.into() })
+}
+
+/// Where routes start: the last reported tile, else the `idle` POI.
+fn start(pet: &PetTile, pois: &HashMap<String, PoiCfg>) -> Result<Tile, RouteError> {
+    pet.current()
+        .or_else(|| poi_tile(pois, "idle").ok())
+        .ok_or(RouteError::UnknownPosition)
+}
+
+// ── routes ──
+
+/// One stop of a route and the tiles walked to reach it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Leg {
+    poi: String,
+    dwell_secs: f64,
+    /// From the previous stop (or where the pet stood) to `poi`, both
+    /// ends included.
+    path: Vec<Tile>,
+}
+
+/// Legs of route `name` from `from` (the first stop when `None`), and for
+/// `repeat` routes the path from the last stop back to the first.
+pub(crate) fn plan(
+    grid: &[Vec<u8>],
+    pois: &HashMap<String, PoiCfg>,
+    name: &str,
+    route: &RouteCfg,
+    from: Option<Tile>,
+) -> Result<(Vec<Leg>, Option<Vec<Tile>>), RouteError> {
+    let route_dwell = route.dwell_secs.unwrap_or(DEFAULT_DWELL_SECS);
+    let stops = route
+        .stops
+        .iter()
+        .map(|stop| {
+            let (poi, dwell) = match stop {
+                RouteStopCfg::Poi(poi) => (poi, route_dwell),
+                RouteStopCfg::Timed { poi, dwell_secs } => (poi, *dwell_secs),
+            };
+            let dwell = if dwell.is_finite() {
+                dwell.max(0.0)
+            } else {
+                0.0
+            };
+            Ok((poi.clone(), dwell, poi_tile(pois, poi)?))
+        })
+        .collect::<Result<Vec<_>, RouteError>>()?;
+    let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+        return Err(RouteError::EmptyRoute { name: name.into() });
+    };
+    let walk =
+        |from: Tile, to: Tile| astar(grid, from, to).ok_or(RouteError::Unreachable { from, to });
+
+    let mut at = from.unwrap_or(first.2);
+    let mut legs = Vec::with_capacity(stops.len());
+    for (poi, dwell_secs, tile) in &stops {
+        legs.push(Leg {
+            poi: poi.clone(),
+            dwell_secs: *dwell_secs,
+            path: walk(at, *tile)?,
+        });
+        at = *tile;
+    }
+    let back = if route.repeat {
+        Some(walk(last.2, first.2)?)
+    } else {
+        None
+    };
+    Ok((legs, back))
+}
+
+/// Returned by `follow_route`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutePlan {
+    /// Tags this walk's events; a newer `follow_route` gets a new one.
+    run: u64,
+    route: String,
+    legs: Vec<Leg>,
+    /// For `repeat` routes: the last stop back to the first, walked in
+    /// place of the first leg from the second lap on.
+    back: Option<Vec<Tile>>,
+}
+
+/// Payload of `route-waypoint`: walk `path`, then stay `dwell_secs`.
+#[derive(Debug, Clone, Serialize)]
+struct Waypoint<'a> {
+    run: u64,
+    route: &'a str,
+    /// Index into the plan's `legs`.
+    stop: usize,
+    lap: u32,
+    poi: &'a str,
+    dwell_secs: f64,
+    path: &'a [Tile],
+}
+
+/// Payload of `route-finished`; `completed` is false after `stop_route`
+/// or a newer `follow_route`.
+#[derive(Debug, Clone, Serialize)]
+struct Finished<'a> {
+    run: u64,
+    route: &'a str,
+    completed: bool,
+}
+
+/// The walk in progress; bumping `run` stops it.
+#[derive(Default)]
+pub(crate) struct Patrol {
+    run: AtomicU64,
+    active: Mutex<Option<String>>,
+}
+
+impl Patrol {
+    /// Sleeps `wait`; false when run `run` was stopped meanwhile.
+    fn sleep(&self, run: u64, wait: Duration) -> bool {
+        let until = Instant::now() + wait;
+        loop {
+            if self.run.load(Ordering::SeqCst) != run {
+                return false;
+            }
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return true;
+            }
+            std::thread::sleep(left.min(STOP_POLL));
+        }
+    }
+}
+
+fn walk(app: AppHandle, plan: RoutePlan, speed: f64) {
+    let patrol = app.state::<Patrol>();
+    let RoutePlan {
+        run,
+        route,
+        legs,
+        back,
+    } = &plan;
+    let mut lap = 0u32;
+    let completed = 'walk: loop {
+        for (stop, leg) in legs.iter().enumerate() {
+            let path = match back {
+                Some(back) if lap > 0 && stop == 0 => back,
+                _ => &leg.path,
+            };
+            let _ = app.emit(
+                "route-waypoint",
+                Waypoint {
+                    run: *run,
+                    route,
+                    stop,
+                    lap,
+                    poi: &leg.poi,
+                    dwell_secs: leg.dwell_secs,
+                    path,
+                },
+            );
+            let tiles = path.len().saturating_sub(1) as f64;
+            let wait = Duration::try_from_secs_f64(tiles / speed + leg.dwell_secs)
+                .unwrap_or(MAX_LEG)
+                .clamp(MIN_LEG, MAX_LEG);
+            if !patrol.sleep(*run, wait) {
+                break 'walk false;
+            }
+            if let Some(&tile) = path.last() {
+                app.state::<PetTile>().set(tile);
+            }
+        }
+        if back.is_none() {
+            break true;
+        }
+        lap += 1;
+    };
+    if patrol.run.load(Ordering::SeqCst) == *run {
+        *patrol.active.lock_or_recover() = None;
+    }
+    log!(
+        "🚶 route {route} {}",
+        if completed { "done" } else { "stopped" }
+    );
+    let _ = app.emit(
+        "route-finished",
+        Finished {
+            run: *run,
+            route,
+            completed,
+        },
+    );
+}
+
+fn map_cfg(paths: &Mutex<AppPaths>, settings: &Mutex<Settings>) -> Result<MapCfgFile, String> {
+    let limits = settings.lock_or_recover().limits.clone();
+    let p = paths.lock_or_recover().clone();
+    let fs = FsAssets::new(&p.layers_dir);
+    let served = asset_protocol::Served::new(&fs);
+    pet_core::read_map_cfg(&locate::with_map(&p, &served, &limits)?, &limits).map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_pet_tile(tile: Tile, pet: tauri::State<'_, PetTile>) {
+    pet.set(tile);
+}
+
+#[tauri::command]
+pub fn find_path(
+    from: Tile,
+    to: Tile,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    pet: tauri::State<'_, PetTile>,
+) -> Result<Option<Vec<Tile>>, String> {
+    let cfg = map_cfg(&paths, &settings)?;
+    for (name, t) in [("from", from), ("to", to)] {
+        if cell(&cfg.collision, t).is_none() {
+            return Err(format!(
+                "find_path: `{name}` ({}, {}) is outside the map",
+                t.col, t.row
+            ));
+        }
+    }
+    pet.set(from);
+    Ok(astar(&cfg.collision, from, to))
+}
+
+/// Waypoints from the pet's last known tile (the `idle` POI before the
+/// frontend reported one) to the POI called `name`, both ends included.
+#[tauri::command]
+pub fn route_to_poi(
+    name: String,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    pet: tauri::State<'_, PetTile>,
+) -> Result<Vec<Tile>, RouteError> {
+    let cfg = map_cfg(&paths, &settings).map_err(|message| RouteError::Map { message })?;
+    let pois = cfg.pois.unwrap_or_default();
+    let to = poi_tile(&pois, &name)?;
+    let from = start(&pet, &pois)?;
+    astar(&cfg.collision, from, to).ok_or(RouteError::Unreachable { from, to })
+}
+
+/// Walks the route called `name` in `map.json`, starting where the pet
+/// stands. Returns the whole plan; each leg then arrives as a
+/// `route-waypoint` event when it is due. Replaces a walk in progress.
+#[tauri::command]
+pub fn follow_route(
+    app: AppHandle,
+    name: String,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    pet: tauri::State<'_, PetTile>,
+    patrol: tauri::State<'_, Patrol>,
+) -> Result<RoutePlan, RouteError> {
+    let cfg = map_cfg(&paths, &settings).map_err(|message| RouteError::Map { message })?;
+    let pois = cfg.pois.unwrap_or_default();
+    let route = cfg
+        .routes
+        .unwrap_or_default()
+        .remove(&name)
+        .ok_or_else(|| RouteError::NoSuchRoute { name: name.clone() })?;
+    let from = start(&pet, &pois)?;
+    let (legs, back) = plan(&cfg.collision, &pois, &name, &route, Some(from))?;
+    let speed = cfg
+        .character
+        .and_then(|c| c.speed)
+        .or(cfg.character_speed)
+        .filter(|s| s.is_finite() && *s > 0.0)
+        .unwrap_or(2.5);
+
+    let run = patrol.run.fetch_add(1, Ordering::SeqCst) + 1;
+    *patrol.active.lock_or_recover() = Some(name.clone());
+    let plan = RoutePlan {
+        run,
+        route: name,
+        legs,
+        back,
+    };
+    log!(
+        "🚶 following route {} ({} stops{})",
+        plan.route,
+        plan.legs.len(),
+        if plan.back.is_some() {
+            ", repeating"
+        } else {
+            ""
+        }
+    );
+    let walking = plan.clone();
+    std::thread::spawn(move || walk(app, walking, speed));
+    Ok(plan)
+}
+
+/// Stops the walk `follow_route` started; the name of the route it was
+/// on, if any.
+#[tauri::command]
+pub fn stop_route(patrol: tauri::State<'_, Patrol>) -> Option<String> {
+    patrol.run.fetch_add(1, Ordering::SeqCst);
+    patrol.active.lock_or_recover().take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(col: u32, row: u32) -> Tile {
+        Tile { col, row }
+    }
+
+    /// `#` blocked, anything else walkable.
+    fn grid(rows: &[&str]) -> Vec<Vec<u8>> {
+        rows.iter()
+            .map(|r| r.chars().map(|c| u8::from(c == '#')).collect())
+            .collect()
+    }
+
+    fn pois(names: &[(&str, Tile)]) -> HashMap<String, PoiCfg> {
+        names
+            .iter()
+            .map(|&(name, tile)| {
+                let poi = PoiCfg {
+                    col: tile.col,
+                    row: tile.row,
+                };
+                (name.to_string(), poi)
+            })
+            .collect()
+    }
+
+    fn route(json: serde_json::Value) -> RouteCfg {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn finds_a_shortest_path_around_walls() {
+        let g = grid(&[
+            "....#", //
+            ".##.#", //
+            "...#.", //
+            "#....",
+        ]);
+        let path = astar(&g, t(0, 0), t(4, 3)).unwrap();
+        assert_eq!(path.first(), Some(&t(0, 0)));
+        assert_eq!(path.last(), Some(&t(4, 3)));
+        // 7 steps: down the left side and along the bottom.
+        assert_eq!(path.len(), 8);
+        for pair in path.windows(2) {
+            assert_eq!(pair[0].distance(pair[1]), 1);
+            assert_eq!(cell(&g, pair[1]), Some(0));
+        }
+    }
+
+    #[test]
+    fn walled_off_goal_has_no_path() {
+        let g = grid(&[
+            "..#..", //
+            "..#..", //
+            "..#..",
+        ]);
+        assert_eq!(astar(&g, t(0, 0), t(4, 2)), None);
+        assert_eq!(astar(&g, t(0, 0), t(2, 1)), None, "goal on a wall");
+        assert_eq!(astar(&g, t(0, 0), t(9, 9)), None, "goal off the map");
+    }
+
+    #[test]
+    fn start_on_the_goal_is_one_tile() {
+        let g = grid(&["...", "..."]);
+        assert_eq!(astar(&g, t(1, 1), t(1, 1)), Some(vec![t(1, 1)]));
+    }
+
+    #[test]
+    fn blocked_start_steps_off_but_off_map_start_does_not() {
+        let g = grid(&["#..", "..."]);
+        assert_eq!(astar(&g, t(0, 0), t(2, 0)).map(|p| p.len()), Some(3));
+        assert_eq!(astar(&g, t(3, 0), t(2, 0)), None);
+        assert_eq!(astar(&g, t(0, 5), t(0, 1)), None);
+    }
+
+    #[test]
+    fn ragged_and_empty_grids() {
+        // The short middle row leaves (3, 1) off the map.
+        let g = grid(&["....", "..", "...."]);
+        assert_eq!(astar(&g, t(3, 0), t(3, 2)).map(|p| p.len()), Some(7));
+        assert_eq!(astar(&g, t(0, 0), t(3, 1)), None);
+
+        assert_eq!(astar(&[], t(0, 0), t(0, 0)), None);
+        assert_eq!(astar(&[vec![]], t(0, 0), t(0, 0)), None);
+    }
+
+    #[test]
+    fn plan_walks_each_stop_from_the_last() {
+        let g = grid(&["....", ".##.", "...."]);
+        let p = pois(&[("desk", t(0, 0)), ("sofa", t(3, 2)), ("door", t(3, 0))]);
+        let r = route(serde_json::json!({
+            "stops": ["desk", { "poi": "sofa", "dwell_secs": 10 }, "door"],
+            "dwell_secs": 2,
+            "repeat": true
+        }));
+        let (legs, back) = plan(&g, &p, "patrol", &r, Some(t(0, 2))).unwrap();
+        let lens: Vec<usize> = legs.iter().map(|l| l.path.len()).collect();
+        assert_eq!(lens, [3, 6, 3]);
+        assert_eq!(legs[0].path.first(), Some(&t(0, 2)));
+        assert_eq!(legs[1].path.first(), Some(&t(0, 0)));
+        let dwell: Vec<f64> = legs.iter().map(|l| l.dwell_secs).collect();
+        assert_eq!(dwell, [2.0, 10.0, 2.0]);
+        assert_eq!(back.map(|b| b.len()), Some(4));
+
+        // Without a known position the first leg is just the first stop.
+        let r = route(serde_json::json!({ "stops": ["desk", "door"] }));
+        let (legs, back) = plan(&g, &p, "patrol", &r, None).unwrap();
+        assert_eq!(legs[0].path, [t(0, 0)]);
+        assert_eq!(legs[0].dwell_secs, DEFAULT_DWELL_SECS);
+        assert!(back.is_none());
+    }
+
+    #[test]
+    fn plan_reports_why_there_is_no_route() {
+        let g = grid(&["..#.", "..#."]);
+        let p = pois(&[("desk", t(0, 0)), ("island", t(3, 1))]);
+        let unreachable = route(serde_json::json!({ "stops": ["desk", "island"] }));
+        assert!(matches!(
+            plan(&g, &p, "r", &unreachable, None),
+            Err(RouteError::Unreachable { from, to }) if from == t(0, 0) && to == t(3, 1)
+        ));
+        let unknown = route(serde_json::json!({ "stops": ["desk", "nowhere"] }));
+        assert!(matches!(
+            plan(&g, &p, "r", &unknown, None),
+            Err(RouteError::NoSuchPoi { name }) if name == "nowhere"
+        ));
+        let empty = route(serde_json::json!({ "stops": [] }));
+        assert!(matches!(
+            plan(&g, &p, "r", &empty, None),
+            Err(RouteError::EmptyRoute { name }) if name == "r"
+        ));
+    }
+}
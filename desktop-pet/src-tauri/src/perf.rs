@@ -0,0 +1,201 @@
+//! Frame-rate telemetry from the webview.
+//!
+//! The frontend calls `report_frame_stats` every few seconds. Samples are
+//! folded into per-minute buckets in the stats store (`stats/frames.json`,
+//! last 24 hours), and a sustained drop below `perf.min_fps` switches the
+//! pet into a lighter mode through the `perf-mode` event until frame rate
+//! recovers.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{stats, AppPaths};
+
+const STATS_NAME: &str = "frames";
+const MAX_MINUTES: usize = 24 * 60;
+/// Animation speed while degraded, as a multiplier of the authored rate.
+const DEGRADED_ANIM_RATE: f64 = 0.5;
+
+/// `perf` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PerfSettings {
+    pub auto_degrade: bool,
+    /// Degrade when reported fps stays below this.
+    pub min_fps: f64,
+    /// Restore once fps stays at or above this.
+    pub recover_fps: f64,
+    /// How long either condition must hold before switching.
+    pub sustain_secs: u32,
+}
+
+impl Default for PerfSettings {
+    fn default() -> Self {
+        PerfSettings {
+            auto_degrade: true,
+            min_fps: 24.0,
+            recover_fps: 50.0,
+            sustain_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bucket {
+    /// Local minute, `YYYY-MM-DDTHH:MM`.
+    minute: String,
+    samples: u32,
+    mean_fps: f64,
+    min_fps: f64,
+    dropped: u64,
+    max_heap_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FrameHistory {
+    minutes: Vec<Bucket>,
+}
+
+/// Payload of `perf-mode`, also returned by every report.
+#[derive(Debug, Clone, Serialize)]
+pub struct PerfMode {
+    degraded: bool,
+    disable_parallax: bool,
+    anim_rate: f64,
+}
+
+impl PerfMode {
+    fn new(degraded: bool) -> Self {
+        PerfMode {
+            degraded,
+            disable_parallax: degraded,
+            anim_rate: if degraded { DEGRADED_ANIM_RATE } else { 1.0 },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Monitor {
+    degraded: bool,
+    low_since: Option<Instant>,
+    high_since: Option<Instant>,
+    bucket: Option<Bucket>,
+}
+
+#[derive(Default)]
+pub(crate) struct Perf(Mutex<Monitor>);
+
+impl Monitor {
+    /// Folds a sample into the current minute; returns the bucket it
+    /// closed, if the minute rolled over.
+    fn add(&mut self, fps: f64, dropped: u64, heap: Option<u64>) -> Option<Bucket> {
+        let minute = chrono::Local::now().format("%Y-%m-%dT%H:%M").to_string();
+        let closed = match &self.bucket {
+            Some(b) if b.minute != minute => self.bucket.take(),
+            _ => None,
+        };
+        let b = self.bucket.get_or_insert_with(|| Bucket {
+            minute,
+            samples: 0,
+            mean_fps: 0.0,
+            min_fps: f64::MAX,
+            dropped: 0,
+            max_heap_bytes: None,
+        });
+        b.samples += 1;
+        b.mean_fps += (fps - b.mean_fps) / f64::from(b.samples);
+        b.min_fps = b.min_fps.min(fps);
+        b.dropped += dropped;
+        b.max_heap_bytes = b.max_heap_bytes.max(heap);
+        closed
+    }
+
+    /// Hysteresis between the two thresholds; returns the new mode when
+    /// it changed.
+    fn judge(&mut self, fps: f64, cfg: &PerfSettings) -> Option<bool> {
+        let now = Instant::now();
+        let sustain = Duration::from_secs(u64::from(cfg.sustain_secs));
+        if !cfg.auto_degrade {
+            self.low_since = None;
+            self.high_since = None;
+            // Turning the feature off lifts a degrade straight away.
+            return self.set(false);
+        }
+        if fps < cfg.min_fps {
+            self.high_since = None;
+            if now - *self.low_since.get_or_insert(now) >= sustain {
+                return self.set(true);
+            }
+        } else if fps >= cfg.recover_fps {
+            self.low_since = None;
+            if now - *self.high_since.get_or_insert(now) >= sustain {
+                return self.set(false);
+            }
+        } else {
+            self.low_since = None;
+            self.high_since = None;
+        }
+        None
+    }
+
+    fn set(&mut self, degraded: bool) -> Option<bool> {
+        (self.degraded != degraded).then(|| {
+            self.degraded = degraded;
+            degraded
+        })
+    }
+}
+
+fn archive(paths: &AppPaths, bucket: Bucket) {
+    let mut history: FrameHistory = stats::load(&paths.data_dir, STATS_NAME);
+    history.minutes.push(bucket);
+    let excess = history.minutes.len().saturating_sub(MAX_MINUTES);
+    history.minutes.drain(..excess);
+    if let Err(e) = stats::save(&paths.data_dir, STATS_NAME, &history) {
+        log!("⚠️ frame stats: {e}");
+    }
+}
+
+/// `fps` averaged since the last report, `dropped` frames since the last
+/// report, `heap` the JS heap size in bytes where the webview exposes it.
+#[tauri::command]
+pub fn report_frame_stats(
+    app: AppHandle,
+    fps: f64,
+    dropped: Option<u64>,
+    heap: Option<u64>,
+    perf: tauri::State<'_, Perf>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<PerfMode, String> {
+    if !fps.is_finite() || fps < 0.0 {
+        return Err(format!("fps must be a non-negative number, got {fps}"));
+    }
+    let cfg = settings.lock_or_recover().perf.clone();
+    let (closed, changed, degraded) = {
+        let mut monitor = perf.0.lock_or_recover();
+        let closed = monitor.add(fps, dropped.unwrap_or(0), heap);
+        let changed = monitor.judge(fps, &cfg);
+        (closed, changed, monitor.degraded)
+    };
+    if let Some(bucket) = closed {
+        let paths = paths.lock_or_recover().clone();
+        archive(&paths, bucket);
+    }
+    let mode = PerfMode::new(degraded);
+    if let Some(now_degraded) = changed {
+        if now_degraded {
+            log!("🐢 sustained low frame rate, degrading animations");
+        } else {
+            log!("🐇 frame rate recovered, full animations");
+        }
+        let _ = app.emit("perf-mode", &mode);
+    }
+    Ok(mode)
+}
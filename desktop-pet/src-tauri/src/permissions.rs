@@ -0,0 +1,374 @@
+//! What each external channel may do, and a record of what it did.
+//!
+//! Every state write, settings change or command that arrives over the
+//! control API, `star-pet-ctl`, D-Bus, MQTT, JSON-RPC or the WebSocket is
+//! checked against `permissions` and appended to
+//! `.star-pet/logs/audit.jsonl`, allowed or not; `get_audit_log` reads it
+//! back. Reads are never restricted. The window itself is not a channel.
+//!
+//! The control API and the WebSocket take tokens. Their own token gets the
+//! channel's scopes; a token from `permissions.tokens` gets only those of
+//! its scopes the channel also has.
+
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::{self, log};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+const FILE: &str = "audit.jsonl";
+/// Past this the log moves to `audit.old.jsonl`.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const DEFAULT_LIMIT: usize = 100;
+
+/// Serialises appends from the channels' threads.
+static APPEND: Mutex<()> = Mutex::new(());
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Control,
+    Ctl,
+    Dbus,
+    Mqtt,
+    Rpc,
+    Ws,
+}
+
+impl Channel {
+    fn takes_tokens(self) -> bool {
+        matches!(self, Channel::Control | Channel::Ws)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// `state.json`, progress included.
+    SetState,
+    /// Blocks of `settings.json`, except `permissions`.
+    ChangeSettings,
+    /// Reloads, break snoozes, keystroke reports, speech bubbles.
+    Commands,
+}
+
+impl Scope {
+    fn name(self) -> &'static str {
+        match self {
+            Scope::SetState => "set_state",
+            Scope::ChangeSettings => "change_settings",
+            Scope::Commands => "commands",
+        }
+    }
+}
+
+/// `permissions.tokens` entry.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TokenGrant {
+    /// Shown in the audit log instead of the token.
+    pub name: String,
+    pub token: String,
+    /// `control` and/or `ws`; both when empty.
+    pub channels: Vec<Channel>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Default for TokenGrant {
+    fn default() -> Self {
+        TokenGrant {
+            name: String::new(),
+            token: String::new(),
+            channels: Vec::new(),
+            scopes: vec![Scope::SetState],
+        }
+    }
+}
+
+/// `permissions` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PermissionsSettings {
+    /// Scopes of the channels not listed in `channels`.
+    pub default: Vec<Scope>,
+    pub channels: BTreeMap<Channel, Vec<Scope>>,
+    pub tokens: Vec<TokenGrant>,
+    /// Write `audit.jsonl`.
+    pub audit: bool,
+}
+
+impl Default for PermissionsSettings {
+    fn default() -> Self {
+        PermissionsSettings {
+            default: vec![Scope::SetState, Scope::Commands],
+            channels: BTreeMap::new(),
+            tokens: Vec::new(),
+            audit: true,
+        }
+    }
+}
+
+impl PermissionsSettings {
+    fn scopes(&self, channel: Channel) -> &[Scope] {
+        self.channels.get(&channel).unwrap_or(&self.default)
+    }
+
+    /// The channel must have `scope`, and so must the grant if one was used.
+    fn allows(&self, caller: &Caller, scope: Scope) -> bool {
+        self.scopes(caller.channel).contains(&scope)
+            && caller
+                .grant
+                .as_ref()
+                .is_none_or(|g| g.scopes.contains(&scope))
+    }
+}
+
+/// Who is asking: a channel and, on token channels, the grant it used.
+#[derive(Debug, Clone)]
+pub(crate) struct Caller {
+    channel: Channel,
+    grant: Option<TokenGrant>,
+}
+
+impl Caller {
+    pub(crate) fn new(channel: Channel) -> Caller {
+        Caller {
+            channel,
+            grant: None,
+        }
+    }
+
+    /// `None` when `token` is neither one of `permissions.tokens` nor the
+    /// channel's `own` token (empty means none is needed).
+    pub(crate) fn from_token(
+        app: &AppHandle,
+        channel: Channel,
+        token: Option<&str>,
+        own: &str,
+    ) -> Option<Caller> {
+        let settings = app.state::<Mutex<Settings>>();
+        let settings = settings.lock_or_recover();
+        Caller::matching(&settings.permissions, channel, token, own)
+    }
+
+    fn matching(
+        cfg: &PermissionsSettings,
+        channel: Channel,
+        token: Option<&str>,
+        own: &str,
+    ) -> Option<Caller> {
+        let grant = token.filter(|t| !t.is_empty()).and_then(|token| {
+            cfg.tokens
+                .iter()
+                .find(|g| {
+                    g.token == token
+                        && channel.takes_tokens()
+                        && (g.channels.is_empty() || g.channels.contains(&channel))
+                })
+                .cloned()
+        });
+        if grant.is_some() {
+            return Some(Caller { channel, grant });
+        }
+        (own.is_empty() || token == Some(own)).then(|| Caller::new(channel))
+    }
+}
+
+/// One line of `audit.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    at: String,
+    channel: Channel,
+    /// Name of the `permissions.tokens` entry used, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    scope: Scope,
+    action: String,
+    allowed: bool,
+}
+
+fn path(data_dir: &Path) -> PathBuf {
+    logs::dir(data_dir).join(FILE)
+}
+
+fn append(data_dir: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let _guard = APPEND.lock_or_recover();
+    let path = path(data_dir);
+    let dir = logs::dir(data_dir);
+    std::fs::create_dir_all(fsutil::long_path(&dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let big = std::fs::metadata(fsutil::long_path(&path))
+        .map(|m| m.len() > MAX_FILE_BYTES)
+        .unwrap_or(false);
+    if big {
+        std::fs::rename(
+            fsutil::long_path(&path),
+            fsutil::long_path(&dir.join("audit.old.jsonl")),
+        )
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(fsutil::long_path(&path))
+        .and_then(|mut file| writeln!(file, "{line}"))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Checks `scope` for `caller` and records the attempt; `action` says what
+/// was asked for, e.g. `PUT /state`.
+pub(crate) fn authorize(
+    app: &AppHandle,
+    caller: &Caller,
+    scope: Scope,
+    action: &str,
+) -> Result<(), String> {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .permissions
+        .clone();
+    let allowed = cfg.allows(caller, scope);
+    if cfg.audit {
+        let entry = AuditEntry {
+            at: Local::now().to_rfc3339(),
+            channel: caller.channel,
+            token: caller.grant.as_ref().map(|g| g.name.clone()),
+            scope,
+            action: action.to_string(),
+            allowed,
+        };
+        let data_dir = app
+            .state::<Mutex<AppPaths>>()
+            .lock_or_recover()
+            .data_dir
+            .clone();
+        if let Err(e) = append(&data_dir, &entry) {
+            log!("⚠️ audit log: {e}");
+        }
+    }
+    if allowed {
+        return Ok(());
+    }
+    let who = match &caller.grant {
+        Some(g) => format!("token `{}`", g.name),
+        None => format!("{:?}", caller.channel).to_lowercase(),
+    };
+    log!("🚫 {who} lacks `{}`: {action}", scope.name());
+    Err(format!("permission denied: {who} lacks `{}`", scope.name()))
+}
+
+/// Most recent last; `limit` defaults to 100.
+#[tauri::command]
+pub fn get_audit_log(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    limit: Option<usize>,
+    channel: Option<Channel>,
+) -> Result<Vec<AuditEntry>, String> {
+    let path = path(&paths.lock_or_recover().data_dir);
+    if !fsutil::exists(&path) {
+        return Ok(Vec::new());
+    }
+    let raw = fsutil::read_text(&path)?;
+    let entries: Vec<AuditEntry> = raw
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .filter(|e| channel.is_none_or(|c| e.channel == c))
+        .collect();
+    let skip = entries.len().saturating_sub(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(token: &str, channels: Vec<Channel>, scopes: Vec<Scope>) -> TokenGrant {
+        TokenGrant {
+            name: format!("{token}-grant"),
+            token: token.into(),
+            channels,
+            scopes,
+        }
+    }
+
+    fn cfg() -> PermissionsSettings {
+        PermissionsSettings {
+            tokens: vec![
+                grant("any", Vec::new(), vec![Scope::SetState]),
+                grant(
+                    "ws-only",
+                    vec![Channel::Ws],
+                    vec![Scope::SetState, Scope::Commands],
+                ),
+            ],
+            ..PermissionsSettings::default()
+        }
+    }
+
+    fn grant_name(caller: &Caller) -> Option<&str> {
+        caller.grant.as_ref().map(|g| g.name.as_str())
+    }
+
+    #[test]
+    fn own_token_or_none_needed() {
+        let cfg = cfg();
+        let own = Caller::matching(&cfg, Channel::Control, Some("secret"), "secret").unwrap();
+        assert_eq!(grant_name(&own), None);
+        assert!(Caller::matching(&cfg, Channel::Control, Some("wrong"), "secret").is_none());
+        assert!(Caller::matching(&cfg, Channel::Control, None, "secret").is_none());
+        // No own token configured: anyone gets the channel's scopes.
+        assert!(Caller::matching(&cfg, Channel::Control, None, "").is_some());
+    }
+
+    #[test]
+    fn empty_token_never_matches_a_grant() {
+        let mut cfg = cfg();
+        cfg.tokens
+            .push(grant("", Vec::new(), vec![Scope::ChangeSettings]));
+        assert!(Caller::matching(&cfg, Channel::Control, Some(""), "secret").is_none());
+        let anon = Caller::matching(&cfg, Channel::Control, Some(""), "").unwrap();
+        assert_eq!(grant_name(&anon), None);
+    }
+
+    #[test]
+    fn grants_are_limited_to_their_channels() {
+        let cfg = cfg();
+        let ws = Caller::matching(&cfg, Channel::Ws, Some("ws-only"), "secret").unwrap();
+        assert_eq!(grant_name(&ws), Some("ws-only-grant"));
+        assert!(Caller::matching(&cfg, Channel::Control, Some("ws-only"), "secret").is_none());
+        let control = Caller::matching(&cfg, Channel::Control, Some("any"), "secret").unwrap();
+        assert_eq!(grant_name(&control), Some("any-grant"));
+        // Channels without tokens ignore grants.
+        let dbus = Caller::matching(&cfg, Channel::Dbus, Some("any"), "").unwrap();
+        assert_eq!(grant_name(&dbus), None);
+    }
+
+    #[test]
+    fn scoped_grants_get_only_what_the_channel_also_has() {
+        let mut cfg = cfg();
+        cfg.channels.insert(Channel::Ws, vec![Scope::SetState]);
+        let ws = Caller::matching(&cfg, Channel::Ws, Some("ws-only"), "").unwrap();
+        assert!(cfg.allows(&ws, Scope::SetState));
+        assert!(!cfg.allows(&ws, Scope::Commands));
+
+        let control = Caller::matching(&cfg, Channel::Control, Some("any"), "").unwrap();
+        assert!(cfg.allows(&control, Scope::SetState));
+        assert!(!cfg.allows(&control, Scope::Commands));
+        let own = Caller::new(Channel::Control);
+        assert!(cfg.allows(&own, Scope::Commands));
+        assert!(!cfg.allows(&own, Scope::ChangeSettings));
+    }
+}
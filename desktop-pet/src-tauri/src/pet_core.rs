@@ -0,0 +1,994 @@
+//! Config and state loading, decoupled from Tauri and the real filesystem.
+//!
+//! The Tauri commands, the CLI and the validator all go through these
+//! functions with an [`AssetSource`] rooted at `layers/` and a
+//! [`StateStore`] for `state.json`, so the same code can run against an
+//! in-memory pack under the `test-support` feature.
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use image::DynamicImage;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+pub use crate::error::AppError;
+pub use crate::limits::Limits;
+use crate::{
+    aseprite, fsutil, imaging, limits, state_file, AnimCfg, AnimItem, CfgFile, CharCfg, CharData,
+    FootprintsData, FullData, HitMasks, LayerItem, MapCfgFile, MapData, PetData, PetState, PoiOut,
+    ShadowData, SheetMasks, SpritesCfg, SpritesData, WalkCycleCfg, WalkSync,
+};
+
+// ── sources ──
+
+/// Read access to a pack's files, addressed by the relative paths used in
+/// `layers.json` / `map.json`.
+pub trait AssetSource: Send + Sync {
+    fn exists(&self, rel: &str) -> bool;
+    fn size(&self, rel: &str) -> Result<u64, AppError>;
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError>;
+
+    fn read_text(&self, rel: &str) -> Result<String, AppError> {
+        let bytes = self.read(rel)?;
+        fsutil::decode_text(&bytes).map_err(|e| AppError::from(format!("{rel}: {e}")))
+    }
+
+    /// A URL the webview can load `rel` from, if this source is served.
+    /// Images are inlined as data URLs otherwise.
+    fn asset_url(&self, _rel: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Where the current [`PetState`] lives.
+pub trait StateStore: Send + Sync {
+    fn load(&self) -> Result<PetState, String>;
+    fn save(&self, state: &PetState) -> Result<(), String>;
+}
+
+/// Assets under a directory on disk, normally `<root>/layers`.
+pub struct FsAssets {
+    dir: PathBuf,
+}
+
+impl FsAssets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FsAssets { dir: dir.into() }
+    }
+
+    pub fn path(&self, rel: &str) -> PathBuf {
+        self.dir.join(rel)
+    }
+}
+
+impl AssetSource for FsAssets {
+    fn exists(&self, rel: &str) -> bool {
+        fsutil::exists(&self.path(rel))
+    }
+
+    fn size(&self, rel: &str) -> Result<u64, AppError> {
+        let path = self.path(rel);
+        std::fs::metadata(fsutil::long_path(&path))
+            .map(|m| m.len())
+            .map_err(|e| AppError::io(path.display(), &e))
+    }
+
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+        fsutil::load_bytes(&self.path(rel))
+    }
+
+    fn read_text(&self, rel: &str) -> Result<String, AppError> {
+        fsutil::load_text(&self.path(rel))
+    }
+}
+
+/// `state.json` on disk, using the locking protocol in `state_file`.
+pub struct FsStateStore {
+    path: PathBuf,
+}
+
+impl FsStateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FsStateStore { path: path.into() }
+    }
+}
+
+impl StateStore for FsStateStore {
+    fn load(&self) -> Result<PetState, String> {
+        state_file::read(&self.path).map_err(String::from)
+    }
+
+    fn save(&self, state: &PetState) -> Result<(), String> {
+        state_file::write(&self.path, state)
+    }
+}
+
+/// `base` with some files replaced in memory, e.g. unsaved edits to
+/// `layers.json`.
+pub struct Overlay<'a> {
+    base: &'a dyn AssetSource,
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(base: &'a dyn AssetSource) -> Self {
+        Overlay {
+            base,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, rel: &str, bytes: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(rel.to_string(), bytes.into());
+        self
+    }
+}
+
+impl AssetSource for Overlay<'_> {
+    fn exists(&self, rel: &str) -> bool {
+        self.files.contains_key(rel) || self.base.exists(rel)
+    }
+
+    fn size(&self, rel: &str) -> Result<u64, AppError> {
+        match self.files.get(rel) {
+            Some(bytes) => Ok(bytes.len() as u64),
+            None => self.base.size(rel),
+        }
+    }
+
+    fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+        match self.files.get(rel) {
+            Some(bytes) => Ok(bytes.clone()),
+            None => self.base.read(rel),
+        }
+    }
+
+    fn asset_url(&self, rel: &str) -> Option<String> {
+        if self.files.contains_key(rel) {
+            return None;
+        }
+        self.base.asset_url(rel)
+    }
+}
+
+/// `source` as written inside the pack file `from` (an image next to a
+/// `.tsx`, say), resolved to a pack path; it may not leave the pack.
+pub(crate) fn resolve_relative(from: &str, source: &str) -> Result<String, String> {
+    let source = source.replace('\\', "/");
+    if source.starts_with('/') || source.contains(':') {
+        return Err(format!(
+            "{from}: `{source}` must be relative to the pack's layers folder"
+        ));
+    }
+    let mut parts: Vec<&str> = from.split('/').collect();
+    parts.pop();
+    for seg in source.split('/') {
+        match seg {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(format!("{from}: `{source}` points outside the pack"));
+                }
+            }
+            seg => parts.push(seg),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+// ── encoding ──
+
+pub(crate) fn read_asset(
+    src: &dyn AssetSource,
+    rel: &str,
+    limits: &Limits,
+) -> Result<Vec<u8>, AppError> {
+    limits::check_size(
+        rel,
+        src.size(rel)?,
+        limits.max_asset_bytes,
+        "max_asset_bytes",
+    )?;
+    src.read(rel)
+}
+
+pub(crate) fn encode_image(
+    src: &dyn AssetSource,
+    rel: &str,
+    limits: &Limits,
+) -> Result<String, AppError> {
+    if let Some(url) = src.asset_url(rel) {
+        limits::check_size(
+            rel,
+            src.size(rel)?,
+            limits.max_asset_bytes,
+            "max_asset_bytes",
+        )?;
+        return Ok(url);
+    }
+    let bytes = read_asset(src, rel, limits)?;
+    Ok(encode_bytes(rel, &bytes))
+}
+
+pub(crate) fn mime_for(rel: &str) -> &'static str {
+    let ext = Path::new(rel)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png");
+    match ext {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "image/png",
+    }
+}
+
+pub(crate) fn encode_bytes(rel: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_for(rel), B64.encode(bytes))
+}
+
+// ── configs ──
+
+pub(crate) fn read_config(
+    src: &dyn AssetSource,
+    name: &str,
+    limits: &Limits,
+) -> Result<String, AppError> {
+    limits::check_size(
+        name,
+        src.size(name)?,
+        limits.max_config_bytes,
+        "max_config_bytes",
+    )?;
+    src.read_text(name)
+}
+
+/// Deepest `extends` / `include` chain followed before giving up.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Config `name` with its `extends` base and `include`s merged in: the base
+/// first, then each include in order, then the file's own keys. Objects
+/// merge key by key; anything else, arrays included, replaces, and `null`
+/// removes the inherited value. Paths are relative to the including file.
+pub(crate) fn read_config_merged(
+    src: &dyn AssetSource,
+    name: &str,
+    limits: &Limits,
+) -> Result<Value, AppError> {
+    resolve_config(src, name, limits, &mut Vec::new())
+}
+
+fn resolve_config(
+    src: &dyn AssetSource,
+    name: &str,
+    limits: &Limits,
+    chain: &mut Vec<String>,
+) -> Result<Value, AppError> {
+    if chain.iter().any(|c| c == name) {
+        return Err(format!("{}: include cycle through {name}", chain.join(" → ")).into());
+    }
+    if chain.len() >= MAX_INCLUDE_DEPTH {
+        return Err(format!("{name}: includes nested deeper than {MAX_INCLUDE_DEPTH}").into());
+    }
+    let raw = read_config(src, name, limits)?;
+    let mut own: Value = serde_json::from_str(&raw).map_err(|e| AppError::parse(name, &e))?;
+    let Some(fields) = own.as_object_mut() else {
+        return Ok(own);
+    };
+    let mut parents = Vec::new();
+    match fields.remove("extends") {
+        None => {}
+        Some(Value::String(base)) => parents.push(base),
+        Some(_) => return Err(format!("{name}: `extends` must be a file name").into()),
+    }
+    match fields.remove("include") {
+        None => {}
+        Some(Value::Array(items)) => {
+            for item in items {
+                let Value::String(file) = item else {
+                    return Err(format!("{name}: `include` must list file names").into());
+                };
+                parents.push(file);
+            }
+        }
+        Some(_) => return Err(format!("{name}: `include` must be a list").into()),
+    }
+    if parents.is_empty() {
+        return Ok(own);
+    }
+
+    chain.push(name.to_string());
+    let mut merged = Value::Object(serde_json::Map::new());
+    for parent in parents {
+        let rel = resolve_relative(name, &parent)?;
+        if !src.exists(&rel) {
+            let message = format!("{name}: {rel} not found");
+            return Err(AppError::missing(rel, message));
+        }
+        merge(&mut merged, resolve_config(src, &rel, limits, chain)?);
+    }
+    chain.pop();
+    merge(&mut merged, own);
+    Ok(merged)
+}
+
+fn merge(into: &mut Value, over: Value) {
+    match (into, over) {
+        (Value::Object(into), Value::Object(over)) => {
+            for (key, value) in over {
+                if value.is_null() {
+                    into.remove(&key);
+                } else if let Some(slot) = into.get_mut(&key) {
+                    merge(slot, value);
+                } else {
+                    into.insert(key, value);
+                }
+            }
+        }
+        (slot, over) => *slot = over,
+    }
+}
+
+pub(crate) fn read_layers_cfg(src: &dyn AssetSource, limits: &Limits) -> Result<CfgFile, AppError> {
+    if !src.exists("layers.json") {
+        return Ok(CfgFile {
+            width: None,
+            height: None,
+            character: None,
+            characters: None,
+            layers: None,
+            sprites: None,
+            skins: None,
+        });
+    }
+    let merged = read_config_merged(src, "layers.json", limits)?;
+    let cfg = serde_json::from_value(merged).map_err(|e| AppError::parse("layers.json", &e))?;
+    limits::check_layers(&cfg, limits)?;
+    Ok(cfg)
+}
+
+pub(crate) fn read_map_cfg(src: &dyn AssetSource, limits: &Limits) -> Result<MapCfgFile, AppError> {
+    if !src.exists("map.json") {
+        return Err(AppError::missing("map.json", "map.json not found".into()));
+    }
+    let merged = read_config_merged(src, "map.json", limits)?;
+    let cfg = serde_json::from_value(merged).map_err(|e| AppError::parse("map.json", &e))?;
+    limits::check_map(&cfg, limits)?;
+    Ok(cfg)
+}
+
+// ── loaders ──
+
+/// `index` of `count` pets; without an `x` they spread out across the scene.
+fn char_data(
+    src: &dyn AssetSource,
+    cc: CharCfg,
+    (w, h): (u32, u32),
+    (index, count): (usize, usize),
+    limits: &Limits,
+) -> Result<CharData, AppError> {
+    let spread = w as f64 * (index + 1) as f64 / (count + 1) as f64;
+    Ok(CharData {
+        x: cc.x.unwrap_or(spread),
+        y: cc.y.unwrap_or(h as f64 * 0.66),
+        scale: cc.scale.unwrap_or(2.5),
+        depth: cc.depth.unwrap_or(0),
+        wander: cc.wander.unwrap_or(18.0),
+        shadow: cc.shadow.map(|s| ShadowData {
+            width: s.width.unwrap_or(20.0),
+            height: s.height.unwrap_or(6.0),
+            opacity: s.opacity.unwrap_or(0.35).clamp(0.0, 1.0),
+            offset_x: s.offset_x.unwrap_or(0.0),
+            offset_y: s.offset_y.unwrap_or(0.0),
+        }),
+        footprints: match cc.footprints {
+            Some(f) if src.exists(&f.image) => Some(FootprintsData {
+                data_url: encode_image(src, &f.image, limits)?,
+                fade_secs: f.fade_secs.unwrap_or(3.0),
+                spacing: f.spacing.unwrap_or(8.0),
+            }),
+            _ => None,
+        },
+    })
+}
+
+fn sprites_data(
+    src: &dyn AssetSource,
+    scfg: SpritesCfg,
+    limits: &Limits,
+) -> Result<SpritesData, AppError> {
+    let sheet = match &scfg.aseprite {
+        Some(rel) => Some(aseprite::read(src, rel, limits)?),
+        None => None,
+    };
+    let (fw, fh) = sprite_frame(&scfg, sheet.as_ref())?;
+    let explicit = scfg.anims.unwrap_or_default();
+    let mut anims = Vec::new();
+    if let Some(sheet) = &sheet {
+        let url = match src.asset_url(&sheet.image) {
+            Some(url) => url,
+            None => encode_image(src, &sheet.image, limits)?,
+        };
+        for anim in sheet
+            .anims
+            .iter()
+            .filter(|a| !explicit.contains_key(&a.key))
+        {
+            anims.push(aseprite_item(anim, &url, sheet));
+        }
+    }
+    for (key, acfg) in explicit {
+        if !src.exists(&acfg.file) {
+            continue;
+        }
+        let bytes = read_asset(src, &acfg.file, limits)?;
+        // Plain sheets start at frame 0; only shared ones need decoding.
+        let first_frame = if acfg.addressed() {
+            let sheet = imaging::decode(Path::new(&acfg.file), &bytes)?;
+            anim_first_frame(&key, &acfg, &sheet, (fw, fh))?
+        } else {
+            0
+        };
+        anims.push(AnimItem {
+            key,
+            data_url: src
+                .asset_url(&acfg.file)
+                .unwrap_or_else(|| encode_bytes(&acfg.file, &bytes)),
+            frames: acfg.frames.unwrap_or(1),
+            rate: acfg.rate.unwrap_or(4),
+            repeat: acfg.repeat,
+            margin: acfg.margin.unwrap_or(0),
+            spacing: acfg.spacing.unwrap_or(0),
+            first_frame,
+            sequence: None,
+            durations_ms: None,
+        });
+    }
+    Ok(SpritesData {
+        frame_width: fw,
+        frame_height: fh,
+        anims,
+    })
+}
+
+pub fn build_layers(src: &dyn AssetSource, limits: &Limits) -> Result<FullData, AppError> {
+    build_layers_skinned(src, None, limits)
+}
+
+/// `layers.json` with the named entry of `skins` in place of `sprites`.
+fn read_skinned_cfg(
+    src: &dyn AssetSource,
+    skin: Option<&str>,
+    limits: &Limits,
+) -> Result<CfgFile, AppError> {
+    let mut cfg = read_layers_cfg(src, limits)?;
+    if let Some(name) = skin {
+        let sheet = cfg
+            .skins
+            .as_mut()
+            .and_then(|skins| skins.remove(name))
+            .ok_or_else(|| format!("layers.json: no skin named `{name}`"))?;
+        cfg.sprites = Some(sheet);
+    }
+    Ok(cfg)
+}
+
+/// `build_layers` with the top-level sheet replaced by the named entry of
+/// `skins`; pets with sheets of their own keep them.
+pub fn build_layers_skinned(
+    src: &dyn AssetSource,
+    skin: Option<&str>,
+    limits: &Limits,
+) -> Result<FullData, AppError> {
+    let cfg = read_skinned_cfg(src, skin, limits)?;
+
+    let w = cfg.width.unwrap_or(200);
+    let h = cfg.height.unwrap_or(250);
+    let base = cfg.character.unwrap_or_default();
+
+    let mut items = Vec::new();
+    for entry in cfg.layers.unwrap_or_default() {
+        if !src.exists(&entry.image) {
+            continue;
+        }
+        items.push(LayerItem {
+            data_url: encode_image(src, &entry.image, limits)?,
+            x: entry.x.unwrap_or(w as f64 / 2.0),
+            y: entry.y.unwrap_or(h as f64 / 2.0),
+            depth: entry.depth.unwrap_or(-1),
+            scale: entry.scale.unwrap_or(1.0),
+            alpha: entry.alpha.unwrap_or(1.0),
+        });
+    }
+
+    let shared = match cfg.sprites {
+        Some(scfg) => Some(sprites_data(src, scfg, limits)?),
+        None => None,
+    };
+    let pets = cfg.characters.unwrap_or_default();
+    let count = pets.len();
+    let mut characters: Vec<PetData> = Vec::with_capacity(count.max(1));
+    for (i, pet) in pets.into_iter().enumerate() {
+        let name = pet.name.unwrap_or_else(|| format!("pet{}", i + 1));
+        if characters.iter().any(|p| p.name == name) {
+            return Err(format!("layers.json: two characters are named `{name}`").into());
+        }
+        let sprites = match pet.sprites {
+            Some(scfg) => Some(sprites_data(src, scfg, limits)?),
+            None => shared.clone(),
+        };
+        let character = char_data(src, pet.character.or(&base), (w, h), (i, count), limits)?;
+        characters.push(PetData {
+            name,
+            character,
+            sprites,
+        });
+    }
+    if characters.is_empty() {
+        characters.push(PetData {
+            name: "pet1".into(),
+            character: char_data(src, base, (w, h), (0, 1), limits)?,
+            sprites: shared,
+        });
+    }
+
+    Ok(FullData {
+        width: w,
+        height: h,
+        character: characters[0].character.clone(),
+        layers: items,
+        sprites: characters[0].sprites.clone(),
+        characters,
+        skin: skin.map(str::to_string),
+    })
+}
+
+pub fn build_map(src: &dyn AssetSource, limits: &Limits) -> Result<MapData, AppError> {
+    let cfg = read_map_cfg(src, limits)?;
+
+    let ts = cfg.tile_size.unwrap_or(16);
+    let cols = cfg
+        .cols
+        .unwrap_or(cfg.ground.first().map_or(12, |r| r.len() as u32));
+    let rows = cfg.rows.unwrap_or(cfg.ground.len() as u32);
+
+    if !src.exists(&cfg.tileset) {
+        let message = format!("tileset not found: {}", cfg.tileset);
+        return Err(AppError::missing(&cfg.tileset, message));
+    }
+    let img_bytes = read_asset(src, &cfg.tileset, limits)?;
+    let tileset = imaging::decode(Path::new(&cfg.tileset), &img_bytes)?;
+    let (tileset_cols, tileset_rows) = imaging::tile_grid(&cfg.tileset, &tileset, ts)?;
+    let tileset_url = src
+        .asset_url(&cfg.tileset)
+        .unwrap_or_else(|| encode_bytes(&cfg.tileset, &img_bytes));
+
+    let mut pois = HashMap::new();
+    for (k, v) in cfg.pois.unwrap_or_default() {
+        pois.insert(
+            k,
+            PoiOut {
+                col: v.col,
+                row: v.row,
+            },
+        );
+    }
+
+    let mut state_icons = HashMap::new();
+    for (state, filename) in cfg.state_icons.unwrap_or_default() {
+        let rel = state_icon_path(&filename);
+        if src.exists(&rel) {
+            if let Ok(url) = encode_image(src, &rel, limits) {
+                state_icons.insert(state, url);
+            }
+        }
+    }
+
+    let zoom = cfg.zoom.unwrap_or(2);
+    let character = cfg.character.unwrap_or_default();
+    let character_speed = character.speed.or(cfg.character_speed).unwrap_or(2.5);
+    let walk = walk_sync(cfg.walk_cycle.as_ref(), character_speed, ts, zoom)?;
+
+    Ok(MapData {
+        tile_size: ts,
+        cols,
+        rows,
+        zoom,
+        tileset_url,
+        tileset_cols,
+        tileset_rows,
+        character_speed,
+        ground: cfg.ground,
+        border: cfg.border.unwrap_or_default(),
+        rug: cfg.rug.unwrap_or_default(),
+        objects: cfg.objects,
+        collision: cfg.collision,
+        pois,
+        state_icons,
+        walk,
+        character,
+    })
+}
+
+/// Builds the map from a Tiled `.tmx` at `rel` instead of `map.json`.
+pub fn build_map_tiled(
+    src: &dyn AssetSource,
+    rel: &str,
+    limits: &Limits,
+) -> Result<MapData, AppError> {
+    let raw = crate::tiled::to_map_json(src, rel, limits)?;
+    build_map(&Overlay::new(src).with("map.json", raw), limits)
+}
+
+/// Ties the walk animation to movement: crossing one tile takes
+/// `1 / speed` seconds, during which `frames_per_tile` frames must play.
+fn walk_sync(
+    cycle: Option<&WalkCycleCfg>,
+    speed: f64,
+    tile_size: u32,
+    zoom: u32,
+) -> Result<WalkSync, String> {
+    // The built-in move anims are four frames over one tile.
+    let frames = cycle.and_then(|c| c.frames).unwrap_or(4);
+    let stride = cycle.and_then(|c| c.stride_tiles).unwrap_or(1.0);
+    if frames == 0 || !stride.is_finite() || stride <= 0.0 {
+        return Err("map.json: walk_cycle.frames and stride_tiles must be greater than 0".into());
+    }
+    let frames_per_tile = f64::from(frames) / stride;
+    Ok(WalkSync {
+        frames_per_tile,
+        frame_rate: frames_per_tile * speed.max(0.0),
+        pixels_per_frame: f64::from(tile_size * zoom) / frames_per_tile,
+    })
+}
+
+/// Linear index of an anim's first frame in its sheet, checking that the
+/// row, start column and frame count all fit in the decoded image.
+pub(crate) fn anim_first_frame(
+    key: &str,
+    acfg: &AnimCfg,
+    sheet: &DynamicImage,
+    frame: (u32, u32),
+) -> Result<u32, String> {
+    let (cols, rows) = imaging::sheet_grid(
+        &acfg.file,
+        sheet,
+        frame,
+        acfg.margin.unwrap_or(0),
+        acfg.spacing.unwrap_or(0),
+    )?;
+    let row = acfg.row.unwrap_or(0);
+    let start = acfg.start_frame.unwrap_or(0);
+    let frames = acfg.frames.unwrap_or(1);
+    if row >= rows {
+        return Err(format!(
+            "anim `{key}`: row {row} is past the last row of {} ({rows} rows)",
+            acfg.file
+        ));
+    }
+    if start >= cols {
+        return Err(format!(
+            "anim `{key}`: start_frame {start} is past the last column of {} ({cols} columns)",
+            acfg.file
+        ));
+    }
+    let first = row * cols + start;
+    if u64::from(first) + u64::from(frames) > u64::from(cols) * u64::from(rows) {
+        return Err(format!(
+            "anim `{key}`: {frames} frames from row {row}, column {start} run past the end of {} ({cols}x{rows} frames)",
+            acfg.file
+        ));
+    }
+    Ok(first)
+}
+
+/// Frame size shared by every anim: `frame_width` / `frame_height`, else
+/// the Aseprite sheet's, else 32×32.
+pub(crate) fn sprite_frame(
+    scfg: &SpritesCfg,
+    sheet: Option<&aseprite::Sheet>,
+) -> Result<(u32, u32), String> {
+    let fw = scfg.frame_width.or(sheet.map(|s| s.frame.0)).unwrap_or(32);
+    let fh = scfg.frame_height.or(sheet.map(|s| s.frame.1)).unwrap_or(32);
+    if let Some(sheet) = sheet {
+        if sheet.frame != (fw, fh) {
+            return Err(format!(
+                "layers.json: sprites are {fw}x{fh} but {} has {}x{} frames",
+                sheet.image, sheet.frame.0, sheet.frame.1
+            ));
+        }
+    }
+    Ok((fw, fh))
+}
+
+fn aseprite_item(anim: &aseprite::Anim, url: &str, sheet: &aseprite::Sheet) -> AnimItem {
+    let first = anim.sequence.first().copied().unwrap_or(0);
+    let frames = anim.sequence.len() as u32;
+    let contiguous = anim.sequence.iter().zip(first..).all(|(i, n)| *i == n);
+    let uniform = anim.durations_ms.windows(2).all(|w| w[0] == w[1]);
+    let total_ms: u64 = anim.durations_ms.iter().map(|&d| u64::from(d)).sum();
+    AnimItem {
+        key: anim.key.clone(),
+        data_url: url.to_string(),
+        frames,
+        rate: (f64::from(frames) * 1000.0 / total_ms.max(1) as f64)
+            .round()
+            .max(1.0) as u32,
+        repeat: anim.repeat,
+        margin: sheet.margin,
+        spacing: sheet.spacing,
+        first_frame: first,
+        sequence: (!contiguous).then(|| anim.sequence.clone()),
+        durations_ms: (!uniform).then(|| anim.durations_ms.clone()),
+    }
+}
+
+// ── hit masks ──
+
+/// Alpha above which a pixel counts as part of the sprite.
+pub const DEFAULT_HIT_ALPHA: u8 = 16;
+
+/// Frame `index` of a sheet as a bitset; `None` once past the last frame.
+fn frame_mask(
+    sheet: &image::RgbaImage,
+    grid: (u32, u32),
+    frame: (u32, u32),
+    margin: u32,
+    spacing: u32,
+    index: u32,
+    threshold: u8,
+) -> Option<Vec<u8>> {
+    let (cols, rows) = grid;
+    let (fw, fh) = frame;
+    let (col, row) = (index % cols, index / cols);
+    if row >= rows {
+        return None;
+    }
+    let x0 = margin + col * (fw + spacing);
+    let y0 = margin + row * (fh + spacing);
+    let mut bits = vec![0u8; (fw as usize * fh as usize).div_ceil(8)];
+    for y in 0..fh {
+        for x in 0..fw {
+            if sheet.get_pixel(x0 + x, y0 + y)[3] > threshold {
+                let i = (y * fw + x) as usize;
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+    }
+    Some(bits)
+}
+
+/// Alpha hit masks for every frame of every anim in `layers.json`.
+pub fn build_hit_masks(
+    src: &dyn AssetSource,
+    limits: &Limits,
+    threshold: u8,
+) -> Result<HitMasks, String> {
+    build_hit_masks_skinned(src, None, limits, threshold)
+}
+
+/// `build_hit_masks` for the sheets `build_layers_skinned` would draw: one
+/// set per pet, the top-level fields being the first pet's.
+pub fn build_hit_masks_skinned(
+    src: &dyn AssetSource,
+    skin: Option<&str>,
+    limits: &Limits,
+    threshold: u8,
+) -> Result<HitMasks, String> {
+    let cfg = read_skinned_cfg(src, skin, limits)?;
+    let shared = sheet_masks(src, cfg.sprites, limits, threshold)?;
+    let mut first = None;
+    let mut characters = BTreeMap::new();
+    for (i, pet) in cfg.characters.unwrap_or_default().into_iter().enumerate() {
+        let name = pet.name.unwrap_or_else(|| format!("pet{}", i + 1));
+        if characters.contains_key(&name) {
+            return Err(format!("layers.json: two characters are named `{name}`"));
+        }
+        let masks = match pet.sprites {
+            Some(scfg) => sheet_masks(src, Some(scfg), limits, threshold)?,
+            None => shared.clone(),
+        };
+        first.get_or_insert_with(|| masks.clone());
+        characters.insert(name, masks);
+    }
+    let first = match first {
+        Some(masks) => masks,
+        None => {
+            characters.insert("pet1".to_string(), shared.clone());
+            shared
+        }
+    };
+    Ok(HitMasks {
+        frame_width: first.frame_width,
+        frame_height: first.frame_height,
+        threshold,
+        anims: first.anims,
+        characters,
+    })
+}
+
+/// Masks for one sheet; 32×32 frames and no anims without one.
+fn sheet_masks(
+    src: &dyn AssetSource,
+    sprites: Option<SpritesCfg>,
+    limits: &Limits,
+    threshold: u8,
+) -> Result<SheetMasks, String> {
+    let sheet = match sprites.as_ref().and_then(|s| s.aseprite.as_deref()) {
+        Some(rel) => Some(aseprite::read(src, rel, limits)?),
+        None => None,
+    };
+    let (fw, fh) = match &sprites {
+        Some(scfg) => sprite_frame(scfg, sheet.as_ref())?,
+        None => (32, 32),
+    };
+    let explicit = sprites.and_then(|s| s.anims).unwrap_or_default();
+    let mut anims = BTreeMap::new();
+    if let Some(sheet) = &sheet {
+        let bytes = read_asset(src, &sheet.image, limits)?;
+        let decoded = imaging::decode(Path::new(&sheet.image), &bytes)?;
+        let (margin, spacing) = (sheet.margin, sheet.spacing);
+        let grid = imaging::sheet_grid(&sheet.image, &decoded, (fw, fh), margin, spacing)?;
+        let rgba = decoded.to_rgba8();
+        for anim in sheet
+            .anims
+            .iter()
+            .filter(|a| !explicit.contains_key(&a.key))
+        {
+            let masks = anim
+                .sequence
+                .iter()
+                .map_while(|&i| frame_mask(&rgba, grid, (fw, fh), margin, spacing, i, threshold))
+                .map(|bits| B64.encode(bits))
+                .collect();
+            anims.insert(anim.key.clone(), masks);
+        }
+    }
+    for (key, acfg) in explicit {
+        if !src.exists(&acfg.file) {
+            continue;
+        }
+        let bytes = read_asset(src, &acfg.file, limits)?;
+        let sheet = imaging::decode(Path::new(&acfg.file), &bytes)?;
+        let (margin, spacing) = (acfg.margin.unwrap_or(0), acfg.spacing.unwrap_or(0));
+        let grid = imaging::sheet_grid(&acfg.file, &sheet, (fw, fh), margin, spacing)?;
+        let first = if acfg.addressed() {
+            anim_first_frame(&key, &acfg, &sheet, (fw, fh))?
+        } else {
+            0
+        };
+        let rgba = sheet.to_rgba8();
+        let masks = (first..first + acfg.frames.unwrap_or(1))
+            .map_while(|i| frame_mask(&rgba, grid, (fw, fh), margin, spacing, i, threshold))
+            .map(|bits| B64.encode(bits))
+            .collect();
+        anims.insert(key, masks);
+    }
+    Ok(SheetMasks {
+        frame_width: fw,
+        frame_height: fh,
+        anims,
+    })
+}
+
+/// `state_icons` entries name files inside the icon pack folder.
+pub(crate) fn state_icon_path(filename: &str) -> String {
+    format!("Small (24x24) PNG/{filename}")
+}
+
+// ── in-memory harness ──
+
+#[cfg(feature = "test-support")]
+pub mod memory {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::{AppError, AssetSource, StateStore};
+    use crate::PetState;
+
+    /// A pack held entirely in memory, keyed by relative path.
+    #[derive(Default)]
+    pub struct MemoryAssets {
+        files: HashMap<String, Vec<u8>>,
+    }
+
+    impl MemoryAssets {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn insert(&mut self, rel: &str, bytes: impl Into<Vec<u8>>) -> &mut Self {
+            self.files.insert(rel.to_string(), bytes.into());
+            self
+        }
+    }
+
+    impl AssetSource for MemoryAssets {
+        fn exists(&self, rel: &str) -> bool {
+            self.files.contains_key(rel)
+        }
+
+        fn size(&self, rel: &str) -> Result<u64, AppError> {
+            self.files
+                .get(rel)
+                .map(|b| b.len() as u64)
+                .ok_or_else(|| AppError::missing(rel, format!("{rel}: not found")))
+        }
+
+        fn read(&self, rel: &str) -> Result<Vec<u8>, AppError> {
+            self.files
+                .get(rel)
+                .cloned()
+                .ok_or_else(|| AppError::missing(rel, format!("{rel}: not found")))
+        }
+    }
+
+    #[derive(Default)]
+    pub struct MemoryStateStore {
+        state: Mutex<Option<PetState>>,
+    }
+
+    impl StateStore for MemoryStateStore {
+        fn load(&self) -> Result<PetState, String> {
+            self.state
+                .lock()
+                .map_err(|e| e.to_string())?
+                .clone()
+                .ok_or_else(|| "state.json: not found".into())
+        }
+
+        fn save(&self, state: &PetState) -> Result<(), String> {
+            *self.state.lock().map_err(|e| e.to_string())? = Some(state.clone());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "test-support")]
+pub mod fixtures {
+    use image::{ImageFormat, RgbaImage};
+    use std::io::Cursor;
+
+    use super::memory::MemoryAssets;
+
+    /// A solid `w`×`h` PNG.
+    pub fn png(w: u32, h: u32) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        RgbaImage::from_pixel(w, h, image::Rgba([255, 0, 255, 255]))
+            .write_to(&mut out, ImageFormat::Png)
+            .expect("encode fixture png");
+        out.into_inner()
+    }
+
+    /// The smallest pack that exercises every loader: one layer, one
+    /// animation, a 2×2 map on a 32×32 tileset and one state icon.
+    pub fn minimal_pack() -> MemoryAssets {
+        let mut pack = MemoryAssets::new();
+        pack.insert(
+            "layers.json",
+            r#"{
+                "width": 100, "height": 80,
+                "layers": [{ "image": "bg.png", "depth": -2 }],
+                "sprites": { "anims": { "idle": { "file": "idle.png", "frames": 4 } } }
+            }"#,
+        )
+        .insert(
+            "map.json",
+            r#"{
+                "tileset": "tiles.png",
+                "ground": [[0, 1], [2, 3]],
+                "objects": [[-1, -1], [-1, -1]],
+                "collision": [[0, 0], [0, 1]],
+                "pois": { "desk": { "col": 1, "row": 0 } },
+                "state_icons": { "writing": "word.png" }
+            }"#,
+        )
+        .insert("bg.png", png(100, 80))
+        .insert("idle.png", png(128, 32))
+        .insert("tiles.png", png(32, 32))
+        .insert("Small (24x24) PNG/word.png", png(24, 24));
+        pack
+    }
+}
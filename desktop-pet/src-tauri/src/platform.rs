@@ -0,0 +1,133 @@
+//! Windowing differences between platforms, mostly Linux compositors.
+//!
+//! Native Wayland lets clients neither place their own windows nor keep
+//! them above others, which a desktop pet relies on. Under a Wayland
+//! session with XWayland available the app therefore runs GTK on X11 by
+//! default (`platform.linux_backend`). `get_platform_capabilities` tells
+//! the frontend what the resulting session can do, so it can fall back
+//! instead of fighting the compositor.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::logs::log;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinuxBackend {
+    /// X11 through XWayland when both are available, else whatever exists.
+    #[default]
+    Auto,
+    X11,
+    Wayland,
+}
+
+/// `platform` block of `settings.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PlatformSettings {
+    /// Ignored outside Linux.
+    pub linux_backend: LinuxBackend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Session {
+    Windows,
+    Macos,
+    X11,
+    /// X11 client on a Wayland compositor.
+    Xwayland,
+    Wayland,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    session: Session,
+    /// Per-pixel transparent windows.
+    transparency: bool,
+    /// Letting clicks pass through the whole window.
+    click_through: bool,
+    /// Moving the window to coordinates of our choosing.
+    positioning: bool,
+    always_on_top: bool,
+    /// wlr layer-shell overlay surfaces; not built into this app yet.
+    layer_shell: bool,
+}
+
+fn env_set(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|v| !v.is_empty())
+}
+
+/// Picks the GTK backend. Must run before any window toolkit starts.
+pub(crate) fn prepare(cfg: &PlatformSettings) {
+    if !cfg!(target_os = "linux") || env_set("GDK_BACKEND") {
+        return;
+    }
+    let backend = match cfg.linux_backend {
+        LinuxBackend::Auto if env_set("WAYLAND_DISPLAY") && env_set("DISPLAY") => "x11",
+        LinuxBackend::Auto => return,
+        LinuxBackend::X11 => "x11",
+        LinuxBackend::Wayland => "wayland",
+    };
+    log!("🪟 GDK backend: {backend}");
+    std::env::set_var("GDK_BACKEND", backend);
+}
+
+pub(crate) fn session() -> Session {
+    if cfg!(target_os = "windows") {
+        return Session::Windows;
+    }
+    if cfg!(target_os = "macos") {
+        return Session::Macos;
+    }
+    let wayland_session = env_set("WAYLAND_DISPLAY")
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+    let forced = std::env::var("GDK_BACKEND").ok();
+    match (wayland_session, forced.as_deref()) {
+        (true, Some("x11")) => Session::Xwayland,
+        (true, _) => Session::Wayland,
+        (false, _) if env_set("DISPLAY") => Session::X11,
+        _ => Session::Unknown,
+    }
+}
+
+pub(crate) fn capabilities() -> Capabilities {
+    let session = session();
+    let native_wayland = session == Session::Wayland;
+    Capabilities {
+        session,
+        transparency: session != Session::Unknown,
+        click_through: session != Session::Unknown,
+        positioning: !native_wayland,
+        always_on_top: !native_wayland,
+        layer_shell: false,
+    }
+}
+
+#[tauri::command]
+pub fn get_platform_capabilities() -> Capabilities {
+    capabilities()
+}
+
+/// Makes a whole window ignore (or take back) mouse input. The frontend
+/// toggles this as the cursor enters and leaves the sprite, which stands
+/// in for a per-pixel input region.
+#[tauri::command]
+pub fn set_click_through(
+    app: tauri::AppHandle,
+    window: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if !capabilities().click_through {
+        return Err("click-through is not available in this session".into());
+    }
+    let target = app
+        .get_webview_window(&window)
+        .ok_or_else(|| format!("no window `{window}`"))?;
+    target
+        .set_ignore_cursor_events(enabled)
+        .map_err(|e| format!("{window}: {e}"))
+}
@@ -0,0 +1,473 @@
+//! Pomodoro timer, counted down in the backend so it keeps going while
+//! the window is hidden.
+//!
+//! `start_pomodoro` saves the current state and writes `focus_state`; a
+//! finished work phase writes `break_state`, a finished break starts the
+//! next round. `stop_pomodoro` (and quitting) puts the saved state back
+//! unless something else wrote one meanwhile. While a timer runs a
+//! `pomodoro-tick` goes out every second and `pomodoro-phase` at every
+//! change of phase. Work phases land in `stats/pomodoro.json`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{notify, stats, AppPaths, PetState};
+
+pub(crate) const STATS_NAME: &str = "pomodoro";
+const KEEP_SESSIONS: usize = 1000;
+const MAX_MINUTES: u32 = 240;
+const TICK: Duration = Duration::from_secs(1);
+
+/// `pomodoro` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PomodoroSettings {
+    /// Used when `start_pomodoro` is called without lengths.
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    /// Written to `state.json` while working.
+    pub focus_state: String,
+    /// Written to `state.json` during breaks.
+    pub break_state: String,
+    /// Native notification at the end of each phase.
+    pub notify: bool,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        PomodoroSettings {
+            work_minutes: 25,
+            break_minutes: 5,
+            focus_state: "focusing".into(),
+            break_state: "on_break".into(),
+            notify: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+/// One work phase, finished or cut short.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroSession {
+    started_at: String,
+    ended_at: String,
+    work_minutes: u32,
+    /// Ran to the end rather than being skipped or stopped.
+    completed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PomodoroHistory {
+    /// Oldest first.
+    pub(crate) sessions: Vec<PomodoroSession>,
+}
+
+/// Returned by every command; also the payload of `pomodoro-tick`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PomodoroStatus {
+    active: bool,
+    phase: Option<Phase>,
+    paused: bool,
+    /// Counts from 1; a round is one work phase and the break after it.
+    round: u32,
+    remaining_secs: u64,
+    phase_secs: u64,
+    work_minutes: u32,
+    break_minutes: u32,
+    /// Work phases completed today, by the local date.
+    completed_today: usize,
+}
+
+/// Payload of `pomodoro-phase`.
+#[derive(Debug, Clone, Serialize)]
+struct PhaseChanged {
+    /// `None` once the timer stops.
+    phase: Option<Phase>,
+    round: u32,
+    message: Option<String>,
+}
+
+struct Timer {
+    phase: Phase,
+    work_minutes: u32,
+    break_minutes: u32,
+    remaining: Duration,
+    /// When `remaining` was last brought up to date; `None` while paused.
+    running_since: Option<Instant>,
+    round: u32,
+    phase_started_at: String,
+    completed_today: usize,
+    /// The state before the timer started, for `stop_pomodoro`.
+    previous: Option<PetState>,
+}
+
+impl Timer {
+    fn phase_len(&self) -> Duration {
+        let minutes = match self.phase {
+            Phase::Work => self.work_minutes,
+            Phase::Break => self.break_minutes,
+        };
+        Duration::from_secs(u64::from(minutes) * 60)
+    }
+
+    fn catch_up(&mut self, now: Instant) {
+        if let Some(since) = self.running_since {
+            self.remaining = self
+                .remaining
+                .saturating_sub(now.saturating_duration_since(since));
+            self.running_since = Some(now);
+        }
+    }
+
+    fn status(&self) -> PomodoroStatus {
+        PomodoroStatus {
+            active: true,
+            phase: Some(self.phase),
+            paused: self.running_since.is_none(),
+            round: self.round,
+            // Rounded up, so a fresh phase reads 25:00 rather than 24:59.
+            remaining_secs: self.remaining.as_millis().div_ceil(1000) as u64,
+            phase_secs: self.phase_len().as_secs(),
+            work_minutes: self.work_minutes,
+            break_minutes: self.break_minutes,
+            completed_today: self.completed_today,
+        }
+    }
+
+    /// Ends the current phase and starts the next; returns the work session
+    /// that ended, if it was a work phase.
+    fn next_phase(&mut self, completed: bool) -> Option<PomodoroSession> {
+        let ended = match self.phase {
+            Phase::Work => {
+                self.completed_today += usize::from(completed);
+                self.phase = Phase::Break;
+                Some(self.session(completed))
+            }
+            Phase::Break => {
+                self.round += 1;
+                self.phase = Phase::Work;
+                None
+            }
+        };
+        self.remaining = self.phase_len();
+        self.phase_started_at = now_rfc3339();
+        if self.running_since.is_some() {
+            self.running_since = Some(Instant::now());
+        }
+        ended
+    }
+
+    fn session(&self, completed: bool) -> PomodoroSession {
+        PomodoroSession {
+            started_at: self.phase_started_at.clone(),
+            ended_at: now_rfc3339(),
+            work_minutes: self.work_minutes,
+            completed,
+        }
+    }
+}
+
+/// Managed by the app; `None` while no timer runs.
+#[derive(Default)]
+pub(crate) struct Pomodoro(Mutex<Option<Timer>>);
+
+fn now_rfc3339() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+fn idle_status(paths: &AppPaths, cfg: &PomodoroSettings) -> PomodoroStatus {
+    PomodoroStatus {
+        active: false,
+        phase: None,
+        paused: false,
+        round: 0,
+        remaining_secs: 0,
+        phase_secs: 0,
+        work_minutes: cfg.work_minutes,
+        break_minutes: cfg.break_minutes,
+        completed_today: completed_today(paths),
+    }
+}
+
+// ── history ──
+
+fn completed_today(paths: &AppPaths) -> usize {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let history: PomodoroHistory = stats::load(&paths.data_dir, STATS_NAME);
+    history
+        .sessions
+        .iter()
+        .filter(|s| s.completed && s.ended_at.starts_with(&today))
+        .count()
+}
+
+fn record(paths: &AppPaths, session: PomodoroSession) {
+    let mut history: PomodoroHistory = stats::load(&paths.data_dir, STATS_NAME);
+    history.sessions.push(session);
+    let excess = history.sessions.len().saturating_sub(KEEP_SESSIONS);
+    history.sessions.drain(..excess);
+    if let Err(e) = stats::save(&paths.data_dir, STATS_NAME, &history) {
+        log!("⚠️ pomodoro stats: {e}");
+    }
+}
+
+// ── effects ──
+
+fn paths(app: &AppHandle) -> AppPaths {
+    app.state::<Mutex<AppPaths>>().lock_or_recover().clone()
+}
+
+fn settings(app: &AppHandle) -> PomodoroSettings {
+    app.state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .pomodoro
+        .clone()
+}
+
+fn write_state(paths: &AppPaths, name: &str, detail: String) {
+    let state = PetState {
+        state: name.to_string(),
+        detail: Some(detail),
+        progress: None,
+        updated_at: Some(now_rfc3339()),
+        progress_sources: Default::default(),
+    };
+    if let Err(e) = FsStateStore::new(&paths.state_path).save(&state) {
+        log!("⚠️ pomodoro state: {e}");
+    }
+}
+
+/// Writes the phase's state, notifies and emits `pomodoro-phase`.
+fn announce(app: &AppHandle, status: &PomodoroStatus, fresh: bool) {
+    let Some(phase) = status.phase else {
+        return;
+    };
+    let (cfg, paths) = (settings(app), paths(app));
+    let i18n = app.state::<I18n>();
+    let mut args = Map::new();
+    args.insert("round".into(), status.round.into());
+    let (state, detail, key) = match phase {
+        Phase::Work => {
+            args.insert("minutes".into(), status.work_minutes.into());
+            let key = if fresh {
+                "pomodoro.started"
+            } else {
+                "pomodoro.break_over"
+            };
+            (&cfg.focus_state, "pomodoro.focus_detail", key)
+        }
+        Phase::Break => {
+            args.insert("minutes".into(), status.break_minutes.into());
+            (
+                &cfg.break_state,
+                "pomodoro.break_detail",
+                "pomodoro.work_done",
+            )
+        }
+    };
+    write_state(&paths, state, i18n.t(detail, &args));
+    let message = i18n.t(key, &args);
+    if cfg.notify && !fresh {
+        let title = i18n.t(&format!("{key}_title"), &args);
+        if let Err(e) = notify::show(&title, &message) {
+            log!("⚠️ {e}");
+        }
+    }
+    log!("🍅 round {}: {phase:?}", status.round);
+    let _ = app.emit(
+        "pomodoro-phase",
+        PhaseChanged {
+            phase: Some(phase),
+            round: status.round,
+            message: Some(message),
+        },
+    );
+}
+
+/// After the timer moved on: records the work phase that ended and
+/// announces the new one.
+fn moved_on(app: &AppHandle, status: &PomodoroStatus, ended: Option<PomodoroSession>) {
+    if let Some(session) = ended {
+        record(&paths(app), session);
+    }
+    announce(app, status, false);
+}
+
+pub(crate) fn spawn_ticker(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        let (status, moved) = {
+            let mut guard = app.state::<Pomodoro>().0.lock_or_recover();
+            let Some(timer) = guard.as_mut() else {
+                continue;
+            };
+            if timer.running_since.is_none() {
+                continue;
+            }
+            timer.catch_up(Instant::now());
+            let moved = timer.remaining.is_zero().then(|| timer.next_phase(true));
+            (timer.status(), moved)
+        };
+        if let Some(ended) = moved {
+            moved_on(&app, &status, ended);
+        }
+        let _ = app.emit("pomodoro-tick", status);
+    });
+}
+
+/// Ends a running timer and puts back the state it replaced, unless
+/// something else wrote one meanwhile. Called on exit too.
+pub(crate) fn stop(app: &AppHandle) {
+    let Some(timer) = app.state::<Pomodoro>().0.lock_or_recover().take() else {
+        return;
+    };
+    let (paths, cfg) = (paths(app), settings(app));
+    if timer.phase == Phase::Work {
+        record(&paths, timer.session(false));
+    }
+    let store = FsStateStore::new(&paths.state_path);
+    let ours = store
+        .load()
+        .is_ok_and(|s| s.state == cfg.focus_state || s.state == cfg.break_state);
+    if let (true, Some(mut previous)) = (ours, timer.previous) {
+        previous.updated_at = Some(now_rfc3339());
+        if let Err(e) = store.save(&previous) {
+            log!("⚠️ restore state: {e}");
+        }
+    }
+    log!("🍅 pomodoro stopped in round {}", timer.round);
+    let _ = app.emit(
+        "pomodoro-phase",
+        PhaseChanged {
+            phase: None,
+            round: timer.round,
+            message: None,
+        },
+    );
+}
+
+fn check_minutes(name: &str, minutes: u32) -> Result<u32, String> {
+    if (1..=MAX_MINUTES).contains(&minutes) {
+        Ok(minutes)
+    } else {
+        Err(format!(
+            "{name} must be between 1 and {MAX_MINUTES} minutes, got {minutes}"
+        ))
+    }
+}
+
+// ── commands ──
+
+/// Starts a fresh timer at round 1 (lengths default to the `pomodoro`
+/// settings), replacing one that is already running.
+#[tauri::command]
+pub fn start_pomodoro(
+    app: AppHandle,
+    work_mins: Option<u32>,
+    break_mins: Option<u32>,
+) -> Result<PomodoroStatus, String> {
+    let cfg = settings(&app);
+    let work_minutes = check_minutes("work_mins", work_mins.unwrap_or(cfg.work_minutes))?;
+    let break_minutes = check_minutes("break_mins", break_mins.unwrap_or(cfg.break_minutes))?;
+    let paths = paths(&app);
+    let status = {
+        let mut guard = app.state::<Pomodoro>().0.lock_or_recover();
+        let previous = match guard.take() {
+            Some(running) => running.previous,
+            None => FsStateStore::new(&paths.state_path).load().ok(),
+        };
+        let timer = Timer {
+            phase: Phase::Work,
+            work_minutes,
+            break_minutes,
+            remaining: Duration::from_secs(u64::from(work_minutes) * 60),
+            running_since: Some(Instant::now()),
+            round: 1,
+            phase_started_at: now_rfc3339(),
+            completed_today: completed_today(&paths),
+            previous,
+        };
+        let status = timer.status();
+        *guard = Some(timer);
+        status
+    };
+    log!("🍅 pomodoro: {work_minutes} min work, {break_minutes} min break");
+    announce(&app, &status, true);
+    Ok(status)
+}
+
+/// Pauses the running timer, or resumes it when already paused.
+#[tauri::command]
+pub fn pause_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    let mut guard = app.state::<Pomodoro>().0.lock_or_recover();
+    let timer = guard
+        .as_mut()
+        .ok_or_else(|| "no pomodoro is running".to_string())?;
+    let now = Instant::now();
+    timer.catch_up(now);
+    timer.running_since = match timer.running_since {
+        Some(_) => None,
+        None => Some(now),
+    };
+    let status = timer.status();
+    drop(guard);
+    let _ = app.emit("pomodoro-tick", status.clone());
+    Ok(status)
+}
+
+/// Ends the current phase early; a skipped work phase is recorded as not
+/// completed.
+#[tauri::command]
+pub fn skip_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    let (status, ended) = {
+        let mut guard = app.state::<Pomodoro>().0.lock_or_recover();
+        let timer = guard
+            .as_mut()
+            .ok_or_else(|| "no pomodoro is running".to_string())?;
+        let ended = timer.next_phase(false);
+        (timer.status(), ended)
+    };
+    moved_on(&app, &status, ended);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_pomodoro(app: AppHandle) -> PomodoroStatus {
+    stop(&app);
+    idle_status(&paths(&app), &settings(&app))
+}
+
+#[tauri::command]
+pub fn get_pomodoro(app: AppHandle) -> PomodoroStatus {
+    let status = app
+        .state::<Pomodoro>()
+        .0
+        .lock_or_recover()
+        .as_mut()
+        .map(|timer| {
+            timer.catch_up(Instant::now());
+            timer.status()
+        });
+    status.unwrap_or_else(|| idle_status(&paths(&app), &settings(&app)))
+}
+
+#[tauri::command]
+pub fn get_pomodoro_history(paths: tauri::State<'_, Mutex<AppPaths>>) -> PomodoroHistory {
+    let data_dir = paths.lock_or_recover().data_dir.clone();
+    stats::load(&data_dir, STATS_NAME)
+}
@@ -0,0 +1,216 @@
+//! Energy saver while running on battery.
+//!
+//! Every `poll_secs` the battery is read from the OS. Once it discharges
+//! below `power.battery_percent`, pollers sleep `slowdown` times longer,
+//! the integrations named in `pause` skip their work entirely, and an
+//! `energy-saver` event suggests the frontend drop to `frame_rate`. Plugging
+//! in, or charging back above the threshold, lifts it again.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+/// `power` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PowerSettings {
+    pub enabled: bool,
+    /// Save energy on battery at or below this charge.
+    pub battery_percent: u8,
+    /// Multiplier for poller intervals while saving.
+    pub slowdown: f64,
+    /// Integrations to pause while saving: `clipboard`, `gaming`, `lan`.
+    pub pause: Vec<String>,
+    /// Suggested frontend frame rate while saving.
+    pub frame_rate: u32,
+    pub poll_secs: u32,
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        PowerSettings {
+            enabled: true,
+            battery_percent: 30,
+            slowdown: 4.0,
+            pause: vec!["clipboard".into(), "gaming".into(), "lan".into()],
+            frame_rate: 15,
+            poll_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Battery {
+    percent: u8,
+    discharging: bool,
+}
+
+/// Payload of `energy-saver`, also returned by `get_energy_saver`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnergySaver {
+    active: bool,
+    /// Last battery reading; `None` on desktops and unknown platforms.
+    battery_percent: Option<u8>,
+    /// Suggested fps while active.
+    frame_rate: Option<u32>,
+}
+
+#[derive(Default)]
+pub(crate) struct Power(Mutex<EnergySaver>);
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "macos")]
+fn battery() -> Option<Battery> {
+    // Now drawing from 'Battery Power'
+    //  -InternalBattery-0 (id=1234)	42%; discharging; 3:12 remaining
+    let out = stdout("pmset", &["-g", "batt"])?;
+    let line = out.lines().find(|l| l.contains("InternalBattery"))?;
+    let percent = line
+        .split('\t')
+        .nth(1)?
+        .split('%')
+        .next()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Battery {
+        percent,
+        discharging: out.contains("'Battery Power'"),
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn battery() -> Option<Battery> {
+    // BatteryStatus 1 means discharging.
+    const SCRIPT: &str = r#"$b = Get-CimInstance Win32_Battery | Select-Object -First 1; if ($b) { "$($b.EstimatedChargeRemaining) $($b.BatteryStatus)" }"#;
+    let out = stdout(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", SCRIPT],
+    )?;
+    let mut fields = out.split_whitespace();
+    let percent = fields.next()?.parse().ok()?;
+    let status: u32 = fields.next()?.parse().ok()?;
+    Some(Battery {
+        percent,
+        discharging: status == 1,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn battery() -> Option<Battery> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    entries.flatten().find_map(|e| {
+        let dir = e.path();
+        let kind = std::fs::read_to_string(dir.join("type")).ok()?;
+        if kind.trim() != "Battery" {
+            return None;
+        }
+        let percent = std::fs::read_to_string(dir.join("capacity"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+        let status = std::fs::read_to_string(dir.join("status")).ok()?;
+        Some(Battery {
+            percent,
+            discharging: status.trim() == "Discharging",
+        })
+    })
+}
+
+/// Whether the reading calls for saving energy.
+fn should_save(battery: Option<Battery>, cfg: &PowerSettings) -> bool {
+    cfg.enabled && battery.is_some_and(|b| b.discharging && b.percent <= cfg.battery_percent)
+}
+
+/// Whether the machine runs on mains power; one without a battery always
+/// does.
+pub(crate) fn on_ac_power() -> bool {
+    battery().is_none_or(|b| !b.discharging)
+}
+
+fn saving(app: &AppHandle) -> bool {
+    app.state::<Power>().0.lock_or_recover().active
+}
+
+/// How long a poller should sleep, stretched while saving energy.
+pub(crate) fn interval(app: &AppHandle, base: Duration) -> Duration {
+    if !saving(app) {
+        return base;
+    }
+    let slowdown = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .power
+        .slowdown;
+    base.mul_f64(slowdown.clamp(1.0, 60.0))
+}
+
+/// Whether the integration named `name` should skip this round.
+pub(crate) fn paused(app: &AppHandle, name: &str) -> bool {
+    saving(app)
+        && app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .power
+            .pause
+            .iter()
+            .any(|p| p == name)
+}
+
+pub(crate) fn spawn_monitor(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let cfg = app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .power
+            .clone();
+        let reading = battery();
+        let active = should_save(reading, &cfg);
+        let next = EnergySaver {
+            active,
+            battery_percent: reading.map(|b| b.percent),
+            frame_rate: active.then_some(cfg.frame_rate.max(1)),
+        };
+        let changed = {
+            let mut current = app.state::<Power>().0.lock_or_recover();
+            let changed = current.active != next.active;
+            *current = next.clone();
+            changed
+        };
+        if changed {
+            if active {
+                log!(
+                    "🔋 energy saver on ({}% on battery)",
+                    next.battery_percent.unwrap_or_default()
+                );
+            } else {
+                log!("🔌 energy saver off");
+            }
+            let _ = app.emit("energy-saver", next);
+        }
+        std::thread::sleep(Duration::from_secs(u64::from(cfg.poll_secs.max(1))));
+    });
+}
+
+#[tauri::command]
+pub fn get_energy_saver(power: tauri::State<'_, Power>) -> EnergySaver {
+    power.0.lock_or_recover().clone()
+}
@@ -0,0 +1,346 @@
+//! Preview strips: every frame of one anim side by side in a PNG, labelled
+//! with the anim's name, frame numbers and timing, so pack authors can
+//! check a sheet (and share it) without running the frontend.
+//!
+//! The strip is cut from the same sheet the window would play, after the
+//! skin and any running season's sheet swaps are applied.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::limits::Limits;
+use crate::logs::log;
+use crate::pet_core::{self, AssetSource, FsAssets};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{aseprite, edit, fsutil, imaging, seasonal, AppPaths};
+
+pub(crate) const DEFAULT_SCALE: u32 = 2;
+const MAX_SCALE: u32 = 8;
+/// Screen pixels per font pixel.
+const TEXT_SCALE: u32 = 2;
+const PAD: u32 = 8;
+const CHECKER: u32 = 8;
+
+const BACKGROUND: Rgba<u8> = Rgba([245, 245, 245, 255]);
+const INK: Rgba<u8> = Rgba([51, 51, 51, 255]);
+const CHECKER_LIGHT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const CHECKER_DARK: Rgba<u8> = Rgba([221, 221, 221, 255]);
+
+/// One anim's frames, cut out of its sheet in play order.
+struct Frames {
+    size: (u32, u32),
+    /// Sheet index, duration and pixels of each frame.
+    cells: Vec<(u32, u32, RgbaImage)>,
+}
+
+fn cut(
+    name: &str,
+    sheet: &DynamicImage,
+    size: (u32, u32),
+    (margin, spacing): (u32, u32),
+    order: impl IntoIterator<Item = (u32, u32)>,
+) -> Result<Frames, String> {
+    let (cols, rows) = imaging::sheet_grid(name, sheet, size, margin, spacing)?;
+    let rgba = sheet.to_rgba8();
+    let (fw, fh) = size;
+    let mut cells = Vec::new();
+    for (index, ms) in order {
+        let (col, row) = (index % cols, index / cols);
+        if row >= rows {
+            return Err(format!(
+                "frame {index} is past the end of {name} ({cols}x{rows} frames)"
+            ));
+        }
+        let x = margin + col * (fw + spacing);
+        let y = margin + row * (fh + spacing);
+        cells.push((
+            index,
+            ms,
+            imageops::crop_imm(&rgba, x, y, fw, fh).to_image(),
+        ));
+    }
+    Ok(Frames { size, cells })
+}
+
+/// Frames of anim `key`; entries in `anims` win over Aseprite tags, as in
+/// `load_layers`.
+fn anim_frames(
+    src: &dyn AssetSource,
+    key: &str,
+    skin: Option<&str>,
+    limits: &Limits,
+) -> Result<Frames, String> {
+    let mut cfg = pet_core::read_layers_cfg(src, limits)?;
+    if let Some(name) = skin {
+        let sheet = cfg
+            .skins
+            .as_mut()
+            .and_then(|skins| skins.remove(name))
+            .ok_or_else(|| format!("layers.json: no skin named `{name}`"))?;
+        cfg.sprites = Some(sheet);
+    }
+    let scfg = cfg
+        .sprites
+        .ok_or_else(|| "layers.json: no sprites".to_string())?;
+    let sheet = match &scfg.aseprite {
+        Some(rel) => Some(aseprite::read(src, rel, limits)?),
+        None => None,
+    };
+    let size = pet_core::sprite_frame(&scfg, sheet.as_ref())?;
+
+    if let Some(acfg) = scfg.anims.as_ref().and_then(|anims| anims.get(key)) {
+        if !src.exists(&acfg.file) {
+            return Err(format!("anim `{key}`: {} not found", acfg.file));
+        }
+        let bytes = pet_core::read_asset(src, &acfg.file, limits)?;
+        let decoded = imaging::decode(Path::new(&acfg.file), &bytes)?;
+        let first = if acfg.addressed() {
+            pet_core::anim_first_frame(key, acfg, &decoded, size)?
+        } else {
+            0
+        };
+        let ms = 1000 / acfg.rate.unwrap_or(4).max(1);
+        let order = (first..first + acfg.frames.unwrap_or(1)).map(|i| (i, ms));
+        let padding = (acfg.margin.unwrap_or(0), acfg.spacing.unwrap_or(0));
+        return cut(&acfg.file, &decoded, size, padding, order);
+    }
+    if let Some(sheet) = &sheet {
+        if let Some(anim) = sheet.anims.iter().find(|a| a.key == key) {
+            let bytes = pet_core::read_asset(src, &sheet.image, limits)?;
+            let decoded = imaging::decode(Path::new(&sheet.image), &bytes)?;
+            let order = anim
+                .sequence
+                .iter()
+                .copied()
+                .zip(anim.durations_ms.iter().copied());
+            return cut(
+                &sheet.image,
+                &decoded,
+                size,
+                (sheet.margin, sheet.spacing),
+                order,
+            );
+        }
+    }
+    Err(format!("layers.json: no anim named `{key}`"))
+}
+
+// ── text ──
+
+/// 3×5 pixel glyphs, one row per entry, high bit on the left. Letters are
+/// upper case only; anything unknown draws as `?`.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0; 5],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+const ADVANCE: u32 = 4 * TEXT_SCALE;
+const LINE: u32 = 6 * TEXT_SCALE;
+
+fn text_width(text: &str) -> u32 {
+    (text.chars().count() as u32 * ADVANCE).saturating_sub(TEXT_SCALE)
+}
+
+fn draw_text(img: &mut RgbaImage, x: u32, y: u32, text: &str) {
+    for (n, c) in text.chars().enumerate() {
+        let gx = x + n as u32 * ADVANCE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                let (px, py) = (gx + col * TEXT_SCALE, y + row as u32 * TEXT_SCALE);
+                for dy in 0..TEXT_SCALE {
+                    for dx in 0..TEXT_SCALE {
+                        if px + dx < img.width() && py + dy < img.height() {
+                            img.put_pixel(px + dx, py + dy, INK);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ── strip ──
+
+/// Anim `key` as a labelled strip: a header with its name, frame count,
+/// frame size and loop length, then each frame `scale`× on a checkerboard
+/// with its sheet index (and duration, when frames differ) below.
+pub fn render_strip(
+    src: &dyn AssetSource,
+    key: &str,
+    skin: Option<&str>,
+    scale: u32,
+    limits: &Limits,
+) -> Result<RgbaImage, String> {
+    let frames = anim_frames(src, key, skin, limits)?;
+    if frames.cells.is_empty() {
+        return Err(format!("anim `{key}` has no frames"));
+    }
+    let scale = scale.clamp(1, MAX_SCALE);
+    let (fw, fh) = (frames.size.0 * scale, frames.size.1 * scale);
+    let uniform = frames.cells.windows(2).all(|w| w[0].1 == w[1].1);
+    let total_ms: u32 = frames.cells.iter().map(|c| c.1).sum();
+
+    let title = key.to_string();
+    let summary = format!(
+        "{} frames {}x{} {}ms",
+        frames.cells.len(),
+        frames.size.0,
+        frames.size.1,
+        total_ms
+    );
+    let labels: Vec<(String, Option<String>)> = frames
+        .cells
+        .iter()
+        .map(|(index, ms, _)| (format!("#{index}"), (!uniform).then(|| format!("{ms}ms"))))
+        .collect();
+    let column = labels
+        .iter()
+        .map(|(a, b)| text_width(a).max(b.as_deref().map_or(0, text_width)))
+        .fold(fw, u32::max);
+    let label_lines = if uniform { 1 } else { 2 };
+
+    let cells_width = PAD + frames.cells.len() as u32 * (column + PAD);
+    let width = cells_width.max(text_width(&title).max(text_width(&summary)) + PAD * 2);
+    let top = PAD + LINE * 2 + PAD;
+    let height = top + fh + TEXT_SCALE * 2 + LINE * label_lines + PAD;
+    let mut img = RgbaImage::from_pixel(width, height, BACKGROUND);
+    draw_text(&mut img, PAD, PAD, &title);
+    draw_text(&mut img, PAD, PAD + LINE, &summary);
+
+    for (n, ((_, _, frame), (index, ms))) in frames.cells.iter().zip(&labels).enumerate() {
+        let x = PAD + n as u32 * (column + PAD) + (column - fw) / 2;
+        for cy in 0..fh {
+            for cx in 0..fw {
+                let dark = (cx / CHECKER + cy / CHECKER) % 2 == 1;
+                let shade = if dark { CHECKER_DARK } else { CHECKER_LIGHT };
+                img.put_pixel(x + cx, top + cy, shade);
+            }
+        }
+        let scaled = imageops::resize(frame, fw, fh, FilterType::Nearest);
+        imageops::overlay(&mut img, &scaled, i64::from(x), i64::from(top));
+        let label_y = top + fh + TEXT_SCALE * 2;
+        let column_x = PAD + n as u32 * (column + PAD);
+        draw_text(&mut img, column_x, label_y, index);
+        if let Some(ms) = ms {
+            draw_text(&mut img, column_x, label_y + LINE, ms);
+        }
+    }
+    Ok(img)
+}
+
+/// Renders the strip and writes it to `out` as a PNG.
+pub(crate) fn write_strip(
+    src: &dyn AssetSource,
+    key: &str,
+    skin: Option<&str>,
+    scale: u32,
+    limits: &Limits,
+    out: &Path,
+) -> Result<(), String> {
+    let img = render_strip(src, key, skin, scale, limits)?;
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("{}: encode: {e}", out.display()))?;
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(fsutil::long_path(parent))
+            .map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    fsutil::write_atomic(out, &png.into_inner())?;
+    log!("🖼️ {key} preview → {}", out.display());
+    Ok(())
+}
+
+/// `.star-pet/previews/<key>.png`, with anything but letters, digits, `-`
+/// and `_` in the key replaced.
+pub(crate) fn default_path(data_dir: &Path, key: &str) -> PathBuf {
+    let stem: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    data_dir.join("previews").join(format!("{stem}.png"))
+}
+
+/// Writes the preview strip of anim `key` (default skin unless `skin` is
+/// given, `scale` 2) to `path`, by default under `.star-pet/previews/`.
+#[tauri::command]
+pub fn render_anim_preview(
+    key: String,
+    skin: Option<String>,
+    scale: Option<u32>,
+    path: Option<String>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    edits: tauri::State<'_, edit::EditSession>,
+) -> Result<PathBuf, String> {
+    let settings = settings.lock_or_recover().clone();
+    let skin = skin.or_else(|| settings.skin.clone());
+    let (layers_dir, data_dir) = {
+        let paths = paths.lock_or_recover();
+        (paths.layers_dir.clone(), paths.data_dir.clone())
+    };
+    let out = path.map_or_else(|| default_path(&data_dir, &key), PathBuf::from);
+    let scale = scale.unwrap_or(DEFAULT_SCALE);
+    edits.with_edits(&FsAssets::new(layers_dir), |src| {
+        seasonal::with_season(src, &settings, |src| {
+            write_strip(src, &key, skin.as_deref(), scale, &settings.limits, &out)
+        })
+    })?;
+    Ok(out)
+}
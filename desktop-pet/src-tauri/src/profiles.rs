@@ -0,0 +1,170 @@
+//! Profiles for several people sharing one machine, each with a pet of
+//! their own.
+//!
+//! A profile is a folder `profiles/<name>/` in the project root holding its
+//! `settings.json` (skin, integrations, everything else), and its own data
+//! folder `.star-pet/profiles/<name>/` (stats, logs, session, audit log).
+//! `state.json` and the asset pack stay shared. `default` is the root
+//! `settings.json` and `.star-pet/` themselves, as before profiles.
+//!
+//! The choice lives in `.star-pet/profile.json`; `STAR_PROFILE` overrides
+//! it for one launch. Switching restarts the app, so every integration
+//! comes up again with the new settings.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{fsutil, locate, shutdown, AppPaths};
+
+pub(crate) const DEFAULT: &str = "default";
+const DIR: &str = "profiles";
+const ACTIVE_FILE: &str = "profile.json";
+
+/// Managed by the app: the profile it runs as.
+pub(crate) struct Current(pub String);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ActiveFile {
+    name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    name: String,
+    active: bool,
+    settings_path: PathBuf,
+    data_dir: PathBuf,
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `.star-pet/` before any profile is applied.
+fn base_data_dir(root: &Path) -> PathBuf {
+    locate::resolve(root).data_dir.path
+}
+
+fn settings_path(root: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT {
+        root.join("settings.json")
+    } else {
+        root.join(DIR).join(name).join("settings.json")
+    }
+}
+
+fn data_dir(base: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT {
+        base.to_path_buf()
+    } else {
+        base.join(DIR).join(name)
+    }
+}
+
+fn chosen(base: &Path) -> String {
+    if let Some(name) = std::env::var("STAR_PROFILE").ok().filter(|n| !n.is_empty()) {
+        return name;
+    }
+    fsutil::read_text(&base.join(ACTIVE_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<ActiveFile>(&raw).ok())
+        .map(|f| f.name)
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| DEFAULT.into())
+}
+
+/// Points `paths` at the chosen profile's settings and data; an unknown
+/// profile falls back to `default`. Returns the profile in use.
+pub(crate) fn apply(paths: &mut AppPaths) -> String {
+    let name = chosen(&paths.data_dir);
+    if name == DEFAULT {
+        return name;
+    }
+    if !valid_name(&name) || !fsutil::exists(&paths.root.join(DIR).join(&name)) {
+        log!("⚠️ no profile `{name}`; using `{DEFAULT}`");
+        return DEFAULT.into();
+    }
+    paths.settings_path = settings_path(&paths.root, &name);
+    paths.data_dir = data_dir(&paths.data_dir, &name);
+    name
+}
+
+#[tauri::command]
+pub fn list_profiles(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    current: tauri::State<'_, Current>,
+) -> Vec<ProfileInfo> {
+    let root = paths.lock_or_recover().root.clone();
+    let base = base_data_dir(&root);
+    let mut names: Vec<String> = std::fs::read_dir(fsutil::long_path(&root.join(DIR)))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| valid_name(n) && n != DEFAULT)
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT.into());
+    names
+        .into_iter()
+        .map(|name| ProfileInfo {
+            active: name == current.0,
+            settings_path: settings_path(&root, &name),
+            data_dir: data_dir(&base, &name),
+            name,
+        })
+        .collect()
+}
+
+/// Makes `name` the profile for this and later launches, creating it
+/// (with empty settings) if it is new, and restarts into it.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, name: String) -> Result<(), String> {
+    if !valid_name(&name) {
+        return Err(format!(
+            "invalid profile name `{name}` (letters, digits, `-` and `_`)"
+        ));
+    }
+    if app.state::<Current>().0 == name {
+        return Ok(());
+    }
+    if std::env::var_os("STAR_PROFILE").is_some_and(|v| !v.is_empty()) {
+        return Err("STAR_PROFILE is set and would win after the restart".into());
+    }
+    let root = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .root
+        .clone();
+    if name != DEFAULT {
+        let settings = settings_path(&root, &name);
+        if !fsutil::exists(&settings) {
+            if let Some(dir) = settings.parent() {
+                std::fs::create_dir_all(fsutil::long_path(dir))
+                    .map_err(|e| format!("{}: {e}", dir.display()))?;
+            }
+            fsutil::write_atomic(&settings, b"{}\n")?;
+            log!("👤 new profile `{name}`");
+        }
+    }
+    let base = base_data_dir(&root);
+    std::fs::create_dir_all(fsutil::long_path(&base))
+        .map_err(|e| format!("{}: {e}", base.display()))?;
+    let file =
+        serde_json::to_vec_pretty(&ActiveFile { name: name.clone() }).map_err(|e| e.to_string())?;
+    fsutil::write_atomic(&base.join(ACTIVE_FILE), &file)?;
+
+    log!("👤 switching to profile `{name}`; restarting");
+    shutdown::run(&app);
+    app.restart()
+}
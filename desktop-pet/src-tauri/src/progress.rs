@@ -0,0 +1,380 @@
+//! Progress from several producers at once.
+//!
+//! Each producer (a download script, a build, a test suite) owns one
+//! named entry of `progress_sources` in `state.json`, written directly or
+//! through `report_progress`. Whenever sources are present, `progress` is
+//! their weighted mean, so the pet shows one bar for all of them.
+//!
+//! A source with `heartbeat_secs` promises to report at least that often.
+//! When it falls silent before finishing it is flagged `stale` on read, and
+//! the watchdog announces it once as `source-stale`.
+//!
+//! The aggregate and `stale` are worked out on the way out (see
+//! [`crate::present`]); `state.json` only ever holds what was reported.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::power;
+use crate::sync::LockExt;
+use crate::{read_state_file, AppPaths, PetState};
+
+/// How often the watchdog re-reads `state.json`.
+const WATCHDOG_POLL: Duration = Duration::from_secs(5);
+
+/// One producer's entry in `progress_sources`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProgressSource {
+    /// 0 to 1; values outside are clamped.
+    pub value: f64,
+    /// Relative share of the total, default 1. Zero or less leaves the
+    /// source out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Longest expected gap between updates, in seconds. Without it the
+    /// source never goes stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_secs: Option<u64>,
+    /// Last report (RFC 3339), stamped by `report_progress`; the state's
+    /// `updated_at` stands in when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Set on read once `heartbeat_secs` passed without an update.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+impl ProgressSource {
+    fn weight(&self) -> f64 {
+        self.weight.unwrap_or(1.0)
+    }
+
+    fn counts(&self) -> bool {
+        self.value.is_finite() && self.weight().is_finite() && self.weight() > 0.0
+    }
+
+    /// Seconds since the last report, if it is known.
+    fn silence(&self, fallback: Option<&str>) -> Option<i64> {
+        let at = self.updated_at.as_deref().or(fallback)?;
+        let at = chrono::DateTime::parse_from_rfc3339(at).ok()?;
+        Some((chrono::Local::now().fixed_offset() - at).num_seconds())
+    }
+
+    /// Finished sources are allowed to stop reporting.
+    fn overdue(&self, fallback: Option<&str>) -> bool {
+        let Some(heartbeat) = self.heartbeat_secs else {
+            return false;
+        };
+        self.value < 1.0
+            && self
+                .silence(fallback)
+                .is_some_and(|s| u64::try_from(s).is_ok_and(|s| s > heartbeat))
+    }
+}
+
+/// Weighted mean of the sources that count; `None` if none do.
+pub(crate) fn aggregate(sources: &BTreeMap<String, ProgressSource>) -> Option<f64> {
+    let (sum, total) =
+        sources
+            .values()
+            .filter(|s| s.counts())
+            .fold((0.0, 0.0), |(sum, total), s| {
+                (
+                    sum + s.value.clamp(0.0, 1.0) * s.weight(),
+                    total + s.weight(),
+                )
+            });
+    (total > 0.0).then(|| sum / total)
+}
+
+/// Replaces `progress` with the aggregate when there are sources, and
+/// refreshes each source's `stale` flag.
+pub(crate) fn resolve(state: &mut PetState) {
+    if state.progress_sources.is_empty() {
+        return;
+    }
+    state.progress = aggregate(&state.progress_sources);
+    for source in state.progress_sources.values_mut() {
+        source.stale = source.overdue(state.updated_at.as_deref());
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceShare {
+    name: String,
+    label: Option<String>,
+    value: f64,
+    weight: f64,
+    /// Fraction of the total weight, 0 for sources left out.
+    share: f64,
+    stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressBreakdown {
+    progress: Option<f64>,
+    sources: Vec<SourceShare>,
+}
+
+fn breakdown(state: &PetState) -> ProgressBreakdown {
+    let total: f64 = state
+        .progress_sources
+        .values()
+        .filter(|s| s.counts())
+        .map(ProgressSource::weight)
+        .sum();
+    let sources = state
+        .progress_sources
+        .iter()
+        .map(|(name, s)| SourceShare {
+            name: name.clone(),
+            label: s.label.clone(),
+            value: s.value,
+            weight: s.weight(),
+            share: if s.counts() && total > 0.0 {
+                s.weight() / total
+            } else {
+                0.0
+            },
+            stale: s.stale,
+        })
+        .collect();
+    ProgressBreakdown {
+        progress: state.progress,
+        sources,
+    }
+}
+
+/// Sets or, with no `value`, removes one source, keeping the rest of
+/// `state.json` as it is. The breakdown is of the state as read back.
+#[tauri::command]
+pub fn report_progress(
+    source: String,
+    value: Option<f64>,
+    weight: Option<f64>,
+    label: Option<String>,
+    heartbeat_secs: Option<u64>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<ProgressBreakdown, String> {
+    if source.trim().is_empty() {
+        return Err("report_progress: `source` must not be empty".into());
+    }
+    if let Some(v) = value.filter(|v| !v.is_finite()) {
+        return Err(format!("report_progress: value must be a number, got {v}"));
+    }
+    let store = FsStateStore::new(paths.lock_or_recover().state_path.clone());
+    let mut state = store.load()?;
+    let now = chrono::Local::now().to_rfc3339();
+    match value {
+        Some(value) => {
+            state.progress_sources.insert(
+                source,
+                ProgressSource {
+                    value,
+                    weight,
+                    label,
+                    heartbeat_secs,
+                    updated_at: Some(now.clone()),
+                    stale: false,
+                },
+            );
+        }
+        None => {
+            state.progress_sources.remove(&source);
+        }
+    }
+    state.updated_at = Some(now);
+    store.save(&state)?;
+    resolve(&mut state);
+    Ok(breakdown(&state))
+}
+
+#[tauri::command]
+pub fn get_progress_breakdown(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<ProgressBreakdown, String> {
+    let state_path = paths.lock_or_recover().state_path.clone();
+    let mut state = FsStateStore::new(state_path).load()?;
+    resolve(&mut state);
+    Ok(breakdown(&state))
+}
+
+// ── watchdog ──
+
+#[derive(Debug, Clone, Serialize)]
+struct StaleSource {
+    source: String,
+    label: Option<String>,
+    heartbeat_secs: u64,
+    /// Seconds since the last report.
+    silent_secs: i64,
+}
+
+/// Announces `source-stale` the first time a source goes quiet; it is
+/// announced again only after it has reported in between.
+pub(crate) fn spawn_watchdog(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut stale = BTreeSet::new();
+        loop {
+            let state_path = app
+                .state::<Mutex<AppPaths>>()
+                .lock_or_recover()
+                .state_path
+                .clone();
+            // Mid-write or missing: keep what we knew until the next round.
+            if let Ok(mut state) = read_state_file(&state_path) {
+                resolve(&mut state);
+                let fallback = state.updated_at.as_deref();
+                let now: BTreeSet<String> = state
+                    .progress_sources
+                    .iter()
+                    .filter(|(_, s)| s.stale)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                for name in now.difference(&stale) {
+                    let s = &state.progress_sources[name];
+                    let payload = StaleSource {
+                        source: name.clone(),
+                        label: s.label.clone(),
+                        heartbeat_secs: s.heartbeat_secs.unwrap_or_default(),
+                        silent_secs: s.silence(fallback).unwrap_or_default(),
+                    };
+                    log!(
+                        "⏱️ progress source `{name}` silent for {}s",
+                        payload.silent_secs
+                    );
+                    let _ = app.emit("source-stale", payload);
+                }
+                stale = now;
+            }
+            std::thread::sleep(power::interval(&app, WATCHDOG_POLL));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(value: f64, weight: Option<f64>) -> ProgressSource {
+        ProgressSource {
+            value,
+            weight,
+            label: None,
+            heartbeat_secs: None,
+            updated_at: None,
+            stale: false,
+        }
+    }
+
+    fn sources(list: &[(&str, ProgressSource)]) -> BTreeMap<String, ProgressSource> {
+        list.iter()
+            .map(|(name, s)| (name.to_string(), s.clone()))
+            .collect()
+    }
+
+    fn ago(secs: i64) -> String {
+        (chrono::Local::now() - chrono::Duration::seconds(secs)).to_rfc3339()
+    }
+
+    fn state(progress_sources: BTreeMap<String, ProgressSource>) -> PetState {
+        PetState {
+            state: "building".into(),
+            detail: None,
+            progress: Some(0.9),
+            updated_at: None,
+            progress_sources,
+        }
+    }
+
+    #[test]
+    fn aggregate_is_the_weighted_mean() {
+        let all = sources(&[
+            ("download", source(0.5, None)),
+            ("build", source(1.0, Some(3.0))),
+        ]);
+        assert_eq!(aggregate(&all), Some((0.5 + 3.0) / 4.0));
+        assert_eq!(aggregate(&BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn zero_negative_and_broken_weights_are_left_out() {
+        let all = sources(&[
+            ("a", source(0.2, None)),
+            ("zero", source(1.0, Some(0.0))),
+            ("negative", source(1.0, Some(-2.0))),
+            ("nan", source(f64::NAN, None)),
+            ("inf", source(1.0, Some(f64::INFINITY))),
+        ]);
+        assert_eq!(aggregate(&all), Some(0.2));
+        let none = sources(&[("zero", source(1.0, Some(0.0)))]);
+        assert_eq!(aggregate(&none), None);
+    }
+
+    #[test]
+    fn values_are_clamped() {
+        let all = sources(&[("over", source(4.0, None)), ("under", source(-1.0, None))]);
+        assert_eq!(aggregate(&all), Some(0.5));
+    }
+
+    #[test]
+    fn resolve_replaces_progress_only_with_sources() {
+        let mut plain = state(BTreeMap::new());
+        resolve(&mut plain);
+        assert_eq!(plain.progress, Some(0.9));
+
+        let mut tracked = state(sources(&[("a", source(0.25, None))]));
+        resolve(&mut tracked);
+        assert_eq!(tracked.progress, Some(0.25));
+
+        // Every source left out: no bar rather than the stored value.
+        let mut ignored = state(sources(&[("a", source(0.25, Some(0.0)))]));
+        resolve(&mut ignored);
+        assert_eq!(ignored.progress, None);
+    }
+
+    #[test]
+    fn silent_sources_go_stale_until_they_finish() {
+        let beat = |value: f64, updated_at: Option<String>| ProgressSource {
+            heartbeat_secs: Some(10),
+            updated_at,
+            ..source(value, None)
+        };
+        let mut s = state(sources(&[
+            ("quiet", beat(0.5, Some(ago(60)))),
+            ("recent", beat(0.5, Some(ago(2)))),
+            ("done", beat(1.0, Some(ago(60)))),
+            ("fallback", beat(0.5, None)),
+            ("unknown", beat(0.5, Some("not a date".into()))),
+            (
+                "no_heartbeat",
+                ProgressSource {
+                    updated_at: Some(ago(3600)),
+                    ..source(0.5, None)
+                },
+            ),
+        ]));
+        s.updated_at = Some(ago(30));
+        resolve(&mut s);
+        let stale: Vec<&str> = s
+            .progress_sources
+            .iter()
+            .filter(|(_, src)| src.stale)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(stale, ["fallback", "quiet"]);
+
+        // Reporting again clears the flag on the next read.
+        s.progress_sources.get_mut("quiet").unwrap().updated_at = Some(ago(0));
+        resolve(&mut s);
+        assert!(!s.progress_sources["quiet"].stale);
+    }
+}
@@ -0,0 +1,166 @@
+//! Community pack registry: a JSON index at a configurable URL listing
+//! downloadable bundles, installed through [`crate::bundle`] after their
+//! SHA-256 is checked.
+//!
+//! ```json
+//! { "packs": [ { "id": "night-office", "name": "Night Office", "kind": "theme",
+//!   "preview": "https://…/preview.png", "url": "https://…/night-office.zip",
+//!   "sha256": "…" } ] }
+//! ```
+//!
+//! Downloads need the `registry` feature.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::bundle::{self, Imported};
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{jobs, net, settings, AppPaths};
+
+const MAX_INDEX_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_PREVIEW_BYTES: u64 = 2 * 1024 * 1024;
+
+/// `registry` block of `settings.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RegistrySettings {
+    /// URL of the pack index; the store is disabled while unset.
+    pub index_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackEntry {
+    id: String,
+    name: String,
+    /// `theme` or `sprites`.
+    kind: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    preview: Option<String>,
+    url: String,
+    sha256: String,
+    /// Filled in by `list_registry_packs` when previews are requested.
+    #[serde(default, skip_deserializing)]
+    preview_data_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Index {
+    packs: Vec<PackEntry>,
+}
+
+/// Plain HTTP is only accepted for local test servers.
+fn check_url(url: &str) -> Result<(), String> {
+    let local = ["http://127.0.0.1", "http://localhost"]
+        .iter()
+        .any(|p| url.starts_with(p));
+    if url.starts_with("https://") || local {
+        Ok(())
+    } else {
+        Err(format!("refusing non-HTTPS URL {url}"))
+    }
+}
+
+fn fetch_index(paths: &AppPaths) -> Result<Vec<PackEntry>, String> {
+    if !cfg!(feature = "registry") {
+        return Err("this build has no pack registry (rebuild with `--features registry`)".into());
+    }
+    let url = settings::load(&paths.settings_path)
+        .registry
+        .index_url
+        .ok_or_else(|| {
+            "no pack registry configured (settings.json registry.index_url)".to_string()
+        })?;
+    check_url(&url)?;
+    let raw = net::get(&url, MAX_INDEX_BYTES)?;
+    let index: Index = serde_json::from_slice(&raw).map_err(|e| format!("{url}: {e}"))?;
+    Ok(index.packs)
+}
+
+// ── commands ──
+
+/// Lists the registry's packs; with `previews`, each preview image is
+/// fetched and inlined as a data URL for the webview.
+#[tauri::command]
+pub async fn list_registry_packs(
+    previews: Option<bool>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<Vec<PackEntry>, String> {
+    let paths = paths.lock_or_recover().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut packs = fetch_index(&paths)?;
+        if previews.unwrap_or(false) {
+            for pack in &mut packs {
+                let Some(url) = pack.preview.as_deref() else {
+                    continue;
+                };
+                match check_url(url).and_then(|_| net::get(url, MAX_PREVIEW_BYTES)) {
+                    Ok(bytes) => {
+                        let rel = url.split(['?', '#']).next().unwrap_or(url);
+                        pack.preview_data_url = Some(crate::pet_core::encode_bytes(rel, &bytes));
+                    }
+                    Err(e) => log!("⚠️ preview for {}: {e}", pack.id),
+                }
+            }
+        }
+        Ok(packs)
+    })
+    .await
+    .map_err(|e| format!("registry task: {e}"))?
+}
+
+/// Downloads a pack by id, verifies its SHA-256 against the index and
+/// installs it as the active pack, as an `install_pack` job.
+#[tauri::command]
+pub async fn install_registry_pack(
+    app: AppHandle,
+    id: String,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<Imported, String> {
+    let paths = paths.lock_or_recover().clone();
+    let handle = app.clone();
+    jobs::run(&app, "install_pack", id.clone(), move |job| {
+        job.progress(0.0, "downloading");
+        let pack = fetch_index(&paths)?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("no pack `{id}` in the registry"))?;
+        check_url(&pack.url)?;
+        let limits = settings::load(&paths.settings_path).limits;
+        let bytes = net::get(&pack.url, limits.max_bundle_bytes)?;
+        job.check()?;
+        let actual = sha256_hex(&bytes);
+        if !actual.eq_ignore_ascii_case(pack.sha256.trim()) {
+            return Err(format!(
+                "{}: SHA-256 mismatch (expected {}, got {actual})",
+                pack.url, pack.sha256
+            ));
+        }
+        let imported = bundle::import(&paths, &pack.name, &bytes, job)?;
+        bundle::activated(&handle);
+        Ok(imported)
+    })
+    .await
+}
+
+#[cfg(feature = "registry")]
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(not(feature = "registry"))]
+fn sha256_hex(_: &[u8]) -> String {
+    String::new()
+}
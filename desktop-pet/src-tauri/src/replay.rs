@@ -0,0 +1,198 @@
+//! Record the pet's event timeline to a JSON-lines file and play it back.
+//!
+//! Each line is one event with its offset from the start of the recording:
+//!
+//! ```json
+//! {"t_ms":1520,"event":"state-changed","payload":{"state":"writing",...}}
+//! ```
+//!
+//! Playback re-emits the events with the original spacing (scaled by
+//! `speed`), so a bug report or a demo video can be reproduced exactly.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition};
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+/// Window moves are recorded under this name; playback also moves the
+/// window so the pet walks the same path.
+pub(crate) const WINDOW_MOVED: &str = "window-moved";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    t_ms: u64,
+    event: String,
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct WindowMoved {
+    pub label: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+struct Recording {
+    path: PathBuf,
+    started: Instant,
+    out: BufWriter<File>,
+    events: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordingSummary {
+    path: PathBuf,
+    events: u64,
+    millis: u64,
+}
+
+/// Managed by the app; `None` while nothing is being recorded.
+#[derive(Default)]
+pub(crate) struct Recorder(Mutex<Option<Recording>>);
+
+impl Recorder {
+    /// Appends an event to the active recording, if any. Cheap no-op
+    /// otherwise, so emit sites can call it unconditionally.
+    pub(crate) fn capture<T: Serialize>(&self, event: &str, payload: &T) {
+        let mut active = self.0.lock_or_recover();
+        let Some(rec) = active.as_mut() else {
+            return;
+        };
+        let entry = Entry {
+            t_ms: rec.started.elapsed().as_millis() as u64,
+            event: event.to_string(),
+            payload: serde_json::to_value(payload).unwrap_or_default(),
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(rec.out, "{line}").map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => rec.events += 1,
+            Err(e) => log!("⚠️ recording {}: {e}", rec.path.display()),
+        }
+    }
+}
+
+// ── commands ──
+
+/// Starts a new recording, at `path` or under `.star-pet/recordings/`.
+/// The current state is written first so playback starts from it.
+#[tauri::command]
+pub fn start_recording(
+    path: Option<String>,
+    recorder: tauri::State<'_, Recorder>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Result<PathBuf, String> {
+    let mut active = recorder.0.lock_or_recover();
+    if let Some(rec) = active.as_ref() {
+        return Err(format!("already recording to {}", rec.path.display()));
+    }
+    let (data_dir, state_path) = {
+        let p = paths.lock_or_recover();
+        (p.data_dir.clone(), p.state_path.clone())
+    };
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => data_dir.join("recordings").join(format!(
+            "{}.jsonl",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        )),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(fsutil::long_path(parent))
+            .map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    let file =
+        File::create(fsutil::long_path(&path)).map_err(|e| format!("{}: {e}", path.display()))?;
+    *active = Some(Recording {
+        path: path.clone(),
+        started: Instant::now(),
+        out: BufWriter::new(file),
+        events: 0,
+    });
+    drop(active);
+
+    if let Ok(state) = crate::read_state_file(&state_path).map(crate::present) {
+        recorder.capture("state-changed", &state);
+    }
+    log!("⏺️ recording to {}", path.display());
+    Ok(path)
+}
+
+#[tauri::command]
+pub fn stop_recording(recorder: tauri::State<'_, Recorder>) -> Result<RecordingSummary, String> {
+    let mut rec = recorder
+        .0
+        .lock_or_recover()
+        .take()
+        .ok_or_else(|| "not recording".to_string())?;
+    rec.out
+        .flush()
+        .map_err(|e| format!("{}: {e}", rec.path.display()))?;
+    log!("⏹️ {} events → {}", rec.events, rec.path.display());
+    Ok(RecordingSummary {
+        path: rec.path,
+        events: rec.events,
+        millis: rec.started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Re-emits a recording in the background. `speed` 2.0 plays twice as
+/// fast; defaults to real time.
+#[tauri::command]
+pub fn play_recording(app: AppHandle, path: String, speed: Option<f64>) -> Result<usize, String> {
+    let speed = speed.unwrap_or(1.0);
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(format!("speed must be a positive number, got {speed}"));
+    }
+    let entries = read_entries(Path::new(&path))?;
+    let count = entries.len();
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        for entry in entries {
+            let due = Duration::from_secs_f64(entry.t_ms as f64 / 1000.0 / speed);
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            replay(&app, entry);
+        }
+        let _ = app.emit("replay-finished", &path);
+    });
+    Ok(count)
+}
+
+fn read_entries(path: &Path) -> Result<Vec<Entry>, String> {
+    let file =
+        File::open(fsutil::long_path(path)).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {e}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| format!("{}:{}: {e}", path.display(), n + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn replay(app: &AppHandle, entry: Entry) {
+    if entry.event == WINDOW_MOVED {
+        if let Ok(moved) = serde_json::from_value::<WindowMoved>(entry.payload.clone()) {
+            if let Some(window) = app.get_webview_window(&moved.label) {
+                let _ = window.set_position(PhysicalPosition::new(moved.x, moved.y));
+            }
+        }
+    }
+    if let Err(e) = app.emit(&entry.event, entry.payload) {
+        log!("⚠️ replay {}: {e}", entry.event);
+    }
+}
@@ -0,0 +1,257 @@
+//! Productivity report over a date range, from the local stats store:
+//! time per state and tasks completed (see [`crate::timeline`]), active
+//! time and breaks (see [`crate::breaks`]). Written as Markdown or a
+//! standalone HTML page under `.star-pet/reports/`.
+
+use chrono::{Duration as Days, Local, NaiveDate};
+use serde_json::Map;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::breaks::{self, BreakStats};
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::timeline::{self, StateHistory};
+use crate::{fsutil, stats, AppPaths};
+
+#[derive(Debug)]
+pub(crate) struct Report {
+    pub(crate) from: NaiveDate,
+    pub(crate) to: NaiveDate,
+    /// (label, seconds), longest first.
+    pub(crate) states: Vec<(String, u64)>,
+    pub(crate) tracked_secs: u64,
+    pub(crate) tasks_completed: u32,
+    pub(crate) active_secs: u64,
+    pub(crate) longest_streak_mins: u64,
+    pub(crate) reminders: u32,
+    pub(crate) breaks: u32,
+    /// (date, tracked seconds, tasks completed) per day with any data.
+    pub(crate) days: Vec<(String, u64, u32)>,
+}
+
+/// `today`, `week` (last 7 days), `month` (last 30 days) or
+/// `YYYY-MM-DD..YYYY-MM-DD`.
+fn parse_range(range: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let today = Local::now().date_naive();
+    let back = |n: i64| Ok((today - Days::days(n - 1), today));
+    match range {
+        "today" | "day" => back(1),
+        "week" => back(7),
+        "month" => back(30),
+        custom => {
+            let (from, to) = custom
+                .split_once("..")
+                .ok_or_else(|| format!("unknown range `{custom}`"))?;
+            let parse = |d: &str| {
+                NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d")
+                    .map_err(|e| format!("range `{custom}`: {e}"))
+            };
+            let (from, to) = (parse(from)?, parse(to)?);
+            if from > to {
+                return Err(format!("range `{custom}` ends before it starts"));
+            }
+            Ok((from, to))
+        }
+    }
+}
+
+pub(crate) fn compile(
+    paths: &AppPaths,
+    from: NaiveDate,
+    to: NaiveDate,
+    label: impl Fn(&str) -> String,
+) -> Report {
+    let history: StateHistory = stats::load(&paths.data_dir, timeline::STATS_NAME);
+    let breaks: BreakStats = stats::load(&paths.data_dir, breaks::STATS_NAME);
+    let (lo, hi) = (from.to_string(), to.to_string());
+    let mut report = Report {
+        from,
+        to,
+        states: Vec::new(),
+        tracked_secs: 0,
+        tasks_completed: 0,
+        active_secs: 0,
+        longest_streak_mins: 0,
+        reminders: 0,
+        breaks: 0,
+        days: Vec::new(),
+    };
+
+    let mut per_state: BTreeMap<String, u64> = BTreeMap::new();
+    for (date, day) in history.days.range(lo.clone()..=hi.clone()) {
+        let total: u64 = day.seconds.values().sum();
+        for (state, secs) in &day.seconds {
+            *per_state.entry(state.clone()).or_default() += secs;
+        }
+        report.tracked_secs += total;
+        report.tasks_completed += day.tasks_completed;
+        report.days.push((date.clone(), total, day.tasks_completed));
+    }
+    let mut states: Vec<(String, u64)> = per_state
+        .into_iter()
+        .map(|(state, secs)| (label(&state), secs))
+        .collect();
+    states.sort_by(|a, b| b.1.cmp(&a.1));
+    report.states = states;
+
+    for day in breaks.days.range(lo..=hi).map(|(_, d)| d) {
+        report.active_secs += day.active_secs;
+        report.longest_streak_mins = report.longest_streak_mins.max(day.longest_streak_mins);
+        report.reminders += day.reminders;
+        report.breaks += day.breaks;
+    }
+    report
+}
+
+pub(crate) fn hours(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+}
+
+fn percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        "–".into()
+    } else {
+        format!("{:.0}%", part as f64 * 100.0 / whole as f64)
+    }
+}
+
+pub(crate) fn markdown(r: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Star Office report: {} – {}\n", r.from, r.to);
+    let _ = writeln!(out, "## Summary\n");
+    let _ = writeln!(out, "- Tracked time: {}", hours(r.tracked_secs));
+    let _ = writeln!(out, "- Tasks completed: {}", r.tasks_completed);
+    let _ = writeln!(out, "- Active at the computer: {}", hours(r.active_secs));
+    let _ = writeln!(
+        out,
+        "- Longest streak without a break: {} min",
+        r.longest_streak_mins
+    );
+    let _ = writeln!(
+        out,
+        "- Break reminders: {} (breaks taken: {})\n",
+        r.reminders, r.breaks
+    );
+
+    let _ = writeln!(out, "## Time per state\n");
+    let _ = writeln!(out, "| State | Time | Share |\n|---|---|---|");
+    for (state, secs) in &r.states {
+        let _ = writeln!(
+            out,
+            "| {state} | {} | {} |",
+            hours(*secs),
+            percent(*secs, r.tracked_secs)
+        );
+    }
+    let _ = writeln!(out, "\n## Per day\n");
+    let _ = writeln!(out, "| Date | Tracked | Tasks |\n|---|---|---|");
+    for (date, secs, tasks) in &r.days {
+        let _ = writeln!(out, "| {date} | {} | {tasks} |", hours(*secs));
+    }
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn html(r: &Report) -> String {
+    let rows = |cells: &[String]| {
+        let tds: String = cells
+            .iter()
+            .map(|c| format!("<td>{}</td>", escape(c)))
+            .collect();
+        format!("<tr>{tds}</tr>")
+    };
+    let states: String = r
+        .states
+        .iter()
+        .map(|(s, secs)| rows(&[s.clone(), hours(*secs), percent(*secs, r.tracked_secs)]))
+        .collect();
+    let days: String = r
+        .days
+        .iter()
+        .map(|(d, secs, tasks)| rows(&[d.clone(), hours(*secs), tasks.to_string()]))
+        .collect();
+    format!(
+        r#"<!doctype html>
+<html><head><meta charset="utf-8"><title>Star Office report {from} – {to}</title>
+<style>body{{font-family:sans-serif;max-width:720px;margin:2em auto;color:#222}}
+table{{border-collapse:collapse;width:100%;margin-bottom:1.5em}}
+td,th{{border-bottom:1px solid #ddd;padding:4px 8px;text-align:left}}
+h1{{color:#e94560}}</style></head><body>
+<h1>Star Office report: {from} – {to}</h1>
+<ul>
+<li>Tracked time: {tracked}</li>
+<li>Tasks completed: {tasks}</li>
+<li>Active at the computer: {active}</li>
+<li>Longest streak without a break: {streak} min</li>
+<li>Break reminders: {reminders} (breaks taken: {breaks})</li>
+</ul>
+<h2>Time per state</h2>
+<table><tr><th>State</th><th>Time</th><th>Share</th></tr>{states}</table>
+<h2>Per day</h2>
+<table><tr><th>Date</th><th>Tracked</th><th>Tasks</th></tr>{days}</table>
+</body></html>
+"#,
+        from = r.from,
+        to = r.to,
+        tracked = hours(r.tracked_secs),
+        tasks = r.tasks_completed,
+        active = hours(r.active_secs),
+        streak = r.longest_streak_mins,
+        reminders = r.reminders,
+        breaks = r.breaks,
+    )
+}
+
+/// The localised name with the raw one after it, or just the raw one.
+pub(crate) fn state_label(i18n: &I18n, state: &str) -> String {
+    let key = format!("state.{state}");
+    let text = i18n.t(&key, &Map::new());
+    if text == key {
+        state.to_string()
+    } else {
+        format!("{text} ({state})")
+    }
+}
+
+pub(crate) fn dir(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("reports")
+}
+
+/// `format` is `markdown` (default) or `html`; returns the written file.
+#[tauri::command]
+pub fn generate_report(
+    range: String,
+    format: Option<String>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    i18n: tauri::State<'_, I18n>,
+) -> Result<PathBuf, String> {
+    let (from, to) = parse_range(&range)?;
+    let paths = paths.lock_or_recover().clone();
+    let report = compile(&paths, from, to, |state| state_label(&i18n, state));
+    let (body, ext) = match format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => (markdown(&report), "md"),
+        "html" => (html(&report), "html"),
+        other => {
+            return Err(format!(
+                "unknown report format `{other}` (markdown or html)"
+            ))
+        }
+    };
+
+    let dir = dir(&paths);
+    std::fs::create_dir_all(fsutil::long_path(&dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(format!("report-{from}_{to}.{ext}"));
+    fsutil::write_atomic(&path, body.as_bytes())?;
+    log!("📊 report → {}", path.display());
+    Ok(path)
+}
@@ -0,0 +1,547 @@
+//! Keyboard and LED colours that follow the pet's state, through an
+//! OpenRGB server or Razer Chroma (built with `--features rgb`).
+//!
+//! `rgb.states` maps state names to a colour, optionally pulsing; states
+//! without an entry use `rgb.default`, or leave the lights alone when that
+//! is unset too. A thread paints the lights about ten times a second while
+//! something pulses, and only on changes otherwise; it reconnects when the
+//! server goes away and lets go of the devices on exit.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::PetState;
+
+/// Name of the state being shown, `None` before the first.
+static STATE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RgbBackend {
+    OpenRgb,
+    Chroma,
+}
+
+/// `rgb` block of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RgbSettings {
+    pub enabled: bool,
+    pub backend: RgbBackend,
+    /// OpenRGB SDK server.
+    pub host: String,
+    pub port: u16,
+    /// Razer Chroma REST endpoint.
+    pub chroma_url: String,
+    /// OpenRGB: parts of device names, all devices when empty. Chroma:
+    /// `keyboard`, `mouse`, `mousepad`, `headset`, `keypad`, `chromalink`;
+    /// all of them when empty.
+    pub devices: Vec<String>,
+    /// 0 to 1, applied to every colour.
+    pub brightness: f64,
+    pub states: BTreeMap<String, Lighting>,
+    pub default: Option<Lighting>,
+}
+
+impl Default for RgbSettings {
+    fn default() -> Self {
+        let red = Lighting {
+            color: "#ff0000".into(),
+            ..Lighting::default()
+        };
+        let blue_pulse = Lighting {
+            color: "#1e64ff".into(),
+            pulse: true,
+            ..Lighting::default()
+        };
+        RgbSettings {
+            enabled: false,
+            backend: RgbBackend::OpenRgb,
+            host: "127.0.0.1".into(),
+            port: 6742,
+            chroma_url: "http://localhost:54235/razer/chromasdk".into(),
+            devices: Vec::new(),
+            brightness: 1.0,
+            states: BTreeMap::from([
+                ("error".into(), red.clone()),
+                ("failed".into(), red),
+                ("building".into(), blue_pulse),
+            ]),
+            default: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Lighting {
+    /// `#rrggbb`.
+    pub color: String,
+    /// Breathe between dim and full instead of holding the colour.
+    pub pulse: bool,
+    pub period_ms: u64,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            color: "#ffffff".into(),
+            pulse: false,
+            period_ms: 1500,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "rgb"), allow(dead_code))]
+fn parse_color(raw: &str) -> Option<[u8; 3]> {
+    let hex = raw.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Colour for the current state `elapsed_ms` into it, `None` to leave the
+/// lights alone.
+#[cfg_attr(not(feature = "rgb"), allow(dead_code))]
+fn color_at(cfg: &RgbSettings, elapsed_ms: u64) -> Option<[u8; 3]> {
+    let state = STATE.lock_or_recover().clone()?;
+    let lighting = cfg.states.get(&state).or(cfg.default.as_ref())?;
+    let base = parse_color(&lighting.color)?;
+    let mut level = cfg.brightness.clamp(0.0, 1.0);
+    if lighting.pulse {
+        let phase =
+            (elapsed_ms % lighting.period_ms.max(100)) as f64 / lighting.period_ms.max(100) as f64;
+        level *= 0.15 + 0.85 * (0.5 - 0.5 * (phase * TAU).cos());
+    }
+    Some(base.map(|c| (f64::from(c) * level).round() as u8))
+}
+
+/// Reports colours that can't be shown, once per settings change.
+#[cfg_attr(not(feature = "rgb"), allow(dead_code))]
+fn check_colors(cfg: &RgbSettings) {
+    let named = cfg.states.iter().map(|(k, l)| (k.as_str(), l));
+    for (name, lighting) in named.chain(cfg.default.iter().map(|l| ("default", l))) {
+        if parse_color(&lighting.color).is_none() {
+            log!("⚠️ rgb.{name}: `{}` isn't #rrggbb", lighting.color);
+        }
+    }
+}
+
+/// Follows every announced state.
+pub(crate) fn show(state: &PetState) {
+    *STATE.lock_or_recover() = Some(state.state.clone());
+}
+
+/// Gives the devices back (Chroma returns them to Synapse) on exit.
+pub(crate) fn stop() {
+    imp::stop();
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let enabled = app.state::<Mutex<Settings>>().lock_or_recover().rgb.enabled;
+    imp::start(app, enabled);
+}
+
+#[cfg(feature = "rgb")]
+mod imp {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use tauri::{AppHandle, Manager};
+
+    use super::{check_colors, color_at, RgbBackend, RgbSettings, STATE};
+    use crate::logs::log;
+    use crate::settings::Settings;
+    use crate::sync::LockExt;
+    use crate::{health, net};
+
+    const FRAME: Duration = Duration::from_millis(100);
+    const RETRY: Duration = Duration::from_secs(10);
+    /// Chroma drops a session that goes quiet for 15 s.
+    const HEARTBEAT: Duration = Duration::from_secs(5);
+    const CHROMA_DEVICES: &[&str] = &[
+        "keyboard",
+        "mouse",
+        "mousepad",
+        "headset",
+        "keypad",
+        "chromalink",
+    ];
+
+    static CLIENT: Mutex<Option<Client>> = Mutex::new(None);
+    static STOPPED: AtomicBool = AtomicBool::new(false);
+
+    // ── OpenRGB SDK protocol (version 0) ──
+
+    const REQUEST_CONTROLLER_COUNT: u32 = 0;
+    const REQUEST_CONTROLLER_DATA: u32 = 1;
+    const SET_CLIENT_NAME: u32 = 50;
+    const UPDATE_LEDS: u32 = 1050;
+    const SET_CUSTOM_MODE: u32 = 1100;
+
+    struct OpenRgb {
+        stream: TcpStream,
+        /// Controller index and LED count of each selected device.
+        devices: Vec<(u32, u16)>,
+    }
+
+    /// Little-endian reader over one controller description.
+    struct Fields<'a>(&'a [u8]);
+
+    impl Fields<'_> {
+        fn take(&mut self, n: usize) -> Result<&[u8], String> {
+            if self.0.len() < n {
+                return Err("OpenRGB: truncated controller data".into());
+            }
+            let (head, rest) = self.0.split_at(n);
+            self.0 = rest;
+            Ok(head)
+        }
+
+        fn u16(&mut self) -> Result<u16, String> {
+            let b = self.take(2)?;
+            Ok(u16::from_le_bytes([b[0], b[1]]))
+        }
+
+        fn u32(&mut self) -> Result<u32, String> {
+            let b = self.take(4)?;
+            Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        }
+
+        /// Length-prefixed, NUL-terminated.
+        fn string(&mut self) -> Result<String, String> {
+            let len = usize::from(self.u16()?);
+            let raw = self.take(len)?;
+            Ok(String::from_utf8_lossy(raw)
+                .trim_end_matches('\0')
+                .to_string())
+        }
+    }
+
+    /// Name and LED count from `REQUEST_CONTROLLER_DATA`.
+    fn parse_controller(data: &[u8]) -> Result<(String, u16), String> {
+        let mut f = Fields(data);
+        f.u32()?; // data size
+        f.u32()?; // type
+        let name = f.string()?;
+        for _ in 0..4 {
+            f.string()?; // description, version, serial, location
+        }
+        let modes = f.u16()?;
+        f.u32()?; // active mode
+        for _ in 0..modes {
+            f.string()?;
+            f.take(9 * 4)?; // value, flags, speed and colour ranges, …
+            let colors = usize::from(f.u16()?);
+            f.take(colors * 4)?;
+        }
+        let zones = f.u16()?;
+        for _ in 0..zones {
+            f.string()?;
+            f.take(4 * 4)?; // type, LED counts
+            let matrix = usize::from(f.u16()?);
+            f.take(matrix)?;
+        }
+        let leds = f.u16()?;
+        for _ in 0..leds {
+            f.string()?;
+            f.u32()?;
+        }
+        Ok((name, f.u16()?))
+    }
+
+    impl OpenRgb {
+        fn send(&mut self, device: u32, id: u32, payload: &[u8]) -> Result<(), String> {
+            let mut packet = Vec::with_capacity(16 + payload.len());
+            packet.extend_from_slice(b"ORGB");
+            packet.extend_from_slice(&device.to_le_bytes());
+            packet.extend_from_slice(&id.to_le_bytes());
+            packet.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            packet.extend_from_slice(payload);
+            self.stream
+                .write_all(&packet)
+                .map_err(|e| format!("OpenRGB: {e}"))
+        }
+
+        /// Body of the next packet `id`; others (device list updates) are
+        /// skipped.
+        fn reply(&mut self, id: u32) -> Result<Vec<u8>, String> {
+            loop {
+                let mut header = [0u8; 16];
+                self.stream
+                    .read_exact(&mut header)
+                    .map_err(|e| format!("OpenRGB: {e}"))?;
+                if &header[..4] != b"ORGB" {
+                    return Err("OpenRGB: not an SDK server".into());
+                }
+                let word = |at: usize| {
+                    u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+                };
+                let mut body = vec![0u8; word(12) as usize];
+                self.stream
+                    .read_exact(&mut body)
+                    .map_err(|e| format!("OpenRGB: {e}"))?;
+                if word(8) == id {
+                    return Ok(body);
+                }
+            }
+        }
+
+        fn connect(cfg: &RgbSettings) -> Result<OpenRgb, String> {
+            let stream = TcpStream::connect((cfg.host.as_str(), cfg.port))
+                .map_err(|e| format!("OpenRGB {}:{}: {e}", cfg.host, cfg.port))?;
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let mut client = OpenRgb {
+                stream,
+                devices: Vec::new(),
+            };
+            client.send(0, SET_CLIENT_NAME, b"Star Desktop Pet\0")?;
+            client.send(0, REQUEST_CONTROLLER_COUNT, &[])?;
+            let count = client.reply(REQUEST_CONTROLLER_COUNT)?;
+            let count = count
+                .get(..4)
+                .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+            for index in 0..count {
+                client.send(index, REQUEST_CONTROLLER_DATA, &[])?;
+                let (name, leds) = parse_controller(&client.reply(REQUEST_CONTROLLER_DATA)?)?;
+                let wanted = cfg.devices.is_empty()
+                    || cfg
+                        .devices
+                        .iter()
+                        .any(|d| name.to_lowercase().contains(&d.to_lowercase()));
+                if wanted && leds > 0 {
+                    client.send(index, SET_CUSTOM_MODE, &[])?;
+                    client.devices.push((index, leds));
+                    log!("🌈 OpenRGB device {index}: {name} ({leds} LEDs)");
+                }
+            }
+            if client.devices.is_empty() {
+                return Err("OpenRGB: no matching devices".into());
+            }
+            Ok(client)
+        }
+
+        fn set(&mut self, [r, g, b]: [u8; 3]) -> Result<(), String> {
+            for (index, leds) in self.devices.clone() {
+                let mut payload = Vec::with_capacity(6 + usize::from(leds) * 4);
+                payload.extend_from_slice(&(6 + u32::from(leds) * 4).to_le_bytes());
+                payload.extend_from_slice(&leds.to_le_bytes());
+                for _ in 0..leds {
+                    payload.extend_from_slice(&[r, g, b, 0]);
+                }
+                self.send(index, UPDATE_LEDS, &payload)?;
+            }
+            Ok(())
+        }
+    }
+
+    // ── Razer Chroma REST ──
+
+    struct Chroma {
+        uri: String,
+        devices: Vec<String>,
+        beat: Instant,
+    }
+
+    impl Chroma {
+        fn connect(cfg: &RgbSettings) -> Result<Chroma, String> {
+            let devices: Vec<String> = if cfg.devices.is_empty() {
+                CHROMA_DEVICES.iter().map(|d| d.to_string()).collect()
+            } else {
+                cfg.devices.clone()
+            };
+            let app = serde_json::json!({
+                "title": "Star Desktop Pet",
+                "description": "Lights follow the desktop pet's state",
+                "author": { "name": "Star Office", "contact": "https://github.com/Zhaohan-Wang/Star-Office-UI" },
+                "device_supported": devices,
+                "category": "application",
+            });
+            let reply = net::send_json("POST", &cfg.chroma_url, &app)?;
+            let uri = reply
+                .get("uri")
+                .and_then(|u| u.as_str())
+                .ok_or_else(|| format!("Chroma: no session in {reply}"))?
+                .to_string();
+            log!("🌈 Chroma session {uri}");
+            // Effects sent right after init are ignored.
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(Chroma {
+                uri,
+                devices,
+                beat: Instant::now(),
+            })
+        }
+
+        fn set(&mut self, [r, g, b]: [u8; 3]) -> Result<(), String> {
+            // Chroma colours are 0xBBGGRR.
+            let bgr = u32::from(r) | (u32::from(g) << 8) | (u32::from(b) << 16);
+            let effect =
+                serde_json::json!({ "effect": "CHROMA_STATIC", "param": { "color": bgr } });
+            for device in &self.devices {
+                net::send_json("PUT", &format!("{}/{device}", self.uri), &effect)?;
+            }
+            self.beat = Instant::now();
+            Ok(())
+        }
+
+        fn keep_alive(&mut self) -> Result<(), String> {
+            if self.beat.elapsed() < HEARTBEAT {
+                return Ok(());
+            }
+            self.beat = Instant::now();
+            net::send_json(
+                "PUT",
+                &format!("{}/heartbeat", self.uri),
+                &serde_json::json!({}),
+            )
+            .map(|_| ())
+        }
+
+        fn close(&self) {
+            let _ = net::send_json("DELETE", &self.uri, &serde_json::Value::Null);
+        }
+    }
+
+    // ── driver ──
+
+    enum Client {
+        OpenRgb(OpenRgb),
+        Chroma(Chroma),
+    }
+
+    impl Client {
+        fn connect(cfg: &RgbSettings) -> Result<Client, String> {
+            match cfg.backend {
+                RgbBackend::OpenRgb => OpenRgb::connect(cfg).map(Client::OpenRgb),
+                RgbBackend::Chroma => Chroma::connect(cfg).map(Client::Chroma),
+            }
+        }
+
+        fn set(&mut self, color: [u8; 3]) -> Result<(), String> {
+            match self {
+                Client::OpenRgb(c) => c.set(color),
+                Client::Chroma(c) => c.set(color),
+            }
+        }
+
+        fn keep_alive(&mut self) -> Result<(), String> {
+            match self {
+                Client::OpenRgb(_) => Ok(()),
+                Client::Chroma(c) => c.keep_alive(),
+            }
+        }
+
+        fn close(self) {
+            if let Client::Chroma(c) = self {
+                c.close();
+            }
+        }
+    }
+
+    pub(super) fn stop() {
+        STOPPED.store(true, Ordering::SeqCst);
+        if let Some(client) = CLIENT.lock_or_recover().take() {
+            client.close();
+        }
+    }
+
+    /// Always runs, so `rgb.enabled` can be switched on later.
+    pub(super) fn start(app: AppHandle, _enabled: bool) {
+        std::thread::spawn(move || {
+            // Settings the client was made for, and what it last showed.
+            let mut made_for: Option<RgbSettings> = None;
+            let mut shown: Option<[u8; 3]> = None;
+            let mut since = (None::<String>, Instant::now());
+            let mut retry_at = Instant::now();
+            while !STOPPED.load(Ordering::SeqCst) {
+                std::thread::sleep(FRAME);
+                let cfg = app.state::<Mutex<Settings>>().lock_or_recover().rgb.clone();
+                let mut client = CLIENT.lock_or_recover();
+                if made_for.as_ref() != Some(&cfg) {
+                    if let Some(old) = client.take() {
+                        old.close();
+                    }
+                    if cfg.enabled {
+                        check_colors(&cfg);
+                    }
+                    made_for = Some(cfg.clone());
+                    shown = None;
+                    retry_at = Instant::now();
+                }
+                if !cfg.enabled || STOPPED.load(Ordering::SeqCst) {
+                    continue;
+                }
+                if client.is_none() {
+                    if Instant::now() < retry_at {
+                        continue;
+                    }
+                    match Client::connect(&cfg) {
+                        Ok(c) => {
+                            health::success(&app, "rgb");
+                            *client = Some(c);
+                        }
+                        Err(e) => {
+                            log!("⚠️ rgb: {e}; retrying in {}s", RETRY.as_secs());
+                            health::failure(&app, "rgb", e.as_str());
+                            health::next_poll(&app, "rgb", RETRY);
+                            retry_at = Instant::now() + RETRY;
+                            continue;
+                        }
+                    }
+                }
+                let Some(c) = client.as_mut() else {
+                    continue;
+                };
+
+                // Pulses start from dim whenever the state changes.
+                let state = STATE.lock_or_recover().clone();
+                if since.0 != state {
+                    since = (state, Instant::now());
+                }
+                let result = match color_at(&cfg, since.1.elapsed().as_millis() as u64) {
+                    Some(color) if shown != Some(color) => {
+                        c.set(color).map(|_| shown = Some(color))
+                    }
+                    _ => c.keep_alive(),
+                };
+                if let Err(e) = result {
+                    log!("⚠️ rgb: {e}; reconnecting");
+                    health::failure(&app, "rgb", e.as_str());
+                    health::next_poll(&app, "rgb", RETRY);
+                    if let Some(old) = client.take() {
+                        old.close();
+                    }
+                    shown = None;
+                    retry_at = Instant::now() + RETRY;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "rgb"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use crate::logs::log;
+
+    pub(super) fn stop() {}
+
+    pub(super) fn start(_: AppHandle, enabled: bool) {
+        if enabled {
+            log!("⚠️ rgb.enabled is set, but this build has no lighting support (rebuild with `--features rgb`)");
+        }
+    }
+}
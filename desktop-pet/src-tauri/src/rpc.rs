@@ -0,0 +1,278 @@
+//! JSON-RPC 2.0 for editor plugins and scripts.
+//!
+//! `--rpc stdio` serves the pet's own stdin/stdout (logs stay on stderr);
+//! `--rpc <port>` listens on `127.0.0.1:<port>` instead, one session per
+//! connection. Messages are newline-delimited JSON objects, one per line.
+//! Method names and params mirror the Tauri commands of the same name, so
+//! the IPC revision rules in [`crate::api`] apply unchanged; methods that
+//! write need the `rpc` channel's scope (see [`crate::permissions`]). Every
+//! session also receives `state-changed` notifications carrying the new
+//! state.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::permissions::{self, Caller, Channel, Scope};
+use crate::sync::LockExt;
+use crate::{api, breaks, bubble, crash, i18n, progress, report, states, typing, PetState};
+
+/// Methods answered here; every other command needs a window.
+pub(crate) const METHODS: &[&str] = &[
+    "get_api_info",
+    "read_state",
+    "write_state",
+    "report_progress",
+    "get_progress_breakdown",
+    "list_known_states",
+    "t",
+    "get_locale",
+    "snooze_break",
+    "get_break_stats",
+    "report_keystrokes",
+    "get_typing_stats",
+    "generate_report",
+    "get_last_crash",
+    "say",
+];
+
+type Out = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Outputs of the open sessions, for [`broadcast`].
+static SESSIONS: Mutex<Vec<Out>> = Mutex::new(Vec::new());
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command itself failed; `message` is its error string.
+const COMMAND_FAILED: i64 = -32000;
+/// `permissions` doesn't give the `rpc` channel the method's scope.
+const PERMISSION_DENIED: i64 = -32001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Stdio,
+    Tcp(u16),
+}
+
+impl Transport {
+    pub(crate) fn parse(raw: &str) -> Result<Transport, String> {
+        match raw {
+            "stdio" => Ok(Transport::Stdio),
+            port => port
+                .parse()
+                .map(Transport::Tcp)
+                .map_err(|_| format!("`{port}` is neither `stdio` nor a port number")),
+        }
+    }
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Named param, `null` when absent so optional ones deserialize to `None`.
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("`{name}`: {e}")))
+}
+
+fn reply<T: Serialize>(result: Result<T, String>) -> Result<Value, RpcError> {
+    let value = result.map_err(|e| RpcError::new(COMMAND_FAILED, e))?;
+    serde_json::to_value(value).map_err(|e| RpcError::new(COMMAND_FAILED, e.to_string()))
+}
+
+/// Scope a method needs; reads need none.
+fn scope(method: &str) -> Option<Scope> {
+    match method {
+        "write_state" | "report_progress" => Some(Scope::SetState),
+        "snooze_break" | "report_keystrokes" | "say" => Some(Scope::Commands),
+        _ => None,
+    }
+}
+
+fn call(app: &AppHandle, method: &str, p: &Value) -> Result<Value, RpcError> {
+    if let Some(scope) = scope(method) {
+        permissions::authorize(app, &Caller::new(Channel::Rpc), scope, method)
+            .map_err(|e| RpcError::new(PERMISSION_DENIED, e))?;
+    }
+    match method {
+        "get_api_info" => reply(Ok(api::get_api_info())),
+        "read_state" => {
+            reply(crate::current_state(app.state(), app.state()).map_err(|e| e.to_string()))
+        }
+        "write_state" => reply(crate::write_state(
+            app.clone(),
+            param(p, "state")?,
+            param(p, "newState")?,
+            param(p, "api_version")?,
+            app.state(),
+            app.state(),
+        )),
+        "report_progress" => reply(progress::report_progress(
+            param(p, "source")?,
+            param(p, "value")?,
+            param(p, "weight")?,
+            param(p, "label")?,
+            param(p, "heartbeat_secs")?,
+            app.state(),
+        )),
+        "get_progress_breakdown" => reply(progress::get_progress_breakdown(app.state())),
+        "list_known_states" => reply(Ok(states::list_known_states(app.state()))),
+        "t" => reply(Ok(i18n::t(
+            param(p, "key")?,
+            param(p, "args")?,
+            app.state(),
+        ))),
+        "get_locale" => reply(Ok(i18n::get_locale(app.state()))),
+        "snooze_break" => {
+            breaks::snooze_break(
+                app.clone(),
+                param(p, "minutes")?,
+                app.state(),
+                app.state(),
+                app.state(),
+            );
+            reply(Ok(()))
+        }
+        "get_break_stats" => reply(Ok(breaks::get_break_stats(app.state()))),
+        "report_keystrokes" => reply(Ok(typing::report_keystrokes(
+            app.clone(),
+            param(p, "count")?,
+            app.state(),
+            app.state(),
+        ))),
+        "get_typing_stats" => reply(Ok(typing::get_typing_stats(app.state(), app.state()))),
+        "generate_report" => reply(report::generate_report(
+            param(p, "range")?,
+            param(p, "format")?,
+            app.state(),
+            app.state(),
+        )),
+        "get_last_crash" => reply(crash::get_last_crash(app.state())),
+        "say" => reply(bubble::say(app.clone(), param(p, "bubble")?)),
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method `{other}`"),
+        )),
+    }
+}
+
+/// Answers one line; `None` for notifications, which get no reply.
+fn handle_line(app: &AppHandle, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => {
+            return Some(error(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let (Some(method), true) = (method, request.get("jsonrpc") == Some(&json!("2.0"))) else {
+        let err = RpcError::new(INVALID_REQUEST, "expected a JSON-RPC 2.0 request object");
+        return Some(error(id.unwrap_or(Value::Null), err));
+    };
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+    if !params.is_object() {
+        let err = RpcError::new(
+            INVALID_PARAMS,
+            "params must be an object of named arguments",
+        );
+        return id.map(|id| error(id, err));
+    }
+    let outcome = call(app, method, &params);
+    let id = id?;
+    Some(match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error(id, e),
+    })
+}
+
+fn error(id: Value, e: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } })
+}
+
+fn send(out: &Mutex<Box<dyn Write + Send>>, message: &Value) -> std::io::Result<()> {
+    let mut out = out.lock_or_recover();
+    writeln!(out, "{message}")?;
+    out.flush()
+}
+
+/// Sends `state` as a `state-changed` notification to every session.
+pub(crate) fn broadcast(state: &PetState) {
+    let mut sessions = SESSIONS.lock_or_recover();
+    if sessions.is_empty() {
+        return;
+    }
+    let note = json!({ "jsonrpc": "2.0", "method": "state-changed", "params": state });
+    sessions.retain(|out| send(out, &note).is_ok());
+}
+
+/// Runs one session until `input` closes.
+fn session(app: &AppHandle, input: impl BufRead, output: Box<dyn Write + Send>) {
+    let out: Out = Arc::new(Mutex::new(output));
+    SESSIONS.lock_or_recover().push(Arc::clone(&out));
+    for line in input.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(app, &line) {
+            if send(&out, &response).is_err() {
+                break;
+            }
+        }
+    }
+    SESSIONS
+        .lock_or_recover()
+        .retain(|other| !Arc::ptr_eq(other, &out));
+}
+
+pub(crate) fn start(app: AppHandle, transport: Transport) -> Result<(), String> {
+    match transport {
+        Transport::Stdio => {
+            log!("🔌 JSON-RPC on stdio");
+            std::thread::spawn(move || {
+                session(&app, std::io::stdin().lock(), Box::new(std::io::stdout()));
+                log!("🔌 JSON-RPC stdin closed");
+            });
+        }
+        Transport::Tcp(port) => {
+            let listener = TcpListener::bind(("127.0.0.1", port))
+                .map_err(|e| format!("JSON-RPC on 127.0.0.1:{port}: {e}"))?;
+            log!("🔌 JSON-RPC on 127.0.0.1:{port}");
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        let Ok(writer) = stream.try_clone() else {
+                            return;
+                        };
+                        session(&app, BufReader::new(stream), Box::new(writer));
+                    });
+                }
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil;
+use crate::logs::log;
+
+const STATE_JSON: &str = include_str!("../templates/state.json");
+const LAYERS_JSON: &str = include_str!("../templates/layers.json");
+const MAP_JSON: &str = include_str!("../templates/map.json");
+
+/// Starter packs `scaffold` can write.
+pub(crate) const TEMPLATES: &[&str] = &["basic", "office"];
+
+enum Content {
+    Text(&'static str),
+    Image(fn() -> RgbaImage),
+}
+
+fn files_for(template: &str) -> Result<Vec<(&'static str, Content)>, String> {
+    let mut files = vec![
+        ("state.json", Content::Text(STATE_JSON)),
+        ("layers/layers.json", Content::Text(LAYERS_JSON)),
+        ("layers/background.png", Content::Image(background)),
+        (
+            "layers/star-idle.png",
+            Content::Image(|| character_sheet(false)),
+        ),
+        (
+            "layers/star-walk.png",
+            Content::Image(|| character_sheet(true)),
+        ),
+    ];
+    match template {
+        "basic" => {}
+        "office" => {
+            files.push(("layers/map.json", Content::Text(MAP_JSON)));
+            files.push(("layers/tileset.png", Content::Image(tileset)));
+        }
+        other => {
+            return Err(format!(
+                "unknown template `{other}` (expected one of: {})",
+                TEMPLATES.join(", ")
+            ))
+        }
+    }
+    Ok(files)
+}
+
+/// Writes a runnable starter project into `dir`. Existing files are left
+/// alone unless `force` is set; returns the files actually written.
+pub(crate) fn scaffold(template: &str, dir: &Path, force: bool) -> Result<Vec<PathBuf>, String> {
+    let files = files_for(template)?;
+    let mut written = Vec::new();
+    for (rel, content) in files {
+        let path = dir.join(rel);
+        if fsutil::exists(&path) && !force {
+            log!("ℹ️ keeping existing {}", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(fsutil::long_path(parent))
+                .map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        let bytes = match content {
+            Content::Text(text) => text.as_bytes().to_vec(),
+            Content::Image(draw) => png_bytes(&draw())?,
+        };
+        std::fs::write(fsutil::long_path(&path), bytes)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[tauri::command]
+pub fn scaffold_project(
+    template: String,
+    dir: String,
+    force: Option<bool>,
+) -> Result<Vec<PathBuf>, String> {
+    scaffold(&template, Path::new(&dir), force.unwrap_or(false))
+}
+
+// ── placeholder art ──
+
+fn png_bytes(img: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    img.write_to(&mut out, ImageFormat::Png)
+        .map_err(|e| format!("encode placeholder: {e}"))?;
+    Ok(out.into_inner())
+}
+
+fn background() -> RgbaImage {
+    RgbaImage::from_fn(200, 250, |_, y| {
+        let shade = 40 + (y * 40 / 250) as u8;
+        Rgba([shade, shade, shade + 30, 200])
+    })
+}
+
+/// Four 32×32 frames of a bobbing star-coloured blob; the walk sheet
+/// shifts sideways so the two anims are distinguishable.
+fn character_sheet(walk: bool) -> RgbaImage {
+    RgbaImage::from_fn(128, 32, |x, y| {
+        let frame = x / 32;
+        let (cx, cy) = (
+            16 + if walk { frame * 2 } else { 0 },
+            18 - if frame % 2 == 1 { 2 } else { 0 },
+        );
+        let (dx, dy) = ((x % 32) as i32 - cx as i32, y as i32 - cy as i32);
+        if dx * dx + dy * dy <= 100 {
+            Rgba([255, 204, 64, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+/// Four 16×16 tiles matching the ids used in the template map:
+/// 0/1 floor checker, 2 wall, 3 desk.
+fn tileset() -> RgbaImage {
+    const COLORS: [[u8; 4]; 4] = [
+        [196, 170, 130, 255],
+        [176, 150, 110, 255],
+        [90, 90, 110, 255],
+        [120, 80, 50, 255],
+    ];
+    RgbaImage::from_fn(64, 16, |x, _| Rgba(COLORS[(x / 16) as usize]))
+}
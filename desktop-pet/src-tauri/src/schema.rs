@@ -0,0 +1,62 @@
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use std::collections::BTreeMap;
+
+use crate::mappers::MappersFile;
+use crate::notifications::NotificationsFile;
+use crate::seasonal::SeasonalFile;
+use crate::settings::Settings;
+use crate::sound::SoundsFile;
+use crate::theme::Theme;
+use crate::{CfgFile, MapCfgFile, PetState};
+
+/// Config kinds that have a published schema, e.g. for `--print-schema`.
+pub(crate) const KINDS: &[&str] = &[
+    "layers",
+    "map",
+    "state",
+    "settings",
+    "theme",
+    "seasonal",
+    "mappers",
+    "notifications",
+    "sounds",
+];
+
+/// JSON Schema for one config file, generated from the structs the loaders
+/// deserialize into so the schema can't drift from what the app accepts.
+pub(crate) fn config_schema(kind: &str) -> Result<RootSchema, String> {
+    match kind.trim_end_matches(".json") {
+        "layers" => Ok(schema_for!(CfgFile)),
+        "map" => Ok(schema_for!(MapCfgFile)),
+        "state" => Ok(schema_for!(PetState)),
+        "settings" => Ok(schema_for!(Settings)),
+        "theme" => Ok(schema_for!(Theme)),
+        "seasonal" => Ok(schema_for!(SeasonalFile)),
+        "mappers" => Ok(schema_for!(MappersFile)),
+        "notifications" => Ok(schema_for!(NotificationsFile)),
+        "sounds" => Ok(schema_for!(SoundsFile)),
+        other => Err(format!(
+            "unknown config kind `{other}` (expected one of: {})",
+            KINDS.join(", ")
+        )),
+    }
+}
+
+/// Every schema, keyed by kind, for editors that load them in one go.
+pub(crate) fn all_schemas() -> BTreeMap<&'static str, RootSchema> {
+    KINDS
+        .iter()
+        .filter_map(|kind| Some((*kind, config_schema(kind).ok()?)))
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_config_schemas() -> BTreeMap<&'static str, RootSchema> {
+    all_schemas()
+}
+
+#[tauri::command]
+pub fn get_config_schema(kind: String) -> Result<RootSchema, String> {
+    config_schema(&kind)
+}
@@ -0,0 +1,192 @@
+//! Screensaver mode after long inactivity.
+//!
+//! Once the OS reports no input for `screensaver.idle_hours`, the pet
+//! switches to a calmer scene: a random alternate map from `layers/maps/`,
+//! a slower tick rate, and an ambient scene that rotates every
+//! `scene_minutes`. The first input ends it straight away. The frontend
+//! follows the `screensaver` event and loads the chosen map with
+//! `load_map(map)`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{activity, fsutil, power, AppPaths};
+
+/// Alternate maps, `layers/maps/<name>.json`.
+pub(crate) const MAPS_DIR: &str = "maps";
+/// How often input is checked while the screensaver runs.
+const WAKE_POLL: Duration = Duration::from_secs(1);
+
+/// `screensaver` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ScreensaverSettings {
+    pub enabled: bool,
+    pub idle_hours: f64,
+    /// Multiplier for the frontend's tick and animation rate.
+    pub tick_rate: f64,
+    /// Ambient scenes for the frontend to rotate through.
+    pub scenes: Vec<String>,
+    pub scene_minutes: u32,
+    pub poll_secs: u32,
+}
+
+impl Default for ScreensaverSettings {
+    fn default() -> Self {
+        ScreensaverSettings {
+            enabled: true,
+            idle_hours: 2.0,
+            tick_rate: 0.25,
+            scenes: vec!["night".into(), "rain".into(), "dawn".into()],
+            scene_minutes: 10,
+            poll_secs: 30,
+        }
+    }
+}
+
+/// Payload of `screensaver`, also returned by `get_screensaver`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Screensaver {
+    active: bool,
+    /// Alternate map to show, `None` for the usual `map.json`.
+    map: Option<String>,
+    scene: Option<String>,
+    tick_rate: f64,
+}
+
+impl Default for Screensaver {
+    fn default() -> Self {
+        Screensaver {
+            active: false,
+            map: None,
+            scene: None,
+            tick_rate: 1.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ScreensaverState(Mutex<Screensaver>);
+
+/// Good enough to vary the scenery; not for anything that matters.
+fn pick(len: usize) -> usize {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos() as usize);
+    nanos % len.max(1)
+}
+
+/// Alternate map names must be plain file stems.
+pub(crate) fn valid_map_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn alternate_maps(layers_dir: &Path) -> Vec<String> {
+    let dir = layers_dir.join(MAPS_DIR);
+    let Ok(entries) = std::fs::read_dir(fsutil::long_path(&dir)) else {
+        return Vec::new();
+    };
+    let mut maps: Vec<String> = entries
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let stem = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == "json" && valid_map_name(&stem)).then_some(stem)
+        })
+        .collect();
+    maps.sort();
+    maps
+}
+
+fn announce(app: &AppHandle, status: Screensaver) {
+    *app.state::<ScreensaverState>().0.lock_or_recover() = status.clone();
+    let _ = app.emit("screensaver", status);
+}
+
+fn enter(app: &AppHandle, cfg: &ScreensaverSettings) -> Screensaver {
+    let layers_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone();
+    let maps = alternate_maps(&layers_dir);
+    let status = Screensaver {
+        active: true,
+        map: (!maps.is_empty()).then(|| maps[pick(maps.len())].clone()),
+        scene: (!cfg.scenes.is_empty()).then(|| cfg.scenes[pick(cfg.scenes.len())].clone()),
+        tick_rate: cfg.tick_rate.clamp(0.01, 1.0),
+    };
+    log!(
+        "🌙 screensaver on (map: {}, scene: {})",
+        status.map.as_deref().unwrap_or("default"),
+        status.scene.as_deref().unwrap_or("none")
+    );
+    status
+}
+
+/// Next scene after `current`, so the rotation visits each in turn.
+fn next_scene(scenes: &[String], current: Option<&str>) -> Option<String> {
+    let at = current.and_then(|c| scenes.iter().position(|s| s == c));
+    let next = at.map_or(0, |i| (i + 1) % scenes.len().max(1));
+    scenes.get(next).cloned()
+}
+
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut scene_since = Instant::now();
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .screensaver
+                .clone();
+            let active = app.state::<ScreensaverState>().0.lock_or_recover().active;
+            let threshold = Duration::from_secs_f64(cfg.idle_hours.max(0.0) * 3600.0);
+            let idle = activity::idle_time();
+
+            match (active, idle) {
+                (false, Some(idle)) if cfg.enabled && idle >= threshold => {
+                    announce(&app, enter(&app, &cfg));
+                    scene_since = Instant::now();
+                }
+                // Input again, or the feature was switched off meanwhile.
+                (true, Some(idle)) if !cfg.enabled || idle < WAKE_POLL * 2 => {
+                    log!("☀️ screensaver off");
+                    announce(&app, Screensaver::default());
+                }
+                (true, _)
+                    if scene_since.elapsed()
+                        >= Duration::from_secs(u64::from(cfg.scene_minutes.max(1)) * 60) =>
+                {
+                    let mut status = app.state::<ScreensaverState>().0.lock_or_recover().clone();
+                    status.scene = next_scene(&cfg.scenes, status.scene.as_deref());
+                    announce(&app, status);
+                    scene_since = Instant::now();
+                }
+                _ => {}
+            }
+
+            let active = app.state::<ScreensaverState>().0.lock_or_recover().active;
+            std::thread::sleep(if active {
+                WAKE_POLL
+            } else {
+                power::interval(&app, Duration::from_secs(u64::from(cfg.poll_secs.max(1))))
+            });
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_screensaver(state: tauri::State<'_, ScreensaverState>) -> Screensaver {
+    state.0.lock_or_recover().clone()
+}
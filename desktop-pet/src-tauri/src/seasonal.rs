@@ -0,0 +1,613 @@
+//! `layers/seasonal.json`: holiday and seasonal extras switched on by date.
+//!
+//! Each season names a date range and what it adds while it runs: extra
+//! `layers`, `skins` replacing the sheet of an anim, and `quotes` per state
+//! for the speech bubble. Dates are `MM-DD`, `easter` or `lunar:MM-DD`
+//! (Chinese calendar, computed on the fly), each optionally shifted by
+//! `+N`/`-N` days. A range whose end comes before its start runs over New
+//! Year. "Today" is taken in the `clock` time zone.
+
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::limits::Limits;
+use crate::logs::log;
+use crate::pet_core::{self, AssetSource, FsAssets, Overlay};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{clock, power, watch, AppPaths, LayerCfg};
+
+pub(crate) const FILE: &str = "seasonal.json";
+const POLL: Duration = Duration::from_secs(15 * 60);
+
+/// `seasonal` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SeasonalSettings {
+    pub enabled: bool,
+    /// `YYYY-MM-DD` to preview as if it were today.
+    pub date: Option<String>,
+}
+
+impl Default for SeasonalSettings {
+    fn default() -> Self {
+        SeasonalSettings {
+            enabled: true,
+            date: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct SeasonalFile {
+    pub seasons: Vec<Season>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Season {
+    pub name: String,
+    /// First day, e.g. `12-01`, `easter-2` or `lunar:01-01`.
+    pub from: String,
+    /// Last day, inclusive; the same day as `from` when unset.
+    pub to: Option<String>,
+    /// Appended to `layers.json`'s `layers`.
+    #[serde(default)]
+    pub layers: Vec<LayerCfg>,
+    /// Anim key to the sheet that replaces its `file`.
+    #[serde(default)]
+    pub skins: BTreeMap<String, String>,
+    /// State to extra bubble lines.
+    #[serde(default)]
+    pub quotes: BTreeMap<String, Vec<String>>,
+}
+
+// ── date expressions ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    Fixed { month: u32, day: u32 },
+    Easter,
+    Lunar { month: u32, day: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateExpr {
+    anchor: Anchor,
+    offset_days: i64,
+}
+
+fn parse_expr(raw: &str) -> Result<DateExpr, String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"^(?:(easter)|lunar:(\d{1,2})-(\d{1,2})|(\d{1,2})-(\d{1,2}))([+-]\d{1,3})?$")
+            .expect("valid regex")
+    });
+    let bad = || format!("`{raw}` is not MM-DD, easter or lunar:MM-DD (with optional +N/-N)");
+    let caps = re.captures(raw.trim()).ok_or_else(bad)?;
+    let num = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+    let anchor = if caps.get(1).is_some() {
+        Anchor::Easter
+    } else if let (Some(month), Some(day)) = (num(2), num(3)) {
+        if !(1..=12).contains(&month) || !(1..=30).contains(&day) {
+            return Err(format!("`{raw}`: lunar months are 1-12, days 1-30"));
+        }
+        Anchor::Lunar { month, day }
+    } else {
+        let (month, day) = (num(4).ok_or_else(bad)?, num(5).ok_or_else(bad)?);
+        // 2000 is a leap year, so 02-29 is accepted.
+        if NaiveDate::from_ymd_opt(2000, month, day).is_none() {
+            return Err(format!("`{raw}`: no such date"));
+        }
+        Anchor::Fixed { month, day }
+    };
+    let offset_days = caps
+        .get(6)
+        .map_or(Ok(0), |m| m.as_str().parse::<i64>())
+        .map_err(|_| bad())?;
+    Ok(DateExpr {
+        anchor,
+        offset_days,
+    })
+}
+
+impl DateExpr {
+    /// The date in Gregorian `year`; lunar dates fall in the lunar year
+    /// that starts in `year`.
+    fn resolve(&self, year: i32) -> Option<NaiveDate> {
+        let base = match self.anchor {
+            // 02-29 in a common year is the 28th.
+            Anchor::Fixed { month, day } => NaiveDate::from_ymd_opt(year, month, day)
+                .or_else(|| NaiveDate::from_ymd_opt(year, month, day - 1))?,
+            Anchor::Easter => easter(year)?,
+            Anchor::Lunar { month, day } => lunar::to_gregorian(year, month, day)?,
+        };
+        base.checked_add_signed(chrono::Duration::days(self.offset_days))
+    }
+}
+
+/// Western (Gregorian) Easter Sunday, by the anonymous algorithm.
+fn easter(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Chinese lunisolar calendar from new moons and solar terms, observed at
+/// UTC+8. Months begin on the day of the new moon; the month holding the
+/// winter solstice is the 11th, and in a year of 13 months the first one
+/// without a major solar term is the leap month.
+mod lunar {
+    use chrono::NaiveDate;
+    use std::f64::consts::PI;
+
+    const CHINA: f64 = 8.0 / 24.0;
+    /// TT − UT, close enough for this century.
+    const DELTA_T: f64 = 69.0 / 86_400.0;
+    const SYNODIC: f64 = 29.530_588_861;
+    /// Julian day number of 0001-01-01 minus one.
+    const CE_OFFSET: i64 = 1_721_425;
+
+    fn sin_deg(deg: f64) -> f64 {
+        (deg * PI / 180.0).sin()
+    }
+
+    /// Julian ephemeris day of new moon `k`, counted from January 2000
+    /// (Meeus, ch. 49).
+    fn new_moon(k: f64) -> f64 {
+        let t = k / 1236.85;
+        let t2 = t * t;
+        let jde = 2_451_550.097_66 + SYNODIC * k + 0.000_154_37 * t2 - 0.000_000_150 * t2 * t;
+        let e = 1.0 - 0.002_516 * t - 0.000_007_4 * t2;
+        let m = 2.5534 + 29.105_356_70 * k - 0.000_001_4 * t2;
+        let mp = 201.5643 + 385.816_935_28 * k + 0.010_758_2 * t2;
+        let f = 160.7108 + 390.670_502_84 * k - 0.001_611_8 * t2;
+        let om = 124.7746 - 1.563_755_88 * k + 0.002_067_2 * t2;
+        jde - 0.40720 * sin_deg(mp)
+            + 0.17241 * e * sin_deg(m)
+            + 0.01608 * sin_deg(2.0 * mp)
+            + 0.01039 * sin_deg(2.0 * f)
+            + 0.00739 * e * sin_deg(mp - m)
+            - 0.00514 * e * sin_deg(mp + m)
+            + 0.00208 * e * e * sin_deg(2.0 * m)
+            - 0.00111 * sin_deg(mp - 2.0 * f)
+            - 0.00057 * sin_deg(mp + 2.0 * f)
+            + 0.00056 * e * sin_deg(2.0 * mp + m)
+            - 0.00042 * sin_deg(3.0 * mp)
+            + 0.00042 * e * sin_deg(m + 2.0 * f)
+            + 0.00038 * e * sin_deg(m - 2.0 * f)
+            - 0.00024 * e * sin_deg(2.0 * mp - m)
+            - 0.00017 * sin_deg(om)
+    }
+
+    /// Apparent solar longitude in degrees (Meeus, ch. 25, low accuracy).
+    fn sun_longitude(jd: f64) -> f64 {
+        let t = (jd - 2_451_545.0) / 36_525.0;
+        let l0 = 280.466_46 + 36_000.769_83 * t + 0.000_303_2 * t * t;
+        let m = 357.529_11 + 35_999.050_29 * t - 0.000_153_7 * t * t;
+        let c = (1.914_602 - 0.004_817 * t - 0.000_014 * t * t) * sin_deg(m)
+            + (0.019_993 - 0.000_101 * t) * sin_deg(2.0 * m)
+            + 0.000_289 * sin_deg(3.0 * m);
+        let om = 125.04 - 1934.136 * t;
+        (l0 + c - 0.005_69 - 0.004_78 * sin_deg(om)).rem_euclid(360.0)
+    }
+
+    /// Julian day number of the China date containing `jd`.
+    fn local_day(jd: f64) -> i64 {
+        (jd + 0.5 + CHINA).floor() as i64
+    }
+
+    fn new_moon_day(k: i64) -> i64 {
+        local_day(new_moon(k as f64) - DELTA_T)
+    }
+
+    fn day_number(date: NaiveDate) -> i64 {
+        i64::from(chrono::Datelike::num_days_from_ce(&date)) + CE_OFFSET
+    }
+
+    fn date_of(day: i64) -> Option<NaiveDate> {
+        NaiveDate::from_num_days_from_ce_opt(i32::try_from(day - CE_OFFSET).ok()?)
+    }
+
+    /// China date of the December solstice of `year`.
+    fn solstice_day(year: i32) -> Option<i64> {
+        let mut lo = day_number(NaiveDate::from_ymd_opt(year, 12, 15)?) as f64 - 0.5;
+        let mut hi = lo + 13.0;
+        for _ in 0..50 {
+            let mid = (lo + hi) / 2.0;
+            if (sun_longitude(mid) - 270.0 + 180.0).rem_euclid(360.0) < 180.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(local_day(lo))
+    }
+
+    /// The latest new moon falling on or before `day`.
+    fn moon_on_or_before(day: i64) -> i64 {
+        let mut k = ((day as f64 - 2_451_550.1) / SYNODIC).floor() as i64;
+        while new_moon_day(k) > day {
+            k -= 1;
+        }
+        while new_moon_day(k + 1) <= day {
+            k += 1;
+        }
+        k
+    }
+
+    /// Whether a major solar term (a multiple of 30°) falls in `[start, end)`.
+    fn has_major_term(start: i64, end: i64) -> bool {
+        let at = |day: i64| (sun_longitude(day as f64 - 0.5 - CHINA) / 30.0).floor();
+        at(start) != at(end)
+    }
+
+    struct Month {
+        number: u32,
+        leap: bool,
+        start: i64,
+        end: i64,
+    }
+
+    /// Months from the 11th month around December `year` up to the next 11th.
+    fn cycle(year: i32) -> Option<Vec<Month>> {
+        let k0 = moon_on_or_before(solstice_day(year)?);
+        let k1 = moon_on_or_before(solstice_day(year + 1)?);
+        let leap = if k1 - k0 == 13 {
+            (1..13).find(|i| !has_major_term(new_moon_day(k0 + i), new_moon_day(k0 + i + 1)))
+        } else {
+            None
+        };
+        let mut months = Vec::new();
+        let mut number = 11;
+        for i in 0..k1 - k0 {
+            let is_leap = Some(i) == leap;
+            if i > 0 && !is_leap {
+                number = number % 12 + 1;
+            }
+            months.push(Month {
+                number,
+                leap: is_leap,
+                start: new_moon_day(k0 + i),
+                end: new_moon_day(k0 + i + 1),
+            });
+        }
+        Some(months)
+    }
+
+    /// Day `day` of (non-leap) month `month` of the lunar year starting in
+    /// Gregorian `year`; the 30th of a short month is its 29th.
+    pub(super) fn to_gregorian(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        let found = if month <= 10 {
+            cycle(year - 1)?
+                .into_iter()
+                .skip_while(|m| m.number != 1)
+                .find(|m| m.number == month && !m.leap)
+        } else {
+            cycle(year)?
+                .into_iter()
+                .take_while(|m| m.number != 1)
+                .find(|m| m.number == month && !m.leap)
+        }?;
+        date_of((found.start + i64::from(day) - 1).min(found.end - 1))
+    }
+}
+
+// ── schedule ──
+
+impl Season {
+    fn range(&self) -> Result<(DateExpr, DateExpr), String> {
+        let from = parse_expr(&self.from)?;
+        let to = match &self.to {
+            Some(to) => parse_expr(to)?,
+            None => from,
+        };
+        Ok((from, to))
+    }
+
+    /// Whether `today` falls in this year's (or last year's, for ranges
+    /// running over New Year) occurrence.
+    fn covers(&self, today: NaiveDate) -> bool {
+        let Ok((from, to)) = self.range() else {
+            return false;
+        };
+        (today.year() - 1..=today.year()).any(|year| {
+            let (Some(start), Some(mut end)) = (from.resolve(year), to.resolve(year)) else {
+                return false;
+            };
+            if end < start {
+                match to.resolve(year + 1) {
+                    Some(next) => end = next,
+                    None => return false,
+                }
+            }
+            start <= today && today <= end
+        })
+    }
+}
+
+impl SeasonalFile {
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for (i, season) in self.seasons.iter().enumerate() {
+            if season.name.trim().is_empty() {
+                out.push(format!("seasons[{i}].name: must not be empty"));
+            }
+            if let Err(e) = season.range() {
+                out.push(format!("seasons[{i}] ({}): {e}", season.name));
+            }
+        }
+        out
+    }
+
+    pub(crate) fn active(&self, today: NaiveDate) -> Vec<&Season> {
+        self.seasons.iter().filter(|s| s.covers(today)).collect()
+    }
+}
+
+/// No seasons when the pack has none; an invalid file is an error.
+pub(crate) fn load(src: &dyn AssetSource, limits: &Limits) -> Result<SeasonalFile, String> {
+    if !src.exists(FILE) {
+        return Ok(SeasonalFile::default());
+    }
+    let raw = pet_core::read_config(src, FILE, limits)?;
+    let file: SeasonalFile = serde_json::from_str(&raw).map_err(|e| format!("{FILE}: {e}"))?;
+    let problems = file.problems();
+    if !problems.is_empty() {
+        return Err(format!("{FILE}: {}", problems.join("; ")));
+    }
+    Ok(file)
+}
+
+/// The date seasons are matched against.
+pub(crate) fn today(settings: &Settings) -> NaiveDate {
+    let preview = settings
+        .seasonal
+        .date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok());
+    preview.unwrap_or_else(|| {
+        let (tz, _) = clock::zone(None, &settings.clock);
+        clock::now_in(tz).date_naive()
+    })
+}
+
+fn active_seasons(src: &dyn AssetSource, settings: &Settings) -> Vec<Season> {
+    if !settings.seasonal.enabled {
+        return Vec::new();
+    }
+    match load(src, &settings.limits) {
+        Ok(file) => file.active(today(settings)).into_iter().cloned().collect(),
+        Err(e) => {
+            log!("⚠️ {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// `layers.json` with the seasons' layers appended and skins swapped in;
+/// later seasons win when two skin the same anim.
+fn patch_layers(
+    src: &dyn AssetSource,
+    limits: &Limits,
+    seasons: &[Season],
+) -> Result<String, String> {
+    let mut cfg = if src.exists("layers.json") {
+        pet_core::read_config_merged(src, "layers.json", limits)?
+    } else {
+        json!({})
+    };
+    let Some(root) = cfg.as_object_mut() else {
+        return Err("layers.json: expected an object".into());
+    };
+    for season in seasons {
+        if !season.layers.is_empty() {
+            let layers = root.entry("layers").or_insert_with(|| json!([]));
+            if let Some(layers) = layers.as_array_mut() {
+                for layer in &season.layers {
+                    layers.push(serde_json::to_value(layer).map_err(|e| e.to_string())?);
+                }
+            }
+        }
+        // Skins for anims the pack doesn't have are reported by `validate`.
+        for (key, file) in &season.skins {
+            let pointer = format!(
+                "/sprites/anims/{}",
+                key.replace('~', "~0").replace('/', "~1")
+            );
+            if let Some(Value::Object(anim)) = root.pointer_mut(&pointer) {
+                anim.insert("file".into(), json!(file));
+            }
+        }
+    }
+    Ok(cfg.to_string())
+}
+
+/// Runs `f` on the pack as it looks today, seasonal extras included.
+pub(crate) fn with_season<T>(
+    src: &dyn AssetSource,
+    settings: &Settings,
+    f: impl FnOnce(&dyn AssetSource) -> T,
+) -> T {
+    let seasons = active_seasons(src, settings);
+    if seasons.is_empty() {
+        return f(src);
+    }
+    match patch_layers(src, &settings.limits, &seasons) {
+        Ok(raw) => f(&Overlay::new(src).with("layers.json", raw)),
+        Err(e) => {
+            log!("⚠️ seasonal layers skipped: {e}");
+            f(src)
+        }
+    }
+}
+
+/// Payload of `seasonal-changed`, also returned by `get_seasonal`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SeasonalInfo {
+    date: String,
+    active: Vec<String>,
+    /// Extra bubble lines per state from every active season.
+    quotes: BTreeMap<String, Vec<String>>,
+}
+
+fn info(app: &AppHandle) -> SeasonalInfo {
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let layers_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone();
+    let seasons = active_seasons(&FsAssets::new(layers_dir), &settings);
+    let mut quotes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for season in &seasons {
+        for (state, lines) in &season.quotes {
+            quotes
+                .entry(state.clone())
+                .or_default()
+                .extend(lines.iter().cloned());
+        }
+    }
+    SeasonalInfo {
+        date: today(&settings).to_string(),
+        active: seasons.into_iter().map(|s| s.name).collect(),
+        quotes,
+    }
+}
+
+/// Re-checks the calendar now and then; a season starting or ending
+/// reloads the layers.
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last: Option<Vec<String>> = None;
+        loop {
+            let current = info(&app);
+            if last.as_ref() != Some(&current.active) {
+                if last.is_some() {
+                    if current.active.is_empty() {
+                        log!("🗓️ seasonal content off");
+                    } else {
+                        log!("🗓️ season: {}", current.active.join(", "));
+                    }
+                    let _ = watch::layers_changed(&app);
+                    let _ = app.emit("seasonal-changed", current.clone());
+                }
+                last = Some(current.active);
+            }
+            std::thread::sleep(power::interval(&app, POLL));
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_seasonal(app: AppHandle) -> SeasonalInfo {
+    info(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn on(raw: &str, year: i32) -> Option<NaiveDate> {
+        parse_expr(raw).unwrap().resolve(year)
+    }
+
+    fn season(from: &str, to: Option<&str>) -> Season {
+        serde_json::from_value(json!({ "name": "test", "from": from, "to": to })).unwrap()
+    }
+
+    #[test]
+    fn easter_sundays() {
+        assert_eq!(easter(2024), Some(ymd(2024, 3, 31)));
+        assert_eq!(easter(2025), Some(ymd(2025, 4, 20)));
+        assert_eq!(easter(2026), Some(ymd(2026, 4, 5)));
+    }
+
+    #[test]
+    fn lunar_new_year_and_mid_autumn() {
+        assert_eq!(lunar::to_gregorian(2024, 1, 1), Some(ymd(2024, 2, 10)));
+        assert_eq!(lunar::to_gregorian(2025, 1, 1), Some(ymd(2025, 1, 29)));
+        assert_eq!(lunar::to_gregorian(2023, 1, 1), Some(ymd(2023, 1, 22)));
+        // 2025 has a leap sixth month before the festival.
+        assert_eq!(lunar::to_gregorian(2025, 8, 15), Some(ymd(2025, 10, 6)));
+        // The 12th month of 2024 is short, so its 30th is New Year's Eve.
+        assert_eq!(lunar::to_gregorian(2024, 12, 30), Some(ymd(2025, 1, 28)));
+    }
+
+    #[test]
+    fn leap_day_falls_back_in_common_years() {
+        assert_eq!(on("02-29", 2024), Some(ymd(2024, 2, 29)));
+        assert_eq!(on("02-29", 2025), Some(ymd(2025, 2, 28)));
+        assert!(season("02-29", None).covers(ymd(2025, 2, 28)));
+        assert!(!season("02-29", None).covers(ymd(2025, 3, 1)));
+    }
+
+    #[test]
+    fn offsets_move_the_anchor() {
+        assert_eq!(on("easter-2", 2024), Some(ymd(2024, 3, 29)));
+        assert_eq!(on("easter+1", 2025), Some(ymd(2025, 4, 21)));
+        assert_eq!(on("lunar:01-01+14", 2024), Some(ymd(2024, 2, 24)));
+        assert_eq!(on("lunar:01-01-1", 2025), Some(ymd(2025, 1, 28)));
+        assert_eq!(on("12-31+1", 2024), Some(ymd(2025, 1, 1)));
+        assert_eq!(on(" 03-01-1 ", 2024), Some(ymd(2024, 2, 29)));
+    }
+
+    #[test]
+    fn ranges_wrap_over_new_year() {
+        let winter = season("12-20", Some("01-05"));
+        assert!(winter.covers(ymd(2024, 12, 20)));
+        assert!(winter.covers(ymd(2024, 12, 31)));
+        assert!(winter.covers(ymd(2025, 1, 1)));
+        assert!(winter.covers(ymd(2025, 1, 5)));
+        assert!(!winter.covers(ymd(2025, 1, 6)));
+        assert!(!winter.covers(ymd(2024, 12, 19)));
+
+        let spring = season("lunar:12-23", Some("lunar:01-15"));
+        assert!(spring.covers(ymd(2025, 1, 29)));
+        assert!(!spring.covers(ymd(2025, 3, 1)));
+    }
+
+    #[test]
+    fn bad_expressions_are_rejected() {
+        for raw in [
+            "",
+            "xmas",
+            "13-01",
+            "02-30",
+            "lunar:13-01",
+            "lunar:01-31",
+            "easter+1000",
+            "easter + 1",
+        ] {
+            assert!(parse_expr(raw).is_err(), "{raw:?}");
+        }
+        let file = SeasonalFile {
+            seasons: vec![season("easter", Some("04-31"))],
+        };
+        assert_eq!(file.problems().len(), 1);
+    }
+}
@@ -0,0 +1,239 @@
+//! `session.json` in the profile's data folder: where the pet was left —
+//! the active map, its tile there, animation, window position and zoom —
+//! so the next start puts it back there.
+//!
+//! The frontend saves what it knows through `save_session`; the window
+//! position, last reported tile and map are filled in by the backend,
+//! which also saves once more when the app quits.
+//!
+//! `running` is set from startup until [`crate::shutdown`] clears it. Still
+//! set at the next start means the last run ended without shutting down
+//! (a crash, a kill, a power cut): the main window then gets
+//! `unclean-shutdown` and `get_session_recovery` answers what was lost.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition};
+
+use crate::logs::log;
+use crate::pathfind::{PetTile, Tile};
+use crate::sync::LockExt;
+use crate::{fsutil, AppPaths};
+
+pub(crate) const FILE: &str = "session.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    tile: Option<Tile>,
+    anim: Option<String>,
+    /// Outer position of the main window, in physical pixels.
+    window: Option<WindowPos>,
+    zoom: Option<f64>,
+    /// `map.json` the tile belongs to.
+    map: Option<PathBuf>,
+    saved_at: Option<String>,
+    running: bool,
+    started_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowPos {
+    x: i32,
+    y: i32,
+}
+
+/// Left by a run that didn't shut down cleanly.
+#[derive(Debug, Clone, Serialize)]
+pub struct Recovery {
+    /// When that run started.
+    started_at: Option<String>,
+    /// When its session was last saved, i.e. how old what was restored is.
+    saved_at: Option<String>,
+}
+
+/// What [`begin`] found, for `get_session_recovery`.
+pub(crate) struct Startup {
+    recovery: Option<Recovery>,
+    announced: AtomicBool,
+}
+
+impl Startup {
+    pub(crate) fn new(recovery: Option<Recovery>) -> Self {
+        Startup {
+            recovery,
+            announced: AtomicBool::new(false),
+        }
+    }
+}
+
+fn file(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join(FILE)
+}
+
+fn path(app: &AppHandle) -> PathBuf {
+    file(&app.state::<Mutex<AppPaths>>().lock_or_recover())
+}
+
+fn write(path: &Path, session: &Session) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(fsutil::long_path(dir))
+            .map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    fsutil::write_atomic(path, json.as_bytes())
+}
+
+/// A missing or broken file is an empty session; the pet then starts as
+/// if for the first time.
+fn load(path: &Path) -> Session {
+    if !fsutil::exists(path) {
+        return Session::default();
+    }
+    let parsed = fsutil::read_text(path)
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| format!("{FILE}: {e}")));
+    parsed.unwrap_or_else(|e| {
+        log!("⚠️ {e}; starting a fresh session");
+        Session::default()
+    })
+}
+
+fn window_pos(app: &AppHandle) -> Option<WindowPos> {
+    let pos = app.get_webview_window("main")?.outer_position().ok()?;
+    Some(WindowPos { x: pos.x, y: pos.y })
+}
+
+/// `update` over what is saved, with the window, tile and map as they are
+/// now. `running` and `started_at` are the backend's alone.
+fn save(app: &AppHandle, update: Session, running: bool) -> Result<Session, String> {
+    let path = path(app);
+    let saved = load(&path);
+    let tile = app.state::<PetTile>().current();
+    let map = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .map_path
+        .clone();
+    let next = Session {
+        tile: update.tile.or(tile).or(saved.tile),
+        anim: update.anim.or(saved.anim),
+        window: window_pos(app).or(update.window).or(saved.window),
+        zoom: update.zoom.or(saved.zoom),
+        map: Some(map),
+        saved_at: Some(chrono::Local::now().to_rfc3339()),
+        running,
+        started_at: saved.started_at,
+    };
+    write(&path, &next)?;
+    Ok(next)
+}
+
+/// Called when the main window closes; the app may keep running.
+pub(crate) fn save_on_exit(app: &AppHandle) {
+    if let Err(e) = save(app, Session::default(), true) {
+        log!("⚠️ session not saved: {e}");
+    }
+}
+
+/// The last save of a clean shutdown.
+pub(crate) fn finish(app: &AppHandle) {
+    if let Err(e) = save(app, Session::default(), false) {
+        log!("⚠️ session not saved: {e}");
+    }
+}
+
+/// Puts back the last session's map unless `map_pinned` (`--map-file`,
+/// `STAR_MAP_FILE`).
+pub(crate) fn restore_map(paths: &mut AppPaths, map_pinned: bool) {
+    match load(&file(paths)).map {
+        Some(map) if !map_pinned && map != paths.map_path && fsutil::exists(&map) => {
+            log!("🗺️ Map   : {} (last session)", map.display());
+            paths.map_path = map;
+        }
+        _ => {}
+    }
+}
+
+/// Before anything loads, after [`restore_map`]: checks how the last run
+/// ended and marks this one as running.
+pub(crate) fn begin(paths: &AppPaths) -> Option<Recovery> {
+    let path = file(paths);
+    let mut session = load(&path);
+    let recovery = session.running.then(|| Recovery {
+        started_at: session.started_at.clone(),
+        saved_at: session.saved_at.clone(),
+    });
+    if let Some(r) = &recovery {
+        log!(
+            "⚠️ the last run (started {}) didn't shut down cleanly; restoring its session",
+            r.started_at.as_deref().unwrap_or("?")
+        );
+    }
+    // A tile on another map means nothing here.
+    if session
+        .map
+        .as_ref()
+        .is_some_and(|map| *map != paths.map_path)
+    {
+        session.tile = None;
+    }
+    session.running = true;
+    session.started_at = Some(chrono::Local::now().to_rfc3339());
+    if let Err(e) = write(&path, &session) {
+        log!("⚠️ {FILE}: {e}");
+    }
+    recovery
+}
+
+/// Tells the main window, once, that the last run ended uncleanly.
+pub(crate) fn announce(app: &AppHandle) {
+    let startup = app.state::<Startup>();
+    let Some(recovery) = &startup.recovery else {
+        return;
+    };
+    if !startup.announced.swap(true, Ordering::SeqCst) {
+        let _ = app.emit("unclean-shutdown", recovery.clone());
+    }
+}
+
+/// Puts the window and the pet's tile back at startup. A position that no
+/// monitor shows any more, e.g. after unplugging one, is skipped.
+pub(crate) fn restore(app: &AppHandle) {
+    let session = load(&path(app));
+    if let Some(tile) = session.tile {
+        app.state::<PetTile>().set(tile);
+    }
+    let (Some(pos), Some(main)) = (session.window, app.get_webview_window("main")) else {
+        return;
+    };
+    let visible = main.available_monitors().is_ok_and(|monitors| {
+        monitors.iter().any(|m| {
+            let (at, size) = (m.position(), m.size());
+            (at.x..at.x + size.width as i32).contains(&pos.x)
+                && (at.y..at.y + size.height as i32).contains(&pos.y)
+        })
+    });
+    if visible {
+        let _ = main.set_position(PhysicalPosition::new(pos.x, pos.y));
+        log!("📍 window restored to {}, {}", pos.x, pos.y);
+    }
+}
+
+/// Fields left out keep their saved value.
+#[tauri::command]
+pub fn save_session(app: AppHandle, session: Option<Session>) -> Result<Session, String> {
+    save(&app, session.unwrap_or_default(), true)
+}
+
+#[tauri::command]
+pub fn restore_session(app: AppHandle) -> Session {
+    load(&path(&app))
+}
+
+/// `null` when the last run shut down cleanly.
+#[tauri::command]
+pub fn get_session_recovery(startup: tauri::State<'_, Startup>) -> Option<Recovery> {
+    startup.recovery.clone()
+}
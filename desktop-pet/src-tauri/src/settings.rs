@@ -0,0 +1,120 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::a11y::AccessibilitySettings;
+use crate::activity::IdleSettings;
+use crate::ambient::AmbientSettings;
+use crate::avoid::AvoidSettings;
+use crate::breaks::BreakSettings;
+use crate::build::BuildSettings;
+use crate::calendar::CalendarSettings;
+use crate::ci::CiSettings;
+use crate::clipboard::ClipboardSettings;
+use crate::clock::ClockSettings;
+use crate::digest::DigestSettings;
+use crate::fsutil;
+use crate::gaming::GamingSettings;
+use crate::lan::LanSettings;
+use crate::limits::Limits;
+use crate::logs::log;
+use crate::maintenance::MaintenanceSettings;
+use crate::mqtt::MqttSettings;
+use crate::onboarding::OnboardingSettings;
+use crate::perf::PerfSettings;
+use crate::permissions::PermissionsSettings;
+use crate::platform::PlatformSettings;
+use crate::pomodoro::PomodoroSettings;
+use crate::power::PowerSettings;
+use crate::registry::RegistrySettings;
+use crate::rgb::RgbSettings;
+use crate::screensaver::ScreensaverSettings;
+use crate::seasonal::SeasonalSettings;
+use crate::sim::SimSettings;
+use crate::sound::SoundSettings;
+use crate::status::StatusSettings;
+use crate::system_stats::SystemStatsSettings;
+use crate::updates::UpdateSettings;
+use crate::voice::VoiceSettings;
+use crate::ws::WsSettings;
+
+/// `settings.json` in the project root. Every field is optional so a partial
+/// file (or none at all) falls back to defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub limits: Limits,
+    /// Language for pet text, e.g. `zh-CN`; detected from the OS when unset.
+    pub locale: Option<String>,
+    /// Entry of `skins` in `layers.json` for `load_layers` calls that don't
+    /// name one, e.g. a profile's own look.
+    pub skin: Option<String>,
+    pub registry: RegistrySettings,
+    pub updates: UpdateSettings,
+    pub perf: PerfSettings,
+    pub accessibility: AccessibilitySettings,
+    pub platform: PlatformSettings,
+    pub gaming: GamingSettings,
+    pub breaks: BreakSettings,
+    pub clipboard: ClipboardSettings,
+    pub screensaver: ScreensaverSettings,
+    pub sounds: SoundSettings,
+    pub lan: LanSettings,
+    pub power: PowerSettings,
+    pub clock: ClockSettings,
+    pub seasonal: SeasonalSettings,
+    pub onboarding: OnboardingSettings,
+    pub status: StatusSettings,
+    pub avoid: AvoidSettings,
+    pub maintenance: MaintenanceSettings,
+    pub voice: VoiceSettings,
+    pub ws: WsSettings,
+    pub digest: DigestSettings,
+    pub ambient: AmbientSettings,
+    pub mqtt: MqttSettings,
+    pub permissions: PermissionsSettings,
+    pub sim: SimSettings,
+    pub idle: IdleSettings,
+    pub system_stats: SystemStatsSettings,
+    pub rgb: RgbSettings,
+    pub pomodoro: PomodoroSettings,
+    pub calendar: CalendarSettings,
+    pub build: BuildSettings,
+    pub ci: CiSettings,
+}
+
+/// Reads settings, treating a missing file as defaults. A broken file is
+/// reported and ignored rather than stopping the pet from starting.
+pub(crate) fn load(path: &Path) -> Settings {
+    if !fsutil::exists(path) {
+        return Settings::default();
+    }
+    let parsed = fsutil::read_text(path)
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| format!("settings.json: {e}")));
+    match parsed {
+        Ok(settings) => settings,
+        Err(e) => {
+            log!("⚠️ {e}; using default settings");
+            Settings::default()
+        }
+    }
+}
+
+/// Rewrites one top-level block of `settings.json`, leaving the rest of the
+/// file as the user wrote it. A file that doesn't parse is left untouched.
+pub(crate) fn save_block<T: Serialize>(path: &Path, key: &str, value: &T) -> Result<(), String> {
+    let mut root = if fsutil::exists(path) {
+        let raw = fsutil::read_text(path)?;
+        serde_json::from_str(&raw).map_err(|e| format!("settings.json: {e}"))?
+    } else {
+        Value::Object(Default::default())
+    };
+    let Some(fields) = root.as_object_mut() else {
+        return Err("settings.json is not an object".into());
+    };
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    fields.insert(key.to_string(), value);
+    let pretty = serde_json::to_string_pretty(&root).map_err(|e| e.to_string())?;
+    fsutil::write_atomic(path, pretty.as_bytes())
+}
@@ -0,0 +1,47 @@
+//! Orderly exit, from the tray, `close_app` or the OS ending the event
+//! loop; whichever comes first runs it, once:
+//!
+//! 1. flushes what is only in memory: the running state interval
+//!    (`stats/states.json`) and unsaved keystrokes (`stats/typing.json`),
+//!    and ends a running pomodoro, putting back the state it replaced
+//! 2. stops integrations that leave traces behind: the control API, the
+//!    `star-pet-ctl` socket and its discovery file, the LAN advertisement,
+//!    the MQTT connection, the Chroma session and the build command
+//! 3. saves the session (window position, tile, active map) and marks it
+//!    clean, see [`crate::session`]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::timeline::Timeline;
+use crate::typing::Typing;
+use crate::{build, control, ctl, lan, mqtt, pomodoro, rgb, session, AppPaths};
+
+static DONE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn run(app: &AppHandle) {
+    if DONE.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log!("👋 shutting down");
+    let data_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .data_dir
+        .clone();
+    app.state::<Timeline>().flush(&data_dir);
+    app.state::<Typing>().flush(&data_dir);
+    pomodoro::stop(app);
+
+    control::stop();
+    ctl::stop(app);
+    lan::stop();
+    mqtt::stop();
+    rgb::stop();
+    build::stop(app);
+
+    session::finish(app);
+}
@@ -0,0 +1,472 @@
+//! The pet's behaviour clock: needs, idle wandering, speech bubbles and
+//! routines advance together in fixed 100 ms ticks, drawing every random
+//! number from one seeded generator and reading time from a virtual clock.
+//!
+//! [`Sim`] knows nothing about Tauri, so the same seed, start time and
+//! state changes give the same [`SimEvent`]s on any machine and at any
+//! frame rate, however the elapsed time is chunked. In the app a thread
+//! feeds it wall time and emits what comes out as `sim-event`; the window
+//! only carries them out (walks, bubbles). `sim.seed` in `settings.json`
+//! pins the seed, and `get_sim_snapshot` / `reset_sim` let a session be
+//! inspected and replayed.
+//!
+//! The virtual clock counts from an instant in the `clock` zone, so the
+//! wall time it shows follows DST changes, and routines fire when that
+//! zone's clock first reads their time: once on a day the clocks go back,
+//! and when the clocks resume if they skip it.
+
+use chrono::{DateTime, Duration as ChronoDuration, NaiveTime};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::replay::Recorder;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{activity, clock, tray, PetState};
+
+/// Length of one tick of virtual time.
+pub const STEP_MS: u64 = 100;
+/// Most ticks run for one `advance`; longer gaps (a suspended laptop, a
+/// stalled thread) move the clock on without simulating them.
+pub const MAX_CATCH_UP: u64 = 50;
+/// Tiles a wander may go in each direction, and how many targets it offers.
+const WANDER_RADIUS: i32 = 3;
+const WANDER_TRIES: usize = 10;
+
+// ── settings ──
+
+/// `sim` block of `settings.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SimSettings {
+    /// Seed for every random decision; a fresh one each launch when unset.
+    pub seed: Option<u64>,
+    /// Expected idle wanders per minute.
+    pub wander_per_min: f64,
+    /// Seconds between speech bubbles, drawn between the two.
+    pub bubble_min_secs: f64,
+    pub bubble_max_secs: f64,
+    /// Values from 1 (met) to 0 that fall over time, by name.
+    pub needs: BTreeMap<String, NeedSettings>,
+    pub routines: Vec<Routine>,
+}
+
+impl Default for SimSettings {
+    fn default() -> Self {
+        SimSettings {
+            seed: None,
+            wander_per_min: 10.0,
+            bubble_min_secs: 6.0,
+            bubble_max_secs: 10.0,
+            needs: BTreeMap::new(),
+            routines: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct NeedSettings {
+    pub decay_per_hour: f64,
+    /// Refill rate while the pet is in one of `restore_in`.
+    pub restore_per_hour: f64,
+    pub restore_in: Vec<String>,
+    /// Falling below this sends `need_low`.
+    pub low: f64,
+}
+
+impl Default for NeedSettings {
+    fn default() -> Self {
+        NeedSettings {
+            decay_per_hour: 0.1,
+            restore_per_hour: 0.5,
+            restore_in: vec!["idle".into()],
+            low: 0.2,
+        }
+    }
+}
+
+/// Something the pet does at a time of day, on the virtual clock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Routine {
+    /// `HH:MM`.
+    pub at: String,
+    pub name: String,
+    /// Said in a bubble when the routine comes up.
+    #[serde(default)]
+    pub bubble: Option<String>,
+}
+
+// ── randomness and time ──
+
+/// SplitMix64: tiny, fast and the same everywhere, which is all that's
+/// needed here.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[lo, hi]`.
+    fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        lo + (self.next_u64() % (hi - lo + 1) as u64) as i32
+    }
+}
+
+/// Seed for a launch without `sim.seed`.
+pub fn fresh_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Time in a zone that only moves when the engine says so.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    start: DateTime<Tz>,
+    elapsed_ms: u64,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<Tz>) -> VirtualClock {
+        VirtualClock {
+            start,
+            elapsed_ms: 0,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Tz> {
+        self.start + ChronoDuration::milliseconds(self.elapsed_ms as i64)
+    }
+
+    fn forward(&mut self, ms: u64) {
+        self.elapsed_ms += ms;
+    }
+}
+
+// ── engine ──
+
+/// What the pet is doing, as far as behaviour goes.
+#[derive(Debug, Clone)]
+pub struct World {
+    pub state: String,
+    pub wander_paused: bool,
+    /// No input for `idle.idle_secs`: the pet sleeps, so no wanders or
+    /// bubbles.
+    pub user_idle: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimAction {
+    /// Tile offsets `[row, col]` to try in order; the first free one wins.
+    Wander {
+        offsets: Vec<[i32; 2]>,
+    },
+    /// Index into the bubble pool, taken modulo its length.
+    Bubble {
+        pick: u32,
+    },
+    Routine {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bubble: Option<String>,
+    },
+    NeedLow {
+        need: String,
+        value: f64,
+    },
+}
+
+/// Payload of `sim-event`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimEvent {
+    pub tick: u64,
+    /// Virtual local time, `YYYY-MM-DDTHH:MM:SS%.3f`.
+    pub at: String,
+    #[serde(flatten)]
+    pub action: SimAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimSnapshot {
+    pub seed: u64,
+    pub tick: u64,
+    pub at: String,
+    pub needs: BTreeMap<String, f64>,
+}
+
+pub struct Sim {
+    cfg: SimSettings,
+    seed: u64,
+    rng: Rng,
+    clock: VirtualClock,
+    tick: u64,
+    /// Wall time not yet worth a whole tick.
+    pending_ms: u64,
+    next_bubble_ms: u64,
+    needs: BTreeMap<String, f64>,
+}
+
+impl Sim {
+    pub fn new(cfg: SimSettings, seed: u64, start: DateTime<Tz>) -> Sim {
+        let mut sim = Sim {
+            cfg: SimSettings::default(),
+            seed,
+            rng: Rng::new(seed),
+            clock: VirtualClock::new(start),
+            tick: 0,
+            pending_ms: 0,
+            next_bubble_ms: 0,
+            needs: BTreeMap::new(),
+        };
+        sim.configure(cfg);
+        sim.next_bubble_ms = sim.bubble_gap();
+        sim
+    }
+
+    /// Swaps in new settings; needs keep their values, new ones start met.
+    pub fn configure(&mut self, cfg: SimSettings) {
+        self.needs.retain(|name, _| cfg.needs.contains_key(name));
+        for name in cfg.needs.keys() {
+            self.needs.entry(name.clone()).or_insert(1.0);
+        }
+        self.cfg = cfg;
+    }
+
+    pub fn settings(&self) -> &SimSettings {
+        &self.cfg
+    }
+
+    pub fn snapshot(&self) -> SimSnapshot {
+        SimSnapshot {
+            seed: self.seed,
+            tick: self.tick,
+            at: self.at(),
+            needs: self.needs.clone(),
+        }
+    }
+
+    /// Runs as many whole ticks as `elapsed_ms` (plus what was left over
+    /// last time) covers.
+    pub fn advance(&mut self, elapsed_ms: u64, world: &World) -> Vec<SimEvent> {
+        self.pending_ms += elapsed_ms;
+        let mut steps = self.pending_ms / STEP_MS;
+        self.pending_ms %= STEP_MS;
+        if steps > MAX_CATCH_UP {
+            let skipped = (steps - MAX_CATCH_UP) * STEP_MS;
+            self.clock.forward(skipped);
+            self.next_bubble_ms = self.next_bubble_ms.saturating_sub(skipped);
+            steps = MAX_CATCH_UP;
+        }
+        let mut events = Vec::new();
+        for _ in 0..steps {
+            self.step(world, &mut events);
+        }
+        events
+    }
+
+    fn at(&self) -> String {
+        self.clock.now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string()
+    }
+
+    fn emit(&self, events: &mut Vec<SimEvent>, action: SimAction) {
+        events.push(SimEvent {
+            tick: self.tick,
+            at: self.at(),
+            action,
+        });
+    }
+
+    fn bubble_gap(&mut self) -> u64 {
+        let lo = self.cfg.bubble_min_secs.max(0.5);
+        let hi = self.cfg.bubble_max_secs.max(lo);
+        ((lo + (hi - lo) * self.rng.next_f64()) * 1000.0) as u64
+    }
+
+    /// One tick, in a fixed order so the draws from `rng` line up.
+    fn step(&mut self, world: &World, events: &mut Vec<SimEvent>) {
+        let before = self.clock.now();
+        self.clock.forward(STEP_MS);
+        self.tick += 1;
+        let hours = STEP_MS as f64 / 3_600_000.0;
+
+        let mut low = Vec::new();
+        for (name, cfg) in &self.cfg.needs {
+            let Some(value) = self.needs.get_mut(name) else {
+                continue;
+            };
+            let old = *value;
+            let rate = if cfg.restore_in.contains(&world.state) {
+                cfg.restore_per_hour
+            } else {
+                -cfg.decay_per_hour
+            };
+            *value = (old + rate * hours).clamp(0.0, 1.0);
+            if old >= cfg.low && *value < cfg.low {
+                low.push((name.clone(), *value));
+            }
+        }
+        for (need, value) in low {
+            self.emit(events, SimAction::NeedLow { need, value });
+        }
+
+        let chance = self.cfg.wander_per_min / 60.0 * STEP_MS as f64 / 1000.0;
+        let roll = self.rng.next_f64();
+        if world.state == "idle" && !world.wander_paused && !world.user_idle && roll < chance {
+            let offsets = (0..WANDER_TRIES)
+                .map(|_| {
+                    [
+                        self.rng.range(-WANDER_RADIUS, WANDER_RADIUS),
+                        self.rng.range(-WANDER_RADIUS, WANDER_RADIUS),
+                    ]
+                })
+                .collect();
+            self.emit(events, SimAction::Wander { offsets });
+        }
+
+        self.next_bubble_ms = self.next_bubble_ms.saturating_sub(STEP_MS);
+        if self.next_bubble_ms == 0 {
+            let pick = self.rng.next_u64() as u32;
+            if !world.user_idle {
+                self.emit(events, SimAction::Bubble { pick });
+            }
+            self.next_bubble_ms = self.bubble_gap();
+        }
+
+        let now = self.clock.now();
+        let due: Vec<Routine> = self
+            .cfg
+            .routines
+            .iter()
+            .filter(|r| clock::parse_time(&r.at).is_some_and(|t| crossed(before, now, t)))
+            .cloned()
+            .collect();
+        for routine in due {
+            let Routine { name, bubble, .. } = routine;
+            self.emit(events, SimAction::Routine { name, bubble });
+        }
+    }
+}
+
+/// Whether the clock first reads `at` o'clock in `(before, now]`.
+fn crossed(before: DateTime<Tz>, now: DateTime<Tz>, at: NaiveTime) -> bool {
+    let tz = now.timezone();
+    [before.date_naive(), now.date_naive()]
+        .into_iter()
+        .any(|day| clock::first_instant(tz, day, at).is_some_and(|c| before < c && c <= now))
+}
+
+// ── in the app ──
+
+/// Managed by the app; `None` until the engine thread starts.
+#[derive(Default)]
+pub struct Engine {
+    sim: Mutex<Option<Sim>>,
+    state: Mutex<String>,
+}
+
+impl Engine {
+    /// Follows the pet's state; called wherever a state is announced.
+    pub(crate) fn observe(&self, state: &PetState) {
+        *self.state.lock_or_recover() = state.state.clone();
+    }
+}
+
+fn start_time(app: &AppHandle) -> DateTime<Tz> {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .clock
+        .clone();
+    clock::now_in(clock::zone(None, &cfg).0)
+}
+
+fn create(app: &AppHandle, seed: Option<u64>) -> Sim {
+    let cfg = app.state::<Mutex<Settings>>().lock_or_recover().sim.clone();
+    let seed = seed.or(cfg.seed).unwrap_or_else(fresh_seed);
+    log!("🎲 sim seed {seed}");
+    Sim::new(cfg, seed, start_time(app))
+}
+
+pub(crate) fn spawn_engine(app: AppHandle) {
+    let engine = app.state::<Engine>();
+    *engine.sim.lock_or_recover() = Some(create(&app, None));
+    {
+        let mut state = engine.state.lock_or_recover();
+        if state.is_empty() {
+            *state = "idle".into();
+        }
+    }
+    std::thread::spawn(move || {
+        let mut last = Instant::now();
+        loop {
+            std::thread::sleep(Duration::from_millis(STEP_MS));
+            let elapsed = last.elapsed().as_millis() as u64;
+            last = Instant::now();
+            let cfg = app.state::<Mutex<Settings>>().lock_or_recover().sim.clone();
+            let engine = app.state::<Engine>();
+            let world = World {
+                state: engine.state.lock_or_recover().clone(),
+                wander_paused: tray::wander_paused(),
+                user_idle: activity::user_idle(),
+            };
+            let events = {
+                let mut sim = engine.sim.lock_or_recover();
+                let Some(sim) = sim.as_mut() else {
+                    continue;
+                };
+                if sim.settings() != &cfg {
+                    sim.configure(cfg);
+                }
+                sim.advance(elapsed, &world)
+            };
+            for event in events {
+                app.state::<Recorder>().capture("sim-event", &event);
+                let _ = app.emit("sim-event", event);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_sim_snapshot(engine: tauri::State<'_, Engine>) -> Result<SimSnapshot, String> {
+    engine
+        .sim
+        .lock_or_recover()
+        .as_ref()
+        .map(Sim::snapshot)
+        .ok_or_else(|| "simulation not started".to_string())
+}
+
+/// Starts over at tick 0 from now, with `seed` or else `sim.seed` or else a
+/// fresh one; the snapshot tells which.
+#[tauri::command]
+pub fn reset_sim(app: AppHandle, seed: Option<u64>) -> SimSnapshot {
+    let sim = create(&app, seed);
+    let snapshot = sim.snapshot();
+    *app.state::<Engine>().sim.lock_or_recover() = Some(sim);
+    snapshot
+}
@@ -0,0 +1,487 @@
+//! Per-state and per-event sounds, played by the backend so they're heard
+//! even while every pet window is hidden.
+//!
+//! Entering a state listed in `sounds.state_sounds`, or in the `states` of
+//! the pack's `layers/sounds.json`, plays its file at most once per
+//! `cooldown_secs`; so does every backend event named in `events`
+//! (`meeting-starting-soon`, …). `play_sound` plays one of them on demand.
+//! Nothing plays during `quiet_hours` or while the OS is in
+//! do-not-disturb.
+//!
+//! With the `audio` feature files are decoded and mixed in-process by
+//! rodio; otherwise they go to the platform's own player.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::clock::{self, ClockSettings};
+use crate::logs::log;
+use crate::settings::{self, Settings};
+use crate::sync::LockExt;
+use crate::{fsutil, notify, AppPaths, PetState};
+
+pub(crate) const FILE: &str = "sounds.json";
+
+static SOUNDS: Mutex<SoundsFile> = Mutex::new(SoundsFile::empty());
+/// Events that already have a listener; they stay registered and do
+/// nothing once removed from the file.
+static LISTENING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+static PLAYER: Mutex<Player> = Mutex::new(Player {
+    last_state: None,
+    played_at: BTreeMap::new(),
+});
+
+/// One entry of `sounds.state_sounds`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StateSound {
+    /// Relative to `layers/`, or absolute.
+    pub file: String,
+    /// 0 to 1, on top of `sounds.volume`.
+    #[serde(default = "full_volume")]
+    pub volume: f64,
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: u32,
+}
+
+fn full_volume() -> f64 {
+    1.0
+}
+
+fn default_cooldown() -> u32 {
+    30
+}
+
+/// A file name, or a file with its own volume and cooldown.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum SoundEntry {
+    File(String),
+    Sound(StateSound),
+}
+
+impl SoundEntry {
+    fn sound(&self) -> StateSound {
+        match self {
+            SoundEntry::File(file) => StateSound {
+                file: file.clone(),
+                volume: full_volume(),
+                cooldown_secs: default_cooldown(),
+            },
+            SoundEntry::Sound(sound) => sound.clone(),
+        }
+    }
+}
+
+/// `layers/sounds.json`: the pack's own sounds.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default)]
+pub(crate) struct SoundsFile {
+    /// 0 to 1 for the whole pack, on top of `sounds.volume`.
+    pub volume: f64,
+    /// By state name, like `sounds.state_sounds`, which wins for a state
+    /// both list.
+    pub states: BTreeMap<String, SoundEntry>,
+    /// By backend event name, e.g. `meeting-starting-soon`.
+    pub events: BTreeMap<String, SoundEntry>,
+}
+
+impl SoundsFile {
+    const fn empty() -> Self {
+        SoundsFile {
+            volume: 1.0,
+            states: BTreeMap::new(),
+            events: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for SoundsFile {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// Wall-clock `HH:MM`; may wrap past midnight.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+    /// IANA zone; `clock.timezone` or the system zone when unset.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// `sounds` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SoundSettings {
+    pub enabled: bool,
+    /// Master volume, 0 to 1.
+    pub volume: f64,
+    pub state_sounds: HashMap<String, StateSound>,
+    pub quiet_hours: Option<QuietHours>,
+    /// Stay silent while the OS reports do-not-disturb / focus mode.
+    pub respect_dnd: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings {
+            enabled: true,
+            volume: 1.0,
+            state_sounds: HashMap::new(),
+            quiet_hours: None,
+            respect_dnd: true,
+        }
+    }
+}
+
+fn in_quiet_hours(quiet: &QuietHours, clock: &ClockSettings) -> bool {
+    let (Some(start), Some(end)) = (
+        clock::parse_time(&quiet.start),
+        clock::parse_time(&quiet.end),
+    ) else {
+        log!(
+            "⚠️ sounds.quiet_hours: expected HH:MM, got {} – {}",
+            quiet.start,
+            quiet.end
+        );
+        return false;
+    };
+    let (tz, _) = clock::zone(quiet.timezone.as_deref(), clock);
+    clock::in_window(start, end, &clock::now_in(tz))
+}
+
+#[cfg(feature = "audio")]
+mod imp {
+    use rodio::{Decoder, OutputStream, Sink};
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::{Mutex, OnceLock};
+
+    use crate::logs::log;
+    use crate::sync::LockExt;
+
+    type Clip = (Decoder<BufReader<File>>, f32);
+
+    /// The output stream can't leave the thread that opened it.
+    static OUTPUT: OnceLock<Mutex<Sender<Clip>>> = OnceLock::new();
+
+    fn output() -> Sender<Clip> {
+        let (tx, rx) = mpsc::channel::<Clip>();
+        std::thread::spawn(move || {
+            let mut stream = None;
+            for (source, volume) in rx {
+                if stream.is_none() {
+                    match OutputStream::try_default() {
+                        Ok(opened) => stream = Some(opened),
+                        Err(e) => {
+                            log!("⚠️ audio output: {e}");
+                            continue;
+                        }
+                    }
+                }
+                let Some((_, handle)) = &stream else {
+                    continue;
+                };
+                match Sink::try_new(handle) {
+                    Ok(sink) => {
+                        sink.set_volume(volume);
+                        sink.append(source);
+                        sink.detach();
+                    }
+                    Err(e) => {
+                        log!("⚠️ audio output: {e}; reopening");
+                        stream = None;
+                    }
+                }
+            }
+        });
+        tx
+    }
+
+    /// Decoded here, so a broken file is reported to the caller.
+    pub(super) fn play(file: &Path, volume: f64) -> Result<(), String> {
+        let reader = File::open(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        let source =
+            Decoder::new(BufReader::new(reader)).map_err(|e| format!("{}: {e}", file.display()))?;
+        OUTPUT
+            .get_or_init(|| Mutex::new(output()))
+            .lock_or_recover()
+            .send((source, volume as f32))
+            .map_err(|_| "the audio thread stopped".to_string())
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+mod imp {
+    use std::path::Path;
+    use std::process::Command;
+
+    #[cfg(target_os = "macos")]
+    fn command(file: &Path, volume: f64) -> Command {
+        let mut c = Command::new("afplay");
+        c.arg("-v").arg(format!("{volume:.2}")).arg(file);
+        c
+    }
+
+    #[cfg(target_os = "windows")]
+    fn command(file: &Path, volume: f64) -> Command {
+        // MediaPlayer handles mp3/wav and volume; the script waits for the end
+        // so the process lives as long as the sound.
+        const SCRIPT: &str = r#"
+Add-Type -AssemblyName PresentationCore
+$p = New-Object System.Windows.Media.MediaPlayer
+$p.Open([Uri]$env:STAR_SOUND_FILE)
+$p.Volume = [double]$env:STAR_SOUND_VOLUME
+$p.Play()
+Start-Sleep -Milliseconds 300
+while ($p.NaturalDuration.HasTimeSpan -and $p.Position -lt $p.NaturalDuration.TimeSpan) { Start-Sleep -Milliseconds 200 }
+"#;
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+            .env("STAR_SOUND_FILE", file)
+            .env("STAR_SOUND_VOLUME", format!("{volume:.2}"));
+        c
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn command(file: &Path, volume: f64) -> Command {
+        // paplay's scale is 0..=65536 for 0..=100%.
+        let mut c = Command::new("paplay");
+        c.arg(format!("--volume={}", (volume * 65536.0).round() as u32))
+            .arg(file);
+        c
+    }
+
+    /// The player runs as long as the sound; a thread waits on it so it
+    /// doesn't linger as a zombie once it exits.
+    pub(super) fn play(file: &Path, volume: f64) -> Result<(), String> {
+        let mut child = command(file, volume)
+            .spawn()
+            .map_err(|e| format!("play {}: {e}", file.display()))?;
+        std::thread::spawn(move || {
+            let _ = child.wait();
+        });
+        Ok(())
+    }
+}
+
+fn play(file: &Path, volume: f64) -> Result<(), String> {
+    if !fsutil::exists(file) {
+        return Err(format!("{}: sound file not found", file.display()));
+    }
+    imp::play(file, volume.clamp(0.0, 1.0))
+}
+
+struct Player {
+    last_state: Option<String>,
+    /// By `state:<name>` / `event:<name>`.
+    played_at: BTreeMap<String, Instant>,
+}
+
+impl Player {
+    /// Whether `state` was just entered.
+    fn entered(&mut self, state: &str) -> bool {
+        let entered = self.last_state.as_deref() != Some(state);
+        self.last_state = Some(state.to_string());
+        entered
+    }
+
+    /// Whether `key` is off its cooldown; if so, it starts again.
+    fn cooled(&mut self, key: String, sound: &StateSound) -> bool {
+        let cooldown = Duration::from_secs(u64::from(sound.cooldown_secs));
+        let now = Instant::now();
+        if self
+            .played_at
+            .get(&key)
+            .is_some_and(|at| now.duration_since(*at) < cooldown)
+        {
+            return false;
+        }
+        self.played_at.insert(key, now);
+        true
+    }
+}
+
+fn resolve(layers_dir: &Path, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        layers_dir.join(path)
+    }
+}
+
+fn layers_dir(app: &AppHandle) -> PathBuf {
+    app.state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .layers_dir
+        .clone()
+}
+
+/// The sound for `state` and the pack volume it plays at.
+fn state_sound(cfg: &SoundSettings, state: &str) -> Option<(StateSound, f64)> {
+    if let Some(sound) = cfg.state_sounds.get(state) {
+        return Some((sound.clone(), 1.0));
+    }
+    let file = SOUNDS.lock_or_recover();
+    let entry = file.states.get(state)?;
+    Some((entry.sound(), file.volume))
+}
+
+fn event_sound(event: &str) -> Option<(StateSound, f64)> {
+    let file = SOUNDS.lock_or_recover();
+    let entry = file.events.get(event)?;
+    Some((entry.sound(), file.volume))
+}
+
+/// Plays `sound` for `label` unless sounds are off or muted right now;
+/// `Ok(false)` when it stayed silent.
+fn sound_out(
+    app: &AppHandle,
+    label: &str,
+    sound: &StateSound,
+    pack_volume: f64,
+) -> Result<bool, String> {
+    let (cfg, clock) = {
+        let settings = app.state::<Mutex<Settings>>();
+        let s = settings.lock_or_recover();
+        (s.sounds.clone(), s.clock.clone())
+    };
+    if !cfg.enabled {
+        return Ok(false);
+    }
+    if cfg
+        .quiet_hours
+        .as_ref()
+        .is_some_and(|q| in_quiet_hours(q, &clock))
+    {
+        return Ok(false);
+    }
+    if cfg.respect_dnd && notify::do_not_disturb() == Some(true) {
+        return Ok(false);
+    }
+    let file = resolve(&layers_dir(app), &sound.file);
+    play(&file, sound.volume * pack_volume * cfg.volume)?;
+    log!("🔔 {label} → {}", file.display());
+    Ok(true)
+}
+
+/// Plays the sound of `state` when the pet enters it.
+pub(crate) fn on_state(app: &AppHandle, state: &PetState) {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .sounds
+        .clone();
+    let mut player = PLAYER.lock_or_recover();
+    if !player.entered(&state.state) {
+        return;
+    }
+    let Some((sound, pack_volume)) = state_sound(&cfg, &state.state) else {
+        return;
+    };
+    if !player.cooled(format!("state:{}", state.state), &sound) {
+        return;
+    }
+    drop(player);
+    if let Err(e) = sound_out(app, &state.state, &sound, pack_volume) {
+        log!("⚠️ {e}");
+    }
+}
+
+fn on_event(app: &AppHandle, event: &str) {
+    let Some((sound, pack_volume)) = event_sound(event) else {
+        return;
+    };
+    if !PLAYER
+        .lock_or_recover()
+        .cooled(format!("event:{event}"), &sound)
+    {
+        return;
+    }
+    if let Err(e) = sound_out(app, event, &sound, pack_volume) {
+        log!("⚠️ {e}");
+    }
+}
+
+/// Reads `layers/sounds.json` and listens for the events it names. A
+/// missing file means no pack sounds; a broken one is reported and leaves
+/// the previous ones in place.
+pub(crate) fn reload(app: &AppHandle) {
+    let path = layers_dir(app).join(FILE);
+    let parsed = if fsutil::exists(&path) {
+        fsutil::read_text(&path).and_then(|raw| {
+            serde_json::from_str::<SoundsFile>(&raw).map_err(|e| format!("{FILE}: {e}"))
+        })
+    } else {
+        Ok(SoundsFile::empty())
+    };
+    let file = match parsed {
+        Ok(file) => file,
+        Err(e) => {
+            log!("⚠️ {e}; keeping the previous sounds");
+            return;
+        }
+    };
+    let mut listening = LISTENING.lock_or_recover();
+    for event in file.events.keys() {
+        if listening.insert(event.clone()) {
+            let (handle, name) = (app.clone(), event.clone());
+            app.listen_any(event.as_str(), move |_| on_event(&handle, &name));
+        }
+    }
+    if !file.states.is_empty() || !file.events.is_empty() {
+        log!(
+            "🔔 {FILE}: {} state and {} event sound(s)",
+            file.states.len(),
+            file.events.len()
+        );
+    }
+    *SOUNDS.lock_or_recover() = file;
+}
+
+/// Plays the sound for a state or event name now, cooldown or not; `false`
+/// when sounds are off or muted by quiet hours or do-not-disturb.
+#[tauri::command]
+pub fn play_sound(app: AppHandle, key: String) -> Result<bool, String> {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .sounds
+        .clone();
+    let (sound, pack_volume) = state_sound(&cfg, &key)
+        .or_else(|| event_sound(&key))
+        .ok_or_else(|| format!("no sound for `{key}` in sounds.state_sounds or {FILE}"))?;
+    sound_out(&app, &key, &sound, pack_volume)
+}
+
+/// Sets and saves `sounds.volume`, 0 to 1.
+#[tauri::command]
+pub fn set_volume(app: AppHandle, volume: f64) -> Result<f64, String> {
+    if !volume.is_finite() {
+        return Err(format!("volume must be a number from 0 to 1, got {volume}"));
+    }
+    let volume = volume.clamp(0.0, 1.0);
+    let sounds = {
+        let settings = app.state::<Mutex<Settings>>();
+        let mut settings = settings.lock_or_recover();
+        settings.sounds.volume = volume;
+        settings.sounds.clone()
+    };
+    let settings_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    settings::save_block(&settings_path, "sounds", &sounds)?;
+    Ok(volume)
+}
@@ -0,0 +1,93 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::logs::log;
+use crate::{fsutil, PetState};
+
+// ── locking protocol ──
+//
+// Writers hold an exclusive lock on `state.json.lock`, write `state.json.tmp`
+// and rename it over `state.json`. Readers hold a shared lock while reading.
+// Producers that ignore the protocol can still tear a read, so parse failures
+// are retried briefly before being reported. See STATE_API.md.
+
+const LOCK_WAIT: Duration = Duration::from_millis(500);
+const READ_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(30);
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Takes the sidecar lock, giving up after `LOCK_WAIT` so a stuck producer
+/// can't freeze the UI. `None` means "proceed unlocked".
+fn acquire(path: &Path, exclusive: bool) -> Option<File> {
+    let lock_path = fsutil::long_path(&sidecar(path, ".lock"));
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)
+        .ok()?;
+    let deadline = Instant::now() + LOCK_WAIT;
+    loop {
+        let attempt = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match attempt {
+            Ok(()) => return Some(file),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(TryLockError::WouldBlock) => {
+                log!("⚠️ state lock busy for {LOCK_WAIT:?}, continuing unlocked");
+                return None;
+            }
+            Err(TryLockError::Error(_)) => return None,
+        }
+    }
+}
+
+// ── read / write ──
+
+pub(crate) fn read(path: &Path) -> Result<PetState, AppError> {
+    let mut attempt = 0;
+    loop {
+        let result: Result<PetState, AppError> = {
+            let _lock = acquire(path, false);
+            let raw = fsutil::load_text(path)?;
+            serde_json::from_str(&raw).map_err(|e| AppError::parse(path.display(), &e))
+        };
+        match result {
+            Err(_) if attempt + 1 < READ_RETRIES => {
+                attempt += 1;
+                std::thread::sleep(RETRY_DELAY);
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Replaces `state.json` atomically so readers only ever see a whole file.
+pub(crate) fn write(path: &Path, state: &PetState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| format!("serialize: {e}"))?;
+    let tmp = sidecar(path, ".tmp");
+    let _lock = acquire(path, true);
+
+    let mut file =
+        File::create(fsutil::long_path(&tmp)).map_err(|e| format!("{}: {e}", tmp.display()))?;
+    file.write_all(json.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("{}: {e}", tmp.display()))?;
+    drop(file);
+
+    fs::rename(fsutil::long_path(&tmp), fsutil::long_path(path))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
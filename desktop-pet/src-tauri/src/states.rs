@@ -0,0 +1,164 @@
+//! Every state name the pet understands, gathered from the built-in list
+//! (see STATE_API.md) and whatever the current pack references, so menus
+//! and tools can offer real choices instead of free text.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use tauri::image::Image;
+
+use crate::limits::Limits;
+use crate::locate;
+use crate::logs::log;
+use crate::pet_core::{self, AssetSource, FsAssets, Overlay};
+use crate::sync::LockExt;
+use crate::{imaging, settings, AppPaths};
+
+/// Standard states, in the order STATE_API.md documents them.
+pub(crate) const BUILTIN: &[(&str, &str)] = &[
+    ("idle", "No task; breathing animation and random wandering"),
+    ("writing", "Writing or taking notes"),
+    ("receiving", "Receiving a message"),
+    ("replying", "Replying to a message"),
+    ("researching", "Researching or looking things up"),
+    ("executing", "Running a task"),
+    ("syncing", "Syncing or backing up"),
+    ("error", "Something went wrong"),
+    ("gaming", "A game is running; set by the app itself"),
+];
+
+/// Alias → standard state, as mapped by the frontend.
+pub(crate) const ALIASES: &[(&str, &str)] = &[
+    ("working", "writing"),
+    ("run", "executing"),
+    ("running", "executing"),
+    ("sync", "syncing"),
+    ("research", "researching"),
+    ("focusing", "writing"),
+    ("on_break", "idle"),
+    ("meeting", "replying"),
+    ("building", "executing"),
+    ("success", "idle"),
+    ("failed", "error"),
+];
+
+#[derive(Debug, Serialize)]
+pub struct KnownState {
+    pub(crate) name: String,
+    builtin: bool,
+    description: Option<&'static str>,
+    aliases: Vec<&'static str>,
+    /// Data URL of the map's state icon, when the pack has one.
+    icon: Option<String>,
+    /// Where the name was found: `builtin`, `state_icons`, `pois`, `anims`.
+    sources: Vec<&'static str>,
+}
+
+impl KnownState {
+    fn new(name: &str) -> Self {
+        let builtin = BUILTIN.iter().find(|(n, _)| *n == name);
+        KnownState {
+            name: name.to_string(),
+            builtin: builtin.is_some(),
+            description: builtin.map(|(_, d)| *d),
+            aliases: ALIASES
+                .iter()
+                .filter(|(_, target)| *target == name)
+                .map(|(alias, _)| *alias)
+                .collect(),
+            icon: None,
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// Built-in states first, then pack-only names alphabetically. A pack
+/// that fails to load still yields the built-in list.
+pub(crate) fn known_states(paths: &AppPaths) -> Vec<KnownState> {
+    let limits = settings::load(&paths.settings_path).limits;
+    let fs = FsAssets::new(&paths.layers_dir);
+    let src = locate::with_map(paths, &fs, &limits).unwrap_or_else(|_| Overlay::new(&fs));
+    let mut found: BTreeMap<String, KnownState> = BTreeMap::new();
+    let mut note = |name: &str, source: &'static str| {
+        let entry = found
+            .entry(name.to_string())
+            .or_insert_with(|| KnownState::new(name));
+        if !entry.sources.contains(&source) {
+            entry.sources.push(source);
+        }
+    };
+
+    for (name, _) in BUILTIN {
+        note(name, "builtin");
+    }
+    let mut icons = Vec::new();
+    if let Ok(map) = pet_core::read_map_cfg(&src, &limits) {
+        for (state, file) in map.state_icons.unwrap_or_default() {
+            note(&state, "state_icons");
+            icons.push((state, pet_core::state_icon_path(&file)));
+        }
+        for state in map.pois.unwrap_or_default().into_keys() {
+            note(&state, "pois");
+        }
+    }
+    // Anim keys also include things like `walk`, so they only annotate
+    // states found elsewhere rather than introducing new ones.
+    let anims: BTreeSet<String> = pet_core::read_layers_cfg(&src, &limits)
+        .map(|cfg| {
+            let pets = cfg.characters.unwrap_or_default();
+            let skins = cfg.skins.unwrap_or_default().into_values().map(Some);
+            std::iter::once(cfg.sprites)
+                .chain(pets.into_iter().map(|p| p.sprites))
+                .chain(skins)
+                .flatten()
+                .flat_map(|s| s.anims.unwrap_or_default().into_keys())
+                .collect()
+        })
+        .unwrap_or_default();
+    for key in &anims {
+        if let Some(entry) = found.get_mut(key) {
+            entry.sources.push("anims");
+        }
+    }
+    for (state, rel) in icons {
+        if let Some(entry) = found.get_mut(&state) {
+            if src.exists(&rel) {
+                entry.icon = pet_core::encode_image(&src, &rel, &limits).ok();
+            }
+        }
+    }
+
+    let mut states: Vec<KnownState> = BUILTIN
+        .iter()
+        .filter_map(|(name, _)| found.remove(*name))
+        .collect();
+    states.extend(found.into_values());
+    states
+}
+
+/// The map's `state_icons` image for `name` or the state it aliases, for
+/// native surfaces: the taskbar badge and the tray icon.
+pub(crate) fn icon_image(paths: &AppPaths, limits: &Limits, name: &str) -> Option<Image<'static>> {
+    let fs = FsAssets::new(&paths.layers_dir);
+    let src = locate::with_map(paths, &fs, limits).ok()?;
+    let icons = pet_core::read_map_cfg(&src, limits).ok()?.state_icons?;
+    let canonical = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, target)| *target);
+    let file = icons.get(name).or_else(|| icons.get(canonical))?;
+    let rel = pet_core::state_icon_path(file);
+    let bytes = src.read(&rel).ok()?;
+    let image = imaging::decode(std::path::Path::new(&rel), &bytes)
+        .map_err(|e| log!("⚠️ state icon {rel}: {e}"))
+        .ok()?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    Some(Image::new_owned(image.into_raw(), width, height))
+}
+
+#[tauri::command]
+pub fn list_known_states(paths: tauri::State<'_, Mutex<AppPaths>>) -> Vec<KnownState> {
+    let paths = paths.lock_or_recover().clone();
+    known_states(&paths)
+}
@@ -0,0 +1,42 @@
+//! Local stats store: one JSON document per collector under
+//! `.star-pet/stats/`, read whole and replaced atomically on save.
+//!
+//! Nothing here leaves the machine.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil;
+use crate::logs::log;
+
+pub(crate) fn dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("stats")
+}
+
+fn path(data_dir: &Path, name: &str) -> PathBuf {
+    dir(data_dir).join(format!("{name}.json"))
+}
+
+/// A missing or unreadable document starts over from the default, so a
+/// corrupt stats file never blocks the feature that writes it.
+pub(crate) fn load<T: DeserializeOwned + Default>(data_dir: &Path, name: &str) -> T {
+    let path = path(data_dir, name);
+    if !fsutil::exists(&path) {
+        return T::default();
+    }
+    fsutil::read_text(&path)
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| format!("{}: {e}", path.display())))
+        .unwrap_or_else(|e| {
+            log!("⚠️ {e}; starting fresh");
+            T::default()
+        })
+}
+
+pub(crate) fn save<T: Serialize>(data_dir: &Path, name: &str, value: &T) -> Result<(), String> {
+    let dir = dir(data_dir);
+    std::fs::create_dir_all(fsutil::long_path(&dir))
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    let json = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    fsutil::write_atomic(&path(data_dir, name), &json)
+}
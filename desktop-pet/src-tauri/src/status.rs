@@ -0,0 +1,313 @@
+//! Read-only status page for checking on the pet host from another device.
+//!
+//! With `status.enabled` the pet answers plain HTTP on `status.port`:
+//! `/status` is a small self-refreshing HTML page with the current state,
+//! uptime, which integrations are on and the latest log lines;
+//! `/status.json` is the same as JSON; `/overlay` is the stream overlay
+//! (see [`crate::overlay`]). `status.token` must be passed as `?token=`;
+//! an empty one is replaced with a random token saved to `settings.json`
+//! unless `status.allow_no_token` opts out. Read once at startup.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::dev::percent_decode;
+use crate::error::AppError;
+use crate::logs::{self, log};
+use crate::settings::{self, Settings};
+use crate::sync::LockExt;
+use crate::{control, health, overlay, power, AppPaths, PetState};
+
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Seconds between reloads of the HTML page.
+const REFRESH_SECS: u32 = 10;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// `status` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct StatusSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Required as `?token=`; generated on start when empty.
+    pub token: String,
+    /// Serve without a token, to anyone on the network.
+    pub allow_no_token: bool,
+    /// How many recent log lines the page shows.
+    pub log_lines: usize,
+}
+
+impl Default for StatusSettings {
+    fn default() -> Self {
+        StatusSettings {
+            enabled: false,
+            port: 18794,
+            token: String::new(),
+            allow_no_token: false,
+            log_lines: 30,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Integration {
+    name: &'static str,
+    enabled: bool,
+    /// Skipped for now because the energy saver is on.
+    paused: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Snapshot {
+    version: &'static str,
+    uptime_secs: u64,
+    /// `None` with `state_error` when the state can't be read.
+    state: Option<PetState>,
+    state_error: Option<AppError>,
+    integrations: Vec<Integration>,
+    log: Vec<String>,
+}
+
+fn snapshot(app: &AppHandle, log_lines: usize) -> Snapshot {
+    let (state, state_error) = match crate::current_state(app.state(), app.state()) {
+        Ok(state) => (Some(state), None),
+        Err(e) => (None, Some(e)),
+    };
+    let settings = app.state::<Mutex<Settings>>().lock_or_recover().clone();
+    let integrations = health::integrations(&settings)
+        .into_iter()
+        .map(|(name, enabled)| Integration {
+            name,
+            enabled,
+            paused: enabled && power::paused(app, name),
+        })
+        .collect();
+    let tail = logs::tail();
+    let log = tail[tail.len().saturating_sub(log_lines)..].to_vec();
+    Snapshot {
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs: STARTED.get().map_or(0, |t| t.elapsed().as_secs()),
+        state,
+        state_error,
+        integrations,
+        log,
+    }
+}
+
+// ── rendering ──
+
+fn escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn uptime(secs: u64) -> String {
+    let (d, h, m) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    match (d, h) {
+        (0, 0) => format!("{m}m {}s", secs % 60),
+        (0, _) => format!("{h}h {m}m"),
+        _ => format!("{d}d {h}h"),
+    }
+}
+
+fn render(s: &Snapshot) -> String {
+    let state = match (&s.state, &s.state_error) {
+        (Some(st), _) => {
+            let mut rows = format!("<tr><th>state</th><td>{}</td></tr>", escape(&st.state));
+            if let Some(detail) = &st.detail {
+                rows += &format!("<tr><th>detail</th><td>{}</td></tr>", escape(detail));
+            }
+            if let Some(p) = st.progress {
+                rows += &format!("<tr><th>progress</th><td>{:.0}%</td></tr>", p * 100.0);
+            }
+            for (name, src) in &st.progress_sources {
+                let mark = if src.stale { " ⏱️ stale" } else { "" };
+                rows += &format!(
+                    "<tr><th>· {}</th><td>{:.0}%{mark}</td></tr>",
+                    escape(src.label.as_deref().unwrap_or(name)),
+                    src.value.clamp(0.0, 1.0) * 100.0
+                );
+            }
+            if let Some(at) = &st.updated_at {
+                rows += &format!("<tr><th>updated</th><td>{}</td></tr>", escape(at));
+            }
+            rows
+        }
+        (None, e) => format!(
+            "<tr><th>state</th><td>unavailable: {}</td></tr>",
+            escape(&e.as_ref().map(ToString::to_string).unwrap_or_default())
+        ),
+    };
+    let integrations: String = s
+        .integrations
+        .iter()
+        .map(|i| {
+            let mark = match (i.enabled, i.paused) {
+                (false, _) => "off",
+                (true, false) => "on",
+                (true, true) => "paused",
+            };
+            format!("<tr><th>{}</th><td>{mark}</td></tr>", i.name)
+        })
+        .collect();
+    let log = escape(&s.log.join("\n"));
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <meta http-equiv=\"refresh\" content=\"{REFRESH_SECS}\">\
+         <title>Star Desktop Pet</title><style>\
+         body{{font:14px system-ui,sans-serif;margin:16px;background:#1e1e2e;color:#ddd}}\
+         th{{text-align:left;padding-right:12px;color:#999;font-weight:normal}}\
+         pre{{white-space:pre-wrap;word-break:break-all;font-size:12px;background:#11111b;padding:8px}}\
+         </style></head><body>\
+         <h2>⭐ Star Desktop Pet</h2>\
+         <table><tr><th>version</th><td>{}</td></tr><tr><th>uptime</th><td>{}</td></tr>{state}</table>\
+         <h3>Integrations</h3><table>{integrations}</table>\
+         <h3>Log</h3><pre>{log}</pre></body></html>",
+        s.version,
+        uptime(s.uptime_secs),
+    )
+}
+
+// ── server ──
+
+pub(crate) fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| percent_decode(&v.replace('+', " ")))
+}
+
+fn handle(app: &AppHandle, mut stream: TcpStream, cfg: &StatusSettings) -> std::io::Result<()> {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let authorized = if cfg.token.is_empty() {
+        cfg.allow_no_token
+    } else {
+        query_param(query, "token").as_deref() == Some(cfg.token.as_str())
+    };
+    let (status, mime, body) = if method != "GET" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            "GET only".to_string(),
+        )
+    } else if !authorized {
+        ("403 Forbidden", "text/plain", "token required".to_string())
+    } else {
+        match path {
+            "/" | "/status" => (
+                "200 OK",
+                "text/html; charset=utf-8",
+                render(&snapshot(app, cfg.log_lines)),
+            ),
+            "/status.json" => (
+                "200 OK",
+                "application/json; charset=utf-8",
+                serde_json::to_string(&snapshot(app, cfg.log_lines)).unwrap_or_default(),
+            ),
+            "/overlay" => (
+                "200 OK",
+                "text/html; charset=utf-8",
+                overlay::PAGE.to_string(),
+            ),
+            "/overlay/pet.json" => (
+                "200 OK",
+                "application/json; charset=utf-8",
+                overlay::pet_json(app, query_param(query, "skin").as_deref()),
+            ),
+            "/overlay/state.json" => (
+                "200 OK",
+                "application/json; charset=utf-8",
+                overlay::state_json(app),
+            ),
+            _ => ("404 Not Found", "text/plain", "not found".to_string()),
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {mime}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())
+}
+
+/// Gives an enabled page without a token one, kept in `settings.json` so
+/// bookmarks keep working. Errs when it can't be saved.
+fn ensure_token(app: &AppHandle, cfg: &mut StatusSettings) -> Result<(), String> {
+    cfg.token = control::fresh_token()?;
+    let settings_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    settings::save_block(&settings_path, "status", &*cfg)?;
+    app.state::<Mutex<Settings>>().lock_or_recover().status = cfg.clone();
+    log!("📊 generated status.token in settings.json");
+    Ok(())
+}
+
+pub(crate) fn start(app: AppHandle) {
+    STARTED.get_or_init(Instant::now);
+    let mut cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .status
+        .clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.token.is_empty() && !cfg.allow_no_token {
+        if let Err(e) = ensure_token(&app, &mut cfg) {
+            log!("⚠️ status page off, no token: {e}");
+            return;
+        }
+    }
+    let listener = match TcpListener::bind(("0.0.0.0", cfg.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log!("⚠️ status page on port {}: {e}", cfg.port);
+            return;
+        }
+    };
+    if cfg.token.is_empty() {
+        log!("📊 status page on port {} (no token)", cfg.port);
+    } else {
+        log!("📊 status page on port {}", cfg.port);
+    }
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (app, cfg) = (app.clone(), cfg.clone());
+            std::thread::spawn(move || {
+                if let Err(e) = handle(&app, stream, &cfg) {
+                    log!("⚠️ status request: {e}");
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,23 @@
+use std::sync::{Mutex, MutexGuard};
+
+use crate::logs::log;
+
+/// Locking that survives a panic in another command.
+///
+/// A command that panics while holding a lock poisons it, and with plain
+/// `lock()` every later command would fail until restart. The guarded data
+/// here is plain config that is always left in a consistent state between
+/// statements, so taking it over is safe.
+pub(crate) trait LockExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            log!("⚠️ recovered poisoned lock");
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
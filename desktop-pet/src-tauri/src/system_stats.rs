@@ -0,0 +1,177 @@
+//! CPU and memory load of the machine, for a pet that sweats when it's
+//! busy.
+//!
+//! A background thread samples every `system_stats.interval_secs`, keeps the
+//! latest sample for `system_stats()` and emits it as `system-stats`.
+//! `heavy` is set past either threshold; the window shows it.
+
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::{ProcessesToUpdate, System, MINIMUM_CPU_UPDATE_INTERVAL};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::power;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+
+/// `system_stats` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SystemStatsSettings {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Busiest processes listed in each sample.
+    pub top_processes: usize,
+    pub heavy_cpu_percent: f32,
+    pub heavy_memory_percent: f32,
+}
+
+impl Default for SystemStatsSettings {
+    fn default() -> Self {
+        SystemStatsSettings {
+            enabled: true,
+            interval_secs: 5,
+            top_processes: 5,
+            heavy_cpu_percent: 85.0,
+            heavy_memory_percent: 90.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStats {
+    pid: u32,
+    name: String,
+    /// Of one core, so it can pass 100.
+    cpu_percent: f32,
+    memory_bytes: u64,
+}
+
+/// Payload of `system-stats`, also returned by `system_stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemStats {
+    at: String,
+    /// Mean over all cores.
+    cpu_percent: f32,
+    cores: usize,
+    memory_used_bytes: u64,
+    memory_total_bytes: u64,
+    memory_percent: f32,
+    heavy: bool,
+    /// Busiest first.
+    processes: Vec<ProcessStats>,
+}
+
+/// Managed by the app; the latest sample, once there is one.
+#[derive(Default)]
+pub(crate) struct SystemMonitor(Mutex<Option<SystemStats>>);
+
+/// CPU figures compare with the previous refresh of `sys`, so the first
+/// sample of a fresh `System` needs a second refresh a moment later.
+fn sample(sys: &mut System, cfg: &SystemStatsSettings) -> SystemStats {
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+    sys.refresh_processes(ProcessesToUpdate::All, true);
+
+    let total = sys.total_memory();
+    let used = sys.used_memory();
+    let memory_percent = if total == 0 {
+        0.0
+    } else {
+        (used as f64 / total as f64 * 100.0) as f32
+    };
+    let cpu_percent = sys.global_cpu_usage();
+
+    let mut processes: Vec<ProcessStats> = sys
+        .processes()
+        .values()
+        .map(|p| ProcessStats {
+            pid: p.pid().as_u32(),
+            name: p.name().to_string_lossy().into_owned(),
+            cpu_percent: p.cpu_usage(),
+            memory_bytes: p.memory(),
+        })
+        .collect();
+    processes.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+    processes.truncate(cfg.top_processes);
+
+    SystemStats {
+        at: Local::now().to_rfc3339(),
+        cpu_percent,
+        cores: sys.cpus().len(),
+        memory_used_bytes: used,
+        memory_total_bytes: total,
+        memory_percent,
+        heavy: cpu_percent >= cfg.heavy_cpu_percent || memory_percent >= cfg.heavy_memory_percent,
+        processes,
+    }
+}
+
+fn first_sample(sys: &mut System, cfg: &SystemStatsSettings) -> SystemStats {
+    sys.refresh_cpu_usage();
+    sys.refresh_processes(ProcessesToUpdate::All, true);
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    sample(sys, cfg)
+}
+
+pub(crate) fn spawn_sampler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut sys = System::new();
+        let mut primed = false;
+        let mut was_heavy = false;
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .system_stats
+                .clone();
+            let interval = Duration::from_secs(cfg.interval_secs.max(1));
+            if !cfg.enabled {
+                primed = false;
+                *app.state::<SystemMonitor>().0.lock_or_recover() = None;
+                std::thread::sleep(interval);
+                continue;
+            }
+            let stats = if primed {
+                sample(&mut sys, &cfg)
+            } else {
+                primed = true;
+                first_sample(&mut sys, &cfg)
+            };
+            if stats.heavy != was_heavy {
+                was_heavy = stats.heavy;
+                let icon = if stats.heavy { "🥵" } else { "😌" };
+                log!(
+                    "{icon} system load: cpu {:.0}%, memory {:.0}%",
+                    stats.cpu_percent,
+                    stats.memory_percent
+                );
+            }
+            *app.state::<SystemMonitor>().0.lock_or_recover() = Some(stats.clone());
+            let _ = app.emit("system-stats", stats);
+            std::thread::sleep(power::interval(&app, interval));
+        }
+    });
+}
+
+/// The sampler's latest; sampled on the spot while it is off or hasn't
+/// run yet.
+#[tauri::command]
+pub async fn system_stats(app: AppHandle) -> Result<SystemStats, String> {
+    let latest = app.state::<SystemMonitor>().0.lock_or_recover().clone();
+    if let Some(stats) = latest {
+        return Ok(stats);
+    }
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .system_stats
+        .clone();
+    tauri::async_runtime::spawn_blocking(move || first_sample(&mut System::new(), &cfg))
+        .await
+        .map_err(|e| format!("system stats task: {e}"))
+}
@@ -0,0 +1,78 @@
+//! Windows taskbar mirror of the pet's state: `progress` as the button's
+//! progress bar (red for `error`) and the map's `state_icons` entry as an
+//! overlay badge. Useful while the pet is minimized or buried under other
+//! windows. Other platforms do nothing.
+
+use tauri::AppHandle;
+
+use crate::PetState;
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use std::sync::Mutex;
+    use tauri::image::Image;
+    use tauri::window::{ProgressBarState, ProgressBarStatus};
+    use tauri::{AppHandle, Manager};
+
+    use crate::settings::Settings;
+    use crate::sync::LockExt;
+    use crate::{states, AppPaths, PetState};
+
+    /// State whose badge is showing, so unchanged states skip the reload.
+    static BADGE: Mutex<Option<String>> = Mutex::new(None);
+
+    fn progress_bar(state: &PetState) -> ProgressBarState {
+        let status = match state.progress {
+            None => ProgressBarStatus::None,
+            Some(_) if state.state == "error" => ProgressBarStatus::Error,
+            Some(_) => ProgressBarStatus::Normal,
+        };
+        ProgressBarState {
+            status: Some(status),
+            progress: state
+                .progress
+                .map(|p| (p.clamp(0.0, 1.0) * 100.0).round() as u64),
+        }
+    }
+
+    fn badge(app: &AppHandle, name: &str) -> Option<Image<'static>> {
+        let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+        let limits = app
+            .state::<Mutex<Settings>>()
+            .lock_or_recover()
+            .limits
+            .clone();
+        states::icon_image(&paths, &limits, name)
+    }
+
+    pub(super) fn update(app: &AppHandle, state: &PetState) {
+        let mut shown = BADGE.lock_or_recover();
+        let new_badge = shown.as_deref() != Some(state.state.as_str());
+        let icon = new_badge.then(|| badge(app, &state.state));
+        for label in ["main", "mini"] {
+            let Some(w) = app.get_webview_window(label) else {
+                continue;
+            };
+            let _ = w.set_progress_bar(progress_bar(state));
+            if let Some(icon) = &icon {
+                let _ = w.set_overlay_icon(icon.clone());
+            }
+        }
+        if new_badge {
+            *shown = Some(state.state.clone());
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use crate::PetState;
+
+    pub(super) fn update(_: &AppHandle, _: &PetState) {}
+}
+
+pub(crate) fn update(app: &AppHandle, state: &PetState) {
+    imp::update(app, state);
+}
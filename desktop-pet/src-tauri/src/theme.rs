@@ -0,0 +1,207 @@
+//! `layers/theme.json`: colours, fonts, speech bubble and progress bar
+//! styling for the UI chrome around the sprites. Every field is optional;
+//! the defaults reproduce the built-in look.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::limits::Limits;
+use crate::pet_core::{self, AssetSource, FsAssets};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::AppPaths;
+
+pub(crate) const FILE: &str = "theme.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub colors: Colors,
+    pub fonts: Fonts,
+    pub bubble: Bubble,
+    pub progress: Progress,
+}
+
+/// Context menu and general chrome.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub background: String,
+    pub text: String,
+    pub accent: String,
+    pub separator: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fonts {
+    /// CSS `font-family`; `ipix` is bundled with the app.
+    pub family: String,
+    pub menu_size_px: f64,
+    pub bubble_size_px: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bubble {
+    pub background: String,
+    pub text: String,
+    pub border: String,
+    pub border_width_px: f64,
+    pub radius_px: f64,
+    pub padding_x_px: f64,
+    pub padding_y_px: f64,
+    pub shadow: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Progress {
+    pub track: String,
+    pub fill: String,
+    /// Fill colour once progress reaches 1.
+    pub done: String,
+    /// Fill colour while the state is `error`.
+    pub error: String,
+    pub height_px: f64,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            background: "rgba(26, 26, 46, 0.95)".into(),
+            text: "#eeeeee".into(),
+            accent: "#e94560".into(),
+            separator: "rgba(233, 69, 96, 0.3)".into(),
+        }
+    }
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Fonts {
+            family: "'ipix', monospace".into(),
+            menu_size_px: 13.0,
+            bubble_size_px: 16.0,
+        }
+    }
+}
+
+impl Default for Bubble {
+    fn default() -> Self {
+        Bubble {
+            background: "rgba(255, 255, 255, 0.95)".into(),
+            text: "#333333".into(),
+            border: "#888888".into(),
+            border_width_px: 2.0,
+            radius_px: 8.0,
+            padding_x_px: 14.0,
+            padding_y_px: 6.0,
+            shadow: true,
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress {
+            track: "rgba(0, 0, 0, 0.25)".into(),
+            fill: "#e94560".into(),
+            done: "#4caf50".into(),
+            error: "#ff5252".into(),
+            height_px: 4.0,
+        }
+    }
+}
+
+// ── validation ──
+
+/// Hex (`#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`), `rgb[a](…)`, `hsl[a](…)`
+/// or `transparent`. Named colours are rejected so typos surface here.
+pub(crate) fn is_color(value: &str) -> bool {
+    let v = value.trim();
+    if let Some(hex) = v.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    let functional = ["rgb(", "rgba(", "hsl(", "hsla("]
+        .iter()
+        .any(|f| v.starts_with(f));
+    v == "transparent" || (functional && v.ends_with(')'))
+}
+
+impl Theme {
+    /// Every invalid value, as `path: problem`.
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let colors = [
+            ("colors.background", &self.colors.background),
+            ("colors.text", &self.colors.text),
+            ("colors.accent", &self.colors.accent),
+            ("colors.separator", &self.colors.separator),
+            ("bubble.background", &self.bubble.background),
+            ("bubble.text", &self.bubble.text),
+            ("bubble.border", &self.bubble.border),
+            ("progress.track", &self.progress.track),
+            ("progress.fill", &self.progress.fill),
+            ("progress.done", &self.progress.done),
+            ("progress.error", &self.progress.error),
+        ];
+        let sizes = [
+            ("fonts.menu_size_px", self.fonts.menu_size_px, 4.0, 96.0),
+            ("fonts.bubble_size_px", self.fonts.bubble_size_px, 4.0, 96.0),
+            (
+                "bubble.border_width_px",
+                self.bubble.border_width_px,
+                0.0,
+                16.0,
+            ),
+            ("bubble.radius_px", self.bubble.radius_px, 0.0, 64.0),
+            ("bubble.padding_x_px", self.bubble.padding_x_px, 0.0, 64.0),
+            ("bubble.padding_y_px", self.bubble.padding_y_px, 0.0, 64.0),
+            ("progress.height_px", self.progress.height_px, 1.0, 32.0),
+        ];
+
+        let mut out = Vec::new();
+        for (path, value) in colors {
+            if !is_color(value) {
+                out.push(format!(
+                    "{path}: `{value}` is not a hex, rgb() or hsl() colour"
+                ));
+            }
+        }
+        for (path, value, min, max) in sizes {
+            if !(min..=max).contains(&value) {
+                out.push(format!("{path}: {value} is outside {min}..{max}"));
+            }
+        }
+        if self.fonts.family.trim().is_empty() {
+            out.push("fonts.family: must not be empty".into());
+        }
+        out
+    }
+}
+
+/// Defaults when the pack has no `theme.json`; an invalid file is an error
+/// so the author sees it rather than a silently half-applied theme.
+pub(crate) fn load(src: &dyn AssetSource, limits: &Limits) -> Result<Theme, String> {
+    if !src.exists(FILE) {
+        return Ok(Theme::default());
+    }
+    let raw = pet_core::read_config(src, FILE, limits)?;
+    let theme: Theme = serde_json::from_str(&raw).map_err(|e| format!("{FILE}: {e}"))?;
+    let problems = theme.problems();
+    if !problems.is_empty() {
+        return Err(format!("{FILE}: {}", problems.join("; ")));
+    }
+    Ok(theme)
+}
+
+#[tauri::command]
+pub fn get_theme(
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> Result<Theme, String> {
+    let limits = settings.lock_or_recover().limits.clone();
+    let layers_dir = paths.lock_or_recover().layers_dir.clone();
+    load(&FsAssets::new(layers_dir), &limits)
+}
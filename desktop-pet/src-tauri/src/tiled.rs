@@ -0,0 +1,336 @@
+//! Tiled (`.tmx` / `.tsx`) maps, converted to the `map.json` layout.
+//!
+//! Tile layers are matched to `ground`, `border`, `rug`, `objects` and
+//! `collision` by name (or a `role` property), object names become POIs
+//! and the map's `zoom` / `character_speed` properties carry over. Tiles
+//! with a `collides` property block their cell on any layer. The map needs
+//! a single orthogonal, finite tileset of square tiles without margin or
+//! spacing, stored as CSV, uncompressed base64 or XML.
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use roxmltree::{Document, Node};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::limits::Limits;
+use crate::logs::log;
+use crate::pet_core::{self, AssetSource};
+
+/// Tiled keeps flip and rotation flags in the top bits of a gid.
+const GID_MASK: u32 = 0x0FFF_FFFF;
+const ROLES: &[&str] = &["ground", "border", "rug", "objects", "collision"];
+
+fn attr<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute(name)
+}
+
+fn num<T: std::str::FromStr>(node: Node<'_, '_>, name: &str, file: &str) -> Result<T, String> {
+    let raw = attr(node, name)
+        .ok_or_else(|| format!("{file}: <{}> has no `{name}`", node.tag_name().name()))?;
+    raw.trim()
+        .parse()
+        .map_err(|_| format!("{file}: `{name}=\"{raw}\"` is not a number"))
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, tag: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+/// `<properties>` of `node` by name.
+fn properties<'a>(node: Node<'a, '_>) -> BTreeMap<&'a str, &'a str> {
+    child(node, "properties")
+        .into_iter()
+        .flat_map(|p| p.children().filter(|n| n.has_tag_name("property")))
+        .filter_map(|p| Some((attr(p, "name")?, attr(p, "value")?)))
+        .collect()
+}
+
+struct Tileset {
+    first_gid: u32,
+    image: String,
+    /// Local tile ids with a truthy `collides` property.
+    collides: BTreeSet<u32>,
+}
+
+impl Tileset {
+    /// Index into the tileset for `map.json`, `-1` for no tile.
+    fn index(&self, gid: u32) -> i32 {
+        if gid == 0 || gid < self.first_gid {
+            -1
+        } else {
+            (gid - self.first_gid) as i32
+        }
+    }
+}
+
+fn read_tileset(
+    src: &dyn AssetSource,
+    tmx: &str,
+    map: Node<'_, '_>,
+    tile_size: u32,
+    limits: &Limits,
+) -> Result<Tileset, String> {
+    let sets: Vec<_> = map
+        .children()
+        .filter(|n| n.has_tag_name("tileset"))
+        .collect();
+    let [set] = sets.as_slice() else {
+        return Err(format!(
+            "{tmx}: expected exactly one tileset, found {}",
+            sets.len()
+        ));
+    };
+    let first_gid = num(*set, "firstgid", tmx)?;
+
+    // External tilesets live in their own `.tsx`.
+    let Some(source) = attr(*set, "source") else {
+        return tileset_from(src, *set, tmx, first_gid, tile_size);
+    };
+    let tsx = pet_core::resolve_relative(tmx, source)?;
+    if !src.exists(&tsx) {
+        return Err(format!("{tmx}: tileset not found: {tsx}"));
+    }
+    let raw = pet_core::read_config(src, &tsx, limits)?;
+    let doc = Document::parse(&raw).map_err(|e| format!("{tsx}: {e}"))?;
+    tileset_from(src, doc.root_element(), &tsx, first_gid, tile_size)
+}
+
+fn tileset_from(
+    src: &dyn AssetSource,
+    node: Node<'_, '_>,
+    file: &str,
+    first_gid: u32,
+    tile_size: u32,
+) -> Result<Tileset, String> {
+    let (tw, th): (u32, u32) = (
+        num(node, "tilewidth", file)?,
+        num(node, "tileheight", file)?,
+    );
+    if tw != tile_size || th != tile_size {
+        return Err(format!(
+            "{file}: tiles are {tw}×{th}, the map uses {tile_size}×{tile_size}"
+        ));
+    }
+    for key in ["margin", "spacing"] {
+        if attr(node, key).is_some_and(|v| v.trim() != "0") {
+            return Err(format!("{file}: tileset {key} is not supported"));
+        }
+    }
+    let image = child(node, "image")
+        .and_then(|i| attr(i, "source"))
+        .ok_or_else(|| {
+            format!("{file}: tileset has no <image>; image collections are not supported")
+        })?;
+    let image = pet_core::resolve_relative(file, image)?;
+    if !src.exists(&image) {
+        return Err(format!("{file}: tileset image not found: {image}"));
+    }
+    let collides = node
+        .children()
+        .filter(|n| n.has_tag_name("tile"))
+        .filter(|t| {
+            properties(*t)
+                .get("collides")
+                .is_some_and(|v| matches!(*v, "true" | "1"))
+        })
+        .filter_map(|t| attr(t, "id")?.parse().ok())
+        .collect();
+    Ok(Tileset {
+        first_gid,
+        image,
+        collides,
+    })
+}
+
+/// The gids of a `<layer>`, row-major.
+fn layer_gids(layer: Node<'_, '_>, file: &str, cells: usize) -> Result<Vec<u32>, String> {
+    let name = attr(layer, "name").unwrap_or("?");
+    let data = child(layer, "data").ok_or_else(|| format!("{file}: layer `{name}` has no data"))?;
+    if child(data, "chunk").is_some() {
+        return Err(format!("{file}: infinite maps are not supported"));
+    }
+    let gids: Vec<u32> = match (attr(data, "encoding"), attr(data, "compression")) {
+        (_, Some(c)) => {
+            return Err(format!(
+                "{file}: layer `{name}` is {c}-compressed; save it as CSV or uncompressed base64"
+            ))
+        }
+        (Some("csv"), None) => data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| format!("{file}: layer `{name}`: bad tile `{s}`"))
+            })
+            .collect::<Result<_, _>>()?,
+        (Some("base64"), None) => {
+            let text: String = data.text().unwrap_or_default().split_whitespace().collect();
+            let bytes = B64
+                .decode(text)
+                .map_err(|e| format!("{file}: layer `{name}`: {e}"))?;
+            bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect()
+        }
+        (None, None) => data
+            .children()
+            .filter(|n| n.has_tag_name("tile"))
+            .map(|t| attr(t, "gid").and_then(|g| g.parse().ok()).unwrap_or(0))
+            .collect(),
+        (Some(other), None) => {
+            return Err(format!(
+                "{file}: layer `{name}`: unknown encoding `{other}`"
+            ))
+        }
+    };
+    if gids.len() != cells {
+        return Err(format!(
+            "{file}: layer `{name}` has {} tiles, the map has {cells}",
+            gids.len()
+        ));
+    }
+    Ok(gids.into_iter().map(|g| g & GID_MASK).collect())
+}
+
+fn role_of(layer: Node<'_, '_>) -> Option<String> {
+    let props = properties(layer);
+    let role = props
+        .get("role")
+        .copied()
+        .or_else(|| attr(layer, "name"))?
+        .trim()
+        .to_lowercase();
+    ROLES.contains(&role.as_str()).then_some(role)
+}
+
+/// POIs are named objects, placed on the cell under their centre.
+fn pois(map: Node<'_, '_>, tile_size: u32, cols: u32, rows: u32) -> Map<String, Value> {
+    let mut out = Map::new();
+    // Tiles' own collision shapes are object groups too.
+    let groups = map.descendants().filter(|n| {
+        n.has_tag_name("objectgroup") && !n.ancestors().any(|a| a.has_tag_name("tileset"))
+    });
+    for object in groups.flat_map(|g| g.children().filter(|n| n.has_tag_name("object"))) {
+        let Some(name) = attr(object, "name").filter(|n| !n.trim().is_empty()) else {
+            continue;
+        };
+        let get = |key: &str| attr(object, key).and_then(|v| v.parse::<f64>().ok());
+        let (x, y) = (get("x").unwrap_or(0.0), get("y").unwrap_or(0.0));
+        let (w, h) = (get("width").unwrap_or(0.0), get("height").unwrap_or(0.0));
+        // Tile objects are anchored at their bottom-left corner.
+        let cy = if attr(object, "gid").is_some() {
+            y - h / 2.0
+        } else {
+            y + h / 2.0
+        };
+        let cell = |v: f64, max: u32| ((v / tile_size as f64).floor().max(0.0) as u32).min(max - 1);
+        out.insert(
+            name.trim().to_string(),
+            json!({ "col": cell(x + w / 2.0, cols), "row": cell(cy, rows) }),
+        );
+    }
+    out
+}
+
+/// `<rel>` as the equivalent `map.json`. `state_icons`, `walk_cycle` and
+/// `character` are kept from the pack's own `map.json`, when it has one.
+pub(crate) fn to_map_json(
+    src: &dyn AssetSource,
+    rel: &str,
+    limits: &Limits,
+) -> Result<String, String> {
+    let raw = pet_core::read_config(src, rel, limits)?;
+    let doc = Document::parse(&raw).map_err(|e| format!("{rel}: {e}"))?;
+    let map = doc.root_element();
+    if !map.has_tag_name("map") {
+        return Err(format!("{rel}: not a Tiled map"));
+    }
+    if attr(map, "orientation").is_some_and(|o| o != "orthogonal") {
+        return Err(format!("{rel}: only orthogonal maps are supported"));
+    }
+    if attr(map, "infinite") == Some("1") {
+        return Err(format!("{rel}: infinite maps are not supported"));
+    }
+    let (cols, rows): (u32, u32) = (num(map, "width", rel)?, num(map, "height", rel)?);
+    if cols == 0 || rows == 0 || cols.max(rows) > limits.max_grid_dim {
+        return Err(format!(
+            "{rel}: {cols}×{rows} tiles is outside 1..{} (limits.max_grid_dim)",
+            limits.max_grid_dim
+        ));
+    }
+    let (tw, th): (u32, u32) = (num(map, "tilewidth", rel)?, num(map, "tileheight", rel)?);
+    if tw != th {
+        return Err(format!("{rel}: tiles must be square, not {tw}×{th}"));
+    }
+    let tileset = read_tileset(src, rel, map, tw, limits)?;
+
+    let cells = (cols * rows) as usize;
+    let grid = |gids: &[u32]| -> Vec<Vec<i32>> {
+        gids.chunks(cols as usize)
+            .map(|row| row.iter().map(|g| tileset.index(*g)).collect())
+            .collect()
+    };
+    let mut out = Map::new();
+    let mut blocked = vec![false; cells];
+    for layer in map.descendants().filter(|n| n.has_tag_name("layer")) {
+        let Some(role) = role_of(layer) else {
+            log!(
+                "ℹ️ {rel}: layer `{}` is not one of {} and is skipped",
+                attr(layer, "name").unwrap_or("?"),
+                ROLES.join(", ")
+            );
+            continue;
+        };
+        let gids = layer_gids(layer, rel, cells)?;
+        for (cell, gid) in blocked.iter_mut().zip(&gids) {
+            let local = tileset.index(*gid);
+            *cell |= (role == "collision" && *gid != 0)
+                || (local >= 0 && tileset.collides.contains(&(local as u32)));
+        }
+        if role != "collision" {
+            out.insert(role, json!(grid(&gids)));
+        }
+    }
+    if !out.contains_key("ground") {
+        return Err(format!("{rel}: no `ground` tile layer"));
+    }
+    out.entry("objects")
+        .or_insert_with(|| json!(vec![vec![-1; cols as usize]; rows as usize]));
+    let collision: Vec<Vec<u8>> = blocked
+        .chunks(cols as usize)
+        .map(|row| row.iter().map(|b| u8::from(*b)).collect())
+        .collect();
+    out.insert("collision".into(), json!(collision));
+
+    out.insert("tile_size".into(), json!(tw));
+    out.insert("cols".into(), json!(cols));
+    out.insert("rows".into(), json!(rows));
+    out.insert("tileset".into(), json!(tileset.image));
+    let props = properties(map);
+    if let Some(zoom) = props.get("zoom").and_then(|v| v.parse::<u32>().ok()) {
+        out.insert("zoom".into(), json!(zoom));
+    }
+    if let Some(speed) = props
+        .get("character_speed")
+        .and_then(|v| v.parse::<f64>().ok())
+    {
+        out.insert("character_speed".into(), json!(speed));
+    }
+    out.insert("pois".into(), Value::Object(pois(map, tw, cols, rows)));
+
+    if src.exists("map.json") {
+        if let Value::Object(own) = pet_core::read_config_merged(src, "map.json", limits)? {
+            for key in ["state_icons", "walk_cycle", "character"] {
+                if let Some(v) = own.get(key) {
+                    out.insert(key.into(), v.clone());
+                }
+            }
+        }
+    }
+    Ok(Value::Object(out).to_string())
+}
@@ -0,0 +1,95 @@
+//! Time spent in each state, per day, for reports.
+//!
+//! Every `state-changed` closes the running interval and credits it to
+//! the previous state in `stats/states.json`. A task counts as completed
+//! when `progress` reaches 1.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{stats, PetState};
+
+pub(crate) const STATS_NAME: &str = "states";
+const KEEP_DAYS: usize = 400;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct StateDay {
+    /// Seconds per state name.
+    pub seconds: BTreeMap<String, u64>,
+    pub tasks_completed: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct StateHistory {
+    /// Keyed by local date, `YYYY-MM-DD`.
+    pub days: BTreeMap<String, StateDay>,
+}
+
+struct Running {
+    state: String,
+    progress: Option<f64>,
+    since: chrono::DateTime<chrono::Local>,
+}
+
+#[derive(Default)]
+pub(crate) struct Timeline(Mutex<Option<Running>>);
+
+impl Timeline {
+    pub(crate) fn observe(&self, data_dir: &Path, state: &PetState) {
+        let now = chrono::Local::now();
+        let previous = self.0.lock_or_recover().replace(Running {
+            state: state.state.clone(),
+            progress: state.progress,
+            since: now,
+        });
+        let completed = state.progress.is_some_and(|p| p >= 1.0)
+            && previous
+                .as_ref()
+                .is_none_or(|r| r.progress.is_none_or(|p| p < 1.0));
+        let credited = previous.map(|r| {
+            let secs = (now - r.since).num_seconds().max(0) as u64;
+            (r.state, secs)
+        });
+        if credited.is_none() && !completed {
+            return;
+        }
+
+        let mut history: StateHistory = stats::load(data_dir, STATS_NAME);
+        let day = history
+            .days
+            .entry(now.format("%Y-%m-%d").to_string())
+            .or_default();
+        if let Some((name, secs)) = credited {
+            *day.seconds.entry(name).or_default() += secs;
+        }
+        if completed {
+            day.tasks_completed += 1;
+        }
+        let excess = history.days.len().saturating_sub(KEEP_DAYS);
+        let old: Vec<String> = history.days.keys().take(excess).cloned().collect();
+        for key in old {
+            history.days.remove(&key);
+        }
+        if let Err(e) = stats::save(data_dir, STATS_NAME, &history) {
+            log!("⚠️ state stats: {e}");
+        }
+    }
+
+    /// Credits the running interval so far, e.g. before exiting.
+    pub(crate) fn flush(&self, data_dir: &Path) {
+        let running = self.0.lock_or_recover().as_ref().map(|r| PetState {
+            state: r.state.clone(),
+            detail: None,
+            progress: r.progress,
+            updated_at: None,
+            progress_sources: Default::default(),
+        });
+        if let Some(state) = running {
+            self.observe(data_dir, &state);
+        }
+    }
+}
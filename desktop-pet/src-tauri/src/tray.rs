@@ -0,0 +1,177 @@
+//! System tray icon and its menu. Labels come from the locale at startup.
+//!
+//! The icon follows the pet's state through the map's `state_icons` (the
+//! app icon when the state has none); the tooltip spells out state and
+//! detail. "Pause wandering" goes to the webview as `wander-paused`.
+
+use serde_json::Map;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+
+use crate::folders::{self, FolderKind};
+use crate::i18n::I18n;
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{fsutil, shutdown, states, AppPaths, PetState};
+
+const ID: &str = "main";
+const TOOLTIP: &str = "Star Desktop Pet";
+const OPEN_PREFIX: &str = "open:";
+const WANDER: &str = "wander";
+const SETTINGS: &str = "settings";
+const QUIT: &str = "quit";
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// State whose icon is showing, so unchanged states skip the reload.
+static SHOWN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Menu entries that change after startup.
+struct Items {
+    wander: CheckMenuItem<Wry>,
+}
+
+pub(crate) fn build(app: &App) -> tauri::Result<()> {
+    let i18n = app.state::<I18n>();
+    let label = |key: &str| i18n.t(key, &Map::new());
+
+    let wander = CheckMenuItem::with_id(
+        app,
+        WANDER,
+        label("tray.pause_wandering"),
+        true,
+        PAUSED.load(Ordering::Relaxed),
+        None::<&str>,
+    )?;
+    let settings = MenuItem::with_id(
+        app,
+        SETTINGS,
+        label("tray.open_settings"),
+        true,
+        None::<&str>,
+    )?;
+    let mut open_items = Vec::new();
+    for kind in FolderKind::ALL {
+        open_items.push(MenuItem::with_id(
+            app,
+            format!("{OPEN_PREFIX}{}", kind.name()),
+            label(&format!("tray.open_{}", kind.name())),
+            true,
+            None::<&str>,
+        )?);
+    }
+    let quit = MenuItem::with_id(app, QUIT, label("tray.quit"), true, None::<&str>)?;
+
+    let menu = Menu::new(app)?;
+    menu.append(&wander)?;
+    menu.append(&settings)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    for item in &open_items {
+        menu.append(item)?;
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&quit)?;
+    app.manage(Items { wander });
+
+    let mut tray = TrayIconBuilder::with_id(ID)
+        .tooltip(TOOLTIP)
+        .menu(&menu)
+        .on_menu_event(|app, event| on_menu(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    Ok(())
+}
+
+pub(crate) fn wander_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+fn set_paused(app: &AppHandle, paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+    if let Some(items) = app.try_state::<Items>() {
+        let _ = items.wander.set_checked(paused);
+    }
+    log!("🚶 wandering {}", if paused { "paused" } else { "resumed" });
+    let _ = app.emit("wander-paused", serde_json::json!({ "paused": paused }));
+}
+
+/// Creates an empty `settings.json` first, so there is something to open.
+fn open_settings(app: &AppHandle) -> Result<(), String> {
+    let path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    if !fsutil::exists(&path) {
+        fsutil::write_atomic(&path, b"{}\n")?;
+    }
+    crate::os_open(path.as_os_str()).map_err(|e| format!("failed to open {}: {e}", path.display()))
+}
+
+fn on_menu(app: &AppHandle, id: &str) {
+    match id {
+        QUIT => {
+            shutdown::run(app);
+            app.exit(0);
+            return;
+        }
+        WANDER => {
+            set_paused(app, !wander_paused());
+            return;
+        }
+        SETTINGS => {
+            if let Err(e) = open_settings(app) {
+                log!("⚠️ {e}");
+            }
+            return;
+        }
+        _ => {}
+    }
+    let Some(kind) = id.strip_prefix(OPEN_PREFIX).and_then(FolderKind::from_name) else {
+        return;
+    };
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    if let Err(e) = folders::open(&paths, kind) {
+        log!("⚠️ {e}");
+    }
+}
+
+/// Shows `state` on the tray icon; does nothing without one (headless).
+pub(crate) fn update(app: &AppHandle, state: &PetState) {
+    let Some(tray) = app.tray_by_id(ID) else {
+        return;
+    };
+    let tooltip = match &state.detail {
+        Some(detail) => format!("{TOOLTIP} · {} · {detail}", state.state),
+        None => format!("{TOOLTIP} · {}", state.state),
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+
+    let mut shown = SHOWN.lock_or_recover();
+    if shown.as_deref() == Some(state.state.as_str()) {
+        return;
+    }
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let limits = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .limits
+        .clone();
+    let icon = states::icon_image(&paths, &limits, &state.state)
+        .or_else(|| app.default_window_icon().map(|i| i.clone().to_owned()));
+    if let Err(e) = tray.set_icon(icon) {
+        log!("⚠️ tray icon: {e}");
+    }
+    *shown = Some(state.state.clone());
+}
+
+/// Lets a reloaded webview pick up the tray's "Pause wandering".
+#[tauri::command]
+pub fn get_wander_paused() -> bool {
+    wander_paused()
+}
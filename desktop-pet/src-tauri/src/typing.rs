@@ -0,0 +1,185 @@
+//! Typing speed from keystroke counts.
+//!
+//! There is no OS-wide keyboard hook in the backend; a sampler (the
+//! frontend, or an agent that already watches input) calls
+//! `report_keystrokes` with how many keys were pressed since its last
+//! call. Only counts arrive, never which keys. WPM is the usual five
+//! keystrokes per word over a rolling one-minute window; daily bests land
+//! in `stats/typing.json`, and beating the all-time best emits
+//! `typing-record` for the pet to celebrate.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::logs::log;
+use crate::sync::LockExt;
+use crate::{stats, AppPaths};
+
+const STATS_NAME: &str = "typing";
+const KEEP_DAYS: usize = 365;
+const WINDOW: Duration = Duration::from_secs(60);
+/// Fewer keys than this in the window is too little to call a speed.
+const MIN_KEYSTROKES: u32 = 50;
+const KEYS_PER_WORD: f64 = 5.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TypingDay {
+    best_wpm: f64,
+    keystrokes: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TypingHistory {
+    /// Keyed by local date, `YYYY-MM-DD`.
+    days: BTreeMap<String, TypingDay>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TypingStats {
+    /// `None` until the window holds enough keystrokes.
+    wpm: Option<f64>,
+    today: TypingDay,
+    best_wpm: f64,
+    days: BTreeMap<String, TypingDay>,
+}
+
+/// Payload of `typing-record`.
+#[derive(Debug, Clone, Serialize)]
+struct Record {
+    wpm: f64,
+    previous_best: f64,
+}
+
+#[derive(Default)]
+struct Sampler {
+    window: VecDeque<(Instant, u32)>,
+    /// Keystrokes not yet written to today's total.
+    unsaved: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Typing(Mutex<Sampler>);
+
+impl Typing {
+    /// Writes the keystrokes still held back, e.g. before exiting.
+    pub(crate) fn flush(&self, data_dir: &Path) {
+        let unsaved = std::mem::take(&mut self.0.lock_or_recover().unsaved);
+        if unsaved == 0 {
+            return;
+        }
+        let mut history: TypingHistory = stats::load(data_dir, STATS_NAME);
+        history.days.entry(today()).or_default().keystrokes += unsaved;
+        if let Err(e) = stats::save(data_dir, STATS_NAME, &history) {
+            log!("⚠️ typing stats: {e}");
+        }
+    }
+}
+
+impl Sampler {
+    fn add(&mut self, count: u32) -> Option<f64> {
+        let now = Instant::now();
+        self.window.push_back((now, count));
+        self.unsaved += u64::from(count);
+        while self
+            .window
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > WINDOW)
+        {
+            self.window.pop_front();
+        }
+        self.wpm()
+    }
+
+    fn wpm(&self) -> Option<f64> {
+        let keys: u64 = self.window.iter().map(|(_, n)| u64::from(*n)).sum();
+        (keys >= u64::from(MIN_KEYSTROKES))
+            .then(|| keys as f64 / KEYS_PER_WORD / (WINDOW.as_secs_f64() / 60.0))
+    }
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn best_ever(history: &TypingHistory) -> f64 {
+    history
+        .days
+        .values()
+        .map(|d| d.best_wpm)
+        .fold(0.0, f64::max)
+}
+
+/// `count` keys pressed since the previous call. Returns the current
+/// rolling WPM, if there is one yet.
+#[tauri::command]
+pub fn report_keystrokes(
+    app: AppHandle,
+    count: u32,
+    typing: tauri::State<'_, Typing>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> Option<f64> {
+    let (wpm, unsaved) = {
+        let mut sampler = typing.0.lock_or_recover();
+        let wpm = sampler.add(count);
+        (wpm, sampler.unsaved)
+    };
+    let paths = paths.lock_or_recover().clone();
+    let mut history: TypingHistory = stats::load(&paths.data_dir, STATS_NAME);
+    let previous_best = best_ever(&history);
+    let day = history.days.entry(today()).or_default();
+    let day_record = wpm.filter(|w| *w > day.best_wpm);
+    // Totals are flushed once a window's worth has piled up, or with a record.
+    if day_record.is_none() && unsaved < u64::from(MIN_KEYSTROKES) {
+        return wpm;
+    }
+    day.keystrokes += unsaved;
+    if let Some(w) = day_record {
+        day.best_wpm = w;
+    }
+    let excess = history.days.len().saturating_sub(KEEP_DAYS);
+    let old: Vec<String> = history.days.keys().take(excess).cloned().collect();
+    for key in old {
+        history.days.remove(&key);
+    }
+    match stats::save(&paths.data_dir, STATS_NAME, &history) {
+        Ok(()) => {
+            let mut sampler = typing.0.lock_or_recover();
+            // `flush` may have taken them meanwhile.
+            sampler.unsaved = sampler.unsaved.saturating_sub(unsaved);
+        }
+        Err(e) => log!("⚠️ typing stats: {e}"),
+    }
+
+    // The very first measurement isn't worth a party.
+    if let Some(w) = day_record.filter(|w| previous_best > 0.0 && *w > previous_best) {
+        log!("⌨️ new typing record: {w:.0} wpm (was {previous_best:.0})");
+        let _ = app.emit(
+            "typing-record",
+            Record {
+                wpm: w,
+                previous_best,
+            },
+        );
+    }
+    wpm
+}
+
+#[tauri::command]
+pub fn get_typing_stats(
+    typing: tauri::State<'_, Typing>,
+    paths: tauri::State<'_, Mutex<AppPaths>>,
+) -> TypingStats {
+    let wpm = typing.0.lock_or_recover().wpm();
+    let data_dir = paths.lock_or_recover().data_dir.clone();
+    let history: TypingHistory = stats::load(&data_dir, STATS_NAME);
+    TypingStats {
+        wpm,
+        today: history.days.get(&today()).cloned().unwrap_or_default(),
+        best_wpm: best_ever(&history),
+        days: history.days,
+    }
+}
@@ -0,0 +1,203 @@
+//! Opt-in check for newer releases on GitHub.
+//!
+//! With `updates.enabled` in `settings.json` a background thread checks
+//! every `interval_hours` and emits `update-available` once per new
+//! version. `check_for_updates` works regardless, for a "check now"
+//! button. Needs the `updates` feature for network access.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{health, net};
+
+const MAX_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+/// Let startup settle before the first scheduled check.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(30);
+/// How soon turning the checker on in `settings.json` is noticed.
+const DISABLED_POLL: Duration = Duration::from_secs(600);
+
+/// `updates` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct UpdateSettings {
+    pub enabled: bool,
+    pub interval_hours: u32,
+    /// `owner/name` of the GitHub repository to check.
+    pub repo: String,
+    pub include_prereleases: bool,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        UpdateSettings {
+            enabled: false,
+            interval_hours: 24,
+            repo: "ringhyacinth/Star-Office-UI".into(),
+            include_prereleases: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GhRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Release {
+    version: String,
+    tag: String,
+    name: Option<String>,
+    notes: Option<String>,
+    url: String,
+    published_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateStatus {
+    current: &'static str,
+    latest: Option<Release>,
+    update_available: bool,
+    checked_at: String,
+}
+
+// ── versions ──
+
+/// `v1.2.3-beta.1` → `(1, 2, 3, Some("beta.1"))`; missing parts are 0.
+pub(crate) fn parse_version(tag: &str) -> Option<(u64, u64, u64, Option<String>)> {
+    let v = tag.trim().trim_start_matches(['v', 'V']);
+    let v = v.split('+').next()?;
+    let (core, pre) = match v.split_once('-') {
+        Some((core, pre)) => (core, Some(pre.to_string())),
+        None => (v, None),
+    };
+    let mut nums = core.split('.').map(|n| n.parse::<u64>());
+    let major = nums.next()?.ok()?;
+    let minor = nums.next().unwrap_or(Ok(0)).ok()?;
+    let patch = nums.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch, pre))
+}
+
+/// Semver precedence, except pre-release identifiers compare as plain
+/// strings; a release always outranks its own pre-releases.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a, b) = (parse_version(a)?, parse_version(b)?);
+    Some(
+        (a.0, a.1, a.2)
+            .cmp(&(b.0, b.1, b.2))
+            .then_with(|| match (&a.3, &b.3) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(x), Some(y)) => x.cmp(y),
+            }),
+    )
+}
+
+// ── checking ──
+
+pub(crate) fn check(cfg: &UpdateSettings) -> Result<UpdateStatus, String> {
+    let url = format!(
+        "https://api.github.com/repos/{}/releases?per_page=20",
+        cfg.repo
+    );
+    let raw = net::get(&url, MAX_RESPONSE_BYTES)?;
+    let releases: Vec<GhRelease> =
+        serde_json::from_slice(&raw).map_err(|e| format!("{url}: {e}"))?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = releases
+        .into_iter()
+        .filter(|r| !r.draft && (cfg.include_prereleases || !r.prerelease))
+        .filter(|r| parse_version(&r.tag_name).is_some())
+        .max_by(|a, b| compare_versions(&a.tag_name, &b.tag_name).unwrap_or(Ordering::Equal))
+        .map(|r| Release {
+            version: r.tag_name.trim_start_matches(['v', 'V']).to_string(),
+            tag: r.tag_name,
+            name: r.name,
+            notes: r.body,
+            url: r.html_url,
+            published_at: r.published_at,
+        });
+    let update_available = latest
+        .as_ref()
+        .and_then(|r| compare_versions(&r.tag, current))
+        == Some(Ordering::Greater);
+    Ok(UpdateStatus {
+        current,
+        latest,
+        update_available,
+        checked_at: chrono::Local::now().to_rfc3339(),
+    })
+}
+
+/// Background schedule. Settings are re-read every round, so enabling or
+/// disabling in `settings.json` takes effect without a restart.
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_CHECK_DELAY);
+        let mut announced: Option<String> = None;
+        loop {
+            let cfg = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .updates
+                .clone();
+            if cfg.enabled {
+                let checked = check(&cfg);
+                match &checked {
+                    Ok(_) => health::success(&app, "updates"),
+                    Err(e) => health::failure(&app, "updates", e.as_str()),
+                }
+                match checked {
+                    Ok(status) if status.update_available => {
+                        let version = status.latest.as_ref().map(|r| r.version.clone());
+                        if version != announced {
+                            log!("🆕 update available: {}", version.as_deref().unwrap_or("?"));
+                            let _ = app.emit("update-available", &status);
+                            announced = version;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log!("⚠️ update check: {e}"),
+                }
+            }
+            let wait = if cfg.enabled {
+                Duration::from_secs(u64::from(cfg.interval_hours.max(1)) * 3600)
+            } else {
+                DISABLED_POLL
+            };
+            if cfg.enabled {
+                health::next_poll(&app, "updates", wait);
+            }
+            std::thread::sleep(wait);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateStatus, String> {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .updates
+        .clone();
+    tauri::async_runtime::spawn_blocking(move || check(&cfg))
+        .await
+        .map_err(|e| format!("update task: {e}"))?
+}
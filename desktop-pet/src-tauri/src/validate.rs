@@ -0,0 +1,492 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::limits::Limits;
+use crate::locate;
+use crate::pathfind::{self, RouteError};
+use crate::pet_core::{self, AssetSource, FsAssets, Overlay};
+use crate::seasonal;
+use crate::states;
+use crate::sync::LockExt;
+use crate::theme::{self, Theme};
+use crate::{
+    aseprite, fsutil, imaging, read_state_file, settings, AnimCfg, AppPaths, CharCfg, PetState,
+    SpritesCfg,
+};
+
+// ── report ──
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Issue {
+    pub level: Level,
+    pub file: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Report {
+    pub root: PathBuf,
+    pub ok: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+}
+
+struct Collector {
+    issues: Vec<Issue>,
+    limits: Limits,
+}
+
+impl Collector {
+    fn push(&mut self, level: Level, file: &str, message: impl Into<String>) {
+        self.issues.push(Issue {
+            level,
+            file: file.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, file: &str, message: impl Into<String>) {
+        self.push(Level::Error, file, message);
+    }
+
+    fn warn(&mut self, file: &str, message: impl Into<String>) {
+        self.push(Level::Warning, file, message);
+    }
+
+    /// Referenced files must exist and be readable; the loaders would
+    /// otherwise skip them silently or fail at runtime.
+    fn asset(&mut self, file: &str, src: &dyn AssetSource, rel: &str, level: Level) {
+        if !src.exists(rel) {
+            self.push(level, file, format!("missing asset: {rel}"));
+        } else if let Err(e) = pet_core::read_asset(src, rel, &self.limits) {
+            self.push(level, file, e);
+        }
+    }
+}
+
+// ── checks ──
+
+/// Loads every config and referenced asset under `root` the same way the
+/// running app would, collecting problems instead of stopping at the first.
+pub(crate) fn validate_root(root: &Path) -> Report {
+    let (paths, _) = crate::resolve_paths(root);
+    let settings = settings::load(&paths.settings_path);
+    let mut c = Collector {
+        issues: Vec::new(),
+        limits: settings.limits,
+    };
+    let fs = FsAssets::new(&paths.layers_dir);
+    let assets = locate::with_map(&paths, &fs, &c.limits).unwrap_or_else(|e| {
+        c.error("map.json", e);
+        Overlay::new(&fs)
+    });
+
+    let state_path = paths.state_path;
+    if !fsutil::exists(&state_path) {
+        c.warn(
+            "state.json",
+            "not found; the app will fall back to the backend",
+        );
+    } else {
+        match read_state_file(&state_path) {
+            Ok(state) => check_state(&mut c, &state),
+            Err(e) => c.error("state.json", e),
+        }
+    }
+
+    check_layers(&mut c, &assets);
+    check_map(&mut c, &assets);
+    check_theme(&mut c, &assets);
+    check_seasonal(&mut c, &assets);
+
+    let errors = c.issues.iter().filter(|i| i.level == Level::Error).count();
+    Report {
+        root: root.to_path_buf(),
+        ok: errors == 0,
+        errors,
+        warnings: c.issues.len() - errors,
+        issues: c.issues,
+    }
+}
+
+fn check_state(c: &mut Collector, state: &PetState) {
+    if let Some(p) = state.progress.filter(|p| !(0.0..=1.0).contains(p)) {
+        c.warn("state.json", format!("progress {p} is outside 0..1"));
+    }
+}
+
+/// `frames` entries below zero, which would otherwise surface as a bare
+/// "expected u32" parse error.
+fn negative_frames(cfg: &Value) -> Vec<String> {
+    let pets = cfg["characters"].as_array().map_or(&[][..], Vec::as_slice);
+    let skins = cfg["skins"]
+        .as_object()
+        .into_iter()
+        .flat_map(|s| s.values());
+    std::iter::once(&cfg["sprites"])
+        .chain(pets.iter().map(|p| &p["sprites"]))
+        .chain(skins)
+        .filter_map(|s| s["anims"].as_object())
+        .flatten()
+        .filter_map(|(key, anim)| {
+            let frames = anim["frames"].as_i64().filter(|n| *n < 0)?;
+            Some(format!(
+                "anim `{key}` has a negative frame count ({frames})"
+            ))
+        })
+        .collect()
+}
+
+fn check_layers(c: &mut Collector, src: &dyn AssetSource) {
+    const FILE: &str = "layers.json";
+    if !src.exists(FILE) {
+        c.warn(FILE, "not found; built-in defaults will be used");
+        return;
+    }
+    if let Ok(raw) = pet_core::read_config_merged(src, FILE, &c.limits) {
+        let negative = negative_frames(&raw);
+        if !negative.is_empty() {
+            for problem in negative {
+                c.error(FILE, problem);
+            }
+            return;
+        }
+    }
+    let cfg = match pet_core::read_layers_cfg(src, &c.limits) {
+        Ok(cfg) => cfg,
+        Err(e) => return c.error(FILE, e),
+    };
+
+    for (i, layer) in cfg.layers.unwrap_or_default().iter().enumerate() {
+        c.asset(FILE, src, &layer.image, Level::Error);
+        if let Some(alpha) = layer.alpha {
+            if !(0.0..=1.0).contains(&alpha) {
+                c.warn(FILE, format!("layers[{i}].alpha {alpha} is outside 0..1"));
+            }
+        }
+    }
+
+    let base = cfg.character.unwrap_or_default();
+    check_character(c, src, "character", &base);
+    if let Some(sprites) = cfg.sprites {
+        check_sprites(c, src, sprites);
+    }
+    let mut names = BTreeSet::new();
+    for (i, pet) in cfg.characters.unwrap_or_default().into_iter().enumerate() {
+        let name = pet.name.unwrap_or_else(|| format!("pet{}", i + 1));
+        if !names.insert(name.clone()) {
+            c.error(FILE, format!("two characters are named `{name}`"));
+        }
+        check_character(c, src, &format!("characters[{i}]"), &pet.character);
+        if let Some(sprites) = pet.sprites {
+            check_sprites(c, src, sprites);
+        }
+    }
+    for (_, sprites) in cfg.skins.unwrap_or_default() {
+        check_sprites(c, src, sprites);
+    }
+}
+
+fn check_character(c: &mut Collector, src: &dyn AssetSource, at: &str, ch: &CharCfg) {
+    const FILE: &str = "layers.json";
+    if let Some(shadow) = &ch.shadow {
+        for (name, size) in [("width", shadow.width), ("height", shadow.height)] {
+            if size.is_some_and(|v| v <= 0.0) {
+                c.error(FILE, format!("{at}.shadow.{name} must be greater than 0"));
+            }
+        }
+        if let Some(opacity) = shadow.opacity {
+            if !(0.0..=1.0).contains(&opacity) {
+                c.warn(
+                    FILE,
+                    format!("{at}.shadow.opacity {opacity} is outside 0..1"),
+                );
+            }
+        }
+    }
+    if let Some(prints) = &ch.footprints {
+        c.asset(FILE, src, &prints.image, Level::Error);
+        if prints.fade_secs.is_some_and(|v| v <= 0.0) {
+            c.error(
+                FILE,
+                format!("{at}.footprints.fade_secs must be greater than 0"),
+            );
+        }
+        if prints.spacing.is_some_and(|v| v <= 0.0) {
+            c.error(
+                FILE,
+                format!("{at}.footprints.spacing must be greater than 0"),
+            );
+        }
+    }
+}
+
+fn check_sprites(c: &mut Collector, src: &dyn AssetSource, sprites: SpritesCfg) {
+    const FILE: &str = "layers.json";
+    let mut frame = (
+        sprites.frame_width.unwrap_or(32),
+        sprites.frame_height.unwrap_or(32),
+    );
+    if let Some(rel) = &sprites.aseprite {
+        match aseprite::read(src, rel, &c.limits) {
+            Ok(sheet) => {
+                frame = (
+                    sprites.frame_width.unwrap_or(sheet.frame.0),
+                    sprites.frame_height.unwrap_or(sheet.frame.1),
+                );
+                if sheet.frame != frame {
+                    c.error(
+                        FILE,
+                        format!(
+                            "sprites are {}x{} but {rel} has {}x{} frames",
+                            frame.0, frame.1, sheet.frame.0, sheet.frame.1
+                        ),
+                    );
+                }
+            }
+            Err(e) => c.error(FILE, e),
+        }
+    }
+    for (key, anim) in sprites.anims.unwrap_or_default() {
+        c.asset(FILE, src, &anim.file, Level::Error);
+        check_anim_bounds(c, src, &key, &anim, frame);
+        if anim.frames == Some(0) {
+            c.error(FILE, format!("anim `{key}` has 0 frames"));
+        }
+        if anim.rate == Some(0) {
+            c.warn(
+                FILE,
+                format!("anim `{key}` has rate 0 and will never advance"),
+            );
+        }
+    }
+}
+
+/// Frames must fit the decoded sheet. Shared sheets (`row`, `margin`, …)
+/// fail to load otherwise; for plain ones the webview just shows blanks.
+fn check_anim_bounds(
+    c: &mut Collector,
+    src: &dyn AssetSource,
+    key: &str,
+    anim: &AnimCfg,
+    frame: (u32, u32),
+) {
+    const FILE: &str = "layers.json";
+    let Ok(bytes) = pet_core::read_asset(src, &anim.file, &c.limits) else {
+        return;
+    };
+    let Ok(sheet) = imaging::decode(Path::new(&anim.file), &bytes) else {
+        return;
+    };
+    if let Err(e) = pet_core::anim_first_frame(key, anim, &sheet, frame) {
+        if anim.addressed() {
+            c.error(FILE, e);
+        } else {
+            c.warn(FILE, e);
+        }
+    }
+}
+
+fn check_map(c: &mut Collector, src: &dyn AssetSource) {
+    const FILE: &str = "map.json";
+    if !src.exists(FILE) {
+        c.warn(FILE, "not found; the pet will run without a map");
+        return;
+    }
+    let cfg = match pet_core::read_map_cfg(src, &c.limits) {
+        Ok(cfg) => cfg,
+        Err(e) => return c.error(FILE, e),
+    };
+
+    if !src.exists(&cfg.tileset) {
+        c.error(FILE, format!("tileset not found: {}", cfg.tileset));
+    } else {
+        let geometry = pet_core::read_asset(src, &cfg.tileset, &c.limits)
+            .map_err(String::from)
+            .and_then(|bytes| imaging::decode(Path::new(&cfg.tileset), &bytes))
+            .and_then(|img| imaging::tile_grid(&cfg.tileset, &img, cfg.tile_size.unwrap_or(16)));
+        if let Err(e) = geometry {
+            c.error(FILE, e);
+        }
+    }
+
+    let cols = cfg
+        .cols
+        .unwrap_or(cfg.ground.first().map_or(12, |r| r.len() as u32)) as usize;
+    let rows = cfg.rows.unwrap_or(cfg.ground.len() as u32) as usize;
+    // A short collision grid lets the pet walk through walls.
+    check_grid(c, "collision", &cfg.collision, (cols, rows), Level::Error);
+    check_grid(c, "ground", &cfg.ground, (cols, rows), Level::Warning);
+    check_grid(c, "objects", &cfg.objects, (cols, rows), Level::Warning);
+    for (name, grid) in [("border", &cfg.border), ("rug", &cfg.rug)] {
+        if let Some(grid) = grid {
+            check_grid(c, name, grid, (cols, rows), Level::Warning);
+        }
+    }
+
+    let character = cfg.character.unwrap_or_default();
+    for (name, value) in [("scale", character.scale), ("speed", character.speed)] {
+        if let Some(v) = value.filter(|v| !v.is_finite() || *v <= 0.0) {
+            c.warn(FILE, format!("character.{name} {v} must be greater than 0"));
+        }
+    }
+    if let Some(skin) = &character.skin {
+        let known = pet_core::read_layers_cfg(src, &c.limits)
+            .is_ok_and(|l| l.skins.is_some_and(|s| s.contains_key(skin)));
+        if !known {
+            c.error(
+                FILE,
+                format!("character.skin `{skin}` is not in the skins of layers.json"),
+            );
+        }
+    }
+
+    let pois = cfg.pois.unwrap_or_default();
+    for (name, route) in cfg.routes.iter().flatten() {
+        match pathfind::plan(&cfg.collision, &pois, name, route, None) {
+            Err(RouteError::NoSuchPoi { name: poi }) => {
+                c.error(FILE, format!("routes.{name}: no POI `{poi}`"))
+            }
+            Err(RouteError::EmptyRoute { .. }) => c.warn(FILE, format!("routes.{name}: no stops")),
+            Err(RouteError::Unreachable { from, to }) => c.warn(
+                FILE,
+                format!("routes.{name}: no path from {from} to {to} on the collision grid"),
+            ),
+            _ => {}
+        }
+    }
+
+    let anims = anim_keys(src, &c.limits);
+    for (state, filename) in cfg.state_icons.unwrap_or_default() {
+        if !src.exists(&pet_core::state_icon_path(&filename)) {
+            c.warn(
+                FILE,
+                format!("state_icons.{state}: missing icon {filename}"),
+            );
+        }
+        let known = states::BUILTIN.iter().any(|(name, _)| *name == state)
+            || states::ALIASES.iter().any(|(alias, _)| *alias == state)
+            || anims.contains(&state);
+        if !known {
+            c.warn(
+                FILE,
+                format!("state_icons.{state}: not a known state or anim, so it never shows"),
+            );
+        }
+    }
+}
+
+fn check_grid<T>(
+    c: &mut Collector,
+    name: &str,
+    grid: &[Vec<T>],
+    (cols, rows): (usize, usize),
+    level: Level,
+) {
+    const FILE: &str = "map.json";
+    if grid.len() != rows {
+        c.push(
+            level,
+            FILE,
+            format!("{name} has {} rows, the map has {rows}", grid.len()),
+        );
+    }
+    if let Some((r, row)) = grid.iter().enumerate().find(|(_, row)| row.len() != cols) {
+        c.push(
+            level,
+            FILE,
+            format!(
+                "{name} row {r} has {} columns, the map has {cols}",
+                row.len()
+            ),
+        );
+    }
+}
+
+/// Anim names from the top-level sheet, every pet's and every skin's.
+fn anim_keys(src: &dyn AssetSource, limits: &Limits) -> BTreeSet<String> {
+    let Ok(cfg) = pet_core::read_layers_cfg(src, limits) else {
+        return BTreeSet::new();
+    };
+    let pets = cfg.characters.unwrap_or_default();
+    let skins = cfg.skins.unwrap_or_default().into_values().map(Some);
+    std::iter::once(cfg.sprites)
+        .chain(pets.into_iter().map(|p| p.sprites))
+        .chain(skins)
+        .flatten()
+        .flat_map(|s| s.anims.unwrap_or_default().into_keys())
+        .collect()
+}
+
+fn check_theme(c: &mut Collector, src: &dyn AssetSource) {
+    if !src.exists(theme::FILE) {
+        return;
+    }
+    let theme: Theme = match pet_core::read_config(src, theme::FILE, &c.limits)
+        .map_err(String::from)
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+    {
+        Ok(theme) => theme,
+        Err(e) => return c.error(theme::FILE, e),
+    };
+    for problem in theme.problems() {
+        c.error(theme::FILE, problem);
+    }
+}
+
+fn check_seasonal(c: &mut Collector, src: &dyn AssetSource) {
+    const FILE: &str = seasonal::FILE;
+    if !src.exists(FILE) {
+        return;
+    }
+    let file = match pet_core::read_config(src, FILE, &c.limits)
+        .map_err(String::from)
+        .and_then(|raw| {
+            serde_json::from_str::<seasonal::SeasonalFile>(&raw).map_err(|e| e.to_string())
+        }) {
+        Ok(file) => file,
+        Err(e) => return c.error(FILE, e),
+    };
+    for problem in file.problems() {
+        c.error(FILE, problem);
+    }
+    let anims = pet_core::read_layers_cfg(src, &c.limits)
+        .ok()
+        .and_then(|cfg| cfg.sprites)
+        .and_then(|s| s.anims)
+        .unwrap_or_default();
+    for season in &file.seasons {
+        for layer in &season.layers {
+            c.asset(FILE, src, &layer.image, Level::Error);
+        }
+        for (key, sheet) in &season.skins {
+            c.asset(FILE, src, sheet, Level::Error);
+            if !anims.contains_key(key) {
+                c.warn(
+                    FILE,
+                    format!("{}: skin for unknown anim `{key}` is ignored", season.name),
+                );
+            }
+        }
+    }
+}
+
+// ── command ──
+
+/// The `--validate` report for the running project, for the UI to list.
+#[tauri::command]
+pub fn validate_project(paths: tauri::State<'_, Mutex<AppPaths>>) -> Report {
+    let root = paths.lock_or_recover().root.clone();
+    validate_root(&root)
+}
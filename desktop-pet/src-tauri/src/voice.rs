@@ -0,0 +1,226 @@
+//! Offline voice commands: a small fixed grammar ("take a break", "status
+//! report", "hide", …) recognised with Vosk from the default microphone.
+//!
+//! Needs the `voice` feature, a downloaded Vosk model and `voice.enabled`;
+//! all three are off by default and nothing leaves the machine. Every
+//! recognised phrase goes out as `voice-command`; the built-in actions are
+//! also carried out here.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logs::log;
+use crate::pet_core::{FsStateStore, StateStore};
+use crate::settings::Settings;
+use crate::sync::LockExt;
+use crate::{AppPaths, PetState};
+
+/// `voice` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct VoiceSettings {
+    pub enabled: bool,
+    /// Folder of an unpacked Vosk model, e.g. `vosk-model-small-en-us-0.15`.
+    pub model_path: String,
+    /// Phrase (lowercase, in the model's language) → action: `hide`,
+    /// `show`, `status`, `state:<name>`, or any other name, which is only
+    /// emitted for the frontend.
+    pub phrases: BTreeMap<String, String>,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        let phrases = [
+            ("take a break", "state:idle"),
+            ("status report", "status"),
+            ("hide", "hide"),
+            ("come back", "show"),
+        ];
+        VoiceSettings {
+            enabled: false,
+            model_path: String::new(),
+            phrases: phrases
+                .into_iter()
+                .map(|(p, a)| (p.to_string(), a.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// Payload of `voice-command`.
+#[derive(Debug, Clone, Serialize)]
+struct VoiceCommand {
+    phrase: String,
+    action: String,
+    /// The current state, for `status`.
+    state: Option<PetState>,
+}
+
+fn state_store(app: &AppHandle) -> FsStateStore {
+    let state_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .state_path
+        .clone();
+    FsStateStore::new(state_path)
+}
+
+/// Carries out what `phrase` is mapped to.
+fn perform(app: &AppHandle, phrase: &str, action: &str) {
+    log!("🎙️ \"{phrase}\" → {action}");
+    let mut state = None;
+    match action {
+        "hide" | "show" => {
+            for label in ["main", "mini"] {
+                if let Some(w) = app.get_webview_window(label) {
+                    let _ = if action == "hide" { w.hide() } else { w.show() };
+                }
+            }
+        }
+        "status" => state = crate::current_state(app.state(), app.state()).ok(),
+        _ => {
+            if let Some(name) = action.strip_prefix("state:") {
+                let next = PetState {
+                    state: name.to_string(),
+                    detail: Some(phrase.to_string()),
+                    progress: None,
+                    updated_at: Some(chrono::Local::now().to_rfc3339()),
+                    progress_sources: Default::default(),
+                };
+                if let Err(e) = state_store(app).save(&next) {
+                    log!("⚠️ voice state: {e}");
+                }
+            }
+        }
+    }
+    let _ = app.emit(
+        "voice-command",
+        VoiceCommand {
+            phrase: phrase.to_string(),
+            action: action.to_string(),
+            state,
+        },
+    );
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let cfg = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .voice
+        .clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.model_path.trim().is_empty() || cfg.phrases.is_empty() {
+        log!("⚠️ voice commands need voice.model_path and at least one phrase");
+        return;
+    }
+    imp::listen(app, cfg);
+}
+
+#[cfg(feature = "voice")]
+mod imp {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::sync::mpsc;
+    use tauri::AppHandle;
+    use vosk::{DecodingState, Model, Recognizer};
+
+    use super::VoiceSettings;
+    use crate::logs::log;
+
+    /// Mono 16-bit samples from the default input device, fed to the
+    /// recogniser on the same thread (audio streams can't move between
+    /// threads on every platform).
+    fn run(app: &AppHandle, cfg: &VoiceSettings) -> Result<(), String> {
+        let model = Model::new(cfg.model_path.as_str())
+            .ok_or_else(|| format!("no Vosk model at {}", cfg.model_path))?;
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or("no microphone")?;
+        let config = device.default_input_config().map_err(|e| e.to_string())?;
+        let channels = usize::from(config.channels()).max(1);
+        let rate = config.sample_rate().0 as f32;
+
+        // `[unk]` soaks up anything outside the grammar.
+        let mut grammar: Vec<&str> = cfg.phrases.keys().map(String::as_str).collect();
+        grammar.push("[unk]");
+        let mut recognizer = Recognizer::new_with_grammar(&model, rate, &grammar)
+            .ok_or("the Vosk model rejected the grammar")?;
+
+        let (tx, rx) = mpsc::channel::<Vec<i16>>();
+        let on_error = |e: cpal::StreamError| log!("⚠️ microphone: {e}");
+        let mono = |frame: &[i16]| {
+            (frame.iter().map(|&s| i32::from(s)).sum::<i32>() / frame.len() as i32) as i16
+        };
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &_| {
+                    let pcm: Vec<i16> = data
+                        .chunks(channels)
+                        .map(|f| {
+                            let s = f.iter().sum::<f32>() / f.len() as f32;
+                            (s.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16
+                        })
+                        .collect();
+                    let _ = tx.send(pcm);
+                },
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &_| {
+                    let _ = tx.send(data.chunks(channels).map(mono).collect());
+                },
+                on_error,
+                None,
+            ),
+            other => return Err(format!("unsupported microphone format {other:?}")),
+        }
+        .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+        log!("🎙️ listening for {} phrase(s)", cfg.phrases.len());
+
+        for pcm in rx {
+            if !matches!(
+                recognizer.accept_waveform(&pcm),
+                Ok(DecodingState::Finalized)
+            ) {
+                continue;
+            }
+            let result = recognizer.result();
+            let Some(text) = result.single().map(|r| r.text.trim().to_string()) else {
+                continue;
+            };
+            if let Some(action) = cfg.phrases.get(&text) {
+                super::perform(app, &text, action);
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn listen(app: AppHandle, cfg: VoiceSettings) {
+        std::thread::spawn(move || {
+            if let Err(e) = run(&app, &cfg) {
+                log!("⚠️ voice commands off: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use super::VoiceSettings;
+    use crate::logs::log;
+
+    pub(super) fn listen(_: AppHandle, _: VoiceSettings) {
+        log!("⚠️ voice.enabled is set, but this build has no voice commands (rebuild with `--features voice`)");
+    }
+}
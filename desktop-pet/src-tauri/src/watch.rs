@@ -0,0 +1,441 @@
+//! One watcher for every config file the app reads.
+//!
+//! Filesystem events are classified by the subsystem they affect, coalesced
+//! over a short quiet period, and re-emitted as typed Tauri events:
+//!
+//! | file                         | event              | payload    |
+//! |------------------------------|--------------------|------------|
+//! | `state.json`                 | `state-changed`    | `PetState` |
+//! | `layers/layers.json`, images | `layers-updated`   | –          |
+//! |                              | `layers-reloaded`  | `FullData` |
+//! | `layers/seasonal.json`       | same as above      |            |
+//! | `layers/map.json`, tileset   | `map-updated`      | –          |
+//! |                              | `map-reloaded`     | `MapData`  |
+//! | other `layers/*.json`        | all of the above   |            |
+//! | `layers/theme.json`          | `theme-changed`    | `Theme`    |
+//! | `layers/sounds.json`         | –                  |            |
+//! | `settings.json`              | `settings-changed` | `Settings` |
+//! | `mappers.json`               | `state-changed`    | `PetState` |
+//! | `notifications.json`         | –                  |            |
+//! | `locales/*.json`             | `locale-changed`   | `LocaleInfo` |
+//!
+//! The `*-reloaded` events carry the re-resolved config so the window can
+//! swap it in directly; a config that no longer loads is only logged, and
+//! the pet keeps what it shows.
+//!
+//! `watch_state(false)` mutes `state-changed` for a frontend that would
+//! rather poll `read_state`; the state timeline keeps recording.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::a11y;
+use crate::dbus;
+use crate::dev::{DevState, ReloadEntry};
+use crate::edit::EditSession;
+use crate::i18n::{self, I18n};
+use crate::locate;
+use crate::logs::log;
+use crate::mappers;
+use crate::mqtt;
+use crate::notifications;
+use crate::observe::Observer;
+use crate::onboarding;
+use crate::pet_core::{self, FsAssets};
+use crate::replay::Recorder;
+use crate::rgb;
+use crate::rpc;
+use crate::seasonal;
+use crate::settings::{self, Settings};
+use crate::sim;
+use crate::sound;
+use crate::sync::LockExt;
+use crate::taskbar;
+use crate::theme;
+use crate::timeline::Timeline;
+use crate::tray;
+use crate::ws;
+use crate::{fsutil, read_state_file, AppPaths, PetState};
+
+/// Editors and producers often touch a file several times per save.
+const QUIET_PERIOD: Duration = Duration::from_millis(150);
+
+/// Whether `state.json` changes go out as `state-changed`.
+static STATE_EVENTS: AtomicBool = AtomicBool::new(true);
+/// Last state announced, so a write the app made itself isn't announced
+/// again once the watcher notices the file.
+static LAST_STATE: Mutex<Option<String>> = Mutex::new(None);
+/// Images the current map pulls in, so editing the tileset reloads it.
+static MAP_ASSETS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Subsystem {
+    State,
+    Layers,
+    Map,
+    Theme,
+    Sounds,
+    Settings,
+    Mappers,
+    Notifications,
+    Locales,
+}
+
+/// Keeps the OS watcher alive for as long as it is managed by the app.
+pub(crate) struct WatchService {
+    _watcher: RecommendedWatcher,
+}
+
+pub(crate) fn classify(paths: &AppPaths, changed: &Path) -> Option<Subsystem> {
+    if changed == paths.state_path {
+        Some(Subsystem::State)
+    } else if changed == paths.settings_path {
+        Some(Subsystem::Settings)
+    } else if changed == paths.root.join(mappers::FILE) {
+        Some(Subsystem::Mappers)
+    } else if changed == paths.root.join(notifications::FILE) {
+        Some(Subsystem::Notifications)
+    } else if changed == paths.map_path {
+        Some(Subsystem::Map)
+    } else if changed == paths.layers_dir.join(theme::FILE) {
+        Some(Subsystem::Theme)
+    } else if changed == paths.layers_dir.join(sound::FILE) {
+        Some(Subsystem::Sounds)
+    } else if changed.starts_with(&paths.layers_dir) {
+        Some(Subsystem::Layers)
+    } else if changed.starts_with(&paths.locales_dir) {
+        Some(Subsystem::Locales)
+    } else {
+        None
+    }
+}
+
+/// A shared base that `layers.json` or `map.json` may pull in through
+/// `extends` / `include`; either could depend on it.
+fn shared_config(paths: &AppPaths, changed: &Path) -> bool {
+    changed.starts_with(&paths.layers_dir)
+        && changed != paths.map_path
+        && changed.extension().is_some_and(|e| e == "json")
+        && ![
+            "layers.json",
+            "map.json",
+            seasonal::FILE,
+            theme::FILE,
+            sound::FILE,
+        ]
+        .iter()
+        .any(|f| changed == paths.layers_dir.join(f))
+}
+
+/// Re-reads which files `map.json` references.
+fn track_map_assets(app: &AppHandle) {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let limits = app
+        .state::<Mutex<Settings>>()
+        .lock_or_recover()
+        .limits
+        .clone();
+    let fs = FsAssets::new(&paths.layers_dir);
+    let tileset = locate::with_map(&paths, &fs, &limits)
+        .and_then(|src| pet_core::read_map_cfg(&src, &limits))
+        .map(|cfg| paths.layers_dir.join(cfg.tileset));
+    // Keep the old set while map.json is broken; the fix reloads anyway.
+    if let Ok(tileset) = tileset {
+        *MAP_ASSETS.lock_or_recover() = BTreeSet::from([tileset]);
+    }
+}
+
+pub(crate) fn start(app: AppHandle) -> Result<WatchService, String> {
+    let (root, layers_dir, locales_dir, moved) = {
+        let paths = app.state::<Mutex<AppPaths>>();
+        let p = paths.lock_or_recover();
+        // `--state-file` / `--map-file` and profiles may point outside the
+        // watched trees.
+        let moved: BTreeSet<_> = [&p.state_path, &p.map_path, &p.settings_path]
+            .into_iter()
+            .filter_map(|f| f.parent())
+            .filter(|dir| *dir != p.root && !dir.starts_with(&p.layers_dir))
+            .map(Path::to_path_buf)
+            .collect();
+        (
+            p.root.clone(),
+            p.layers_dir.clone(),
+            p.locales_dir.clone(),
+            moved,
+        )
+    };
+
+    track_map_assets(&app);
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("watcher: {e}"))?;
+    watcher
+        .watch(&root, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("watch {}: {e}", root.display()))?;
+    if fsutil::exists(&layers_dir) {
+        watcher
+            .watch(&layers_dir, RecursiveMode::Recursive)
+            .map_err(|e| format!("watch {}: {e}", layers_dir.display()))?;
+    }
+    if fsutil::exists(&locales_dir) {
+        watcher
+            .watch(&locales_dir, RecursiveMode::Recursive)
+            .map_err(|e| format!("watch {}: {e}", locales_dir.display()))?;
+    }
+    for dir in moved.iter().filter(|d| fsutil::exists(d)) {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("watch {}: {e}", dir.display()))?;
+    }
+
+    std::thread::spawn(move || {
+        let mut pending = BTreeSet::new();
+        loop {
+            let received = if pending.is_empty() {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                rx.recv_timeout(QUIET_PERIOD)
+            };
+            match received {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        continue;
+                    }
+                    let paths = app.state::<Mutex<AppPaths>>();
+                    let p = paths.lock_or_recover();
+                    pending.extend(event.paths.iter().filter_map(|path| classify(&p, path)));
+                    let map_assets = MAP_ASSETS.lock_or_recover();
+                    if event
+                        .paths
+                        .iter()
+                        .any(|path| shared_config(&p, path) || map_assets.contains(path))
+                    {
+                        pending.insert(Subsystem::Map);
+                    }
+                }
+                Ok(Err(e)) => log!("⚠️ watch error: {e}"),
+                Err(RecvTimeoutError::Timeout) => {
+                    for subsystem in std::mem::take(&mut pending) {
+                        dispatch(&app, subsystem);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    log!(
+        "👀 watching {} and {}",
+        root.display(),
+        layers_dir.display()
+    );
+    Ok(WatchService { _watcher: watcher })
+}
+
+/// Re-arms the watcher after a directory it watched was replaced, e.g.
+/// when a bundle swaps in a new `layers/`.
+pub(crate) fn restart(app: &AppHandle) {
+    let Some(service) = app.try_state::<Mutex<WatchService>>() else {
+        return;
+    };
+    match start(app.clone()) {
+        Ok(fresh) => *service.lock_or_recover() = fresh,
+        Err(e) => log!("⚠️ config watcher disabled: {e}"),
+    }
+}
+
+fn dispatch(app: &AppHandle, subsystem: Subsystem) {
+    let started = Instant::now();
+    let paths = app.state::<Mutex<AppPaths>>();
+    let result = match subsystem {
+        // Observers follow the remote pet, not the local file.
+        Subsystem::State if app.state::<Observer>().remote().is_some() => Ok(()),
+        Subsystem::State => {
+            let state_path = paths.lock_or_recover().state_path.clone();
+            match read_state_file(&state_path) {
+                Ok(state) => announce_state(app, state),
+                // Mid-write or deleted; the next event will carry the new file.
+                Err(_) => Ok(()),
+            }
+        }
+        // Our own edit-mode write-back was already sent as a patch event.
+        Subsystem::Layers if app.state::<EditSession>().is_own_write() => Ok(()),
+        Subsystem::Layers => layers_changed(app),
+        Subsystem::Map => map_changed(app),
+        Subsystem::Theme => {
+            let layers_dir = paths.lock_or_recover().layers_dir.clone();
+            let limits = app
+                .state::<Mutex<Settings>>()
+                .lock_or_recover()
+                .limits
+                .clone();
+            match theme::load(&FsAssets::new(layers_dir), &limits) {
+                Ok(fresh) => app.emit("theme-changed", fresh),
+                // Keep the current theme until the file is fixed.
+                Err(e) => {
+                    log!("⚠️ {e}");
+                    Ok(())
+                }
+            }
+        }
+        Subsystem::Settings => {
+            let settings_path = paths.lock_or_recover().settings_path.clone();
+            let fresh = settings::load(&settings_path);
+            let previous = std::mem::replace(
+                &mut *app.state::<Mutex<Settings>>().lock_or_recover(),
+                fresh.clone(),
+            );
+            // Accessibility modes change which assets `load_layers` returns.
+            let relayer = a11y::resolve(&previous.accessibility)
+                != a11y::resolve(&fresh.accessibility)
+                || previous.accessibility.reduced_motion_max_rate
+                    != fresh.accessibility.reduced_motion_max_rate;
+            reload_locale(app, false)
+                .and_then(|_| app.emit("settings-changed", fresh))
+                .and_then(|_| if relayer { layers_changed(app) } else { Ok(()) })
+        }
+        // The current state may read differently under the new rules.
+        Subsystem::Mappers => {
+            let root = paths.lock_or_recover().root.clone();
+            mappers::reload(&root);
+            dispatch(app, Subsystem::State);
+            Ok(())
+        }
+        Subsystem::Notifications => {
+            notifications::reload(&paths.lock_or_recover().root);
+            Ok(())
+        }
+        Subsystem::Sounds => {
+            sound::reload(app);
+            Ok(())
+        }
+        Subsystem::Locales => reload_locale(app, true),
+    };
+    if let Err(e) = &result {
+        log!("⚠️ emit {subsystem:?}: {e}");
+    }
+    app.state::<DevState>().record(ReloadEntry {
+        subsystem: format!("{subsystem:?}").to_lowercase(),
+        at: chrono::Local::now().to_rfc3339(),
+        millis: started.elapsed().as_secs_f64() * 1000.0,
+        ok: result.is_ok(),
+    });
+}
+
+/// Announces that `load_layers` would now return something else, with the
+/// fresh result attached.
+pub(crate) fn layers_changed(app: &AppHandle) -> tauri::Result<()> {
+    app.emit("layers-updated", ())?;
+    match crate::layers_data(None, app.state(), app.state(), app.state()) {
+        Ok(data) => app.emit("layers-reloaded", data),
+        Err(e) => {
+            log!("⚠️ layers not reloaded: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Same as `layers_changed`, for `load_map`.
+pub(crate) fn map_changed(app: &AppHandle) -> tauri::Result<()> {
+    track_map_assets(app);
+    app.emit("map-updated", ())?;
+    match crate::map_data(None, app.state(), app.state()) {
+        Ok(data) => app.emit("map-reloaded", data),
+        Err(e) => {
+            log!("⚠️ map not reloaded: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Handles every subsystem as if its files had just changed, e.g. for
+/// `POST /reload`. Settings go first, since the rest read them.
+pub(crate) fn reload_all(app: &AppHandle) {
+    for subsystem in [
+        Subsystem::Settings,
+        Subsystem::Mappers,
+        Subsystem::Notifications,
+        Subsystem::Theme,
+        Subsystem::Sounds,
+        Subsystem::Layers,
+        Subsystem::Map,
+        Subsystem::Locales,
+    ] {
+        dispatch(app, subsystem);
+    }
+}
+
+/// Records and emits a new state, presented as `read_state` returns it;
+/// repeats of the last one are dropped.
+pub(crate) fn announce_state(app: &AppHandle, state: PetState) -> tauri::Result<()> {
+    let state = crate::present(state);
+    let key = serde_json::to_string(&state).ok();
+    {
+        let mut last = LAST_STATE.lock_or_recover();
+        if key.is_some() && *last == key {
+            return Ok(());
+        }
+        *last = key;
+    }
+    let data_dir = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .data_dir
+        .clone();
+    app.state::<Recorder>().capture("state-changed", &state);
+    app.state::<Timeline>().observe(&data_dir, &state);
+    onboarding::observe_state(app, &state);
+    notifications::observe(&state);
+    app.state::<sim::Engine>().observe(&state);
+    ws::broadcast(&state);
+    mqtt::publish(&state);
+    rgb::show(&state);
+    follow_state(app, &state);
+    if STATE_EVENTS.load(Ordering::Relaxed) {
+        app.emit("state-changed", state)
+    } else {
+        Ok(())
+    }
+}
+
+/// The tray, taskbar, sounds, D-Bus and RPC, which keep showing the state
+/// while `watch_state` mutes the webview.
+pub(crate) fn follow_state(app: &AppHandle, state: &PetState) {
+    tray::update(app, state);
+    taskbar::update(app, state);
+    sound::on_state(app, state);
+    dbus::publish(state);
+    rpc::broadcast(state);
+}
+
+/// Rebuilds the string table; settings changes only announce it when the
+/// language actually changed.
+fn reload_locale(app: &AppHandle, always_emit: bool) -> tauri::Result<()> {
+    let paths = app.state::<Mutex<AppPaths>>().lock_or_recover().clone();
+    let fresh = i18n::load_for(&paths);
+    let i18n = app.state::<I18n>();
+    let changed = i18n.lang() != fresh.lang;
+    i18n.replace(fresh.clone());
+    if always_emit || changed {
+        app.emit("locale-changed", fresh)
+    } else {
+        Ok(())
+    }
+}
+
+/// Turns the webview's `state-changed` on or off; the backend followers
+/// keep going either way. Turning it on sends the current state straight
+/// away, so nothing missed while muted lingers.
+#[tauri::command]
+pub fn watch_state(app: AppHandle, enabled: bool) {
+    let was = STATE_EVENTS.swap(enabled, Ordering::Relaxed);
+    if enabled && !was {
+        *LAST_STATE.lock_or_recover() = None;
+        dispatch(&app, Subsystem::State);
+    }
+}
@@ -0,0 +1,282 @@
+//! WebSocket push channel, so a companion CLI or a pet on another machine
+//! can mirror this one live instead of polling.
+//!
+//! Every state that goes out as `state-changed` (file watcher or
+//! `write_state`, see [`crate::watch::announce_state`]) is sent to each
+//! client as `{ "type": "state", "state": … }`, starting with the current
+//! one on connect. A client may send a state object back, which is written
+//! like `write_state` does; failures come back as
+//! `{ "type": "error", "error": … }`.
+//!
+//! Needs the `ws` feature and `ws.enabled`. `ws.token`, or one from
+//! `permissions.tokens`, must be passed as `?token=` on the handshake URL;
+//! an empty one is replaced with a random token saved to `settings.json`
+//! unless `ws.allow_no_token` opts out. Browsers don't stop pages from
+//! connecting to `127.0.0.1`, so a handshake with an `Origin` not in
+//! `ws.allowed_origins` is refused. Writes need `set_state` (see
+//! [`crate::permissions`]). Read once at startup.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::logs::log;
+use crate::settings::{self, Settings};
+use crate::sync::LockExt;
+use crate::{control, AppPaths, PetState};
+
+/// One sender per connected client; dropped once its receiver is gone.
+static CLIENTS: Mutex<Vec<mpsc::Sender<String>>> = Mutex::new(Vec::new());
+
+/// `ws` block of `settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WsSettings {
+    pub enabled: bool,
+    /// `0.0.0.0` to let other machines connect.
+    pub host: String,
+    pub port: u16,
+    /// Required as `?token=`; generated on start when empty.
+    pub token: String,
+    /// Accept clients without a token, e.g. from any web page.
+    pub allow_no_token: bool,
+    /// Browser origins allowed to connect, e.g. `http://localhost:5173`.
+    /// Clients that send no `Origin` (CLIs, other pets) aren't affected.
+    pub allowed_origins: Vec<String>,
+    /// Ignore states sent by clients.
+    pub read_only: bool,
+}
+
+impl Default for WsSettings {
+    fn default() -> Self {
+        WsSettings {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 18796,
+            token: String::new(),
+            allow_no_token: false,
+            allowed_origins: Vec::new(),
+            read_only: false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(not(feature = "ws"), allow(dead_code))]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Outgoing<'a> {
+    State { state: &'a PetState },
+    Error { error: String },
+}
+
+fn encode(msg: &Outgoing) -> String {
+    serde_json::to_string(msg).unwrap_or_default()
+}
+
+/// Sends `state` to every client.
+pub(crate) fn broadcast(state: &PetState) {
+    let mut clients = CLIENTS.lock_or_recover();
+    if clients.is_empty() {
+        return;
+    }
+    let text = encode(&Outgoing::State { state });
+    clients.retain(|tx| tx.send(text.clone()).is_ok());
+}
+
+/// Whether a handshake with `origin` may go ahead.
+fn origin_allowed(origin: Option<&str>, allowed: &[String]) -> bool {
+    origin.is_none_or(|origin| {
+        allowed
+            .iter()
+            .any(|a| a.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    })
+}
+
+/// Gives an enabled channel without a token one, kept in `settings.json`.
+fn ensure_token(app: &AppHandle, cfg: &mut WsSettings) -> Result<(), String> {
+    cfg.token = control::fresh_token()?;
+    let settings_path = app
+        .state::<Mutex<AppPaths>>()
+        .lock_or_recover()
+        .settings_path
+        .clone();
+    settings::save_block(&settings_path, "ws", &*cfg)?;
+    app.state::<Mutex<Settings>>().lock_or_recover().ws = cfg.clone();
+    log!("🔌 generated ws.token in settings.json");
+    Ok(())
+}
+
+pub(crate) fn start(app: AppHandle) {
+    let mut cfg = app.state::<Mutex<Settings>>().lock_or_recover().ws.clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.token.is_empty() && !cfg.allow_no_token {
+        if let Err(e) = ensure_token(&app, &mut cfg) {
+            log!("⚠️ WebSocket off, no token: {e}");
+            return;
+        }
+    }
+    imp::listen(app, cfg);
+}
+
+#[cfg(feature = "ws")]
+mod imp {
+    use std::io::ErrorKind;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tauri::{AppHandle, Manager};
+    use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use tungstenite::http::StatusCode;
+    use tungstenite::{Error, Message};
+
+    use super::{Outgoing, WsSettings};
+    use crate::logs::log;
+    use crate::permissions::{self, Caller, Channel, Scope};
+    use crate::status::query_param;
+    use crate::sync::LockExt;
+    use crate::{api, PetState};
+
+    /// How often a connection checks for states to push.
+    const POLL: Duration = Duration::from_millis(200);
+
+    /// Handles a message from a client; `Some` is the reply.
+    fn receive(app: &AppHandle, cfg: &WsSettings, caller: &Caller, text: &str) -> Option<String> {
+        let written = if cfg.read_only {
+            Err("ws.read_only is set".to_string())
+        } else {
+            serde_json::from_str::<PetState>(text)
+                .map_err(|e| format!("state: {e}"))
+                .and_then(|state| {
+                    let action = format!("state {}", state.state);
+                    permissions::authorize(app, caller, Scope::SetState, &action)?;
+                    crate::write_state(
+                        app.clone(),
+                        Some(state),
+                        None,
+                        Some(api::IPC_REVISION),
+                        app.state(),
+                        app.state(),
+                    )
+                })
+        };
+        written
+            .err()
+            .map(|error| super::encode(&Outgoing::Error { error }))
+    }
+
+    fn serve(app: &AppHandle, stream: TcpStream, cfg: &WsSettings) -> Result<(), String> {
+        let mut caller = None;
+        let check = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+            let deny = |why: &str| {
+                let mut denied = ErrorResponse::new(Some(why.to_string()));
+                *denied.status_mut() = StatusCode::FORBIDDEN;
+                denied
+            };
+            let origin = req
+                .headers()
+                .get("origin")
+                .map(|o| o.to_str().unwrap_or("?"));
+            if !super::origin_allowed(origin, &cfg.allowed_origins) {
+                log!(
+                    "⚠️ WebSocket: refused origin {}",
+                    origin.unwrap_or_default()
+                );
+                return Err(deny("origin not allowed"));
+            }
+            let token = req.uri().query().and_then(|q| query_param(q, "token"));
+            caller = Caller::from_token(app, Channel::Ws, token.as_deref(), &cfg.token);
+            if caller.is_some() {
+                return Ok(resp);
+            }
+            Err(deny("token required"))
+        };
+        let mut ws = tungstenite::accept_hdr(stream, check).map_err(|e| e.to_string())?;
+        let caller = caller.ok_or("handshake accepted without a caller")?;
+        ws.get_ref()
+            .set_read_timeout(Some(POLL))
+            .map_err(|e| e.to_string())?;
+
+        if let Ok(state) = crate::current_state(app.state(), app.state()) {
+            let text = super::encode(&Outgoing::State { state: &state });
+            ws.send(Message::Text(text)).map_err(|e| e.to_string())?;
+        }
+        let (tx, rx) = mpsc::channel();
+        super::CLIENTS.lock_or_recover().push(tx);
+
+        loop {
+            for text in rx.try_iter() {
+                ws.send(Message::Text(text)).map_err(|e| e.to_string())?;
+            }
+            match ws.read() {
+                Ok(Message::Text(text)) => {
+                    if let Some(reply) = receive(app, cfg, &caller, &text) {
+                        ws.send(Message::Text(reply)).map_err(|e| e.to_string())?;
+                    }
+                }
+                Ok(Message::Close(_)) | Err(Error::ConnectionClosed) => return Ok(()),
+                Ok(_) => {}
+                Err(Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    pub(super) fn listen(app: AppHandle, cfg: WsSettings) {
+        let listener = match TcpListener::bind((cfg.host.as_str(), cfg.port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log!("⚠️ WebSocket on {}:{}: {e}", cfg.host, cfg.port);
+                return;
+            }
+        };
+        if cfg.token.is_empty() {
+            log!("🔌 WebSocket on {}:{} (no token)", cfg.host, cfg.port);
+        } else {
+            log!("🔌 WebSocket on {}:{}", cfg.host, cfg.port);
+        }
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (app, cfg) = (app.clone(), cfg.clone());
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(&app, stream, &cfg) {
+                        log!("⚠️ WebSocket client: {e}");
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "ws"))]
+mod imp {
+    use tauri::AppHandle;
+
+    use super::WsSettings;
+    use crate::logs::log;
+
+    pub(super) fn listen(_: AppHandle, _: WsSettings) {
+        log!("⚠️ ws.enabled is set, but this build has no WebSocket channel (rebuild with `--features ws`)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::origin_allowed;
+
+    #[test]
+    fn only_listed_browser_origins_connect() {
+        let allowed = vec!["http://localhost:5173/".to_string()];
+        assert!(origin_allowed(None, &[]));
+        assert!(origin_allowed(None, &allowed));
+        assert!(origin_allowed(Some("http://localhost:5173"), &allowed));
+        assert!(origin_allowed(Some("HTTP://LOCALHOST:5173"), &allowed));
+        assert!(!origin_allowed(Some("https://evil.example"), &allowed));
+        assert!(!origin_allowed(Some("http://localhost:5174"), &allowed));
+        assert!(!origin_allowed(Some("null"), &allowed));
+        assert!(!origin_allowed(Some("http://localhost:5173"), &[]));
+    }
+}
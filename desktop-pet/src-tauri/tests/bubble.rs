@@ -0,0 +1,47 @@
+use serde_json::json;
+use star_desktop_pet_lib::bubble::{validate, Bubble};
+
+fn bubble(value: serde_json::Value) -> Bubble {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn bubble_accepts_buttons_for_allowed_commands() {
+    let mut b = bubble(json!({
+        "text": "  Build failed. Retry?  ",
+        "icon": "error",
+        "actions": [
+            { "label": " Focus ", "command": "start_pomodoro", "args": { "workMins": 25 } },
+            { "label": "Log", "command": "open_external_url", "args": { "url": "https://ci.example.com/1" } }
+        ],
+        "duration_ms": 120000
+    }));
+    validate(&mut b).unwrap();
+    assert_eq!(b.text, "Build failed. Retry?");
+    assert_eq!(b.actions[0].label, "Focus");
+    assert_eq!(b.actions[0].args["workMins"], 25);
+    assert_eq!(b.duration_ms, Some(60_000));
+}
+
+#[test]
+fn bubble_rejects_unsafe_or_oversized_content() {
+    let rejected = [
+        json!({ "text": "   " }),
+        json!({ "text": "hi", "icon": "nope" }),
+        json!({ "text": "hi", "actions": [{ "label": "Bye", "command": "close_app" }] }),
+        json!({ "text": "hi", "actions": [{ "label": "", "command": "stop_pomodoro" }] }),
+        json!({ "text": "hi", "actions": [
+            { "label": "Open", "command": "open_external_url", "args": { "url": "file:///etc/passwd" } }
+        ] }),
+        json!({ "text": "hi", "actions": [
+            { "label": "1", "command": "stop_pomodoro" },
+            { "label": "2", "command": "stop_pomodoro" },
+            { "label": "3", "command": "stop_pomodoro" },
+            { "label": "4", "command": "stop_pomodoro" }
+        ] }),
+    ];
+    for value in rejected {
+        let mut b = bubble(value.clone());
+        assert!(validate(&mut b).is_err(), "accepted {value}");
+    }
+}
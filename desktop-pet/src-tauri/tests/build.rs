@@ -0,0 +1,99 @@
+use star_desktop_pet_lib::build::{BuildEvent, BuildParser};
+
+fn feed_all(parser: &mut BuildParser, output: &str) -> Vec<BuildEvent> {
+    output.lines().filter_map(|l| parser.feed(l)).collect()
+}
+
+#[test]
+fn build_follows_cargo_watch_runs() {
+    let mut parser = BuildParser::new();
+    let events = feed_all(
+        &mut parser,
+        "[Running 'cargo check']\n\
+         \u{1b}[32m   Compiling\u{1b}[0m serde v1.0.200\n\
+         \x20   Building [=====>        ] 1/4: serde\n\
+         \x20  Compiling app v0.1.0\n\
+         \x20   Building [==========>   ] 3/4: app\n",
+    );
+    assert_eq!(
+        events,
+        vec![
+            BuildEvent::Started,
+            BuildEvent::Progress,
+            BuildEvent::Progress,
+            BuildEvent::Progress,
+        ]
+    );
+    assert!(parser.running);
+    assert_eq!(parser.progress(), Some(0.75));
+
+    let events = feed_all(
+        &mut parser,
+        "warning: unused variable: `x`\n\
+         warning: `app` (bin \"app\") generated 1 warning\n\
+         \x20   Finished `dev` profile [unoptimized + debuginfo] target(s) in 2.31s\n\
+         [Finished running. Exit status: 0]\n",
+    );
+    assert_eq!(events, vec![BuildEvent::Finished { ok: true }]);
+    assert_eq!(parser.warnings, 1);
+
+    // Without a bar, the last build's size is the estimate.
+    let events = feed_all(
+        &mut parser,
+        "[Running 'cargo check']\n\
+         \x20  Compiling app v0.1.0\n\
+         error[E0425]: cannot find value `y` in this scope\n\
+         error: could not compile `app` (bin \"app\") due to 1 previous error\n",
+    );
+    assert_eq!(events, vec![BuildEvent::Started, BuildEvent::Progress]);
+    assert_eq!(parser.progress(), Some(0.25));
+    assert_eq!(parser.errors, 1);
+    assert_eq!(
+        parser.feed("[Finished running. Exit status: 101]"),
+        Some(BuildEvent::Finished { ok: false })
+    );
+}
+
+#[test]
+fn build_reads_tsc_webpack_and_exit_codes() {
+    let mut tsc = BuildParser::new();
+    let events = feed_all(
+        &mut tsc,
+        "[10:00:00] File change detected. Starting incremental compilation...\n\
+         src/a.ts(3,1): error TS2304: Cannot find name 'x'.\n\
+         [10:00:02] Found 1 error. Watching for file changes.\n",
+    );
+    assert_eq!(
+        events,
+        vec![BuildEvent::Started, BuildEvent::Finished { ok: false }]
+    );
+    assert_eq!(tsc.errors, 1);
+
+    let mut webpack = BuildParser::new();
+    let events = feed_all(
+        &mut webpack,
+        "10% building 1/3 entries\n\
+         70% sealing\n\
+         webpack 5.90.0 compiled successfully in 812 ms\n",
+    );
+    assert_eq!(
+        events,
+        vec![
+            BuildEvent::Started,
+            BuildEvent::Progress,
+            BuildEvent::Finished { ok: true },
+        ]
+    );
+
+    // A plain command: the exit code settles it.
+    let mut npm = BuildParser::new();
+    feed_all(
+        &mut npm,
+        "> app@1.0.0 build\n35% compiling\nnpm ERR! code 1\n",
+    );
+    assert_eq!(
+        npm.exited(Some(1)),
+        Some(BuildEvent::Finished { ok: false })
+    );
+    assert_eq!(npm.exited(Some(1)), None);
+}
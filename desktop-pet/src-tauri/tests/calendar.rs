@@ -0,0 +1,163 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use star_desktop_pet_lib::calendar::{occurrences, parse, Occurrence};
+
+fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+fn ics(events: &str) -> String {
+    format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n")
+}
+
+fn starts(list: &[Occurrence]) -> Vec<DateTime<Utc>> {
+    list.iter().map(|o| o.start).collect()
+}
+
+#[test]
+fn calendar_reads_zones_durations_and_folded_lines() {
+    let text = ics(concat!(
+        "BEGIN:VEVENT\r\n",
+        "UID:standup\r\n",
+        "SUMMARY:Stand-up\\, daily \r\n",
+        " sync\r\n",
+        "DTSTART;TZID=Europe/Berlin:20260302T093000\r\n",
+        "DURATION:PT15M\r\n",
+        "BEGIN:VALARM\r\n",
+        "TRIGGER:-PT5M\r\n",
+        "DTSTART:19700101T000000Z\r\n",
+        "END:VALARM\r\n",
+        "END:VEVENT\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:lunch\r\n",
+        "SUMMARY:Lunch\r\n",
+        "DTSTART:20260302T120000\r\n",
+        "DTEND:20260302T130000\r\n",
+        "END:VEVENT\r\n",
+    ));
+    let events = parse(&text, Tz::Asia__Shanghai).unwrap();
+    let found = occurrences(&events, utc(2026, 3, 1, 0, 0), utc(2026, 3, 3, 0, 0));
+
+    assert_eq!(found.len(), 2);
+    // Floating time read in the given zone (UTC+8).
+    assert_eq!(found[0].summary, "Lunch");
+    assert_eq!(found[0].start, utc(2026, 3, 2, 4, 0));
+    assert_eq!(found[0].end, utc(2026, 3, 2, 5, 0));
+    // Berlin is UTC+1 in March; the alarm's DTSTART is not the event's.
+    assert_eq!(found[1].summary, "Stand-up, daily sync");
+    assert_eq!(found[1].start, utc(2026, 3, 2, 8, 30));
+    assert_eq!(found[1].end, utc(2026, 3, 2, 8, 45));
+}
+
+#[test]
+fn calendar_expands_weekly_rules_with_exceptions() {
+    let text = ics(concat!(
+        "BEGIN:VEVENT\r\n",
+        "UID:sync\r\n",
+        "SUMMARY:Team sync\r\n",
+        "DTSTART:20260302T100000Z\r\n",
+        "DTEND:20260302T103000Z\r\n",
+        "RRULE:FREQ=WEEKLY;BYDAY=MO,TH;COUNT=6\r\n",
+        "EXDATE:20260305T100000Z\r\n",
+        "END:VEVENT\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:sync\r\n",
+        "SUMMARY:Team sync (moved)\r\n",
+        "RECURRENCE-ID:20260309T100000Z\r\n",
+        "DTSTART:20260309T140000Z\r\n",
+        "DTEND:20260309T143000Z\r\n",
+        "END:VEVENT\r\n",
+    ));
+    let events = parse(&text, Tz::UTC).unwrap();
+    let found = occurrences(&events, utc(2026, 3, 1, 0, 0), utc(2026, 4, 1, 0, 0));
+
+    // Mon 2, Thu 5 (excluded), Mon 9 (moved), Thu 12, Mon 16, Thu 19; the
+    // count covers the exceptions too.
+    assert_eq!(
+        starts(&found),
+        vec![
+            utc(2026, 3, 2, 10, 0),
+            utc(2026, 3, 9, 14, 0),
+            utc(2026, 3, 12, 10, 0),
+            utc(2026, 3, 16, 10, 0),
+            utc(2026, 3, 19, 10, 0),
+        ]
+    );
+    assert_eq!(found[1].summary, "Team sync (moved)");
+
+    // A window in the middle only sees what overlaps it.
+    let now = utc(2026, 3, 12, 10, 15);
+    let running = occurrences(&events, now, now + chrono::Duration::minutes(1));
+    assert_eq!(starts(&running), vec![utc(2026, 3, 12, 10, 0)]);
+}
+
+#[test]
+fn calendar_keeps_flags_and_drops_cancelled_events() {
+    let text = ics(concat!(
+        "BEGIN:VEVENT\r\n",
+        "UID:review\r\n",
+        "SUMMARY:Monthly review\r\n",
+        "DTSTART:20260130T150000Z\r\n",
+        "DURATION:PT1H\r\n",
+        "RRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20260401T000000Z\r\n",
+        "END:VEVENT\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:offsite\r\n",
+        "SUMMARY:Offsite\r\n",
+        "DTSTART;VALUE=DATE:20260320\r\n",
+        "TRANSP:TRANSPARENT\r\n",
+        "END:VEVENT\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:dropped\r\n",
+        "SUMMARY:Dropped\r\n",
+        "STATUS:CANCELLED\r\n",
+        "DTSTART:20260310T090000Z\r\n",
+        "DTEND:20260310T100000Z\r\n",
+        "END:VEVENT\r\n",
+    ));
+    let events = parse(&text, Tz::UTC).unwrap();
+    let found = occurrences(&events, utc(2026, 1, 1, 0, 0), utc(2026, 12, 31, 0, 0));
+
+    let reviews: Vec<_> = found.iter().filter(|o| o.uid == "review").collect();
+    assert_eq!(
+        reviews.iter().map(|o| o.start).collect::<Vec<_>>(),
+        vec![
+            utc(2026, 1, 30, 15, 0),
+            utc(2026, 2, 27, 15, 0),
+            utc(2026, 3, 27, 15, 0),
+        ]
+    );
+
+    let offsite = found.iter().find(|o| o.uid == "offsite").unwrap();
+    assert!(offsite.all_day && !offsite.busy);
+    assert_eq!(offsite.end - offsite.start, chrono::Duration::days(1));
+
+    assert!(found.iter().all(|o| o.uid != "dropped"));
+}
+
+#[test]
+fn calendar_survives_non_ascii_byday() {
+    let text = ics(concat!(
+        "BEGIN:VEVENT\r\n",
+        "UID:odd\r\n",
+        "SUMMARY:Réunion\r\n",
+        "DTSTART:20260302T100000Z\r\n",
+        "DTEND:20260302T110000Z\r\n",
+        "RRULE:FREQ=WEEKLY;BYDAY=éa,1MO;COUNT=4\r\n",
+        "END:VEVENT\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:emoji\r\n",
+        "SUMMARY:Party\r\n",
+        "DTSTART:20260303T100000Z\r\n",
+        "DTEND:20260303T110000Z\r\n",
+        "RRULE:FREQ=WEEKLY;BYDAY=🎉\r\n",
+        "END:VEVENT\r\n",
+    ));
+    let events = parse(&text, Tz::UTC).unwrap();
+    let found = occurrences(&events, utc(2026, 3, 1, 0, 0), utc(2026, 4, 1, 0, 0));
+    // The broken rules are dropped, leaving each first occurrence.
+    assert_eq!(
+        starts(&found),
+        vec![utc(2026, 3, 2, 10, 0), utc(2026, 3, 3, 10, 0)]
+    );
+}
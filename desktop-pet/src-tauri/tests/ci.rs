@@ -0,0 +1,95 @@
+use serde_json::json;
+use star_desktop_pet_lib::ci::{
+    job_progress, pick, CiEvent, CiPhase, CiSettings, CiTracker, RunJob, WorkflowRun,
+};
+
+fn run(id: u64, name: &str, branch: &str, status: &str, conclusion: Option<&str>) -> WorkflowRun {
+    serde_json::from_value(json!({
+        "id": id,
+        "name": name,
+        "path": format!(".github/workflows/{}.yml", name.to_lowercase()),
+        "head_branch": branch,
+        "run_number": id,
+        "status": status,
+        "conclusion": conclusion,
+        "html_url": format!("https://github.com/o/r/actions/runs/{id}"),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn ci_picks_the_newest_matching_run_preferring_running_ones() {
+    let runs = [
+        run(1, "CI", "main", "completed", Some("success")),
+        run(2, "Lint", "main", "in_progress", None),
+        run(3, "CI", "feature/x", "queued", None),
+        run(4, "CI", "main", "completed", Some("failure")),
+    ];
+    let all = CiSettings::default();
+    assert_eq!(pick(&runs, &all).map(|r| r.id), Some(3));
+
+    let main = CiSettings {
+        branch: Some("main".into()),
+        ..CiSettings::default()
+    };
+    assert_eq!(pick(&runs, &main).map(|r| r.id), Some(2));
+
+    // By name or by file.
+    for workflow in ["CI", "ci.yml"] {
+        let ci_main = CiSettings {
+            workflows: vec![workflow.into()],
+            ..main.clone()
+        };
+        assert_eq!(pick(&runs, &ci_main).map(|r| r.id), Some(4));
+    }
+
+    let jobs: Vec<RunJob> = serde_json::from_value(json!([
+        { "status": "in_progress", "steps": [
+            { "status": "completed" }, { "status": "completed" },
+            { "status": "in_progress" }, { "status": "queued" }
+        ] },
+        { "status": "queued", "steps": [] },
+        { "status": "completed", "steps": [] }
+    ]))
+    .unwrap();
+    assert_eq!(job_progress(&jobs), Some(0.5));
+    assert_eq!(job_progress(&[]), None);
+}
+
+#[test]
+fn ci_tracker_reports_only_news() {
+    let mut tracker = CiTracker::new();
+    // Finished before the pet looked: not announced.
+    let old = run(7, "CI", "main", "completed", Some("failure"));
+    assert_eq!(tracker.observe(Some(&old), None), None);
+    assert_eq!(tracker.observe(Some(&old), None), None);
+
+    let queued = run(8, "CI", "main", "queued", None);
+    assert_eq!(tracker.observe(Some(&queued), None), Some(CiEvent::Started));
+    let running = run(8, "CI", "main", "in_progress", None);
+    assert_eq!(tracker.observe(Some(&running), None), None);
+    assert_eq!(
+        tracker.observe(Some(&running), Some(0.42)),
+        Some(CiEvent::Progress)
+    );
+    assert_eq!(tracker.observe(Some(&running), Some(0.425)), None);
+
+    let done = run(8, "CI", "main", "completed", Some("success"));
+    assert_eq!(
+        tracker.observe(Some(&done), None),
+        Some(CiEvent::Finished(CiPhase::Success))
+    );
+    let cancelled = run(9, "CI", "main", "completed", Some("cancelled"));
+    assert_eq!(
+        tracker.observe(Some(&cancelled), None),
+        Some(CiEvent::Finished(CiPhase::Cancelled))
+    );
+
+    // Webhooks only: the first delivery already counts.
+    let mut pushed = CiTracker::new();
+    pushed.prime();
+    assert_eq!(
+        pushed.observe(Some(&old), None),
+        Some(CiEvent::Finished(CiPhase::Failed))
+    );
+}
@@ -0,0 +1,385 @@
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use star_desktop_pet_lib::pet_core::fixtures::{minimal_pack, png};
+use star_desktop_pet_lib::pet_core::memory::{MemoryAssets, MemoryStateStore};
+use star_desktop_pet_lib::pet_core::{
+    build_hit_masks, build_hit_masks_skinned, build_layers, build_layers_skinned, build_map,
+    build_map_tiled, AppError, Limits, StateStore,
+};
+use star_desktop_pet_lib::preview::render_strip;
+use star_desktop_pet_lib::PetState;
+
+fn to_json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+#[test]
+fn layers_load_from_memory_pack() {
+    let data = to_json(&build_layers(&minimal_pack(), &Limits::default()).unwrap());
+    assert_eq!(data["width"], 100);
+    assert_eq!(data["layers"].as_array().unwrap().len(), 1);
+    assert_eq!(data["sprites"]["anims"][0]["key"], "idle");
+    assert_eq!(data["sprites"]["anims"][0]["frames"], 4);
+    assert!(data["layers"][0]["data_url"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:image/png;base64,"));
+}
+
+#[test]
+fn missing_layers_json_uses_defaults() {
+    let data = to_json(&build_layers(&MemoryAssets::new(), &Limits::default()).unwrap());
+    assert_eq!(data["width"], 200);
+    assert_eq!(data["height"], 250);
+    assert!(data["sprites"].is_null());
+}
+
+#[test]
+fn layers_extend_a_base_and_merge_includes() {
+    let mut pack = minimal_pack();
+    pack.insert(
+        "base_layers.json",
+        r#"{ "width": 100, "height": 80, "layers": [{ "image": "bg.png", "depth": -2 }] }"#,
+    )
+    .insert(
+        "shared/sprites.json",
+        r#"{ "sprites": { "anims": { "idle": { "file": "idle.png", "frames": 4 } } } }"#,
+    )
+    .insert(
+        "layers.json",
+        r#"{
+            "extends": "base_layers.json",
+            "include": ["shared/sprites.json"],
+            "height": 120,
+            "sprites": { "anims": { "idle": { "frames": 2 } } }
+        }"#,
+    );
+    let data = to_json(&build_layers(&pack, &Limits::default()).unwrap());
+    assert_eq!(data["width"], 100);
+    assert_eq!(data["height"], 120);
+    assert_eq!(data["layers"].as_array().unwrap().len(), 1);
+    assert_eq!(data["sprites"]["anims"][0]["frames"], 2);
+
+    pack.insert("base_layers.json", r#"{ "extends": "layers.json" }"#);
+    let err = build_layers(&pack, &Limits::default())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("cycle"), "{err}");
+}
+
+#[test]
+fn characters_share_the_scene() {
+    let mut pack = minimal_pack();
+    pack.insert(
+        "layers.json",
+        r#"{
+            "width": 300, "height": 100,
+            "character": { "scale": 3, "wander": 10 },
+            "characters": [
+                { "name": "star" },
+                { "name": "cat", "x": 40, "wander": 0,
+                  "sprites": { "anims": { "sit": { "file": "idle.png", "frames": 4 } } } }
+            ],
+            "sprites": { "anims": { "idle": { "file": "idle.png", "frames": 4 } } }
+        }"#,
+    );
+    let data = to_json(&build_layers(&pack, &Limits::default()).unwrap());
+    let pets = data["characters"].as_array().unwrap();
+    assert_eq!(pets.len(), 2);
+    assert_eq!(pets[0]["name"], "star");
+    assert_eq!(pets[0]["character"]["x"], 100.0);
+    assert_eq!(pets[0]["character"]["scale"], 3.0);
+    assert_eq!(pets[0]["sprites"]["anims"][0]["key"], "idle");
+    assert_eq!(pets[1]["character"]["x"], 40.0);
+    assert_eq!(pets[1]["character"]["wander"], 0.0);
+    assert_eq!(pets[1]["sprites"]["anims"][0]["key"], "sit");
+    assert_eq!(data["character"], pets[0]["character"]);
+}
+
+#[test]
+fn maps_override_the_character_and_pick_a_skin() {
+    let mut pack = minimal_pack();
+    pack.insert(
+        "layers.json",
+        r#"{
+            "sprites": { "anims": { "idle": { "file": "idle.png", "frames": 4 } } },
+            "skins": { "chibi": { "frame_width": 16, "frame_height": 16,
+                "anims": { "idle": { "file": "idle.png", "frames": 8 } } } }
+        }"#,
+    )
+    .insert(
+        "map.json",
+        r#"{
+            "tileset": "tiles.png",
+            "ground": [[0, 1], [2, 3]],
+            "objects": [[-1, -1], [-1, -1]],
+            "collision": [[0, 0], [0, 1]],
+            "character_speed": 4,
+            "character": { "scale": 1.5, "speed": 1, "skin": "chibi" }
+        }"#,
+    );
+    let map = to_json(&build_map(&pack, &Limits::default()).unwrap());
+    assert_eq!(map["character"]["scale"], 1.5);
+    assert_eq!(map["character"]["skin"], "chibi");
+    assert_eq!(map["character_speed"], 1.0);
+
+    let skinned = to_json(&build_layers_skinned(&pack, Some("chibi"), &Limits::default()).unwrap());
+    assert_eq!(skinned["skin"], "chibi");
+    assert_eq!(skinned["sprites"]["frame_width"], 16);
+    assert_eq!(skinned["sprites"]["anims"][0]["frames"], 8);
+    let plain = to_json(&build_layers(&pack, &Limits::default()).unwrap());
+    assert_eq!(plain["sprites"]["anims"][0]["frames"], 4);
+    assert!(build_layers_skinned(&pack, Some("giant"), &Limits::default()).is_err());
+}
+
+#[test]
+fn aseprite_tags_become_anims() {
+    let mut pack = MemoryAssets::new();
+    pack.insert(
+        "layers.json",
+        r#"{ "sprites": { "aseprite": "pet/star.json" } }"#,
+    )
+    .insert(
+        "pet/star.json",
+        r#"{
+            "frames": {
+                "star 0.aseprite": { "frame": { "x": 1, "y": 1, "w": 32, "h": 32 }, "duration": 100 },
+                "star 1.aseprite": { "frame": { "x": 35, "y": 1, "w": 32, "h": 32 }, "duration": 100 },
+                "star 2.aseprite": { "frame": { "x": 69, "y": 1, "w": 32, "h": 32 }, "duration": 300 }
+            },
+            "meta": { "image": "star.png", "frameTags": [
+                { "name": "idle", "from": 0, "to": 1, "direction": "forward" },
+                { "name": "blink", "from": 0, "to": 2, "direction": "pingpong", "repeat": "2" }
+            ] }
+        }"#,
+    )
+    // 1px border padding, 2px between frames.
+    .insert("pet/star.png", png(102, 34));
+    let data = to_json(&build_layers(&pack, &Limits::default()).unwrap());
+    assert_eq!(data["sprites"]["frame_width"], 32);
+    let anims = data["sprites"]["anims"].as_array().unwrap();
+    let anim = |key: &str| anims.iter().find(|a| a["key"] == key).unwrap().clone();
+
+    let idle = anim("idle");
+    assert_eq!(idle["frames"], 2);
+    assert_eq!(idle["rate"], 10);
+    assert_eq!(idle["repeat"], -1);
+    assert_eq!(idle["margin"], 1);
+    assert_eq!(idle["spacing"], 2);
+    assert!(idle.get("sequence").is_none());
+
+    let blink = anim("blink");
+    assert_eq!(blink["sequence"], serde_json::json!([0, 1, 2, 1]));
+    assert_eq!(
+        blink["durations_ms"],
+        serde_json::json!([100, 100, 300, 100])
+    );
+    assert_eq!(blink["rate"], 7);
+    assert_eq!(blink["repeat"], 1);
+}
+
+#[test]
+fn hit_masks_cover_every_frame() {
+    let data = to_json(&build_hit_masks(&minimal_pack(), &Limits::default(), 16).unwrap());
+    let frames = data["anims"]["idle"].as_array().unwrap();
+    assert_eq!(frames.len(), 4);
+    // 32×32 opaque pixels: 128 bytes, every bit set.
+    let solid = B64.decode(frames[0].as_str().unwrap()).unwrap();
+    assert_eq!(solid, vec![0xFF; 128]);
+
+    let data = to_json(&build_hit_masks(&minimal_pack(), &Limits::default(), 255).unwrap());
+    let none = B64
+        .decode(data["anims"]["idle"][3].as_str().unwrap())
+        .unwrap();
+    assert!(none.iter().all(|b| *b == 0));
+}
+
+#[test]
+fn hit_masks_follow_each_character_and_the_skin() {
+    let mut pack = minimal_pack();
+    pack.insert(
+        "layers.json",
+        r#"{
+            "characters": [
+                { "name": "star" },
+                { "name": "cat",
+                  "sprites": { "anims": { "sit": { "file": "idle.png", "frames": 2 } } } }
+            ],
+            "sprites": { "anims": { "idle": { "file": "idle.png", "frames": 4 } } },
+            "skins": { "chibi": { "frame_width": 16, "frame_height": 16,
+                "anims": { "idle": { "file": "idle.png", "frames": 8 } } } }
+        }"#,
+    );
+    let data = to_json(&build_hit_masks(&pack, &Limits::default(), 16).unwrap());
+    assert_eq!(
+        data["characters"]["star"]["anims"]["idle"]
+            .as_array()
+            .unwrap()
+            .len(),
+        4
+    );
+    assert_eq!(
+        data["characters"]["cat"]["anims"]["sit"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(data["characters"]["cat"]["anims"]["idle"].is_null());
+    assert_eq!(data["anims"], data["characters"]["star"]["anims"]);
+
+    let skinned =
+        to_json(&build_hit_masks_skinned(&pack, Some("chibi"), &Limits::default(), 16).unwrap());
+    let star = &skinned["characters"]["star"];
+    assert_eq!(star["frame_width"], 16);
+    assert_eq!(star["anims"]["idle"].as_array().unwrap().len(), 8);
+    // 16×16 frames: 32 bytes each.
+    let bits = B64
+        .decode(star["anims"]["idle"][0].as_str().unwrap())
+        .unwrap();
+    assert_eq!(bits.len(), 32);
+    // The cat keeps its own sheet.
+    assert_eq!(skinned["characters"]["cat"]["frame_width"], 32);
+    assert!(build_hit_masks_skinned(&pack, Some("giant"), &Limits::default(), 16).is_err());
+}
+
+#[test]
+fn preview_strip_lays_frames_side_by_side() {
+    let strip = render_strip(&minimal_pack(), "idle", None, 2, &Limits::default()).unwrap();
+    // Four 64px frames with 8px padding; header, frame and one label row.
+    assert_eq!(strip.dimensions(), (296, 128));
+    assert_eq!(strip.get_pixel(8, 40).0, [255, 0, 255, 255]);
+    assert_eq!(strip.get_pixel(296 - 9, 40 + 63).0, [255, 0, 255, 255]);
+
+    let err = render_strip(&minimal_pack(), "dance", None, 2, &Limits::default()).unwrap_err();
+    assert!(err.contains("no anim named `dance`"), "{err}");
+}
+
+#[test]
+fn map_reports_tileset_geometry_and_icons() {
+    let data = to_json(&build_map(&minimal_pack(), &Limits::default()).unwrap());
+    assert_eq!(data["tileset_cols"], 2);
+    assert_eq!(data["tileset_rows"], 2);
+    assert_eq!(data["cols"], 2);
+    assert_eq!(data["pois"]["desk"]["col"], 1);
+    assert!(data["state_icons"]["writing"].is_string());
+}
+
+#[test]
+fn walk_rate_follows_character_speed() {
+    let data = to_json(&build_map(&minimal_pack(), &Limits::default()).unwrap());
+    // Defaults: four frames per tile at 2.5 tiles per second, 16px tiles at 2x.
+    assert_eq!(data["walk"]["frames_per_tile"], 4.0);
+    assert_eq!(data["walk"]["frame_rate"], 10.0);
+    assert_eq!(data["walk"]["pixels_per_frame"], 8.0);
+}
+
+#[test]
+fn tiled_map_converts_layers_and_objects() {
+    let mut pack = minimal_pack();
+    pack.insert(
+        "maps/office.tmx",
+        r#"<map orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+             <properties><property name="zoom" type="int" value="3"/></properties>
+             <tileset firstgid="1" source="office.tsx"/>
+             <layer name="Ground" width="2" height="2"><data encoding="csv">1,2,
+3,4</data></layer>
+             <layer name="objects" width="2" height="2"><data encoding="csv">0,0,0,2147483652</data></layer>
+             <objectgroup name="pois"><object name="desk" x="20" y="4" width="8" height="8"/></objectgroup>
+           </map>"#,
+    )
+    .insert(
+        "maps/office.tsx",
+        r#"<tileset tilewidth="16" tileheight="16" columns="2">
+             <image source="../tiles.png" width="32" height="32"/>
+             <tile id="3"><properties><property name="collides" type="bool" value="true"/></properties></tile>
+           </tileset>"#,
+    );
+    let data = to_json(&build_map_tiled(&pack, "maps/office.tmx", &Limits::default()).unwrap());
+    assert_eq!(data["ground"], serde_json::json!([[0, 1], [2, 3]]));
+    // Flip flags are dropped; tile 3 collides wherever it is placed.
+    assert_eq!(data["objects"], serde_json::json!([[-1, -1], [-1, 3]]));
+    assert_eq!(data["collision"], serde_json::json!([[0, 0], [0, 1]]));
+    assert_eq!(data["zoom"], 3);
+    assert_eq!(data["pois"]["desk"]["col"], 1);
+    assert_eq!(data["pois"]["desk"]["row"], 0);
+    assert!(data["state_icons"]["writing"].is_string());
+}
+
+#[test]
+fn map_rejects_tileset_at_wrong_scale() {
+    let mut pack = minimal_pack();
+    pack.insert("tiles.png", png(30, 32));
+    let err = build_map(&pack, &Limits::default())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("30x32"), "{err}");
+}
+
+#[test]
+fn load_errors_are_typed_where_they_happen() {
+    let mut pack = minimal_pack();
+    pack.insert("layers.json", "{\n  \"width\": 100,\n  oops\n}");
+    match build_layers(&pack, &Limits::default()).unwrap_err() {
+        AppError::Parse { file, line, .. } => {
+            assert_eq!(file, "layers.json");
+            assert_eq!(line, 3);
+        }
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+
+    let mut pack = minimal_pack();
+    pack.insert(
+        "map.json",
+        r#"{ "tileset": "gone.png", "ground": [[0]], "objects": [[-1]], "collision": [[0]] }"#,
+    );
+    match build_map(&pack, &Limits::default()).unwrap_err() {
+        AppError::MissingAsset { path, .. } => assert_eq!(path, "gone.png"),
+        other => panic!("expected a missing asset, got {other:?}"),
+    }
+
+    let limits = Limits {
+        max_grid_dim: 1,
+        ..Limits::default()
+    };
+    assert!(matches!(
+        build_map(&minimal_pack(), &limits),
+        Err(AppError::LimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn oversized_grid_is_rejected() {
+    let limits = Limits {
+        max_grid_dim: 1,
+        ..Limits::default()
+    };
+    let err = build_map(&minimal_pack(), &limits).unwrap_err().to_string();
+    assert!(err.contains("max_grid_dim"), "{err}");
+}
+
+#[test]
+fn bom_prefixed_config_parses() {
+    let mut pack = MemoryAssets::new();
+    pack.insert("layers.json", b"\xEF\xBB\xBF{\"width\": 64}".to_vec());
+    let data = to_json(&build_layers(&pack, &Limits::default()).unwrap());
+    assert_eq!(data["width"], 64);
+}
+
+#[test]
+fn state_round_trips_through_store() {
+    let store = MemoryStateStore::default();
+    assert!(store.load().is_err());
+    store
+        .save(&PetState {
+            state: "writing".into(),
+            detail: Some("draft".into()),
+            progress: Some(0.5),
+            updated_at: None,
+            progress_sources: Default::default(),
+        })
+        .unwrap();
+    let loaded = store.load().unwrap();
+    assert_eq!(loaded.state, "writing");
+    assert_eq!(loaded.progress, Some(0.5));
+}
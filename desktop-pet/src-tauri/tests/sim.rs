@@ -0,0 +1,152 @@
+use chrono::{DateTime, TimeZone};
+use chrono_tz::{Europe::Berlin, Tz};
+use star_desktop_pet_lib::sim::{NeedSettings, Routine, Sim, SimAction, SimSettings, World};
+
+fn start() -> DateTime<Tz> {
+    Tz::UTC.with_ymd_and_hms(2026, 3, 2, 11, 59, 0).unwrap()
+}
+
+fn idle() -> World {
+    World {
+        state: "idle".into(),
+        wander_paused: false,
+        user_idle: false,
+    }
+}
+
+fn settings() -> SimSettings {
+    let mut cfg = SimSettings::default();
+    cfg.needs.insert("energy".into(), NeedSettings::default());
+    cfg.routines.push(Routine {
+        at: "12:00".into(),
+        name: "lunch".into(),
+        bubble: Some("Lunch!".into()),
+    });
+    cfg
+}
+
+#[test]
+fn same_seed_gives_same_events_however_time_is_chunked() {
+    let mut whole = Sim::new(settings(), 42, start());
+    let mut chunked = Sim::new(settings(), 42, start());
+    let mut other = Sim::new(settings(), 43, start());
+    let (mut a, mut b, mut c) = (Vec::new(), Vec::new(), Vec::new());
+    for _ in 0..12 {
+        a.extend(whole.advance(5_000, &idle()));
+        for ms in [16, 17, 33, 250, 1_000, 16, 3_668] {
+            b.extend(chunked.advance(ms, &idle()));
+        }
+        c.extend(other.advance(5_000, &idle()));
+    }
+    assert!(a
+        .iter()
+        .any(|e| matches!(e.action, SimAction::Bubble { .. })));
+    assert_eq!(a, b);
+    assert_eq!(whole.snapshot().tick, 600);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn routines_follow_the_virtual_clock() {
+    let mut sim = Sim::new(settings(), 7, start());
+    let mut events = Vec::new();
+    for _ in 0..70 {
+        events.extend(sim.advance(1_000, &idle()));
+    }
+    let lunch: Vec<_> = events
+        .iter()
+        .filter(|e| matches!(&e.action, SimAction::Routine { name, .. } if name == "lunch"))
+        .collect();
+    assert_eq!(lunch.len(), 1);
+    assert_eq!(lunch[0].at, "2026-03-02T12:00:00.000");
+}
+
+/// Runs `sim` for `secs` seconds and returns when each routine fired.
+fn routine_times(sim: &mut Sim, secs: u32) -> Vec<String> {
+    let mut at = Vec::new();
+    for _ in 0..secs / 5 {
+        for e in sim.advance(5_000, &idle()) {
+            if matches!(e.action, SimAction::Routine { .. }) {
+                at.push(e.at);
+            }
+        }
+    }
+    at
+}
+
+fn at_half_two() -> SimSettings {
+    let mut cfg = SimSettings::default();
+    cfg.routines.push(Routine {
+        at: "02:30".into(),
+        name: "night".into(),
+        bubble: None,
+    });
+    cfg
+}
+
+#[test]
+fn routines_fire_once_when_the_clocks_go_back() {
+    // 2024-10-27: Berlin goes from 03:00 CEST back to 02:00 CET, so 02:30
+    // happens twice.
+    let start = Berlin.with_ymd_and_hms(2024, 10, 27, 1, 0, 0).unwrap();
+    let mut sim = Sim::new(at_half_two(), 5, start);
+    assert_eq!(
+        routine_times(&mut sim, 4 * 3600),
+        ["2024-10-27T02:30:00.000"]
+    );
+    assert_eq!(sim.snapshot().at, "2024-10-27T04:00:00.000");
+}
+
+#[test]
+fn routines_skipped_by_dst_fire_when_the_clocks_resume() {
+    // 2024-03-31: Berlin jumps from 02:00 CET to 03:00 CEST.
+    let start = Berlin.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap();
+    let mut sim = Sim::new(at_half_two(), 5, start);
+    assert_eq!(
+        routine_times(&mut sim, 2 * 3600),
+        ["2024-03-31T03:00:00.000"]
+    );
+    assert_eq!(sim.snapshot().at, "2024-03-31T04:00:00.000");
+}
+
+#[test]
+fn paused_or_busy_pets_do_not_wander() {
+    let mut cfg = settings();
+    cfg.needs.get_mut("energy").unwrap().restore_in.clear();
+    let mut sim = Sim::new(cfg, 1, start());
+    let busy = World {
+        state: "writing".into(),
+        wander_paused: false,
+        user_idle: false,
+    };
+    let paused = World {
+        state: "idle".into(),
+        wander_paused: true,
+        user_idle: false,
+    };
+    let mut events = Vec::new();
+    for _ in 0..60 {
+        events.extend(sim.advance(5_000, &busy));
+        events.extend(sim.advance(5_000, &paused));
+    }
+    assert!(events
+        .iter()
+        .all(|e| !matches!(e.action, SimAction::Wander { .. })));
+    assert!(sim.snapshot().needs["energy"] < 1.0);
+}
+
+#[test]
+fn an_absent_user_gets_no_wanders_or_bubbles() {
+    let mut sim = Sim::new(settings(), 3, start());
+    let asleep = World {
+        user_idle: true,
+        ..idle()
+    };
+    let mut events = Vec::new();
+    for _ in 0..24 {
+        events.extend(sim.advance(5_000, &asleep));
+    }
+    assert!(events
+        .iter()
+        .all(|e| matches!(e.action, SimAction::Routine { .. })));
+}
@@ -34,8 +34,19 @@ def load_state():
     }
 
 def save_state(state):
-    with open(STATE_FILE, "w", encoding="utf-8") as f:
-        json.dump(state, f, ensure_ascii=False, indent=2)
+    # 写入协议见 desktop-pet/STATE_API.md：持有 .lock 排他锁，写临时文件后原子替换
+    tmp_path = STATE_FILE + ".tmp"
+    with open(STATE_FILE + ".lock", "a") as lock:
+        try:
+            import fcntl
+            fcntl.flock(lock, fcntl.LOCK_EX)
+        except ImportError:
+            pass
+        with open(tmp_path, "w", encoding="utf-8") as f:
+            json.dump(state, f, ensure_ascii=False, indent=2)
+            f.flush()
+            os.fsync(f.fileno())
+        os.replace(tmp_path, STATE_FILE)
 
 if __name__ == "__main__":
     if len(sys.argv) < 2: